@@ -0,0 +1,184 @@
+//! A record of what the launcher changed and when, e.g. which version an instance was updated
+//! from, to help figure out what happened when a game or a save suddenly misbehaves. Shown on the
+//! stats tab.
+
+use crate::{get_data_dir, read_only};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use time::{format_description, OffsetDateTime};
+
+/// Serializes read-modify-write cycles of activity.json, since jobs finish in parallel
+static LOCK: Mutex<()> = Mutex::new(());
+/// Older entries are forgotten
+const ENTRY_LIMIT: usize = 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Event {
+    Installed {
+        instance: String,
+        version: String,
+    },
+    Updated {
+        instance: String,
+        from: String,
+        to: String,
+    },
+    SwitchedVersion {
+        instance: String,
+        from: String,
+        to: String,
+    },
+    Renamed {
+        from: String,
+        to: String,
+    },
+    Cloned {
+        from: String,
+        to: String,
+    },
+    RestoredBackup {
+        instance: String,
+        backup: String,
+    },
+    /// An instance or plugin, `what` says which
+    Deleted {
+        what: String,
+    },
+    /// Undoing a deletion
+    Undeleted {
+        what: String,
+    },
+    PluginInstalled {
+        plugin: String,
+        version: Option<String>,
+    },
+    PluginUpdated {
+        plugin: String,
+        from: Option<String>,
+        to: Option<String>,
+    },
+    /// Linked into the plugins folders of these instances at once
+    PluginAddedTo {
+        plugin: String,
+        instances: Vec<String>,
+    },
+    PluginRemovedFrom {
+        plugin: String,
+        instances: Vec<String>,
+    },
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Installed { instance, version } => {
+                write!(f, "Installed {instance} ({version:.32})")
+            }
+            Self::Updated { instance, from, to } => {
+                write!(f, "Updated {instance} from {from:.32} to {to:.32}")
+            }
+            Self::SwitchedVersion { instance, from, to } => {
+                write!(
+                    f,
+                    "Switched {instance} from {from:.32} to the kept {to:.32}"
+                )
+            }
+            Self::Renamed { from, to } => write!(f, "Renamed {from} to {to}"),
+            Self::Cloned { from, to } => write!(f, "Cloned {from} as {to}"),
+            Self::RestoredBackup { instance, backup } => {
+                write!(f, "Restored {instance} from the backup of {backup}")
+            }
+            Self::Deleted { what } => write!(f, "Deleted {what}"),
+            Self::Undeleted { what } => write!(f, "Undid deleting {what}"),
+            Self::PluginInstalled { plugin, version } => match version {
+                Some(version) => write!(f, "Installed the plugin {plugin} ({version})"),
+                None => write!(f, "Installed the plugin {plugin}"),
+            },
+            Self::PluginUpdated { plugin, from, to } => write!(
+                f,
+                "Updated the plugin {plugin} from {} to {}",
+                from.as_deref().unwrap_or("an unknown version"),
+                to.as_deref().unwrap_or("an unknown version")
+            ),
+            Self::PluginAddedTo { plugin, instances } => {
+                write!(f, "Put the plugin {plugin} into {}", instances.join(", "))
+            }
+            Self::PluginRemovedFrom { plugin, instances } => {
+                write!(
+                    f,
+                    "Removed the plugin {plugin} from {}",
+                    instances.join(", ")
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// Unix timestamp
+    pub at: i64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+impl Entry {
+    pub fn time(&self) -> String {
+        OffsetDateTime::from_unix_timestamp(self.at)
+            .ok()
+            .and_then(|t| {
+                t.format(
+                    &format_description::parse("[year]-[month]-[day] [hour]:[minute] UTC").ok()?,
+                )
+                .ok()
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn activity_file() -> Option<PathBuf> {
+    Some(get_data_dir()?.join("activity.json"))
+}
+
+/// The entries, oldest first.
+pub fn load() -> Vec<Entry> {
+    activity_file()
+        .filter(|p| p.exists())
+        .and_then(|p| File::open(p).ok())
+        .and_then(|f| match serde_json::from_reader(f) {
+            Ok(entries) => Some(entries),
+            Err(e) => {
+                warn!("Failed to deserialize activity.json: {}", e);
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+fn save(entries: &[Entry]) -> Result<()> {
+    let path = activity_file().ok_or_else(|| anyhow!("Failed to get app save dir"))?;
+    serde_json::to_writer_pretty(File::create(path)?, entries)?;
+    Ok(())
+}
+
+pub fn record(event: Event) {
+    if !read_only::is_persistent() {
+        return;
+    }
+    let _guard = LOCK.lock();
+    let mut entries = load();
+    entries.push(Entry {
+        at: OffsetDateTime::now_utc().unix_timestamp(),
+        event,
+    });
+    let excess = entries.len().saturating_sub(ENTRY_LIMIT);
+    entries.drain(..excess);
+    if let Err(e) = save(&entries) {
+        error!("Failed to save activity.json: {:#}", e);
+    }
+}
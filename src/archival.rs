@@ -0,0 +1,359 @@
+//! Freezes instances that are rarely played into a single compressed file, which takes up less
+//! space and far fewer files than the extracted game, and thaws them again on demand. The kept
+//! versions and backups of the instance go into the same file, so nothing is left behind.
+
+use crate::instance::{get_instances_dir, Instance, InstanceState};
+use crate::instance_lock::{self, InstanceLock};
+use crate::notifications::{notify, Level, Notification};
+use crate::settings::Settings;
+use crate::stats::{self, format_size};
+use crate::style::text_button;
+use crate::{archive, desktop_entry, send_message, trash, versions, Message};
+use anyhow::{Context, Result};
+use iced::widget::{Button, Column, Container, Row, Space, Text};
+use iced::{theme, Alignment, Color, Command, Element, Length};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use time::OffsetDateTime;
+
+/// Serializes read-modify-write cycles of archived.json, since several instances may be
+/// archived at once
+static LOCK: Mutex<()> = Mutex::new(());
+/// Ends the name of the folder an instance is extracted into while thawing
+pub const THAWING_SUFFIX: &str = " thawing";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedInstance {
+    pub instance: Instance,
+    pub archive: PathBuf,
+    /// Size of the archive in bytes
+    pub size: u64,
+    pub archived_at: i64,
+}
+
+fn archived_file() -> Option<PathBuf> {
+    Some(get_instances_dir()?.join("archived.json"))
+}
+
+/// The archived instances by name.
+pub fn load() -> BTreeMap<String, ArchivedInstance> {
+    archived_file()
+        .filter(|p| p.exists())
+        .and_then(|p| File::open(p).ok())
+        .and_then(|f| match serde_json::from_reader(f) {
+            Ok(archived) => Some(archived),
+            Err(e) => {
+                warn!("Failed to deserialize archived.json: {}", e);
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+fn save(archived: &BTreeMap<String, ArchivedInstance>) -> Result<()> {
+    let path = archived_file().ok_or_else(|| anyhow!("Failed to get Instances dir"))?;
+    serde_json::to_writer_pretty(File::create(path)?, archived)?;
+    Ok(())
+}
+
+/// The folders besides the instance's own that belong to it, by their name in the archive.
+fn belongings(name: &str) -> Vec<(&'static str, PathBuf)> {
+    let backups = Settings::load().local.backups;
+    versions::get_versions_dir(name)
+        .map(|dir| ("versions", dir))
+        .into_iter()
+        .chain(
+            backups
+                .has_dir()
+                .then(|| ("backups", backups.instance_dir(name))),
+        )
+        .filter(|(_, dir)| dir.exists())
+        .collect()
+}
+
+fn remove_dir(dir: &Path) {
+    if let Err(e) = fs::remove_dir_all(dir) {
+        warn!(
+            "Failed to remove {} after archiving it: {}",
+            dir.to_string_lossy(),
+            e
+        );
+    }
+}
+
+/// Packs the instance into the archived folder and removes its files. The instance is no
+/// longer in the list afterwards, unless this fails.
+pub async fn perform_archive(mut instance: Instance) -> Result<()> {
+    let name = instance.name.clone();
+    let belongings = belongings(&name);
+    let result = (|| -> Result<(ArchivedInstance, InstanceLock)> {
+        // The lock file ends up in the archive as well, it's removed again when thawing
+        let lock = instance_lock::acquire(&instance.path, "archiving")?;
+        if load().contains_key(&name) {
+            return Err(anyhow!("An archived instance named {name} exists already"));
+        }
+        let dir = get_instances_dir()
+            .ok_or_else(|| anyhow!("Failed to get Instances dir"))?
+            .join("archived");
+        fs::create_dir_all(&dir)?;
+        let archive = dir.join(format!("{name}.tar.gz"));
+        let dirs: Vec<(&str, &Path)> = std::iter::once(("instance", instance.path.as_path()))
+            .chain(
+                belongings
+                    .iter()
+                    .map(|(prefix, dir)| (*prefix, dir.as_path())),
+            )
+            .collect();
+        if let Err(e) = archive::pack_all(&dirs, &archive) {
+            // A partial archive is of no use
+            let _ = fs::remove_file(&archive);
+            return Err(e);
+        }
+        let size = fs::metadata(&archive)?.len();
+        instance.state = InstanceState::Ready;
+        let archived = ArchivedInstance {
+            instance: instance.clone(),
+            archive,
+            size,
+            archived_at: OffsetDateTime::now_utc().unix_timestamp(),
+        };
+        {
+            let _guard = LOCK.lock();
+            let mut all = load();
+            all.insert(name.clone(), archived.clone());
+            save(&all)?;
+        }
+        Ok((archived, lock))
+    })();
+    match result {
+        Ok((archived, _lock)) => {
+            // The archive is saved, so the files aren't needed anymore
+            remove_dir(&instance.path);
+            for (_, dir) in &belongings {
+                remove_dir(dir);
+            }
+            desktop_entry::remove(&name);
+            info!(
+                "Archived {} to {} ({})",
+                name,
+                archived.archive.to_string_lossy(),
+                format_size(archived.size)
+            );
+            notify(Notification::new(
+                Level::Success,
+                format!("Archived {name} ({})", format_size(archived.size)),
+            ));
+            send_message(Message::ArchivalMessage(ArchivalMessage::Reload));
+            Ok(())
+        }
+        Err(e) => {
+            error!("Failed to archive {}: {:#}", name, e);
+            Err(e)
+        }
+    }
+}
+
+/// Extracts an archived instance back to where it was, then adds it to the list again.
+async fn perform_thaw(archived: ArchivedInstance) {
+    let name = archived.instance.name.clone();
+    let path = archived.instance.path.clone();
+    let work_dir = path.with_file_name(format!(".{name}{THAWING_SUFFIX}"));
+    let result = (|| -> Result<()> {
+        if path.exists() {
+            return Err(anyhow!("{} exists already", path.to_string_lossy()));
+        }
+        if work_dir.exists() {
+            fs::remove_dir_all(&work_dir)?;
+        }
+        fs::create_dir_all(&work_dir)?;
+        archive::unpack(&archived.archive, &work_dir, false, None)?;
+        let lock_file = work_dir.join("instance").join(instance_lock::FILE_NAME);
+        if lock_file.exists() {
+            fs::remove_file(lock_file)?;
+        }
+        fs::rename(work_dir.join("instance"), &path)
+            .with_context(|| format!("Failed to move {name} into place"))?;
+        // Older archives only have the instance
+        let backups = Settings::load().local.backups;
+        let targets = [
+            ("versions", versions::get_versions_dir(&name)),
+            (
+                "backups",
+                backups.has_dir().then(|| backups.instance_dir(&name)),
+            ),
+        ];
+        // The instance is in place already, so these only cost a warning
+        for (prefix, target) in targets {
+            let unpacked = work_dir.join(prefix);
+            let result = match target {
+                _ if !unpacked.exists() => Ok(()),
+                Some(target) if !target.exists() => trash::move_dir(&unpacked, &target),
+                // Whatever is there already stays
+                Some(target) => {
+                    let options = fs_extra::dir::CopyOptions::new()
+                        .content_only(true)
+                        .skip_exist(true);
+                    fs_extra::dir::move_dir(&unpacked, &target, &options)
+                        .map(|_| ())
+                        .map_err(anyhow::Error::from)
+                }
+                None => Err(anyhow!("There's nowhere to put them")),
+            };
+            if let Err(e) = result {
+                warn!("Failed to thaw the {} of {}: {:#}", prefix, name, e);
+            }
+        }
+        let _guard = LOCK.lock();
+        let mut all = load();
+        all.remove(&name);
+        save(&all)?;
+        Ok(())
+    })();
+    if work_dir.exists() {
+        if let Err(e) = fs::remove_dir_all(&work_dir) {
+            warn!("Failed to clean up {}: {}", work_dir.to_string_lossy(), e);
+        }
+    }
+    match result {
+        Ok(()) => {
+            if let Err(e) = fs::remove_file(&archived.archive) {
+                warn!(
+                    "Failed to remove {}: {}",
+                    archived.archive.to_string_lossy(),
+                    e
+                );
+            }
+            info!("Thawed {}", name);
+            send_message(Message::AddInstance(Box::new(archived.instance)));
+            notify(Notification::new(Level::Success, format!("Thawed {name}")));
+        }
+        Err(e) => {
+            error!("Failed to thaw {}: {:#}", name, e);
+            notify(Notification::new(
+                Level::Error,
+                format!("Thawing {name} failed, see the log for details"),
+            ));
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ArchivalMessage {
+    /// Reads archived.json again
+    Reload,
+    ToggleExpanded,
+    /// Thaws the archived instance with this name
+    Thaw(String),
+    Thawed(String),
+}
+
+/// The collapsed section below the instance list.
+#[derive(Debug, Clone)]
+pub struct Archival {
+    archived: BTreeMap<String, ArchivedInstance>,
+    expanded: bool,
+    /// Instances being extracted right now
+    thawing: BTreeSet<String>,
+}
+
+impl Default for Archival {
+    fn default() -> Self {
+        Self {
+            archived: load(),
+            expanded: false,
+            thawing: BTreeSet::new(),
+        }
+    }
+}
+
+impl Archival {
+    /// `instances` are the ones in the list, which an archived instance can't be thawed over.
+    pub fn update(
+        &mut self,
+        message: ArchivalMessage,
+        instances: &BTreeMap<String, Instance>,
+    ) -> Command<Message> {
+        match message {
+            ArchivalMessage::Reload => self.archived = load(),
+            ArchivalMessage::ToggleExpanded => self.expanded = !self.expanded,
+            ArchivalMessage::Thaw(name) => {
+                if instances.contains_key(&name) {
+                    notify(Notification::new(
+                        Level::Error,
+                        format!(
+                            "An instance named {name} exists already, rename it to thaw this one"
+                        ),
+                    ));
+                    return Command::none();
+                }
+                let Some(archived) = self.archived.get(&name).cloned() else {
+                    return Command::none();
+                };
+                self.thawing.insert(name.clone());
+                return Command::perform(perform_thaw(archived), move |()| {
+                    Message::ArchivalMessage(ArchivalMessage::Thawed(name))
+                });
+            }
+            ArchivalMessage::Thawed(name) => {
+                self.thawing.remove(&name);
+                self.archived = load();
+            }
+        }
+        Command::none()
+    }
+
+    /// Only shown while there are archived instances.
+    pub fn view(&self) -> Option<Element<ArchivalMessage>> {
+        if self.archived.is_empty() {
+            return None;
+        }
+        let header = Button::new(Text::new(format!(
+            "{} Archived ({})",
+            if self.expanded { "▾" } else { "▸" },
+            self.archived.len()
+        )))
+        .style(text_button())
+        .on_press(ArchivalMessage::ToggleExpanded);
+        let list = self.expanded.then(|| {
+            self.archived
+                .values()
+                .fold(Column::new().spacing(5), |column, archived| {
+                    let name = archived.instance.name.clone();
+                    let thawing = self.thawing.contains(&name);
+                    column.push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .push(Text::new(name.clone()))
+                            .push(
+                                Text::new(format!(
+                                    "{}, {}, archived {}",
+                                    archived.instance.version,
+                                    format_size(archived.size),
+                                    stats::format_age(archived.archived_at)
+                                ))
+                                .size(14)
+                                .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6))),
+                            )
+                            .push(Space::with_width(Length::Fill))
+                            .push(
+                                Button::new(Text::new(if thawing { "Thawing…" } else { "Thaw" }))
+                                    .on_press_maybe(
+                                        (!thawing).then_some(ArchivalMessage::Thaw(name)),
+                                    ),
+                            ),
+                    )
+                })
+        });
+        Some(
+            Container::new(Column::new().spacing(10).push(header).push_maybe(list))
+                .padding(10)
+                .width(Length::Fill)
+                .style(theme::Container::Box)
+                .into(),
+        )
+    }
+}
@@ -1,33 +1,242 @@
-use anyhow::Result;
+use crate::errors::ExtractionFailed;
+use crate::job::{self, Progress};
+use anyhow::{anyhow, Context, Result};
 use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rayon::prelude::*;
 use std::ffi::OsStr;
-use std::fs::{create_dir, File};
+use std::fs::{self, create_dir, File};
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
-use tar::Archive;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tar::{Archive, Builder};
+use zip::ZipArchive;
 
-/// Unpacks a .tar.gz or .zip archive.
+const MIB: u64 = 1024 * 1024;
+
+/// Unpacks a .tar.gz or .zip archive, reporting the progress to `job` if given.
 /// If `strip_toplevel` is true, zip archives containing a single folder will extract the contents of that folder instead.
 /// .tar.gz archives are not affected by `strip_toplevel`.
-pub fn unpack(archive_file: &Path, destination: &PathBuf, strip_toplevel: bool) -> Result<()> {
+pub fn unpack(
+    archive_file: &Path,
+    destination: &Path,
+    strip_toplevel: bool,
+    job: Option<&str>,
+) -> Result<()> {
     info!(
         "Extracting {} to {}",
         archive_file.to_string_lossy(),
         destination.to_string_lossy()
     );
     match archive_file.extension().and_then(OsStr::to_str) {
-        Some("gz") => {
-            let file = File::open(archive_file)?;
-            let decompressed = GzDecoder::new(file);
-            let mut a = Archive::new(decompressed);
-            a.unpack(destination)?;
+        Some("gz") => unpack_tar_gz(archive_file, destination, job),
+        Some("zip") => unpack_zip(archive_file, destination, strip_toplevel, job),
+        _ => Err(anyhow!(
+            "{} isn't a .tar.gz or .zip archive",
+            archive_file.to_string_lossy()
+        )),
+    }
+    .context(ExtractionFailed)
+}
+
+/// Sums up the progress of all threads working on an extraction.
+struct ExtractionProgress<'a> {
+    job: Option<&'a str>,
+    done: AtomicU64,
+    total: u64,
+}
+
+impl<'a> ExtractionProgress<'a> {
+    fn new(job: Option<&'a str>, total: u64) -> Self {
+        Self {
+            job,
+            done: AtomicU64::new(0),
+            total,
         }
-        Some("zip") => {
-            if !destination.exists() {
-                create_dir(destination)?;
-            }
-            zip_extract::extract(File::open(archive_file)?, destination, strip_toplevel)?;
+    }
+
+    fn add(&self, bytes: u64) {
+        let Some(job) = self.job else {
+            return;
+        };
+        let before = self.done.fetch_add(bytes, Ordering::Relaxed);
+        let after = before + bytes;
+        // Once per MiB, so archives with lots of small files don't flood the GUI with messages
+        if after / MIB > before / MIB {
+            job::progress(
+                job,
+                Progress::new((after / MIB) as u32)
+                    .total((self.total / MIB) as u32)
+                    .units("MiB"),
+            );
         }
-        _ => panic!("Unsupported archive!"),
-    };
+    }
+}
+
+struct CountingReader<'a, R> {
+    inner: R,
+    progress: &'a ExtractionProgress<'a>,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.progress.add(read as u64);
+        Ok(read)
+    }
+}
+
+fn unpack_tar_gz(archive_file: &Path, destination: &Path, job: Option<&str>) -> Result<()> {
+    let file = File::open(archive_file)?;
+    let progress = ExtractionProgress::new(job, file.metadata()?.len());
+    // Gzip can't be decompressed in parallel, so this counts the compressed bytes read instead
+    let decompressed = GzDecoder::new(CountingReader {
+        inner: file,
+        progress: &progress,
+    });
+    let mut a = Archive::new(decompressed);
+    a.unpack(destination)?;
+    Ok(())
+}
+
+/// The folder all entries are in, if there's only one at the top.
+fn find_toplevel(names: &[PathBuf]) -> Option<PathBuf> {
+    if names.len() < 2 {
+        return None;
+    }
+    let toplevel: PathBuf = names[0].components().take(1).collect();
+    names
+        .iter()
+        .all(|name| name.starts_with(&toplevel))
+        .then_some(toplevel)
+}
+
+/// Extracts the entries on all cores, each thread reading the archive through its own handle.
+fn unpack_zip(
+    archive_file: &Path,
+    destination: &Path,
+    strip_toplevel: bool,
+    job: Option<&str>,
+) -> Result<()> {
+    if !destination.exists() {
+        create_dir(destination)?;
+    }
+    let mut archive = ZipArchive::new(File::open(archive_file)?)?;
+    let mut names = Vec::with_capacity(archive.len());
+    let mut total = 0;
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i)?;
+        names.push(entry.mangled_name());
+        total += entry.size();
+    }
+    let toplevel = strip_toplevel.then(|| find_toplevel(&names)).flatten();
+    let progress = ExtractionProgress::new(job, total);
+
+    let dir_modes = (0..names.len())
+        .into_par_iter()
+        .map_init(
+            || Ok::<_, anyhow::Error>(ZipArchive::new(File::open(archive_file)?)?),
+            |archive, i| -> Result<Option<(PathBuf, u32)>> {
+                let archive = archive.as_mut().map_err(|e| anyhow!("{:#}", e))?;
+                if let Some(job) = job {
+                    job::check_cancelled(job)?;
+                }
+                let relative = match &toplevel {
+                    Some(toplevel) => names[i].strip_prefix(toplevel)?,
+                    None => &names[i],
+                };
+                if relative.as_os_str().is_empty() {
+                    return Ok(None);
+                }
+                let path = destination.join(relative);
+                let mut entry = archive.by_index(i)?;
+                if entry.is_dir() {
+                    fs::create_dir_all(&path)?;
+                    // Applied last, in case it doesn't allow writing the files inside
+                    return Ok(entry.unix_mode().map(|mode| (path, mode)));
+                }
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                io::copy(&mut entry, &mut File::create(&path)?)?;
+                if let Some(mode) = entry.unix_mode() {
+                    set_mode(&path, mode)?;
+                }
+                progress.add(entry.size());
+                Ok(None)
+            },
+        )
+        .collect::<Result<Vec<_>>>()?;
+    for (dir, mode) in dir_modes.into_iter().flatten() {
+        set_mode(&dir, mode)?;
+    }
+    debug!("Extracted {} files", names.len());
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+/// Packs the contents of `dir` into a .tar.gz archive, below a folder named `prefix`.
+pub fn pack(dir: &Path, prefix: &str, archive_file: &Path) -> Result<()> {
+    pack_all(&[(prefix, dir)], archive_file)
+}
+
+/// Packs several folders into one .tar.gz archive, each below the folder name it's paired with.
+pub fn pack_all(dirs: &[(&str, &Path)], archive_file: &Path) -> Result<()> {
+    let encoder = GzEncoder::new(File::create(archive_file)?, Compression::default());
+    let mut builder = Builder::new(encoder);
+    // AppImages and app bundles may contain symlinks, which should stay symlinks
+    builder.follow_symlinks(false);
+    for (prefix, dir) in dirs {
+        info!(
+            "Packing {} into {}",
+            dir.to_string_lossy(),
+            archive_file.to_string_lossy()
+        );
+        builder.append_dir_all(prefix, dir)?;
+    }
+    builder.into_inner()?.finish()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn find_toplevel_finds_the_common_folder() {
+        assert_eq!(
+            find_toplevel(&paths(&[
+                "build/",
+                "build/endless-sky",
+                "build/data/ships.txt"
+            ])),
+            Some(PathBuf::from("build"))
+        );
+        assert_eq!(
+            find_toplevel(&paths(&["build/endless-sky", "data/ships.txt"])),
+            None
+        );
+        assert_eq!(
+            find_toplevel(&paths(&["build/endless-sky", "build-2/endless-sky"])),
+            None
+        );
+        // A single entry could just as well be a file
+        assert_eq!(find_toplevel(&paths(&["build/endless-sky"])), None);
+        assert_eq!(find_toplevel(&[]), None);
+    }
+}
@@ -0,0 +1,307 @@
+//! Scheduled archives of instances in a folder of the user's choice, so a broken update or a
+//! corrupted pilot can be rolled back even if the launcher's own data is lost.
+
+use crate::instance::{Instance, InstanceMessage, InstanceState};
+use crate::job::JobStatus;
+use crate::notifications::{notify, Level, Notification};
+use crate::settings::Settings;
+use crate::{archive, instance_lock, saves, send_message, Message};
+use anyhow::Result;
+use fs_extra::dir::{copy, CopyOptions};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How often to look for instances that are due for a backup
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Length of the timestamp the archive names start with
+const TIMESTAMP_LEN: usize = "0000-00-00 00-00-00".len();
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BackupScope {
+    /// The whole instance folder, including the game itself
+    #[default]
+    Instance,
+    /// Only the pilots and plug-ins in the instance's config dir
+    SavesAndPlugins,
+}
+
+impl BackupScope {
+    pub const ALL: [Self; 2] = [Self::Instance, Self::SavesAndPlugins];
+}
+
+impl fmt::Display for BackupScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Instance => write!(f, "Whole instance"),
+            Self::SavesAndPlugins => write!(f, "Saves and plug-ins"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Where the archives are stored, scheduled backups are off while this is empty
+    pub dir: String,
+    /// Hours between backups of an instance, 0 disables them
+    pub interval_hours: u64,
+    /// How many archives to keep per instance
+    pub keep: usize,
+    pub scope: BackupScope,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            dir: String::new(),
+            interval_hours: 24,
+            keep: 5,
+            scope: BackupScope::default(),
+        }
+    }
+}
+
+impl BackupConfig {
+    pub fn has_dir(&self) -> bool {
+        !self.dir.trim().is_empty()
+    }
+
+    pub fn is_scheduled(&self) -> bool {
+        self.has_dir() && self.interval_hours > 0
+    }
+
+    /// Where the backups of the instance go.
+    pub fn instance_dir(&self, instance_name: &str) -> PathBuf {
+        PathBuf::from(self.dir.trim()).join(instance_name)
+    }
+}
+
+/// The backups of an instance, newest first.
+/// They are named by their creation time, which sorts chronologically.
+pub fn list(config: &BackupConfig, instance_name: &str) -> Vec<PathBuf> {
+    if !config.has_dir() {
+        return vec![];
+    }
+    let Ok(entries) = fs::read_dir(config.instance_dir(instance_name)) else {
+        return vec![];
+    };
+    let mut archives: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.to_string_lossy().ends_with(".tar.gz"))
+        .collect();
+    archives.sort();
+    archives.reverse();
+    archives
+}
+
+/// The file name of a backup without the extension, which is how backups are shown.
+pub fn label(archive: &Path) -> String {
+    archive
+        .file_name()
+        .map(|n| n.to_string_lossy().trim_end_matches(".tar.gz").to_string())
+        .unwrap_or_default()
+}
+
+/// The game version stored in a backup of a whole instance, if any.
+fn version(archive: &Path) -> Option<String> {
+    let label = label(archive);
+    let version = label.get(TIMESTAMP_LEN..)?.trim();
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+fn is_due(config: &BackupConfig, instance_name: &str) -> bool {
+    let interval = Duration::from_secs(config.interval_hours * 60 * 60);
+    let recent = list(config, instance_name)
+        .first()
+        .and_then(|newest| fs::metadata(newest).and_then(|m| m.modified()).ok())
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < interval);
+    !recent
+}
+
+/// Archives the instance according to `config` and removes backups beyond the retention limit.
+pub fn create(config: &BackupConfig, instance: &Instance) -> Result<PathBuf> {
+    let _lock = instance_lock::acquire(&instance.path, "backing up")?;
+    create_locked(config, instance)
+}
+
+/// Like [`create`], for callers that hold the instance's lock already.
+fn create_locked(config: &BackupConfig, instance: &Instance) -> Result<PathBuf> {
+    if !config.has_dir() {
+        return Err(anyhow!("No backup folder is configured"));
+    }
+    let dir = config.instance_dir(&instance.name);
+    fs::create_dir_all(&dir)?;
+    let config_dir = instance
+        .config_dir()
+        .ok_or_else(|| anyhow!("Failed to get the config dir"))?;
+    let (name, dirs) = match config.scope {
+        BackupScope::Instance => {
+            // Keeps the version, so restoring can put it back into the instance list
+            let version: String = instance
+                .version
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || ".-_".contains(c) {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            (
+                format!("{} {version}", saves::timestamp()?),
+                vec![("instance", instance.path.clone())],
+            )
+        }
+        BackupScope::SavesAndPlugins => (
+            saves::timestamp()?,
+            ["saves", "plugins"]
+                .into_iter()
+                .map(|name| (name, config_dir.join(name)))
+                .filter(|(_, dir)| dir.exists())
+                .collect(),
+        ),
+    };
+    let archive = dir.join(format!("{name}.tar.gz"));
+    let dirs: Vec<(&str, &Path)> = dirs.iter().map(|(n, d)| (*n, d.as_path())).collect();
+    if let Err(e) = archive::pack_all(&dirs, &archive) {
+        // A partial archive would count as the newest backup
+        if let Err(e) = fs::remove_file(&archive) {
+            warn!("Failed to remove {}: {}", archive.to_string_lossy(), e);
+        }
+        return Err(e);
+    }
+    info!(
+        "Backed up {} to {}",
+        instance.name,
+        archive.to_string_lossy()
+    );
+
+    for old in list(config, &instance.name).iter().skip(config.keep.max(1)) {
+        debug!("Removing old backup {}", old.to_string_lossy());
+        fs::remove_file(old)?;
+    }
+    Ok(archive)
+}
+
+/// Puts the contents of a backup back into place, replacing what's there now.
+/// Returns the instance as it is after restoring.
+pub fn restore(mut instance: Instance, archive: &Path) -> Result<Instance> {
+    let _lock = instance_lock::acquire(&instance.path, "restoring a backup")?;
+    let config_dir = instance
+        .config_dir()
+        .ok_or_else(|| anyhow!("Failed to get the config dir"))?;
+    let settings = Settings::load();
+    // The current saves might be worth more than the backup
+    if let Err(e) = saves::snapshot(&config_dir, &instance.name, settings.save_snapshots) {
+        error!("Failed to snapshot saves: {:#}", e);
+    }
+
+    // Unpacked next to the instance, so moving things into place doesn't cross filesystems
+    let work_dir = instance
+        .path
+        .with_file_name(format!(".{} restoring", instance.name));
+    if work_dir.exists() {
+        fs::remove_dir_all(&work_dir)?;
+    }
+    let result = (|| -> Result<()> {
+        archive::unpack(archive, &work_dir, false, None)?;
+        // Backups are made while holding the lock, which isn't held anymore
+        let lock_file = work_dir.join("instance").join(instance_lock::FILE_NAME);
+        if lock_file.exists() {
+            fs::remove_file(lock_file)?;
+        }
+        for entry in fs::read_dir(&work_dir)? {
+            let source = entry?.path();
+            let target = match source.file_name().and_then(|n| n.to_str()) {
+                Some("instance") => instance.path.clone(),
+                Some(name @ ("saves" | "plugins")) => config_dir.join(name),
+                _ => continue,
+            };
+            if target.exists() {
+                fs::remove_dir_all(&target)?;
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if fs::rename(&source, &target).is_err() {
+                // The config dir may be on another filesystem than the instance
+                fs::create_dir_all(&target)?;
+                let options = CopyOptions {
+                    content_only: true,
+                    ..CopyOptions::new()
+                };
+                copy(&source, &target, &options)
+                    .map_err(|e| anyhow!("Failed to copy {}: {}", source.to_string_lossy(), e))?;
+            }
+        }
+        Ok(())
+    })();
+    if work_dir.exists() {
+        if let Err(e) = fs::remove_dir_all(&work_dir) {
+            warn!("Failed to remove {}: {}", work_dir.to_string_lossy(), e);
+        }
+    }
+    result?;
+    if let Some(version) = version(archive) {
+        instance.version = version;
+    }
+    info!(
+        "Restored {} from {}",
+        instance.name,
+        archive.to_string_lossy()
+    );
+    Ok(instance)
+}
+
+/// Moves the backups of a renamed instance along with it.
+pub fn rename(config: &BackupConfig, old_name: &str, new_name: &str) -> Result<()> {
+    let from = config.instance_dir(old_name);
+    if config.has_dir() && from.exists() {
+        fs::rename(from, config.instance_dir(new_name))?;
+    }
+    Ok(())
+}
+
+/// Backs up those of `instances` whose last backup is older than the configured interval.
+pub async fn perform_scheduled(instances: Vec<Instance>, config: BackupConfig) {
+    for instance in instances {
+        if !is_due(&config, &instance.name) {
+            continue;
+        }
+        // Tried again at the next check, rather than failing while the game is running
+        let lock = match instance_lock::acquire(&instance.path, "backing up") {
+            Ok(lock) => lock,
+            Err(e) => {
+                debug!("Skipping the backup of {}: {:#}", instance.name, e);
+                continue;
+            }
+        };
+        let set_state = |state| {
+            send_message(Message::InstanceMessage(
+                instance.name.clone(),
+                InstanceMessage::StateChanged(state),
+            ));
+        };
+        set_state(InstanceState::Working(JobStatus::new("Backing up")));
+        let result = create_locked(&config, &instance);
+        drop(lock);
+        set_state(InstanceState::Ready);
+        if let Err(e) = result {
+            error!("Failed to back up {}: {:#}", instance.name, e);
+            notify(Notification::new(
+                Level::Error,
+                format!(
+                    "The scheduled backup of {} failed, see the log for details",
+                    instance.name
+                ),
+            ));
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Keeps track of how much the launcher downloads per day, for people on metered connections,
+//! and warns before downloads that take this month's total over the cap they set.
+
+use crate::notifications::{notify, Level, Notification};
+use crate::settings::LocalSettings;
+use crate::stats::format_size;
+use crate::{get_data_dir, read_only};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use time::{format_description, OffsetDateTime};
+
+/// Serializes read-modify-write cycles of bandwidth.json, since downloads run in parallel
+static LOCK: Mutex<()> = Mutex::new(());
+/// Days older than this are forgotten
+const KEEP_DAYS: usize = 400;
+const GIB: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ledger {
+    /// Bytes downloaded per day, by UTC date like 2024-05-31
+    pub days: BTreeMap<String, u64>,
+}
+
+/// Today's UTC date, which sorts chronologically and starts with the month.
+fn today() -> String {
+    format_description::parse("[year]-[month]-[day]")
+        .ok()
+        .and_then(|format| OffsetDateTime::now_utc().format(&format).ok())
+        .unwrap_or_default()
+}
+
+impl Ledger {
+    fn path() -> Option<PathBuf> {
+        Some(get_data_dir()?.join("bandwidth.json"))
+    }
+
+    pub fn load() -> Self {
+        Self::path()
+            .filter(|p| p.exists())
+            .and_then(|p| File::open(p).ok())
+            .and_then(|f| match serde_json::from_reader(f) {
+                Ok(ledger) => Some(ledger),
+                Err(e) => {
+                    warn!("Failed to deserialize bandwidth.json: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = Self::path().ok_or_else(|| anyhow!("Failed to get app save dir"))?;
+        serde_json::to_writer_pretty(File::create(path)?, self)?;
+        Ok(())
+    }
+
+    pub fn today(&self) -> u64 {
+        self.days.get(&today()).copied().unwrap_or_default()
+    }
+
+    pub fn this_month(&self) -> u64 {
+        let today = today();
+        self.months()
+            .get(today.get(..7).unwrap_or_default())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Bytes downloaded in the months before this one, newest first
+    pub fn previous_months(&self) -> Vec<(String, u64)> {
+        let today = today();
+        let this_month = today.get(..7).unwrap_or_default();
+        self.months()
+            .into_iter()
+            .rev()
+            .filter(|(month, _)| month != this_month)
+            .collect()
+    }
+
+    /// Bytes downloaded per month, by month like 2024-05
+    fn months(&self) -> BTreeMap<String, u64> {
+        let mut months = BTreeMap::new();
+        for (day, bytes) in &self.days {
+            let month = day.get(..7).unwrap_or(day);
+            *months.entry(month.to_string()).or_default() += bytes;
+        }
+        months
+    }
+}
+
+pub fn record(bytes: u64) {
+    if bytes == 0 || !read_only::is_persistent() {
+        return;
+    }
+    let _guard = LOCK.lock();
+    let mut ledger = Ledger::load();
+    *ledger.days.entry(today()).or_default() += bytes;
+    let excess = ledger.days.len().saturating_sub(KEEP_DAYS);
+    let expired: Vec<String> = ledger.days.keys().take(excess).cloned().collect();
+    for day in expired {
+        ledger.days.remove(&day);
+    }
+    if let Err(e) = ledger.save() {
+        error!("Failed to save bandwidth.json: {:#}", e);
+    }
+}
+
+/// Counts the bytes of a download and records them once dropped, so downloads that fail or
+/// get cancelled halfway are counted too.
+#[derive(Debug, Default)]
+pub struct Meter {
+    bytes: u64,
+}
+
+impl Meter {
+    pub fn add(&mut self, bytes: usize) {
+        self.bytes += bytes as u64;
+    }
+}
+
+impl Drop for Meter {
+    fn drop(&mut self) {
+        record(self.bytes);
+    }
+}
+
+/// Warns if a download of `size` bytes, or of unknown size, takes this month over the cap.
+/// The cap is a soft one, so the download goes ahead either way.
+pub fn check_cap(size: Option<u64>) {
+    let cap = LocalSettings::load().download_cap * GIB;
+    if cap == 0 {
+        return;
+    }
+    let used = Ledger::load().this_month();
+    let expected = used + size.unwrap_or_default();
+    if expected <= cap {
+        return;
+    }
+    warn!(
+        "Downloading {} this month, over the cap of {}",
+        format_size(expected),
+        format_size(cap)
+    );
+    let text = match size {
+        Some(size) => format!(
+            "This download of {} takes this month's downloads over your cap: {} of {}",
+            format_size(size),
+            format_size(expected),
+            format_size(cap)
+        ),
+        None => format!(
+            "This month's downloads are over your cap: {} of {}",
+            format_size(used),
+            format_size(cap)
+        ),
+    };
+    notify(Notification::new(Level::Warning, text));
+}
@@ -0,0 +1,395 @@
+//! Times how long builds take to load, so performance regressions between versions stand out.
+//! The game is started with `--parse-assets`, which loads all data, images and sounds and exits
+//! without opening a window, a few times in a row. Where it can be told when a window shows up,
+//! the game is also launched normally to time that. The results are charted on the stats tab.
+
+use crate::instance::{Instance, InstanceState, InstanceType};
+use crate::notifications::{notify, ErrorBanner, Level, Notification};
+use crate::{get_data_dir, instance_lock, job, libraries, logger, sandbox};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+
+/// Serializes read-modify-write cycles of benchmarks.json
+static LOCK: Mutex<()> = Mutex::new(());
+/// Loads per benchmark, the first of which also warms up the disk cache
+const RUNS: usize = 3;
+/// Builds that don't know `--parse-assets` open the game instead, so they're stopped after this
+const TIMEOUT: Duration = Duration::from_secs(180);
+/// How many loads are remembered per instance
+const SAMPLE_LIMIT: usize = 100;
+/// Checking for a window starts a process, so it's done less often than checking for an exit
+const WINDOW_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sample {
+    pub version: String,
+    /// Unix timestamp
+    pub measured_at: i64,
+    /// How long loading took, in milliseconds
+    pub load_ms: u64,
+    /// How long it took until the window showed up when launched normally, in milliseconds.
+    /// None where that can't be told.
+    #[serde(default)]
+    pub window_ms: Option<u64>,
+}
+
+fn benchmarks_file() -> Option<PathBuf> {
+    Some(get_data_dir()?.join("benchmarks.json"))
+}
+
+/// The samples of each instance by name, oldest first.
+pub fn load() -> BTreeMap<String, Vec<Sample>> {
+    benchmarks_file()
+        .filter(|p| p.exists())
+        .and_then(|p| File::open(p).ok())
+        .and_then(|f| match serde_json::from_reader(f) {
+            Ok(benchmarks) => Some(benchmarks),
+            Err(e) => {
+                warn!("Failed to deserialize benchmarks.json: {}", e);
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+fn modify(f: impl FnOnce(&mut BTreeMap<String, Vec<Sample>>)) {
+    let _guard = LOCK.lock();
+    let mut benchmarks = load();
+    f(&mut benchmarks);
+    let result = benchmarks_file()
+        .ok_or_else(|| anyhow!("Failed to get app save dir"))
+        .and_then(|path| {
+            Ok(serde_json::to_writer_pretty(
+                File::create(path)?,
+                &benchmarks,
+            )?)
+        });
+    if let Err(e) = result {
+        error!("Failed to save benchmarks.json: {:#}", e);
+    }
+}
+
+/// Moves the samples of a renamed instance over to its new name.
+pub fn rename(old_name: &str, new_name: &str) {
+    modify(|benchmarks| {
+        if let Some(samples) = benchmarks.remove(old_name) {
+            benchmarks.insert(new_name.into(), samples);
+        }
+    });
+}
+
+/// Removes the samples of a deleted instance.
+pub fn forget(name: &str) {
+    modify(|benchmarks| {
+        benchmarks.remove(name);
+    });
+}
+
+fn median(mut values: Vec<u64>) -> u64 {
+    values.sort_unstable();
+    values.get(values.len() / 2).copied().unwrap_or_default()
+}
+
+/// The median of `time` for each version in milliseconds, in the order they were first measured.
+/// Versions without any such time are left out.
+pub fn by_version(samples: &[Sample], time: impl Fn(&Sample) -> Option<u64>) -> Vec<(String, u64)> {
+    let mut versions: Vec<(String, Vec<u64>)> = vec![];
+    for sample in samples {
+        let Some(ms) = time(sample) else {
+            continue;
+        };
+        match versions.iter_mut().find(|(v, _)| *v == sample.version) {
+            Some((_, times)) => times.push(ms),
+            None => versions.push((sample.version.clone(), vec![ms])),
+        }
+    }
+    versions
+        .into_iter()
+        .map(|(version, times)| (version, median(times)))
+        .collect()
+}
+
+pub fn format_ms(ms: u64) -> String {
+    format!("{:.1}s", ms as f64 / 1000.)
+}
+
+/// Loads the game once, returning how long it took.
+fn load_once(instance: &Instance) -> Result<Duration> {
+    let mut cmd = Command::new(&instance.executable);
+    cmd.arg("--parse-assets");
+    // Plugins are part of what gets loaded
+    if let Some(config_dir) = instance.config_arg() {
+        cmd.arg("--config").arg(config_dir);
+    }
+    let mut cmd = sandbox::host_command(cmd);
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    let started = Instant::now();
+    let mut child = cmd.spawn()?;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if !status.success() {
+                return Err(anyhow!("The game exited with {status} while loading"));
+            }
+            return Ok(started.elapsed());
+        }
+        let stop = if started.elapsed() > TIMEOUT {
+            Err(anyhow!(
+                "The game didn't finish loading within {} seconds, this build may not support --parse-assets",
+                TIMEOUT.as_secs()
+            ))
+        } else {
+            job::check_cancelled(&instance.name)
+        };
+        if let Err(e) = stop {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e);
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Whether the game's window showing up can be told on this system.
+fn can_see_windows() -> bool {
+    // The process started in the sandbox only relays to the game on the host
+    if sandbox::current().is_some() {
+        return false;
+    }
+    #[cfg(target_os = "linux")]
+    {
+        // Needs X11 or XWayland
+        std::env::var_os("DISPLAY").is_some()
+            && Command::new("xdotool")
+                .arg("version")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|s| s.success())
+    }
+    #[cfg(target_os = "macos")]
+    {
+        // Needs the accessibility permission, asking about the launcher itself tells whether it has it
+        has_window_query(std::process::id()).is_some()
+    }
+    #[cfg(windows)]
+    {
+        true
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+    {
+        false
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn has_window(pid: u32) -> bool {
+    Command::new("xdotool")
+        .args(["search", "--onlyvisible", "--pid", &pid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+#[cfg(target_os = "macos")]
+fn has_window_query(pid: u32) -> Option<bool> {
+    let output = Command::new("osascript")
+        .arg("-e")
+        .arg(format!(
+            "tell application \"System Events\" to count windows of (first process whose unix id is {pid})"
+        ))
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let count: u32 = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .ok()?;
+    Some(count > 0)
+}
+
+#[cfg(target_os = "macos")]
+fn has_window(pid: u32) -> bool {
+    // Fails until the game registered with System Events
+    has_window_query(pid).unwrap_or(false)
+}
+
+#[cfg(windows)]
+fn has_window(pid: u32) -> bool {
+    let Ok(output) = Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/V", "/FO", "CSV", "/NH"])
+        .output()
+    else {
+        return false;
+    };
+    // The window title is the last column, N/A until there is a window
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains(&format!("\"{pid}\"")))
+        .and_then(|line| line.rsplit(',').next())
+        .map(|title| title.trim().trim_matches('"'))
+        .is_some_and(|title| !title.is_empty() && title != "N/A")
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn has_window(_pid: u32) -> bool {
+    false
+}
+
+/// Launches the game normally, returning how long it took until its window showed up.
+fn window_once(instance: &Instance) -> Result<Duration> {
+    let mut cmd = Command::new(&instance.executable);
+    if let Some(config_dir) = instance.config_arg() {
+        cmd.arg("--config").arg(config_dir);
+    }
+    let mut cmd = sandbox::host_command(cmd);
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    let started = Instant::now();
+    let mut child = cmd.spawn()?;
+    let result = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                break Err(anyhow!(
+                    "The game exited with {status} before its window showed up"
+                ))
+            }
+            Ok(None) => {}
+            Err(e) => break Err(e.into()),
+        }
+        if has_window(child.id()) {
+            break Ok(started.elapsed());
+        }
+        if started.elapsed() > TIMEOUT {
+            break Err(anyhow!(
+                "The game's window didn't show up within {} seconds",
+                TIMEOUT.as_secs()
+            ));
+        }
+        if let Err(e) = job::check_cancelled(&instance.name) {
+            break Err(e);
+        }
+        thread::sleep(WINDOW_POLL_INTERVAL);
+    };
+    // Only the time matters, the game isn't played
+    let _ = child.kill();
+    let _ = child.wait();
+    result
+}
+
+fn median_of(times: &[Option<u64>]) -> Option<u64> {
+    let times: Vec<u64> = times.iter().copied().collect::<Option<_>>()?;
+    (!times.is_empty()).then(|| median(times))
+}
+
+/// Returns the median load time and time to window.
+fn run(instance: &Instance) -> Result<(u64, Option<u64>)> {
+    if instance.instance_type == InstanceType::Linux {
+        libraries::check(&instance.executable)?;
+    }
+    let _lock = instance_lock::acquire(&instance.path, "benchmarking")?;
+    let see_windows = can_see_windows();
+    if !see_windows {
+        info!("Can't tell when the game's window shows up here, only timing how long it loads");
+    }
+    let mut times = vec![];
+    let mut window_times = vec![];
+    for i in 0..RUNS {
+        job::stage(&instance.name, format!("Loading {} of {RUNS}", i + 1));
+        let elapsed = load_once(instance)?;
+        debug!("Load {} of {} took {:?}", i + 1, instance.name, elapsed);
+        times.push(elapsed.as_millis() as u64);
+        window_times.push(if see_windows {
+            job::stage(&instance.name, format!("Opening {} of {RUNS}", i + 1));
+            let elapsed = window_once(instance)?;
+            debug!("Window {} of {} took {:?}", i + 1, instance.name, elapsed);
+            Some(elapsed.as_millis() as u64)
+        } else {
+            None
+        });
+    }
+    let measured_at = OffsetDateTime::now_utc().unix_timestamp();
+    modify(|benchmarks| {
+        let samples = benchmarks.entry(instance.name.clone()).or_default();
+        samples.extend(
+            times
+                .iter()
+                .zip(&window_times)
+                .map(|(&load_ms, &window_ms)| Sample {
+                    version: instance.version.clone(),
+                    measured_at,
+                    load_ms,
+                    window_ms,
+                }),
+        );
+        let excess = samples.len().saturating_sub(SAMPLE_LIMIT);
+        samples.drain(..excess);
+    });
+    Ok((median(times), median_of(&window_times)))
+}
+
+/// Loads the game a few times and notes how long it took, comparing it to the previous version
+/// that was measured.
+pub async fn perform_benchmark(instance: Instance) -> InstanceState {
+    // Waiting for the game would hold up the executor
+    let runner = instance.clone();
+    let result =
+        tokio::task::spawn_blocking(move || logger::in_job(runner.name.clone(), || run(&runner)))
+            .await
+            .unwrap_or_else(|e| Err(anyhow!("The benchmark stopped unexpectedly: {e}")));
+    job::complete(&instance.name);
+    match result {
+        Ok((ms, window_ms)) => {
+            let samples = load().remove(&instance.name).unwrap_or_default();
+            let comparison = by_version(&samples, |s| Some(s.load_ms))
+                .into_iter()
+                .rev()
+                .find(|(version, _)| *version != instance.version)
+                .filter(|(_, previous)| *previous > 0)
+                .map(|(version, previous)| {
+                    let change = (ms as f64 / previous as f64 - 1.) * 100.;
+                    if change >= 0. {
+                        format!(", {change:.0}% slower than {version}")
+                    } else {
+                        format!(", {:.0}% faster than {version}", -change)
+                    }
+                })
+                .unwrap_or_default();
+            let window = window_ms
+                .map(|ms| format!(", its window shows up after {}", format_ms(ms)))
+                .unwrap_or_default();
+            info!(
+                "{} loads in {}{}{}",
+                instance.name,
+                format_ms(ms),
+                comparison,
+                window
+            );
+            notify(Notification::new(
+                Level::Info,
+                format!(
+                    "{} loads in {} (median of {RUNS} runs){comparison}{window}",
+                    instance.name,
+                    format_ms(ms)
+                ),
+            ));
+            InstanceState::Ready
+        }
+        Err(e) if job::is_cancelled(&e) => InstanceState::Ready,
+        Err(e) => {
+            error!("Failed to benchmark {}: {:#}", instance.name, e);
+            InstanceState::Failed(ErrorBanner::new("Benchmarking failed", &e))
+        }
+    }
+}
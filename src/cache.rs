@@ -0,0 +1,102 @@
+//! Size limits for the launcher's caches, evicting what was used least recently.
+//! Cache hits refresh the modification time, which is what "used" means here.
+
+use crate::get_data_dir;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Folders in the data dir that only hold files which can be fetched again
+const DIRS: [&str; 1] = ["icons"];
+
+struct Entry {
+    path: PathBuf,
+    size: u64,
+    used: SystemTime,
+}
+
+fn entries() -> Vec<Entry> {
+    let Some(data_dir) = get_data_dir() else {
+        return vec![];
+    };
+    DIRS.iter()
+        .filter_map(|dir| fs::read_dir(data_dir.join(dir)).ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+            Some(Entry {
+                path: entry.path(),
+                size: metadata.len(),
+                used: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            })
+        })
+        .collect()
+}
+
+/// In bytes
+pub fn size() -> u64 {
+    entries().iter().map(|e| e.size).sum()
+}
+
+/// Marks a cached file as used, so it's evicted last.
+pub fn touch(path: &Path) {
+    if let Err(e) = File::options()
+        .write(true)
+        .open(path)
+        .and_then(|f| f.set_modified(SystemTime::now()))
+    {
+        debug!("Failed to touch {}: {}", path.to_string_lossy(), e);
+    }
+}
+
+/// Removes the least recently used files until the caches fit into `limit` bytes,
+/// returning how many bytes were freed.
+fn collect(limit: u64) -> u64 {
+    let mut entries = entries();
+    let mut size: u64 = entries.iter().map(|e| e.size).sum();
+    entries.sort_by_key(|e| e.used);
+    let mut freed = 0;
+    for entry in entries {
+        if size <= limit {
+            break;
+        }
+        match fs::remove_file(&entry.path) {
+            Ok(()) => {
+                size -= entry.size;
+                freed += entry.size;
+            }
+            Err(e) => warn!("Failed to remove {}: {}", entry.path.to_string_lossy(), e),
+        }
+    }
+    freed
+}
+
+/// Enforces the cache limit in MiB, 0 meaning unlimited.
+pub async fn perform_collect(limit_mib: u64) {
+    if limit_mib == 0 {
+        return;
+    }
+    let freed = collect(limit_mib * 1024 * 1024);
+    if freed > 0 {
+        info!(
+            "Freed {} of cached files to stay below {} MiB",
+            crate::stats::format_size(freed),
+            limit_mib
+        );
+    }
+}
+
+/// Empties the caches, returning their remaining size, which is 0 unless removing files failed.
+pub async fn perform_clear() -> u64 {
+    let freed = collect(0);
+    info!(
+        "Cleared {} of cached files",
+        crate::stats::format_size(freed)
+    );
+    size()
+}
+
+pub async fn perform_size() -> u64 {
+    size()
+}
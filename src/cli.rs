@@ -0,0 +1,210 @@
+//! Headless subcommands for scripts and other tools.
+//!
+//! Note that on Windows, the launcher is built as a GUI application, so output
+//! is only visible when redirected, e.g. `eslauncher2 list --json > instances.json`.
+
+use crate::install_frame::InstanceSource;
+use crate::instance::{self, load_instances, perform_save_instances, InstanceType};
+use crate::job::JobEvent;
+use crate::logger::{self, LogLevel};
+use crate::stats::{self, Stats};
+use crate::{activity, diagnostics, update, Message};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+const USAGE: &str =
+    "Usage: eslauncher2 [--verbose] [--quiet] [--kiosk|--no-kiosk] [--portable] [--data-dir <dir>] [--profile <name>] [list [--json] | update <name>|--all | --diagnostics]";
+
+#[derive(Serialize)]
+struct InstanceInfo {
+    name: String,
+    version: String,
+    path: PathBuf,
+    source: InstanceSource,
+    /// RFC 3339 timestamp
+    last_played: Option<String>,
+}
+
+/// Runs the subcommand given in `args`, if any, and returns its exit code.
+/// Returns `None` if the GUI should be started instead.
+pub fn run(args: &[String]) -> Option<i32> {
+    // Strip the global options, which are handled elsewhere
+    let mut args_iter = args.iter().map(String::as_str);
+    let mut args = vec![];
+    while let Some(arg) = args_iter.next() {
+        match arg {
+            "--verbose" | "-v" | "--quiet" | "--kiosk" | "--no-kiosk" | "--portable" => {}
+            "--data-dir" | "--profile" => {
+                args_iter.next();
+            }
+            _ if arg.starts_with("--data-dir=") || arg.starts_with("--profile=") => {}
+            _ => args.push(arg),
+        }
+    }
+    let result = match args.as_slice() {
+        [] => return None,
+        ["list"] => list(false),
+        ["list", "--json"] => list(true),
+        ["update", "--all"] => update(None),
+        ["update", name] => update(Some(name)),
+        ["--diagnostics"] => {
+            println!("{}", diagnostics::report());
+            Ok(())
+        }
+        ["help" | "--help" | "-h"] => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            return Some(2);
+        }
+    };
+    Some(match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            1
+        }
+    })
+}
+
+fn list(json: bool) -> Result<()> {
+    let stats = Stats::load();
+    let instances: Vec<InstanceInfo> = load_instances()?
+        .into_iter()
+        .map(|instance| InstanceInfo {
+            last_played: stats
+                .instances
+                .get(&instance.name)
+                .and_then(|s| s.last_played)
+                .and_then(|t| OffsetDateTime::from_unix_timestamp(t).ok())
+                .and_then(|t| t.format(&Rfc3339).ok()),
+            name: instance.name,
+            version: instance.version,
+            path: instance.path,
+            source: instance.source,
+        })
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&instances)?);
+    } else {
+        for instance in instances {
+            println!(
+                "{}\t{}\t{} {}\t{}",
+                instance.name,
+                instance.version,
+                instance.source.r#type,
+                instance.source.identifier,
+                instance.path.to_string_lossy()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Prints the job events the update sends to the (absent) GUI until `done` is set.
+fn spawn_progress_printer(done: Arc<AtomicBool>) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        while !done.load(Ordering::SeqCst) {
+            let message = crate::MESSAGE_QUEUE
+                .lock()
+                .ok()
+                .and_then(|mut q| q.pop_front());
+            match message {
+                Some(Message::JobEvent(name, JobEvent::StageChanged(stage))) => {
+                    println!("\n{name}: {stage}");
+                }
+                Some(Message::JobEvent(_, JobEvent::Progress(progress))) => {
+                    print!(
+                        "\r  {}/{}{}{}",
+                        progress.done,
+                        if progress.total_approx { "~" } else { "" },
+                        progress.total.map_or_else(|| "?".into(), |t| t.to_string()),
+                        progress.units.unwrap_or_default()
+                    );
+                    std::io::stdout().flush().ok();
+                }
+                Some(_) => {}
+                None => thread::sleep(Duration::from_millis(10)),
+            }
+        }
+    })
+}
+
+/// Updates one or all instances, returning an error if any of them failed.
+fn update(name: Option<&str>) -> Result<()> {
+    logger::init(LogLevel::Info);
+    let mut instances: BTreeMap<String, instance::Instance> = load_instances()?
+        .into_iter()
+        .map(|i| (i.name.clone(), i))
+        .collect();
+    let names: Vec<String> = match name {
+        Some(name) if instances.contains_key(name) => vec![name.to_string()],
+        Some(name) => return Err(anyhow!("No instance named {}", name)),
+        None => instances.keys().cloned().collect(),
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let done = Arc::new(AtomicBool::new(false));
+    let printer = spawn_progress_printer(done.clone());
+
+    let mut failed = vec![];
+    for name in names {
+        let instance = instances[&name].clone();
+        if instance.instance_type == InstanceType::Unknown {
+            println!("{name}: Skipping, unknown instance type");
+            continue;
+        }
+        match update::latest_version(&instance) {
+            Ok(version) if version == instance.version => {
+                println!("{name}: Already up to date");
+                continue;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("{name}: Failed to check for updates: {e:#}");
+                failed.push(name);
+                continue;
+            }
+        }
+        let from = instance.version.clone();
+        match runtime.block_on(instance::snapshot_and_update(instance)) {
+            Ok(updated) => {
+                println!("\n{name}: Updated to {}", updated.version);
+                activity::record(activity::Event::Updated {
+                    instance: name.clone(),
+                    from,
+                    to: updated.version.clone(),
+                });
+                instances.insert(name, updated);
+                perform_save_instances(instances.clone());
+                stats::record_update();
+            }
+            Err(e) => {
+                eprintln!("\n{name}: Update failed: {e:#}");
+                failed.push(name);
+            }
+        }
+    }
+
+    done.store(true, Ordering::SeqCst);
+    printer.join().ok();
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("Failed to update {}", failed.join(", ")))
+    }
+}
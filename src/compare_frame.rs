@@ -0,0 +1,393 @@
+use crate::instance::{Instance, InstanceType};
+use crate::plugin_metadata;
+use crate::style::text_button;
+use crate::Message;
+use anyhow::Result;
+use iced::widget::{Button, Column, Container, PickList, Row, Scrollable, Space, Text};
+use iced::{theme, Alignment, Color, Command, Element, Length};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// How many differing data files are listed before the rest get summarized
+const MAX_LISTED_FILES: usize = 200;
+
+#[derive(Debug, Clone)]
+pub enum CompareMessage {
+    LeftSelected(String),
+    RightSelected(String),
+    Compare,
+    Loaded(Box<Comparison>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    left: String,
+    right: String,
+    /// Label, left value, right value
+    properties: Vec<(String, String, String)>,
+    /// Plugin name and its version in each instance
+    plugins: Vec<(String, String, String)>,
+    data: Result<DataDiff, String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DataDiff {
+    only_left: Vec<String>,
+    only_right: Vec<String>,
+    changed: Vec<String>,
+    unchanged: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+enum CompareState {
+    #[default]
+    Idle,
+    Loading,
+    Ready(Box<Comparison>),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CompareFrame {
+    left: Option<String>,
+    right: Option<String>,
+    state: CompareState,
+}
+
+impl CompareFrame {
+    pub fn update(
+        &mut self,
+        message: CompareMessage,
+        instances: &BTreeMap<String, Instance>,
+    ) -> Command<Message> {
+        match message {
+            CompareMessage::LeftSelected(name) => self.left = Some(name),
+            CompareMessage::RightSelected(name) => self.right = Some(name),
+            CompareMessage::Compare => {
+                let left = self.left.as_ref().and_then(|n| instances.get(n));
+                let right = self.right.as_ref().and_then(|n| instances.get(n));
+                if let (Some(left), Some(right)) = (left, right) {
+                    self.state = CompareState::Loading;
+                    return Command::perform(compare(left.clone(), right.clone()), |c| {
+                        Message::CompareMessage(CompareMessage::Loaded(Box::new(c)))
+                    });
+                }
+            }
+            CompareMessage::Loaded(comparison) => self.state = CompareState::Ready(comparison),
+        }
+        Command::none()
+    }
+
+    pub fn view(&self, instances: Vec<String>) -> Element<CompareMessage> {
+        let mut compare_button = Button::new(Text::new("Compare")).style(text_button());
+        if self.left.is_some()
+            && self.right.is_some()
+            && self.left != self.right
+            && !matches!(self.state, CompareState::Loading)
+        {
+            compare_button = compare_button.on_press(CompareMessage::Compare);
+        }
+        let header = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Text::new("Compare").size(26))
+            .push(Space::new(Length::Fill, Length::Shrink))
+            .push(
+                PickList::new(
+                    instances.clone(),
+                    self.left.clone(),
+                    CompareMessage::LeftSelected,
+                )
+                .placeholder("Instance"),
+            )
+            .push(Text::new("with"))
+            .push(
+                PickList::new(instances, self.right.clone(), CompareMessage::RightSelected)
+                    .placeholder("Instance"),
+            )
+            .push(compare_button);
+
+        let content: Element<_> = match &self.state {
+            CompareState::Idle => {
+                grey("Pick two instances to see what differs between them.").into()
+            }
+            CompareState::Loading => {
+                grey("Comparing, checksumming data files may take a while…").into()
+            }
+            CompareState::Ready(comparison) => comparison.view(),
+        };
+
+        Container::new(Scrollable::new(
+            Column::new()
+                .spacing(20)
+                .padding(20)
+                .push(header)
+                .push(content),
+        ))
+        .width(Length::Fill)
+        .padding(iced::Padding {
+            top: 0.0,
+            right: 30.0,
+            bottom: 0.0,
+            left: 30.0,
+        })
+        .into()
+    }
+}
+
+impl Comparison {
+    fn view(&self) -> Element<CompareMessage> {
+        let mut properties = Column::new().spacing(5).push(row(
+            Text::new(""),
+            Text::new(&self.left).size(20),
+            Text::new(&self.right).size(20),
+        ));
+        for (label, left, right) in &self.properties {
+            properties = properties.push(row(
+                Text::new(label),
+                highlight(left, left != right),
+                highlight(right, left != right),
+            ));
+        }
+
+        let mut plugins = Column::new()
+            .spacing(5)
+            .push(Text::new("Plug-ins").size(20));
+        if self.plugins.is_empty() {
+            plugins = plugins.push(grey("Neither instance has any plug-ins installed."));
+        }
+        for (name, left, right) in &self.plugins {
+            plugins = plugins.push(row(
+                Text::new(name),
+                highlight(left, left != right),
+                highlight(right, left != right),
+            ));
+        }
+
+        let mut data = Column::new()
+            .spacing(5)
+            .push(Text::new("Data files").size(20));
+        match &self.data {
+            Err(e) => data = data.push(grey(e)),
+            Ok(diff) => {
+                data = data.push(Text::new(format!(
+                    "{} identical, {} changed, {} only in {}, {} only in {}",
+                    diff.unchanged,
+                    diff.changed.len(),
+                    diff.only_left.len(),
+                    self.left,
+                    diff.only_right.len(),
+                    self.right
+                )));
+                let listed = diff
+                    .changed
+                    .iter()
+                    .map(|f| ("changed", f))
+                    .chain(diff.only_left.iter().map(|f| ("removed", f)))
+                    .chain(diff.only_right.iter().map(|f| ("added", f)));
+                let total = diff.changed.len() + diff.only_left.len() + diff.only_right.len();
+                for (kind, file) in listed.take(MAX_LISTED_FILES) {
+                    data = data.push(
+                        Row::new()
+                            .spacing(10)
+                            .push(Text::new(kind).size(14).width(Length::Fixed(70.)))
+                            .push(Text::new(file).size(14)),
+                    );
+                }
+                if total > MAX_LISTED_FILES {
+                    data = data.push(grey(format!("…and {} more", total - MAX_LISTED_FILES)));
+                }
+            }
+        }
+
+        Column::new()
+            .spacing(20)
+            .push(properties)
+            .push(plugins)
+            .push(data)
+            .into()
+    }
+}
+
+fn row<'a>(label: Text<'a>, left: Text<'a>, right: Text<'a>) -> Element<'a, CompareMessage> {
+    Row::new()
+        .spacing(10)
+        .push(label.width(Length::FillPortion(1)))
+        .push(left.width(Length::FillPortion(2)))
+        .push(right.width(Length::FillPortion(2)))
+        .into()
+}
+
+fn grey<'a>(text: impl ToString) -> Text<'a> {
+    Text::new(text.to_string()).style(theme::Text::Color(Color::from_rgb8(150, 150, 150)))
+}
+
+fn highlight<'a>(text: impl ToString, differs: bool) -> Text<'a> {
+    let text = Text::new(text.to_string());
+    if differs {
+        text.style(theme::Text::Color(Color::from_rgb8(230, 160, 40)))
+    } else {
+        text
+    }
+}
+
+/// Where the game's data files are, relative to the executable.
+pub(crate) fn find_data_dir(instance: &Instance) -> Option<PathBuf> {
+    if instance.instance_type == InstanceType::AppImage {
+        return None;
+    }
+    let exe_dir = instance.executable.parent()?;
+    [
+        exe_dir.join("data"),
+        // macOS app bundles keep them in Contents/Resources
+        exe_dir.parent()?.join("Resources").join("data"),
+    ]
+    .into_iter()
+    .find(|dir| dir.is_dir())
+}
+
+pub(crate) fn checksum(path: &Path) -> io::Result<u32> {
+    let mut file = File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(hasher.finalize());
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+/// All files in `dir`, keyed by their path relative to it.
+pub(crate) fn files(dir: &Path) -> Result<BTreeMap<String, PathBuf>> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                let relative = path.strip_prefix(dir)?.to_string_lossy().replace('\\', "/");
+                files.insert(relative, path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Checksums of all files in `dir`, keyed by their path relative to it.
+pub(crate) fn checksums(dir: &Path) -> Result<BTreeMap<String, u32>> {
+    files(dir)?
+        .into_iter()
+        .map(|(relative, path)| Ok((relative, checksum(&path)?)))
+        .collect()
+}
+
+fn diff_data(left: &Instance, right: &Instance) -> Result<DataDiff, String> {
+    let dirs = (find_data_dir(left), find_data_dir(right));
+    let (Some(left_dir), Some(right_dir)) = dirs else {
+        return Err(
+            "Data files can't be compared, since they're packed into AppImages or weren't found."
+                .into(),
+        );
+    };
+    let left_sums =
+        checksums(&left_dir).map_err(|e| format!("Failed to read data files: {e:#}"))?;
+    let right_sums =
+        checksums(&right_dir).map_err(|e| format!("Failed to read data files: {e:#}"))?;
+
+    let mut diff = DataDiff::default();
+    for (file, sum) in &left_sums {
+        match right_sums.get(file) {
+            None => diff.only_left.push(file.clone()),
+            Some(other) if other != sum => diff.changed.push(file.clone()),
+            Some(_) => diff.unchanged += 1,
+        }
+    }
+    diff.only_right = right_sums
+        .keys()
+        .filter(|file| !left_sums.contains_key(*file))
+        .cloned()
+        .collect();
+    Ok(diff)
+}
+
+/// Installed plugins and their versions.
+fn plugins(instance: &Instance) -> BTreeMap<String, Option<String>> {
+    instance
+        .config_dir()
+        .map(|dir| plugin_metadata::load_installed(&dir.join("plugins")))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|p| (p.name, p.version))
+        .collect()
+}
+
+async fn compare(left: Instance, right: Instance) -> Comparison {
+    info!("Comparing {} with {}", left.name, right.name);
+    let properties = vec![
+        ("Version", left.version.clone(), right.version.clone()),
+        (
+            "Source",
+            format!("{} {}", left.source.r#type, left.source.identifier),
+            format!("{} {}", right.source.r#type, right.source.identifier),
+        ),
+        (
+            "Type",
+            format!("{:?}", left.instance_type),
+            format!("{:?}", right.instance_type),
+        ),
+        (
+            "Separate config",
+            left.isolated_config.to_string(),
+            right.isolated_config.to_string(),
+        ),
+        (
+            "Launch arguments",
+            left.launch_args.clone(),
+            right.launch_args.clone(),
+        ),
+        (
+            "Launch options",
+            left.launch_options.args().join(" "),
+            right.launch_options.args().join(" "),
+        ),
+        (
+            "Plug-in profile",
+            left.plugin_profile.clone().unwrap_or_default(),
+            right.plugin_profile.clone().unwrap_or_default(),
+        ),
+    ]
+    .into_iter()
+    .map(|(label, l, r)| (label.to_string(), l, r))
+    .collect();
+
+    let left_plugins = plugins(&left);
+    let right_plugins = plugins(&right);
+    let describe = |version: Option<&Option<String>>| match version {
+        None => "Not installed".to_string(),
+        Some(version) => version.clone().unwrap_or_else(|| "Installed".into()),
+    };
+    let names: BTreeSet<&String> = left_plugins.keys().chain(right_plugins.keys()).collect();
+    let plugins = names
+        .into_iter()
+        .map(|name| {
+            (
+                name.clone(),
+                describe(left_plugins.get(name)),
+                describe(right_plugins.get(name)),
+            )
+        })
+        .collect();
+
+    Comparison {
+        data: diff_data(&left, &right),
+        left: left.name,
+        right: right.name,
+        properties,
+        plugins,
+    }
+}
@@ -0,0 +1,126 @@
+use crate::style::text_button;
+use crate::Message;
+use iced::widget::scrollable::{self, RelativeOffset};
+use iced::widget::{Button, Column, Container, PickList, Row, Scrollable, Space, Text};
+use iced::{theme, Alignment, Color, Command, Element, Font, Length};
+use lazy_static::lazy_static;
+use std::collections::{BTreeMap, VecDeque};
+
+/// How many lines are kept per instance, older ones get dropped
+const MAX_LINES: usize = 2000;
+
+lazy_static! {
+    static ref SCROLLABLE_ID: scrollable::Id = scrollable::Id::unique();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone)]
+pub enum ConsoleMessage {
+    /// A game was launched, replacing the previous output of its instance
+    Started(String),
+    Line(String, Stream, String),
+    InstanceSelected(String),
+    Clear,
+}
+
+/// Live output of the games launched from the launcher, per instance.
+#[derive(Debug, Clone, Default)]
+pub struct ConsoleFrame {
+    selected: Option<String>,
+    output: BTreeMap<String, VecDeque<(Stream, String)>>,
+}
+
+impl ConsoleFrame {
+    pub fn update(&mut self, message: ConsoleMessage) -> Command<Message> {
+        match message {
+            ConsoleMessage::Started(name) => {
+                self.output.insert(name.clone(), VecDeque::new());
+                self.selected.get_or_insert(name);
+            }
+            ConsoleMessage::Line(name, stream, line) => {
+                let lines = self.output.entry(name.clone()).or_default();
+                lines.push_back((stream, line));
+                if lines.len() > MAX_LINES {
+                    lines.pop_front();
+                }
+                if self.selected.as_ref() == Some(&name) {
+                    return scrollable::snap_to(SCROLLABLE_ID.clone(), RelativeOffset::END);
+                }
+            }
+            ConsoleMessage::InstanceSelected(name) => {
+                self.selected = Some(name);
+                return scrollable::snap_to(SCROLLABLE_ID.clone(), RelativeOffset::END);
+            }
+            ConsoleMessage::Clear => {
+                if let Some(lines) = self.selected.as_ref().and_then(|n| self.output.get_mut(n)) {
+                    lines.clear();
+                }
+            }
+        }
+        Command::none()
+    }
+
+    pub fn view(&self, instances: Vec<String>) -> Element<ConsoleMessage> {
+        let header = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Text::new("Console").size(26))
+            .push(Space::new(Length::Fill, Length::Shrink))
+            .push(
+                PickList::new(
+                    instances,
+                    self.selected.clone(),
+                    ConsoleMessage::InstanceSelected,
+                )
+                .placeholder("Choose an instance"),
+            )
+            .push(
+                Button::new(Text::new("Clear"))
+                    .style(text_button())
+                    .on_press(ConsoleMessage::Clear),
+            );
+
+        let lines = self.selected.as_ref().and_then(|n| self.output.get(n));
+        let content: Element<_> = match lines {
+            Some(lines) if !lines.is_empty() => lines
+                .iter()
+                .fold(Column::new().spacing(1), |column, (stream, line)| {
+                    let text = Text::new(line)
+                        .size(12)
+                        .font(Font::with_name("DejaVu Sans Mono"));
+                    column.push(match stream {
+                        Stream::Stdout => text,
+                        Stream::Stderr => {
+                            text.style(theme::Text::Color(Color::from_rgb8(220, 80, 80)))
+                        }
+                    })
+                })
+                .into(),
+            _ => Text::new("Output of games launched from here shows up live in this console.")
+                .style(theme::Text::Color(Color::from_rgb8(150, 150, 150)))
+                .into(),
+        };
+
+        Container::new(
+            Column::new().spacing(20).padding(20).push(header).push(
+                Scrollable::new(content)
+                    .id(SCROLLABLE_ID.clone())
+                    .width(Length::Fill)
+                    .height(Length::Fill),
+            ),
+        )
+        .width(Length::Fill)
+        .padding(iced::Padding {
+            top: 0.0,
+            right: 30.0,
+            bottom: 0.0,
+            left: 30.0,
+        })
+        .into()
+    }
+}
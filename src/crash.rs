@@ -0,0 +1,213 @@
+//! Crash reports: a panic hook that writes the details to a file, and a small window
+//! showing them, so crashes don't go unnoticed in a console nobody looks at.
+
+use crate::get_data_dir;
+use iced::widget::{Button, Column, Container, Row, Scrollable, Space, Text};
+use iced::{executor, window, Application, Command, Element, Font, Length, Theme};
+use std::backtrace::Backtrace;
+use std::fs;
+use std::panic::{self, PanicHookInfo};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use time::{format_description, OffsetDateTime};
+
+/// Starts the launcher in crash dialog mode, followed by the path of the report
+pub const FLAG: &str = "--crash-report";
+
+const ISSUES_URL: &str = "https://github.com/EndlessSkyCommunity/ESLauncher2/issues/new";
+/// Keeps the issue URL below the length browsers and GitHub accept
+const MAX_BACKTRACE_LINES: usize = 40;
+
+/// Only the first panic gets a dialog, the ones it causes would just repeat it
+static REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Makes panics write a crash report and open a dialog showing it, in addition to the usual output.
+pub fn install_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        if REPORTED.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let report = report(info);
+        error!("{}", report);
+        match write_report(&report) {
+            Ok(path) => show_dialog(path),
+            Err(e) => error!("Failed to write crash report: {:#}", e),
+        }
+    }));
+}
+
+fn report(info: &PanicHookInfo) -> String {
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|s| (*s).to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Unknown panic".into());
+    let location = info
+        .location()
+        .map(|l| format!("{}:{}", l.file(), l.line()))
+        .unwrap_or_default();
+    format!(
+        "ESLauncher2 v{} crashed on {} {}\n\
+         Thread '{}' panicked at {}:\n{}\n\n\
+         Backtrace:\n{}",
+        version!(),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        thread::current().name().unwrap_or("unnamed"),
+        location,
+        message,
+        Backtrace::force_capture()
+    )
+}
+
+fn write_report(report: &str) -> anyhow::Result<PathBuf> {
+    let dir = get_data_dir()
+        .ok_or_else(|| anyhow!("Failed to get app save dir"))?
+        .join("crashes");
+    fs::create_dir_all(&dir)?;
+    let timestamp = OffsetDateTime::now_utc().format(&format_description::parse(
+        "[year]-[month]-[day] [hour]-[minute]-[second]",
+    )?)?;
+    let path = dir.join(format!("{timestamp}.txt"));
+    fs::write(&path, report)?;
+    Ok(path)
+}
+
+/// The dialog runs in a new process, since this one might not be able to draw anything anymore.
+fn show_dialog(report: PathBuf) {
+    let result = std::env::current_exe()
+        .and_then(|exe| process::Command::new(exe).arg(FLAG).arg(report).spawn());
+    if let Err(e) = result {
+        error!("Failed to show the crash dialog: {}", e);
+    }
+}
+
+/// Replaces everything but unreserved characters with percent escapes, for URL query values.
+pub(crate) fn url_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub enum CrashMessage {
+    ReportIssue,
+    OpenReport,
+    Close,
+}
+
+pub struct CrashDialog {
+    path: PathBuf,
+    report: String,
+}
+
+impl CrashDialog {
+    fn issue_url(&self) -> String {
+        let mut lines = self.report.lines();
+        let title = lines
+            .nth(1)
+            .map_or("Crash", |l| l.trim_end_matches(':'))
+            .to_string();
+        let mut body: Vec<&str> = self.report.lines().take(MAX_BACKTRACE_LINES).collect();
+        if self.report.lines().count() > MAX_BACKTRACE_LINES {
+            body.push("[…]");
+        }
+        let body = format!(
+            "**What were you doing when it crashed?**\n\n\n\
+             **Crash report** (the full one is in `{}`)\n```\n{}\n```\n",
+            self.path.to_string_lossy(),
+            body.join("\n")
+        );
+        format!(
+            "{ISSUES_URL}?title={}&body={}",
+            url_encode(&format!("Crash: {title}")),
+            url_encode(&body)
+        )
+    }
+}
+
+impl Application for CrashDialog {
+    type Executor = executor::Default;
+    type Message = CrashMessage;
+    type Theme = Theme;
+    type Flags = PathBuf;
+
+    fn new(path: PathBuf) -> (Self, Command<CrashMessage>) {
+        let report = fs::read_to_string(&path)
+            .unwrap_or_else(|e| format!("Failed to read {}: {}", path.to_string_lossy(), e));
+        (Self { path, report }, Command::none())
+    }
+
+    fn title(&self) -> String {
+        "ESLauncher2 crashed".into()
+    }
+
+    fn update(&mut self, message: CrashMessage) -> Command<CrashMessage> {
+        match message {
+            CrashMessage::ReportIssue => {
+                if let Err(e) = open::that(self.issue_url()) {
+                    error!("Failed to open the issue tracker: {}", e);
+                }
+            }
+            CrashMessage::OpenReport => {
+                if let Err(e) = crate::sandbox::open(&self.path) {
+                    error!("Failed to open the crash report: {:#}", e);
+                }
+            }
+            CrashMessage::Close => return window::close(window::Id::MAIN),
+        }
+        Command::none()
+    }
+
+    fn view(&self) -> Element<CrashMessage> {
+        Container::new(
+            Column::new()
+                .spacing(10)
+                .push(Text::new("ESLauncher2 crashed").size(24))
+                .push(Text::new(format!(
+                    "Sorry about that! The details below were saved to {}. \
+                     Reporting the crash helps getting it fixed.",
+                    self.path.to_string_lossy()
+                )))
+                .push(
+                    Container::new(
+                        Scrollable::new(
+                            Text::new(&self.report)
+                                .font(Font::MONOSPACE)
+                                .size(12)
+                                .width(Length::Fill),
+                        )
+                        .height(Length::Fill),
+                    )
+                    .padding(5)
+                    .style(iced::theme::Container::Box),
+                )
+                .push(
+                    Row::new()
+                        .spacing(10)
+                        .push(
+                            Button::new(Text::new("Report issue"))
+                                .on_press(CrashMessage::ReportIssue),
+                        )
+                        .push(
+                            Button::new(Text::new("Open report"))
+                                .on_press(CrashMessage::OpenReport),
+                        )
+                        .push(Space::with_width(Length::Fill))
+                        .push(Button::new(Text::new("Close")).on_press(CrashMessage::Close)),
+                ),
+        )
+        .padding(20)
+        .into()
+    }
+}
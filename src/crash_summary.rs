@@ -0,0 +1,91 @@
+//! Summaries of games that exited abnormally, formatted for an issue in the repository the build
+//! came from, so reporting a crash doesn't mean digging through logfiles and plugin folders first.
+
+use crate::crash::url_encode;
+use crate::instance::Instance;
+use crate::stats::Run;
+use std::fmt::Write;
+use std::fs;
+
+/// How much of the game's error output ends up in the summary
+const STDERR_TAIL_LINES: usize = 30;
+/// GitHub rejects longer URLs, so the error output gets cut down to fit
+const MAX_URL_LENGTH: usize = 8000;
+
+/// The plugins in the instance's config dir, which is where the game loads them from.
+fn plugins(instance: &Instance) -> Vec<String> {
+    let Some(dir) = instance.config_dir().map(|d| d.join("plugins")) else {
+        return vec![];
+    };
+    let mut plugins: Vec<String> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    plugins.sort();
+    plugins
+}
+
+/// Describes the crash in Markdown, with the last `stderr_lines` lines of the error output.
+fn render(instance: &Instance, run: &Run, stderr: &str, stderr_lines: usize) -> String {
+    let build = instance
+        .provenance
+        .commit
+        .clone()
+        .unwrap_or_else(|| instance.version.clone());
+    let mut summary = String::new();
+    // Writing to a String can't fail
+    let _ = writeln!(summary, "**Build:** {} ({})", build, instance.source.r#type);
+    let _ = writeln!(
+        summary,
+        "**OS:** {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let _ = writeln!(
+        summary,
+        "**Ran for:** {}s, then {}",
+        run.duration,
+        run.status()
+    );
+    let plugins = plugins(instance);
+    if plugins.is_empty() {
+        let _ = writeln!(summary, "**Plugins:** none");
+    } else {
+        let _ = writeln!(summary, "**Plugins:** {}", plugins.join(", "));
+    }
+    let lines: Vec<&str> = stderr.lines().collect();
+    let tail = &lines[lines.len().saturating_sub(stderr_lines)..];
+    if !tail.is_empty() {
+        let _ = write!(
+            summary,
+            "\n**Error output:**\n```\n{}\n```\n",
+            tail.join("\n")
+        );
+    }
+    summary
+}
+
+pub fn summary(instance: &Instance, run: &Run, stderr: &str) -> String {
+    render(instance, run, stderr, STDERR_TAIL_LINES)
+}
+
+/// A new issue in the repository the build came from, e.g. a fork's, filled in with the summary.
+pub fn issue_url(instance: &Instance, run: &Run, stderr: &str) -> String {
+    let issues_url = format!("https://github.com/{}/issues/new", instance.source.repo());
+    let title = url_encode(&format!("Crash: {}", run.status()));
+    let url = |stderr_lines| {
+        let body = format!(
+            "**What were you doing when it crashed?**\n\n\n{}",
+            render(instance, run, stderr, stderr_lines)
+        );
+        format!("{issues_url}?title={title}&body={}", url_encode(&body))
+    };
+    (1..=STDERR_TAIL_LINES)
+        .rev()
+        .map(url)
+        .find(|url| url.len() <= MAX_URL_LENGTH)
+        .unwrap_or_else(|| url(0))
+}
@@ -0,0 +1,232 @@
+//! Application menu entries for AppImage instances on Linux, following the XDG desktop entry spec,
+//! and desktop shortcuts for instances on all platforms.
+
+use crate::instance::{Instance, InstanceType};
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// XDG_DATA_HOME, usually ~/.local/share
+fn data_home() -> Option<PathBuf> {
+    Some(platform_dirs::AppDirs::new(None, false)?.data_dir)
+}
+
+/// Desktop entries are identified by their file name, so it has to be unique per instance.
+fn file_stem(instance_name: &str) -> String {
+    let name: String = instance_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("eslauncher2-{name}")
+}
+
+fn entry_path(instance_name: &str) -> Option<PathBuf> {
+    Some(
+        data_home()?
+            .join("applications")
+            .join(format!("{}.desktop", file_stem(instance_name))),
+    )
+}
+
+pub(crate) fn icon_path(instance_name: &str) -> Option<PathBuf> {
+    Some(
+        data_home()?
+            .join("icons")
+            .join(format!("{}.png", file_stem(instance_name))),
+    )
+}
+
+fn is_integrated(instance: &Instance) -> bool {
+    cfg!(target_os = "linux") && instance.instance_type == InstanceType::AppImage
+}
+
+/// Quotes an argument of the Exec key, if needed.
+fn quote(arg: &str) -> String {
+    // Field codes are expanded even inside quotes
+    if !arg.contains(|c: char| c.is_whitespace() || "\"'\\`$;&|<>()*?#~".contains(c)) {
+        return arg.replace('%', "%%");
+    }
+    let mut quoted = String::from('"');
+    for c in arg.chars() {
+        match c {
+            '"' | '`' | '$' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '%' => quoted.push_str("%%"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The executable and arguments that launch the game like the launcher does.
+pub(crate) fn command_line(instance: &Instance) -> Vec<String> {
+    let mut args = vec![instance.executable.to_string_lossy().to_string()];
+    args.extend(instance.launch_options.args());
+    args.extend(instance.launch_args.split_whitespace().map(String::from));
+    if instance.debug_by_default {
+        args.push("-d".into());
+    }
+    if let Some(config_dir) = instance.config_arg() {
+        args.push("--config".into());
+        args.push(config_dir.to_string_lossy().to_string());
+    }
+    args
+}
+
+fn entry(instance: &Instance) -> String {
+    let exec: Vec<String> = command_line(instance).iter().map(|a| quote(a)).collect();
+    let icon = icon_path(&instance.name)
+        .filter(|p| p.exists())
+        .map_or_else(|| "endless-sky".into(), |p| p.to_string_lossy().to_string());
+
+    format!(
+        "[Desktop Entry]\n\
+         Name=Endless Sky ({})\n\
+         Comment=Installed by ESLauncher2\n\
+         Exec={}\n\
+         Icon={}\n\
+         Terminal=false\n\
+         Type=Application\n\
+         Categories=Game;\n",
+        instance.name.replace('\n', " "),
+        exec.join(" "),
+        icon
+    )
+}
+
+/// Creates or refreshes the entry of an AppImage instance, so the game shows up in the application menu.
+pub fn write(instance: &Instance) -> Result<()> {
+    if !is_integrated(instance) {
+        return Ok(());
+    }
+    let path = entry_path(&instance.name).ok_or_else(|| anyhow!("Failed to get XDG data dir"))?;
+    let entry = entry(instance);
+    if fs::read_to_string(&path).is_ok_and(|existing| existing == entry) {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, entry)?;
+    debug!("Wrote desktop entry {}", path.to_string_lossy());
+    Ok(())
+}
+
+/// Copies the icon out of the AppImage, which can extract parts of itself.
+pub fn extract_icon(instance: &Instance) -> Result<()> {
+    if !is_integrated(instance) {
+        return Ok(());
+    }
+    let target = icon_path(&instance.name).ok_or_else(|| anyhow!("Failed to get XDG data dir"))?;
+    let work_dir = std::env::temp_dir().join(file_stem(&instance.name));
+    if work_dir.exists() {
+        fs::remove_dir_all(&work_dir)?;
+    }
+    fs::create_dir_all(&work_dir)?;
+
+    let result = (|| {
+        let extract = |pattern: &Path| -> Result<PathBuf> {
+            let status = Command::new(&instance.executable)
+                .arg("--appimage-extract")
+                .arg(pattern)
+                .current_dir(&work_dir)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()?;
+            if !status.success() {
+                return Err(anyhow!("Extracting {} failed", pattern.to_string_lossy()));
+            }
+            Ok(work_dir.join("squashfs-root").join(pattern))
+        };
+        // .DirIcon is usually a symlink to the actual icon, which has to be extracted separately
+        let mut icon = extract(Path::new(".DirIcon"))?;
+        if let Ok(link) = fs::read_link(&icon) {
+            icon = extract(&link)?;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&icon, &target)?;
+        Ok(())
+    })();
+    if let Err(e) = fs::remove_dir_all(&work_dir) {
+        warn!("Failed to remove {}: {}", work_dir.to_string_lossy(), e);
+    }
+    result
+}
+
+/// Removes the entry and icon of an instance, e.g. when it gets deleted.
+pub fn remove(instance_name: &str) {
+    if !cfg!(target_os = "linux") {
+        return;
+    }
+    for path in [entry_path(instance_name), icon_path(instance_name)]
+        .into_iter()
+        .flatten()
+        .filter(|p| p.exists())
+    {
+        if let Err(e) = fs::remove_file(&path) {
+            error!("Failed to remove {}: {}", path.to_string_lossy(), e);
+        }
+    }
+}
+
+/// Moves the icon over to the new name and removes the old entry, which [`write`] recreates.
+pub fn rename(old_name: &str, new_name: &str) {
+    if !cfg!(target_os = "linux") {
+        return;
+    }
+    if let (Some(from), Some(to)) = (icon_path(old_name), icon_path(new_name)) {
+        if from.exists() {
+            if let Err(e) = fs::rename(&from, &to) {
+                error!("Failed to move {}: {}", from.to_string_lossy(), e);
+            }
+        }
+    }
+    remove(old_name);
+}
+
+/// Creates a launcher for the instance on the user's desktop, in the format native to the platform.
+pub fn create_shortcut(instance: &Instance) -> Result<PathBuf> {
+    let desktop = platform_dirs::UserDirs::new()
+        .map(|dirs| dirs.desktop_dir)
+        .ok_or_else(|| anyhow!("Failed to get desktop dir"))?;
+    let name = format!("Endless Sky ({})", instance.name.replace('\n', " "));
+    let args = command_line(instance);
+    let (path, contents) = if cfg!(windows) {
+        let args: Vec<String> = args.iter().map(|a| format!("\"{a}\"")).collect();
+        (
+            desktop.join(format!("{name}.cmd")),
+            format!("@echo off\r\nstart \"\" {}\r\n", args.join(" ")),
+        )
+    } else if cfg!(target_os = "macos") {
+        let args: Vec<String> = args
+            .iter()
+            .map(|a| format!("'{}'", a.replace('\'', "'\\''")))
+            .collect();
+        (
+            desktop.join(format!("{name}.command")),
+            format!("#!/bin/sh\nexec {}\n", args.join(" ")),
+        )
+    } else {
+        (
+            desktop.join(format!("{}.desktop", file_stem(&instance.name))),
+            entry(instance),
+        )
+    };
+    fs::create_dir_all(&desktop)?;
+    fs::write(&path, contents)?;
+    #[cfg(unix)]
+    {
+        // Desktop environments only run launchers that are executable
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    }
+    info!("Created shortcut {}", path.to_string_lossy());
+    Ok(path)
+}
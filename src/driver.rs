@@ -0,0 +1,316 @@
+//! Drives the launcher without a window, for end-to-end tests of flows like installing, updating
+//! and deleting instances in a temporary data dir. Messages go through `ESLauncher::update` like
+//! they do in the GUI, and the commands it returns are run until nothing is left to do.
+
+// Only used by tests, which don't need all of it
+#![allow(dead_code)]
+
+use crate::instance::Instance;
+use crate::{
+    take_messages, ESLauncher, Message, StartupFlags, DATA_DIR_OVERRIDE, GAME_DATA_DIR_OVERRIDE,
+};
+use anyhow::Result;
+use futures::StreamExt;
+use iced::{Application, Command};
+use iced_runtime::command::Action;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+/// Commands running longer are given up on, e.g. ones waiting for the undo window to pass
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The data dir and the message queue belong to the process, so only one driver runs at a time,
+/// even though the test runner runs tests in parallel.
+static ACTIVE: Mutex<()> = Mutex::new(());
+
+/// Shared by all drivers in the process, since the data dir is fixed once it's been looked up.
+/// It's emptied whenever a driver starts.
+fn data_dir() -> &'static Path {
+    static DIR: OnceLock<PathBuf> = OnceLock::new();
+    DIR.get_or_init(|| {
+        std::env::temp_dir().join(format!("eslauncher2-driver-{}", std::process::id()))
+    })
+}
+
+pub struct Driver {
+    app: ESLauncher,
+    runtime: Runtime,
+    queue: VecDeque<Message>,
+    // Declared last, so it's released once everything else is gone
+    _active: MutexGuard<'static, ()>,
+}
+
+impl Driver {
+    /// Starts a launcher with an empty data dir, waiting for the one running before to finish.
+    pub fn new() -> Result<Self> {
+        // A test failing with a driver doesn't keep the others from using one
+        let active = ACTIVE.lock().unwrap_or_else(PoisonError::into_inner);
+        let dir = data_dir();
+        let configured = DATA_DIR_OVERRIDE.get_or_init(|| Some(dir.to_path_buf()));
+        if configured.as_deref() != Some(dir) {
+            return Err(anyhow!(
+                "The data dir was set to {:?} before the driver could set it",
+                configured
+            ));
+        }
+        // The game's data goes in there too, instead of the player's own
+        let game_dir = dir.join("endless-sky");
+        if GAME_DATA_DIR_OVERRIDE.get_or_init(|| game_dir.clone()) != &game_dir {
+            return Err(anyhow!(
+                "The game data dir was set before the driver could set it"
+            ));
+        }
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        fs::create_dir_all(dir)?;
+        // Left over from the driver before
+        take_messages();
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let _guard = runtime.enter();
+        // The startup work goes online, so it's left out
+        let (app, _startup) = ESLauncher::new(StartupFlags {
+            quiet: true,
+            headless: true,
+            ..StartupFlags::default()
+        });
+        Ok(Self {
+            app,
+            runtime,
+            queue: VecDeque::new(),
+            _active: active,
+        })
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        data_dir()
+    }
+
+    /// Handles `message` and everything that follows from it.
+    pub fn send(&mut self, message: Message) {
+        self.queue.push_back(message);
+        self.settle();
+    }
+
+    /// Handles queued messages and runs the commands they return, until there are none left.
+    pub fn settle(&mut self) {
+        loop {
+            self.queue.extend(take_messages());
+            let Some(message) = self.queue.pop_front() else {
+                return;
+            };
+            let command = self.app.update(message);
+            self.run(command);
+        }
+    }
+
+    fn run(&mut self, command: Command<Message>) {
+        for action in command.actions() {
+            // The timeouts need the runtime to be created, so they're created in there
+            let finished = match action {
+                Action::Future(future) => self
+                    .runtime
+                    .block_on(async { tokio::time::timeout(COMMAND_TIMEOUT, future).await })
+                    .map(|message| vec![message]),
+                Action::Stream(stream) => self.runtime.block_on(async {
+                    tokio::time::timeout(COMMAND_TIMEOUT, stream.collect::<Vec<_>>()).await
+                }),
+                // Windows, the clipboard and the like only exist in the GUI
+                _ => continue,
+            };
+            match finished {
+                Ok(messages) => self.queue.extend(messages),
+                Err(_) => warn!("Gave up on a command after {:?}", COMMAND_TIMEOUT),
+            }
+        }
+    }
+
+    pub fn instances(&self) -> &BTreeMap<String, Instance> {
+        &self.app.instances_frame.instances
+    }
+
+    pub fn instance(&self, name: &str) -> Option<&Instance> {
+        self.instances().get(name)
+    }
+}
+
+impl Drop for Driver {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(data_dir()) {
+            warn!("Failed to remove {}: {}", data_dir().to_string_lossy(), e);
+        }
+    }
+}
+
+/// An empty folder for the files of a test that doesn't need a whole launcher, removed once
+/// it's dropped. `name` has to be unique among the tests.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("eslauncher2-{}-{}", name, std::process::id()));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::install_frame::InstallFrameMessage;
+    use crate::instance::{InstanceMessage, InstanceState};
+    use crate::settings_frame::SettingsMessage;
+
+    /// Enough of an ELF header for the build to be detected as a Linux one
+    const FAKE_EXECUTABLE: &[u8] = b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0";
+
+    /// A Linux build in a folder named `name` outside of the instances folder, to be imported.
+    fn fake_build(driver: &Driver, name: &str) -> PathBuf {
+        let builds = driver.data_dir().join("builds");
+        let build = builds.join(name);
+        fs::create_dir_all(build.join("data")).unwrap();
+        fs::write(build.join("endless-sky"), FAKE_EXECUTABLE).unwrap();
+        fs::write(build.join("data").join("ships.txt"), "ship \"Shuttle\"\n").unwrap();
+        builds
+    }
+
+    fn import(driver: &mut Driver, name: &str) -> Instance {
+        let builds = fake_build(driver, name);
+        driver.send(Message::InstallFrameMessage(
+            InstallFrameMessage::ImportDirChanged(builds.to_string_lossy().to_string()),
+        ));
+        driver.send(Message::InstallFrameMessage(InstallFrameMessage::Import));
+        driver.instance(name).expect("imported").clone()
+    }
+
+    #[test]
+    fn game_data_stays_in_the_data_dir() {
+        let driver = Driver::new().unwrap();
+        let game_dir = crate::get_game_data_dir().unwrap();
+        assert!(game_dir.starts_with(driver.data_dir()));
+        let espim_dir = crate::plugins_frame::espim_dir().unwrap();
+        assert!(espim_dir.starts_with(driver.data_dir()));
+    }
+
+    #[test]
+    fn import_copies_the_build_into_an_instance() {
+        let mut driver = Driver::new().unwrap();
+        let instance = import(&mut driver, "Imported");
+
+        assert!(instance.state.is_ready());
+        assert!(instance.path.starts_with(driver.data_dir()));
+        assert!(instance.executable.is_file());
+        assert!(instance.path.join("data").join("ships.txt").is_file());
+    }
+
+    #[test]
+    fn import_leaves_existing_folders_alone() {
+        let mut driver = Driver::new().unwrap();
+        let builds = driver.data_dir().join("builds");
+        fs::create_dir_all(&builds).unwrap();
+        // An AppImage's type marker follows the ELF header at offset 8
+        fs::write(
+            builds.join("Taken.AppImage"),
+            b"\x7fELF\x02\x01\x01\0AI\x02",
+        )
+        .unwrap();
+        fs::write(builds.join("Free.AppImage"), b"\x7fELF\x02\x01\x01\0AI\x02").unwrap();
+        let taken = driver.data_dir().join("instances").join("Taken");
+        fs::create_dir_all(&taken).unwrap();
+        fs::write(taken.join("endless-sky.AppImage"), "someone else's").unwrap();
+
+        driver.send(Message::InstallFrameMessage(
+            InstallFrameMessage::ImportDirChanged(builds.to_string_lossy().to_string()),
+        ));
+        driver.send(Message::InstallFrameMessage(InstallFrameMessage::Import));
+
+        assert!(driver.instance("Taken").is_none());
+        assert_eq!(
+            fs::read_to_string(taken.join("endless-sky.AppImage")).unwrap(),
+            "someone else's"
+        );
+        assert!(driver
+            .instance("Free")
+            .expect("imported")
+            .executable
+            .is_file());
+    }
+
+    #[test]
+    fn rename_moves_the_instance_folder() {
+        let mut driver = Driver::new().unwrap();
+        let old_path = import(&mut driver, "Before").path;
+
+        for message in [
+            InstanceMessage::StartRename,
+            InstanceMessage::RenameChanged("After".into()),
+            InstanceMessage::ConfirmRename,
+        ] {
+            driver.send(Message::InstanceMessage("Before".into(), message));
+        }
+
+        assert!(driver.instance("Before").is_none());
+        let renamed = driver.instance("After").expect("renamed");
+        assert_eq!(renamed.path, old_path.with_file_name("After"));
+        assert!(renamed.executable.is_file());
+        assert!(!old_path.exists());
+    }
+
+    #[test]
+    fn failed_update_keeps_the_build() {
+        let mut driver = Driver::new().unwrap();
+        let before = import(&mut driver, "Updated");
+        // Nothing listens there, so every request fails right away instead of going online
+        driver.send(Message::SettingsMessage(SettingsMessage::ProxyChanged(
+            "http://127.0.0.1:9".into(),
+        )));
+        driver.send(Message::SettingsMessage(SettingsMessage::ProxySubmitted));
+
+        driver.send(Message::InstanceMessage(
+            "Updated".into(),
+            InstanceMessage::Update,
+        ));
+
+        let after = driver.instance("Updated").expect("still there");
+        assert!(matches!(after.state, InstanceState::Failed(_)));
+        assert_eq!(after.version, before.version);
+        assert!(after.executable.is_file());
+        assert!(after.path.join("data").join("ships.txt").is_file());
+    }
+
+    #[test]
+    fn delete_purges_the_instance_after_the_undo_window() {
+        let mut driver = Driver::new().unwrap();
+        let path = import(&mut driver, "Deleted").path;
+
+        driver.send(Message::InstanceMessage(
+            "Deleted".into(),
+            InstanceMessage::Delete,
+        ));
+
+        assert!(driver.instance("Deleted").is_none());
+        assert!(!path.exists());
+        let trash = driver.data_dir().join("trash");
+        assert!(fs::read_dir(trash).map_or(true, |mut d| d.next().is_none()));
+    }
+}
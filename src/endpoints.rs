@@ -0,0 +1,174 @@
+//! GitHub serves release assets and source archives from more than one endpoint, and which is
+//! fastest depends on the network. Downloads remember how fast their endpoint was, and later ones
+//! try each endpoint once and then prefer the fastest, unless one is picked in the settings.
+
+use crate::get_data_dir;
+use crate::github::ReleaseAsset;
+use crate::settings::LocalSettings;
+use crate::stats::format_size;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Serializes read-modify-write cycles of endpoints.json, since downloads run in parallel
+static LOCK: Mutex<()> = Mutex::new(());
+/// Smaller downloads are over too quickly to tell how fast an endpoint is
+const MIN_MEASURED_SIZE: u64 = 1024 * 1024;
+/// Weight of the latest download in an endpoint's speed
+const SMOOTHING: f64 = 0.3;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Endpoint {
+    /// Whichever was fastest so far
+    #[default]
+    Automatic,
+    /// Links on github.com, which redirect to GitHub's CDN
+    GitHub,
+    /// GitHub's API, which redirects to a signed link on the CDN
+    Api,
+    /// codeload.github.com, which only serves source archives
+    Codeload,
+}
+
+impl Endpoint {
+    pub const ALL: [Self; 4] = [Self::Automatic, Self::GitHub, Self::Api, Self::Codeload];
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Automatic => write!(f, "Automatic"),
+            Self::GitHub => write!(f, "github.com"),
+            Self::Api => write!(f, "GitHub API"),
+            Self::Codeload => write!(f, "codeload.github.com"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Measurement {
+    /// Smoothed over the downloads so far, failed ones counting as 0
+    bytes_per_second: f64,
+    downloads: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Measurements {
+    endpoints: BTreeMap<Endpoint, Measurement>,
+}
+
+impl Measurements {
+    fn path() -> Option<PathBuf> {
+        Some(get_data_dir()?.join("endpoints.json"))
+    }
+
+    fn load() -> Self {
+        Self::path()
+            .filter(|p| p.exists())
+            .and_then(|p| File::open(p).ok())
+            .and_then(|f| match serde_json::from_reader(f) {
+                Ok(measurements) => Some(measurements),
+                Err(e) => {
+                    warn!("Failed to deserialize endpoints.json: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = Self::path().ok_or_else(|| anyhow!("Failed to get app save dir"))?;
+        serde_json::to_writer_pretty(File::create(path)?, self)?;
+        Ok(())
+    }
+
+    fn add(endpoint: Endpoint, bytes_per_second: f64) {
+        let _guard = LOCK.lock();
+        let mut measurements = Self::load();
+        let measurement = measurements.endpoints.entry(endpoint).or_default();
+        measurement.bytes_per_second = if measurement.downloads == 0 {
+            bytes_per_second
+        } else {
+            SMOOTHING * bytes_per_second + (1.0 - SMOOTHING) * measurement.bytes_per_second
+        };
+        measurement.downloads += 1;
+        if let Err(e) = measurements.save() {
+            warn!("Failed to save endpoints.json: {:#}", e);
+        }
+    }
+}
+
+/// Where a release asset can be downloaded from.
+pub fn release_asset(repo_slug: &str, asset: &ReleaseAsset) -> Vec<(Endpoint, String)> {
+    vec![
+        (Endpoint::GitHub, asset.browser_download_url.clone()),
+        (
+            Endpoint::Api,
+            format!(
+                "https://api.github.com/repos/{repo_slug}/releases/assets/{}",
+                asset.id
+            ),
+        ),
+    ]
+}
+
+/// Where a `.tar.gz` of a branch can be downloaded from.
+pub fn source_archive(repo_slug: &str, branch: &str) -> Vec<(Endpoint, String)> {
+    vec![
+        (
+            Endpoint::GitHub,
+            format!("https://github.com/{repo_slug}/archive/refs/heads/{branch}.tar.gz"),
+        ),
+        (
+            Endpoint::Codeload,
+            format!("https://codeload.github.com/{repo_slug}/tar.gz/refs/heads/{branch}"),
+        ),
+    ]
+}
+
+/// The candidates in the order to try them: the one picked in the settings, then ones that
+/// weren't tried yet, then the fastest.
+pub fn order(mut candidates: Vec<(Endpoint, String)>) -> Vec<(Endpoint, String)> {
+    let preferred = LocalSettings::load().http.download_endpoint;
+    let measurements = Measurements::load();
+    // The sort is stable, so untried endpoints keep their order
+    candidates.sort_by(|(a, _), (b, _)| {
+        let speed = |endpoint| {
+            measurements
+                .endpoints
+                .get(endpoint)
+                .map_or(f64::INFINITY, |m| m.bytes_per_second)
+        };
+        (*b == preferred)
+            .cmp(&(*a == preferred))
+            .then(speed(b).total_cmp(&speed(a)))
+    });
+    candidates
+}
+
+/// Notes how fast a finished download from `endpoint` was.
+pub fn record(endpoint: Endpoint, bytes: u64, elapsed: Duration) {
+    if bytes < MIN_MEASURED_SIZE {
+        return;
+    }
+    let bytes_per_second = bytes as f64 / elapsed.as_secs_f64().max(0.001);
+    info!(
+        "Downloaded {} from {} at {}/s",
+        format_size(bytes),
+        endpoint,
+        format_size(bytes_per_second as u64)
+    );
+    Measurements::add(endpoint, bytes_per_second);
+}
+
+/// Makes `endpoint` less likely to be tried first after a download from it failed.
+pub fn record_failure(endpoint: Endpoint) {
+    Measurements::add(endpoint, 0.0);
+}
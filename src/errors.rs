@@ -0,0 +1,188 @@
+//! Sorts the errors of failed operations into kinds, so the GUI can suggest what to do about them.
+
+use crate::libraries::MissingLibraries;
+use std::fmt;
+use std::io;
+
+/// Ordered by priority, e.g. a full disk is the actual problem when extracting an archive fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorKind {
+    DiskFull,
+    PermissionDenied,
+    ChecksumMismatch,
+    ExtractionFailed,
+    MissingRuntime,
+    Network,
+    Other,
+}
+
+impl ErrorKind {
+    pub fn of(error: &anyhow::Error) -> Self {
+        let marked = if error.downcast_ref::<ChecksumMismatch>().is_some() {
+            Self::ChecksumMismatch
+        } else if error.downcast_ref::<ExtractionFailed>().is_some() {
+            Self::ExtractionFailed
+        } else if error.downcast_ref::<MissingRuntime>().is_some() {
+            Self::MissingRuntime
+        } else {
+            Self::Other
+        };
+        error
+            .chain()
+            .map(Self::of_cause)
+            .chain([marked])
+            .min()
+            .unwrap_or(Self::Other)
+    }
+
+    fn of_cause(cause: &(dyn std::error::Error + 'static)) -> Self {
+        if let Some(e) = cause.downcast_ref::<io::Error>() {
+            return Self::of_io(e);
+        }
+        if let Some(e) = cause.downcast_ref::<ureq::Error>() {
+            return match e {
+                ureq::Error::Transport(_) => Self::Network,
+                // Server errors tend to go away, unlike e.g. a build that doesn't exist
+                ureq::Error::Status(status, _) if *status >= 500 => Self::Network,
+                ureq::Error::Status(..) => Self::Other,
+            };
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            return if e.status().is_some_and(|s| s.is_client_error()) {
+                Self::Other
+            } else {
+                Self::Network
+            };
+        }
+        if cause.is::<zip::result::ZipError>() {
+            return Self::ExtractionFailed;
+        }
+        Self::Other
+    }
+
+    fn of_io(error: &io::Error) -> Self {
+        // ENOSPC on unix, ERROR_HANDLE_DISK_FULL and ERROR_DISK_FULL on Windows
+        let disk_full_codes: &[i32] = if cfg!(windows) { &[39, 112] } else { &[28] };
+        if error
+            .raw_os_error()
+            .is_some_and(|code| disk_full_codes.contains(&code))
+        {
+            return Self::DiskFull;
+        }
+        match error.kind() {
+            io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => Self::DiskFull,
+            io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => {
+                Self::PermissionDenied
+            }
+            io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::HostUnreachable
+            | io::ErrorKind::NetworkUnreachable
+            | io::ErrorKind::NetworkDown
+            | io::ErrorKind::TimedOut => Self::Network,
+            _ => Self::Other,
+        }
+    }
+
+    /// What the user can do about it, if anything specific.
+    pub fn hint(self) -> Option<&'static str> {
+        match self {
+            Self::DiskFull => Some(
+                "The disk is full. Free up some space, e.g. by deleting instances or kept versions you don't need anymore, then try again.",
+            ),
+            Self::PermissionDenied => Some(
+                "The launcher isn't allowed to change these files. Check the permissions of the instances folder, and whether another program has the files open.",
+            ),
+            Self::ChecksumMismatch => Some(
+                "The download got corrupted on the way. Trying again downloads it anew.",
+            ),
+            Self::ExtractionFailed => Some(
+                "The downloaded archive seems to be broken. Trying again downloads it anew; if that fails too, the build itself may be broken.",
+            ),
+            Self::MissingRuntime => Some(
+                "The game needs the Microsoft Visual C++ runtime, which isn't installed. Install it from the notification, then play again.",
+            ),
+            Self::Network => Some(
+                "Check your internet connection and the proxy in the settings, then try again.",
+            ),
+            Self::Other => None,
+        }
+    }
+}
+
+/// What the user can do about an error, from the error itself where it knows better than its kind.
+pub fn hint(error: &anyhow::Error) -> Option<String> {
+    if let Some(missing) = error.downcast_ref::<MissingLibraries>() {
+        return Some(missing.hint());
+    }
+    ErrorKind::of(error).hint().map(String::from)
+}
+
+/// Marks an error as coming from data that didn't match its checksum.
+#[derive(Debug)]
+pub struct ChecksumMismatch;
+
+impl fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Downloaded data didn't match its checksum")
+    }
+}
+
+/// Marks an error as coming from an archive that couldn't be unpacked.
+#[derive(Debug)]
+pub struct ExtractionFailed;
+
+impl fmt::Display for ExtractionFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Failed to extract the archive")
+    }
+}
+
+/// Marks a Windows game as having exited because the Visual C++ runtime is missing.
+#[derive(Debug)]
+pub struct MissingRuntime;
+
+impl fmt::Display for MissingRuntime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("The Microsoft Visual C++ runtime is missing")
+    }
+}
+
+impl std::error::Error for MissingRuntime {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn io_error(kind: io::ErrorKind) -> anyhow::Error {
+        io::Error::from(kind).into()
+    }
+
+    #[test]
+    fn of_looks_through_the_whole_chain() {
+        let error = io_error(io::ErrorKind::PermissionDenied).context("Failed to copy the build");
+        assert_eq!(ErrorKind::of(&error), ErrorKind::PermissionDenied);
+        let error = io_error(io::ErrorKind::TimedOut).context("Failed to download the build");
+        assert_eq!(ErrorKind::of(&error), ErrorKind::Network);
+        assert_eq!(ErrorKind::of(&anyhow!("Something else")), ErrorKind::Other);
+    }
+
+    #[test]
+    fn of_prefers_the_actual_problem_over_the_marker() {
+        let error = io_error(io::ErrorKind::StorageFull).context(ExtractionFailed);
+        assert_eq!(ErrorKind::of(&error), ErrorKind::DiskFull);
+        let error = io_error(io::ErrorKind::InvalidData).context(ExtractionFailed);
+        assert_eq!(ErrorKind::of(&error), ErrorKind::ExtractionFailed);
+        let error = anyhow!("Hashes differ").context(ChecksumMismatch);
+        assert_eq!(ErrorKind::of(&error), ErrorKind::ChecksumMismatch);
+    }
+
+    #[test]
+    fn of_recognizes_os_error_codes() {
+        let code = if cfg!(windows) { 112 } else { 28 };
+        let error = anyhow::Error::from(io::Error::from_raw_os_error(code));
+        assert_eq!(ErrorKind::of(&error), ErrorKind::DiskFull);
+    }
+}
@@ -0,0 +1,270 @@
+//! The stdout/stderr logfiles written for every game session, two per launch.
+
+use crate::stats::format_size;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// A logfile that keeps the beginning and the end of a session's output once it grows past a
+/// limit and leaves out the middle, so a game spamming its output can't fill the disk.
+pub struct CappedLog {
+    file: File,
+    path: PathBuf,
+    /// In bytes, 0 means unlimited
+    limit: u64,
+    written: u64,
+    /// The output past the beginning goes into two files next to the logfile, taking turns.
+    /// Once the current one is full the other one is emptied and written to next, so the end
+    /// is kept on disk instead of in memory. Both are appended once the session ends.
+    tails: [Option<File>; 2],
+    tail_sizes: [u64; 2],
+    current: usize,
+    /// Bytes of output left out so far
+    skipped: u64,
+}
+
+impl CappedLog {
+    /// `path` is where `file` is, the tail files are put next to it.
+    pub fn new(file: File, path: &Path, limit_mib: u64) -> Self {
+        Self {
+            file,
+            path: path.to_path_buf(),
+            limit: limit_mib * 1024 * 1024,
+            written: 0,
+            tails: [None, None],
+            tail_sizes: [0, 0],
+            current: 0,
+            skipped: 0,
+        }
+    }
+
+    fn tail_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".tail{index}"));
+        self.path.with_file_name(name)
+    }
+
+    pub fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        let len = line.len() as u64;
+        let in_tail = self.tails.iter().any(Option::is_some);
+        // Half of the limit goes to the beginning, half to the end
+        if self.limit == 0 || (!in_tail && self.written + len <= self.limit / 2) {
+            self.written += len;
+            return self.file.write_all(line);
+        }
+        // Each tail file holds a quarter, so between a quarter and half of the limit is kept
+        if self.tail_sizes[self.current] > 0 && self.tail_sizes[self.current] + len > self.limit / 4
+        {
+            self.current = 1 - self.current;
+            self.skipped += self.tail_sizes[self.current];
+            self.tail_sizes[self.current] = 0;
+            self.tails[self.current] = None;
+        }
+        let current = self.current;
+        if self.tails[current].is_none() {
+            self.tails[current] = Some(File::create(self.tail_path(current))?);
+        }
+        if let Some(tail) = &mut self.tails[current] {
+            tail.write_all(line)?;
+        }
+        self.tail_sizes[current] += len;
+        Ok(())
+    }
+
+    /// Writes the end of the output, after a note on how much was left out before it.
+    pub fn finish(mut self) -> io::Result<()> {
+        if self.skipped > 0 {
+            writeln!(
+                self.file,
+                "\n[ESLauncher2 left out {} of output here to stay within the game log size limit]\n",
+                format_size(self.skipped)
+            )?;
+        }
+        // The older one first
+        for index in [1 - self.current, self.current] {
+            if self.tails[index].take().is_some() {
+                let path = self.tail_path(index);
+                io::copy(&mut File::open(&path)?, &mut self.file)?;
+                fs::remove_file(path)?;
+            }
+        }
+        self.file.flush()
+    }
+}
+
+/// Groups the logfiles in `dir` by session, oldest first. Tail files a crashed launcher left
+/// behind, see [`CappedLog`], belong to their session too.
+/// Sessions are named by their start time, which sorts chronologically.
+fn sessions(dir: &Path) -> Result<BTreeMap<String, Vec<PathBuf>>> {
+    let mut sessions: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if let Some((stem, extension)) = name.split_once('.') {
+            let kind = extension.split('.').next();
+            if kind == Some("out") || kind == Some("err") {
+                let stem = stem.to_string();
+                sessions.entry(stem).or_default().push(path);
+            }
+        }
+    }
+    Ok(sessions)
+}
+
+fn is_older_than(path: &Path, max_age: Duration) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age > max_age)
+}
+
+/// Removes all but the `keep_sessions` newest sessions, and all sessions older than `keep_days`.
+/// 0 disables either limit.
+pub fn prune(dir: &Path, keep_sessions: usize, keep_days: u64) -> Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+    let sessions = sessions(dir)?;
+    let excess = if keep_sessions == 0 {
+        0
+    } else {
+        sessions.len().saturating_sub(keep_sessions)
+    };
+    let max_age = Duration::from_secs(keep_days * 24 * 60 * 60);
+    let mut removed = 0;
+    for (i, files) in sessions.values().enumerate() {
+        let expired = keep_days > 0 && files.iter().all(|f| is_older_than(f, max_age));
+        if i < excess || expired {
+            for file in files {
+                fs::remove_file(file)?;
+            }
+            removed += 1;
+        }
+    }
+    if removed > 0 {
+        debug!(
+            "Removed {} old game sessions from {}",
+            removed,
+            dir.to_string_lossy()
+        );
+    }
+    Ok(())
+}
+
+/// Removes all logfiles in `dir`.
+pub fn clear(dir: &Path) -> Result<()> {
+    if dir.exists() {
+        for files in sessions(dir)?.values() {
+            for file in files {
+                fs::remove_file(file)?;
+            }
+        }
+        info!("Cleared logs in {}", dir.to_string_lossy());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::TempDir;
+
+    /// 1 KiB, numbered so it can be told apart
+    fn line(number: usize) -> Vec<u8> {
+        format!("{number:01023}\n").into_bytes()
+    }
+
+    #[test]
+    fn capped_log_keeps_the_beginning_and_the_end() {
+        let dir = TempDir::new("capped-log");
+        let path = dir.path().join("session.out");
+        let mut log = CappedLog::new(File::create(&path).unwrap(), &path, 1);
+        for number in 0..2000 {
+            log.write_line(&line(number)).unwrap();
+        }
+        log.finish().unwrap();
+
+        let written = fs::read(&path).unwrap();
+        // Half a MiB of beginning, then the two newest quarters of the end, minus what's missing
+        // from the current one
+        let note = b"\n[ESLauncher2 left out 1.0 MiB of output here to stay within the game log size limit]\n\n";
+        let mut expected: Vec<u8> = (0..512).flat_map(line).collect();
+        expected.extend(note);
+        expected.extend((1536..2000).flat_map(line));
+        assert!(written == expected);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn capped_log_without_a_limit_keeps_everything() {
+        let dir = TempDir::new("uncapped-log");
+        let path = dir.path().join("session.err");
+        let mut log = CappedLog::new(File::create(&path).unwrap(), &path, 0);
+        for number in 0..2000 {
+            log.write_line(&line(number)).unwrap();
+        }
+        log.finish().unwrap();
+        assert!(fs::read(&path).unwrap() == (0..2000).flat_map(line).collect::<Vec<_>>());
+    }
+
+    fn session(dir: &Path, name: &str, age_days: u64) {
+        for extension in ["out", "err"] {
+            let file = File::create(dir.join(format!("{name}.{extension}"))).unwrap();
+            let age = Duration::from_secs(age_days * 24 * 60 * 60);
+            file.set_modified(SystemTime::now() - age).unwrap();
+        }
+    }
+
+    fn remaining(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn prune_keeps_the_newest_sessions() {
+        let dir = TempDir::new("prune-sessions");
+        session(dir.path(), "2024-01-01 10-00-00", 0);
+        session(dir.path(), "2024-01-02 10-00-00", 0);
+        session(dir.path(), "2024-01-03 10-00-00", 0);
+        // Left behind by a crash, so it goes with its session
+        File::create(dir.path().join("2024-01-01 10-00-00.out.tail0")).unwrap();
+        fs::write(dir.path().join("notes.txt"), "not a log").unwrap();
+
+        prune(dir.path(), 2, 0).unwrap();
+        assert_eq!(
+            remaining(dir.path()),
+            [
+                "2024-01-02 10-00-00.err",
+                "2024-01-02 10-00-00.out",
+                "2024-01-03 10-00-00.err",
+                "2024-01-03 10-00-00.out",
+                "notes.txt",
+            ]
+        );
+
+        prune(dir.path(), 0, 0).unwrap();
+        assert_eq!(remaining(dir.path()).len(), 5);
+    }
+
+    #[test]
+    fn prune_removes_old_sessions() {
+        let dir = TempDir::new("prune-days");
+        session(dir.path(), "2024-01-01 10-00-00", 10);
+        session(dir.path(), "2024-01-02 10-00-00", 1);
+
+        prune(dir.path(), 0, 7).unwrap();
+        assert_eq!(
+            remaining(dir.path()),
+            ["2024-01-02 10-00-00.err", "2024-01-02 10-00-00.out"]
+        );
+        assert!(prune(&dir.path().join("missing"), 1, 1).is_ok());
+    }
+}
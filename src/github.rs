@@ -1,17 +1,21 @@
-use crate::instance::Progress;
-use crate::send_progress_message;
+use crate::bandwidth::{self, Meter};
+use crate::endpoints::{self, Endpoint};
+use crate::http;
+use crate::job::{self, Progress};
+use crate::notifications::{notify, Level, Notification};
+use crate::recovery::PARTIAL_SUFFIX;
 use anyhow::Result;
-use progress_streams::ProgressReader;
+use futures::StreamExt;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
-use std::fs::File;
-use std::io::{copy, BufReader};
+use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::Arc;
-use std::thread;
-use std::time::Duration;
+use std::time::Instant;
 use time::OffsetDateTime;
+use tokio::io::AsyncWriteExt;
+
+/// The game's own repository, as `owner/repo`
+pub const GAME_REPO: &str = "endless-sky/endless-sky";
 
 #[derive(Deserialize, Debug)]
 pub struct Repo {
@@ -22,11 +26,39 @@ pub trait Artifact {
     fn name(&self) -> &str;
 
     fn expired(&self) -> bool;
+
+    /// Whether it's downloaded as a zip, whatever its name says
+    fn zipped(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Deserialize, Debug)]
 pub struct PR {
     pub head: PRHead,
+    /// "open" or "closed", merged PRs are closed too
+    pub state: String,
+    #[serde(default)]
+    pub merged: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PRState {
+    Open,
+    Merged,
+    Closed,
+}
+
+impl PR {
+    pub fn pr_state(&self) -> PRState {
+        if self.merged {
+            PRState::Merged
+        } else if self.state == "closed" {
+            PRState::Closed
+        } else {
+            PRState::Open
+        }
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -43,6 +75,17 @@ pub fn get_pr(id: u16) -> Result<PR> {
     ))
 }
 
+#[derive(Deserialize, Debug)]
+struct User {
+    login: String,
+}
+
+/// The account the GitHub token belongs to, which fails if GitHub doesn't accept the token.
+pub fn get_token_login() -> Result<String> {
+    let user: User = make_request("https://api.github.com/user")?;
+    Ok(user.login)
+}
+
 pub fn unblock_artifact_download(artifact_id: u32) -> String {
     format!("https://artifact-unblocker.mcofficer.workers.dev/artifact/{artifact_id}",)
 }
@@ -117,6 +160,8 @@ pub struct WorkflowRunArtifact {
     pub size_in_bytes: u32,
     name: String,
     expired: bool,
+    #[serde(default)]
+    pub created_at: Option<String>,
 }
 
 impl Artifact for WorkflowRunArtifact {
@@ -127,6 +172,10 @@ impl Artifact for WorkflowRunArtifact {
     fn expired(&self) -> bool {
         self.expired
     }
+
+    fn zipped(&self) -> bool {
+        true
+    }
 }
 
 pub fn get_workflow_run_artifacts(run_id: u64) -> Result<Vec<WorkflowRunArtifact>> {
@@ -160,17 +209,43 @@ pub fn get_git_ref(name: &str) -> Result<GitRef> {
 pub struct Release {
     pub id: i64,
     pub tag_name: String,
+    /// The release notes, in Markdown
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Commit {
+    sha: String,
 }
 
-pub fn get_release_by_tag(tag: &str) -> Result<Release> {
+/// The SHA of the commit `reference` (a tag, branch or SHA) points to.
+pub fn get_commit_sha(repo_slug: &str, reference: &str) -> Result<String> {
+    let commit: Commit = make_request(&format!(
+        "https://api.github.com/repos/{repo_slug}/commits/{reference}"
+    ))?;
+    Ok(commit.sha)
+}
+
+pub fn get_release_by_tag(repo_slug: &str, tag: &str) -> Result<Release> {
     make_request(&format!(
-        "https://api.github.com/repos/endless-sky/endless-sky/releases/tags/{tag}"
+        "https://api.github.com/repos/{repo_slug}/releases/tags/{tag}"
     ))
 }
 
+/// The tags of all releases, newest first.
+pub fn get_release_tags() -> Result<Vec<String>> {
+    let pages: Vec<Vec<Release>> = make_paginated_request(
+        "https://api.github.com/repos/endless-sky/endless-sky/releases?per_page=100",
+    )?;
+    let tags: Vec<String> = pages.into_iter().flatten().map(|r| r.tag_name).collect();
+    info!("Got {} releases", tags.len());
+    Ok(tags)
+}
+
 pub fn get_latest_release(repo_slug: &str) -> Result<String> {
     let url = &format!("https://github.com/{repo_slug}/releases/latest");
-    let res = ureq::get(url).call()?;
+    let res = http::agent().get(url).call()?;
 
     if res.status() >= 400 {
         warn!(
@@ -191,7 +266,12 @@ struct ReleaseAssets(Vec<ReleaseAsset>);
 pub struct ReleaseAsset {
     pub id: i64,
     name: String,
+    /// In bytes
+    #[serde(default)]
+    pub size: u64,
     pub browser_download_url: String,
+    #[serde(default)]
+    pub updated_at: Option<String>,
 }
 
 impl Artifact for ReleaseAsset {
@@ -204,9 +284,9 @@ impl Artifact for ReleaseAsset {
     }
 }
 
-pub fn get_release_assets(release_id: i64) -> Result<Vec<ReleaseAsset>> {
+pub fn get_release_assets(repo_slug: &str, release_id: i64) -> Result<Vec<ReleaseAsset>> {
     let assets: ReleaseAssets = make_request(&format!(
-        "https://api.github.com/repos/endless-sky/endless-sky/releases/{release_id}/assets"
+        "https://api.github.com/repos/{repo_slug}/releases/{release_id}/assets"
     ))?;
     info!("Got {} assets for release {}", assets.0.len(), release_id);
     Ok(assets.0)
@@ -214,7 +294,7 @@ pub fn get_release_assets(release_id: i64) -> Result<Vec<ReleaseAsset>> {
 
 fn make_request<T: DeserializeOwned>(url: &str) -> Result<T> {
     debug!("Requesting {}", url);
-    let res = ureq::get(url).set("User-Agent", "ESLauncher2").call()?;
+    let res = http::github_get(url).call()?;
     check_ratelimit(&res);
     if res.status() >= 400 {
         warn!(
@@ -234,7 +314,7 @@ fn make_paginated_request<T: DeserializeOwned>(url: &str) -> Result<Vec<T>> {
     while next_url.is_some() {
         let url = next_url.clone().unwrap();
         debug!("Requesting {}", url);
-        let res = ureq::get(&url).set("User-Agent", "ESLauncher2").call()?;
+        let res = http::github_get(&url).call()?;
         check_ratelimit(&res);
 
         if let Some(link_header) = res.header("link") {
@@ -262,15 +342,19 @@ fn check_ratelimit(res: &ureq::Response) {
             Ok(remaining) => {
                 if remaining == 0 {
                     error!("Github API RateLimit exceeded!");
+                    let mut text = String::from("GitHub rate limit exceeded");
                     if let Some(resets_at) = res.header("X-RateLimit-Reset") {
                         match resets_at.parse::<i64>() {
-                            Ok(resets_at) => info!(
-                                "RateLimit resets in {} minutes",
-                                (resets_at - OffsetDateTime::now_utc().unix_timestamp()) / 60
-                            ),
+                            Ok(resets_at) => {
+                                let minutes =
+                                    (resets_at - OffsetDateTime::now_utc().unix_timestamp()) / 60;
+                                info!("RateLimit resets in {} minutes", minutes);
+                                text = format!("{text}, try again in {minutes} minutes");
+                            }
                             Err(e) => warn!("Failed to parse X-RateLimit-Reset Header: {}", e),
                         };
                     }
+                    notify(Notification::new(Level::Warning, text));
                 } else if remaining < 10 {
                     warn!("Only {} github API requests remaining", remaining);
                 }
@@ -280,58 +364,94 @@ fn check_ratelimit(res: &ureq::Response) {
     }
 }
 
-pub fn download(
+/// Streams `url` into `folder/name` on the executor, reporting progress and stopping once the job gets cancelled.
+/// Starts over if the job is retried, e.g. because the download hung.
+pub async fn download(
     instance_name: &str,
     url: &str,
     name: &str,
     folder: &Path,
     size_hint: Option<u32>,
 ) -> Result<PathBuf> {
-    let mut output_path = folder.to_path_buf();
-    output_path.push(name);
-    let mut output_file = File::create(&output_path)?;
-
-    info!("Downloading {} to {}", url, name);
-    send_progress_message(instance_name, "Downloading".into());
-
-    let res = ureq::get(url).call()?;
-    let total: Option<u32> = res
-        .header("Content-Length")
-        .and_then(|s| s.parse().ok())
-        .or(size_hint);
-    let fetched = Arc::new(AtomicUsize::new(0));
-    let finished = Arc::new(AtomicBool::new(false));
-
-    let bufreader = BufReader::with_capacity(128 * 1024, res.into_reader());
-    let mut reader = ProgressReader::new(bufreader, |progress| {
-        fetched.fetch_add(progress, Ordering::SeqCst);
-    });
-
-    let thread_fetched = fetched.clone();
-    let thread_finished = finished.clone();
-    let thread_instance_name = instance_name.to_string();
-    thread::spawn(move || loop {
-        if thread_finished.load(Ordering::SeqCst) {
-            break;
+    let output_path = folder.join(name);
+    // Only complete downloads get their actual name, see `recovery`
+    let partial_path = folder.join(format!("{name}{PARTIAL_SUFFIX}"));
+    // Each iteration is an attempt, started over when the job is retried
+    let mut output_file = 'attempt: loop {
+        let mut output_file = tokio::fs::File::create(&partial_path).await?;
+
+        info!("Downloading {} to {}", url, name);
+        job::stage(instance_name, "Downloading");
+
+        let Some(res) = job::watch(instance_name, http::download_request(url).send()).await? else {
+            continue;
+        };
+        let res = res?.error_for_status()?;
+        let total: Option<u32> = res
+            .content_length()
+            .and_then(|l| u32::try_from(l).ok())
+            .or(size_hint);
+        bandwidth::check_cap(total.map(u64::from));
+        let mut chunks = res.bytes_stream();
+        let mut fetched = 0;
+        let mut meter = Meter::default();
+        loop {
+            let Some(chunk) = job::watch(instance_name, chunks.next()).await? else {
+                info!("Starting the download of {} over", name);
+                continue 'attempt;
+            };
+            let Some(chunk) = chunk else {
+                break 'attempt output_file;
+            };
+            job::check_cancelled(instance_name)?;
+            let chunk = chunk?;
+            meter.add(chunk.len());
+            output_file.write_all(&chunk).await?;
+            let previous_mib = fetched / (1024 * 1024);
+            fetched += chunk.len();
+            // Once per MiB is plenty for the progress bar
+            if fetched / (1024 * 1024) != previous_mib {
+                job::progress(
+                    instance_name,
+                    Progress::new((fetched / (1024 * 1024)) as u32)
+                        .total(total.map(|t| t / (1024 * 1024)))
+                        .units("MiB"),
+                );
+            }
         }
-        let fetched = thread_fetched.load(Ordering::SeqCst);
-        send_progress_message(
-            &thread_instance_name,
-            Progress::from("Downloading")
-                .done((fetched / (1024 * 1024)) as u32)
-                .total(total.map(|t| t / (1024 * 1024)))
-                .units("MiB"),
-        );
-        thread::sleep(Duration::from_millis(30));
-    });
-
-    let res = copy(&mut reader, &mut output_file);
-    // Make sure we end the logging thread before potentially erroring out
-    finished.store(true, Ordering::SeqCst);
-    // Tiny sleep to make sure we avoid a potential race condition
-    thread::sleep(Duration::from_millis(20));
-    res?;
+    };
+    output_file.flush().await?;
+    drop(output_file);
+    tokio::fs::rename(&partial_path, &output_path).await?;
 
     info!("Download finished");
     Ok(output_path)
 }
+
+/// Like `download`, trying the candidates in the order `endpoints` prefers until one works.
+pub async fn download_fastest(
+    instance_name: &str,
+    candidates: Vec<(Endpoint, String)>,
+    name: &str,
+    folder: &Path,
+    size_hint: Option<u32>,
+) -> Result<PathBuf> {
+    let mut last_error = None;
+    for (endpoint, url) in endpoints::order(candidates) {
+        let started = Instant::now();
+        match download(instance_name, &url, name, folder, size_hint).await {
+            Ok(path) => {
+                let size = fs::metadata(&path).map(|m| m.len()).unwrap_or_default();
+                endpoints::record(endpoint, size, started.elapsed());
+                return Ok(path);
+            }
+            Err(e) => {
+                job::check_cancelled(instance_name)?;
+                warn!("Downloading {} from {} failed: {:#}", name, endpoint, e);
+                endpoints::record_failure(endpoint);
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| anyhow!("Nowhere to download {name} from")))
+}
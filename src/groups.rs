@@ -0,0 +1,109 @@
+//! Groups of instances, e.g. the ones for testing PRs, whose defaults new instances in the
+//! group start out with.
+
+use crate::get_data_dir;
+use crate::install_frame::{InstanceSource, InstanceSourceType};
+use crate::instance::Instance;
+use crate::templates::Template;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+
+/// What instances installed into a group start out with
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GroupDefaults {
+    /// Preselected in the install flow when picking the group
+    pub channel: Option<InstanceSourceType>,
+    pub plugin_profile: Option<String>,
+    pub launch_args: String,
+    pub isolated_config: bool,
+}
+
+impl GroupDefaults {
+    pub fn from_instance(instance: &Instance) -> Self {
+        Self {
+            channel: Some(instance.source.r#type),
+            plugin_profile: instance.plugin_profile.clone(),
+            launch_args: instance.launch_args.clone(),
+            isolated_config: instance.isolated_config,
+        }
+    }
+
+    /// A template installing `source` into `group` with these defaults.
+    pub fn template(&self, group: &str, source: InstanceSource) -> Template {
+        Template {
+            source,
+            plugin_profile: self.plugin_profile.clone(),
+            launch_args: self.launch_args.clone(),
+            isolated_config: self.isolated_config,
+            group: Some(group.to_string()),
+        }
+    }
+}
+
+/// Groups by name. A group exists once something was installed into it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Groups(pub BTreeMap<String, GroupDefaults>);
+
+impl Groups {
+    pub fn load() -> Self {
+        let Some(mut groups_file) = get_data_dir() else {
+            return Self::default();
+        };
+        groups_file.push("groups.json");
+        if !groups_file.exists() {
+            return Self::default();
+        }
+
+        match File::open(groups_file)
+            .with_context(|| "Failed to open groups.json")
+            .and_then(|f| {
+                serde_json::from_reader(f).with_context(|| "Failed to deserialize groups.json")
+            }) {
+            Ok(g) => g,
+            Err(e) => {
+                warn!("{:#?}", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let mut groups_file =
+            get_data_dir().ok_or_else(|| anyhow!("Failed to get app save dir"))?;
+        groups_file.push("groups.json");
+
+        let file = File::create(groups_file)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.0.keys().cloned().collect()
+    }
+}
+
+/// Adds a group without any defaults, unless it exists already.
+pub fn ensure(name: &str) -> Result<()> {
+    let mut groups = Groups::load();
+    if groups.0.contains_key(name) {
+        return Ok(());
+    }
+    groups.0.insert(name.to_string(), GroupDefaults::default());
+    groups.save()
+}
+
+/// Makes the configuration of `instance` the defaults of its group.
+pub fn save_defaults(instance: &Instance) -> Result<()> {
+    let group = instance
+        .group
+        .clone()
+        .ok_or_else(|| anyhow!("{} isn't in a group", instance.name))?;
+    let mut groups = Groups::load();
+    groups
+        .0
+        .insert(group, GroupDefaults::from_instance(instance));
+    groups.save()
+}
@@ -0,0 +1,124 @@
+//! The high-DPI graphics pack, which the game loads like a plugin. It's downloaded into the plugin
+//! pool once and linked into the plugins folder of each instance that uses it.
+
+use crate::instance::{Instance, InstanceState};
+use crate::notifications::{notify, ErrorBanner, Level, Notification};
+use crate::plugin_pool::{self, get_pool_dir};
+use crate::{archive, endpoints, github, job, send_message, Message};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+const REPO: &str = "endless-sky/endless-sky-high-dpi";
+const BRANCH: &str = "master";
+/// The pack's folder in the plugin pool and in plugins folders
+pub const PLUGIN_NAME: &str = "endless-sky-high-dpi";
+
+fn pooled() -> Option<PathBuf> {
+    Some(get_pool_dir()?.join(PLUGIN_NAME))
+}
+
+/// Where the pack is downloaded and extracted before it's moved into the pool
+pub fn work_dir() -> Option<PathBuf> {
+    Some(get_pool_dir()?.join(format!(".{PLUGIN_NAME} download")))
+}
+
+/// Downloads the pack into the plugin pool, unless it's there already.
+async fn download(job_name: &str) -> Result<()> {
+    let pooled = pooled().ok_or_else(|| anyhow!("Failed to get plugin pool dir"))?;
+    if pooled.exists() {
+        return Ok(());
+    }
+    let work_dir = work_dir().ok_or_else(|| anyhow!("Failed to get plugin pool dir"))?;
+    if work_dir.exists() {
+        fs::remove_dir_all(&work_dir)?;
+    }
+    fs::create_dir_all(&work_dir)?;
+    let result = async {
+        let archive = github::download_fastest(
+            job_name,
+            endpoints::source_archive(REPO, BRANCH),
+            "high-dpi.tar.gz",
+            &work_dir,
+            None,
+        )
+        .await?;
+        job::stage(job_name, "Extracting");
+        let extracted = work_dir.join("extracted");
+        archive::unpack(&archive, &extracted, true, Some(job_name))?;
+        // GitHub puts everything into a folder named after the repository and branch
+        let toplevel = fs::read_dir(&extracted)?
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .find(|p| p.is_dir())
+            .ok_or_else(|| anyhow!("The high-DPI pack archive is empty"))?;
+        fs::rename(&toplevel, &pooled)
+            .context("Failed to move the high-DPI pack into the plugin pool")?;
+        Ok(())
+    }
+    .await;
+    if let Err(e) = fs::remove_dir_all(&work_dir) {
+        warn!("Failed to clean up {}: {}", work_dir.to_string_lossy(), e);
+    }
+    result
+}
+
+fn plugins_dir(instance: &Instance) -> Result<PathBuf> {
+    Ok(instance
+        .config_dir()
+        .ok_or_else(|| anyhow!("Failed to get the config dir of {}", instance.name))?
+        .join("plugins"))
+}
+
+/// Links the pack into the instance's plugins folder if it uses it and it's been downloaded,
+/// e.g. after the instance switched to a separate config dir.
+pub fn apply(instance: &Instance) {
+    if !instance.high_dpi || !pooled().is_some_and(|p| p.exists()) {
+        return;
+    }
+    if let Err(e) = plugins_dir(instance).and_then(|dir| plugin_pool::link(PLUGIN_NAME, &dir)) {
+        error!(
+            "Failed to link the high-DPI pack for {}: {:#}",
+            instance.name, e
+        );
+    }
+}
+
+/// Removes the pack from the instance's plugins folder, unless it was put there by hand.
+pub fn remove(instance: &Instance) -> Result<()> {
+    let plugin_dir = plugins_dir(instance)?.join(PLUGIN_NAME);
+    if plugin_dir.symlink_metadata().is_ok() && plugin_pool::is_linked(&plugin_dir) {
+        plugin_pool::unlink(&plugin_dir)?;
+        info!("Removed the high-DPI pack from {}", instance.name);
+    }
+    Ok(())
+}
+
+/// Downloads the pack if needed and links it into the instance's plugins folder.
+pub async fn perform_enable(mut instance: Instance) {
+    let result = async {
+        download(&instance.name).await?;
+        plugin_pool::link(PLUGIN_NAME, &plugins_dir(&instance)?)
+    }
+    .await;
+    match result {
+        Ok(()) => {
+            info!("Set up the high-DPI pack for {}", instance.name);
+            notify(Notification::new(
+                Level::Success,
+                format!("{} uses high-DPI graphics now", instance.name),
+            ));
+            instance.state = InstanceState::Ready;
+        }
+        Err(e) => {
+            error!(
+                "Failed to set up the high-DPI pack for {}: {:#}",
+                instance.name, e
+            );
+            instance.high_dpi = false;
+            instance.state =
+                InstanceState::Failed(ErrorBanner::new("Setting up high-DPI graphics failed", &e));
+        }
+    }
+    send_message(Message::AddInstance(Box::new(instance)));
+}
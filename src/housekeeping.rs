@@ -0,0 +1,146 @@
+//! Suggests deleting PR instances that nobody plays anymore and whose PR is closed,
+//! since those pile up quickly when testing pull requests.
+
+use crate::install_frame::InstanceSourceType;
+use crate::instance::{Instance, InstanceMessage};
+use crate::stats::{self, Stats};
+use crate::style::text_button;
+use crate::{github, Message};
+use iced::widget::{Button, Checkbox, Column, Container, Row, Space, Text};
+use iced::{theme, Alignment, Command, Element, Length};
+use std::collections::BTreeSet;
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone)]
+pub enum HousekeepingMessage {
+    /// Names of the stale instances, with when they were last played or installed
+    Found(Vec<(String, i64)>),
+    Toggled(String, bool),
+    DeleteSelected,
+    Dismiss,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Housekeeping {
+    stale: Vec<(String, i64)>,
+    selected: BTreeSet<String>,
+}
+
+/// Finds the PR instances that weren't played for `days` days and whose PR has been merged or closed.
+pub async fn find_stale(instances: Vec<Instance>, days: u64) -> Vec<(String, i64)> {
+    let stats = Stats::load();
+    let cutoff = OffsetDateTime::now_utc().unix_timestamp() - (days * 24 * 60 * 60) as i64;
+    let mut stale = vec![];
+    for instance in instances {
+        if instance.source.r#type != InstanceSourceType::PR {
+            continue;
+        }
+        let last_used = stats
+            .instances
+            .get(&instance.name)
+            .and_then(|s| s.last_played)
+            .or(instance.installed);
+        let Some(last_used) = last_used.filter(|t| *t < cutoff) else {
+            continue;
+        };
+        let Ok(id) = instance.source.identifier.trim_start_matches('#').parse() else {
+            continue;
+        };
+        match github::get_pr(id) {
+            Ok(pr) if pr.state == "closed" => stale.push((instance.name, last_used)),
+            Ok(_) => {}
+            Err(e) => warn!("Failed to check whether PR #{} is closed: {:#}", id, e),
+        }
+    }
+    if !stale.is_empty() {
+        info!("Found {} stale PR instances", stale.len());
+    }
+    stale
+}
+
+impl Housekeeping {
+    pub fn update(&mut self, message: HousekeepingMessage) -> Command<Message> {
+        match message {
+            HousekeepingMessage::Found(stale) => {
+                self.selected = stale.iter().map(|(name, _)| name.clone()).collect();
+                self.stale = stale;
+            }
+            HousekeepingMessage::Toggled(name, selected) => {
+                if selected {
+                    self.selected.insert(name);
+                } else {
+                    self.selected.remove(&name);
+                }
+            }
+            HousekeepingMessage::DeleteSelected => {
+                let selected = std::mem::take(&mut self.selected);
+                self.stale.clear();
+                return Command::batch(selected.into_iter().map(|name| {
+                    Command::perform(async {}, move |()| {
+                        Message::InstanceMessage(name, InstanceMessage::Delete)
+                    })
+                }));
+            }
+            HousekeepingMessage::Dismiss => {
+                self.stale.clear();
+                self.selected.clear();
+            }
+        }
+        Command::none()
+    }
+
+    /// Only shown while there are suggestions.
+    pub fn view(&self, days: u64) -> Option<Element<HousekeepingMessage>> {
+        if self.stale.is_empty() {
+            return None;
+        }
+        let list = self
+            .stale
+            .iter()
+            .fold(Column::new().spacing(3), |column, (name, last_used)| {
+                let name = name.clone();
+                column.push(
+                    Checkbox::new(
+                        format!("{name} (last used {})", stats::format_age(*last_used)),
+                        self.selected.contains(&name),
+                    )
+                    .on_toggle(move |selected| HousekeepingMessage::Toggled(name.clone(), selected))
+                    .text_size(14)
+                    .size(14),
+                )
+            });
+        Some(
+            Container::new(
+                Column::new()
+                    .spacing(10)
+                    .push(Text::new(format!(
+                        "These PR instances weren't played for {days} days and their PR is closed. Delete them?"
+                    )))
+                    .push(list)
+                    .push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .push(Space::with_width(Length::Fill))
+                            .push(
+                                Button::new(Text::new("Keep them"))
+                                    .style(text_button())
+                                    .on_press(HousekeepingMessage::Dismiss),
+                            )
+                            .push(
+                                Button::new(Text::new("Delete selected"))
+                                    .style(theme::Button::Destructive)
+                                    .on_press_maybe(
+                                        (!self.selected.is_empty())
+                                            .then_some(HousekeepingMessage::DeleteSelected),
+                                    ),
+                            ),
+                    ),
+            )
+            .padding(10)
+            .width(Length::Fill)
+            .style(theme::Container::Box)
+            .into(),
+        )
+    }
+}
@@ -1,17 +1,26 @@
 use crate::github::{get_workflow_run_artifacts, Artifact};
 use crate::install_frame::{InstanceSource, InstanceSourceType};
-use crate::instance::{Instance, InstanceState, InstanceType};
-use crate::send_progress_message;
-use crate::{archive, github};
+use crate::instance::{Arch, Instance, InstanceState, InstanceType, Provenance};
+use crate::job;
+use crate::recovery::{self, DOWNLOAD_DIR};
+use crate::{
+    archive, endpoints, github, instance, instance_lock, modifications, network, versions,
+};
 use anyhow::{Context, Result};
 use fs_extra::dir::{copy, CopyOptions};
+use lazy_static::lazy_static;
 use regex::Regex;
+use std::fmt;
 use std::fs;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 
-pub fn install(
+lazy_static! {
+    static ref VERSION_REGEX: Regex = Regex::new(r"\d+(?:\.\d+)+").unwrap();
+}
+
+pub async fn install(
     destination: PathBuf,
     name: String,
     instance_type: InstanceType,
@@ -21,7 +30,7 @@ pub fn install(
     if instance_type == InstanceType::Unknown {
         return Err(anyhow!("Cannot install InstanceType::Unknown",));
     }
-    send_progress_message(&name, "Preparing directories".into());
+    job::stage(&name, "Preparing directories");
 
     // If it's a PR, try to strip the leading `#`
     if InstanceSourceType::PR == instance_source.r#type
@@ -36,52 +45,129 @@ pub fn install(
     {
         instance_source.identifier.insert(0, 'v');
     }
+    // Looked for before clearing the destination, which may be the only copy
+    let local = if network::is_online() {
+        None
+    } else {
+        Some(local_build(&instance_source, instance_type, &destination).ok_or_else(|| {
+            anyhow!("Installing needs a network connection, and no installed instance has this build")
+        })?)
+    };
 
-    if destination.exists() {
-        fs::remove_dir_all(&destination)?;
-    }
     fs::create_dir_all(&destination)?;
+    for entry in fs::read_dir(&destination)? {
+        let entry = entry?;
+        // The lock belongs to whatever is installing here, logs and configs to the user, and
+        // downloads to the interrupted install this may be resuming
+        if versions::belongs_to_user(&entry.file_name()) || entry.file_name() == DOWNLOAD_DIR {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            fs::remove_dir_all(entry.path())?;
+        } else {
+            fs::remove_file(entry.path())?;
+        }
+    }
+
+    if let Some(local) = local {
+        return copy_local_build(&local, destination, name, instance_source);
+    }
 
-    let (archive_file, version) = match instance_source.r#type {
-        InstanceSourceType::Continuous => (
-            download_release_asset(&name, "continuous", &destination, instance_type)?,
-            github::get_git_ref("tags/continuous")?.object.sha,
-        ),
-        InstanceSourceType::Release => (
-            download_release_asset(
+    let arch = instance_source.arch;
+    let (archive_file, version, provenance) = match instance_source.r#type {
+        InstanceSourceType::Continuous => {
+            let (archive_file, mut provenance) = download_release_asset(
+                &name,
+                github::GAME_REPO,
+                "continuous",
+                &destination,
+                instance_type,
+                arch,
+                instance_source.asset_pattern.as_deref(),
+            )
+            .await?;
+            let version = github::get_git_ref("tags/continuous")?.object.sha;
+            provenance.commit = Some(version.clone());
+            (archive_file, version, provenance)
+        }
+        InstanceSourceType::Release => {
+            // Presets leave the version open, meaning the latest one
+            if instance_source.identifier.is_empty() {
+                job::stage(&name, "Fetching latest release");
+                instance_source.identifier = github::get_latest_release(instance_source.repo())?;
+            }
+            let (archive_file, mut provenance) = download_release_asset(
                 &name,
+                instance_source.repo(),
                 &instance_source.identifier,
                 &destination,
                 instance_type,
-            )?,
-            String::from(&instance_source.identifier),
-        ),
-        InstanceSourceType::PR => download_pr_asset(
-            &name,
-            &destination,
-            instance_type,
-            instance_source.identifier.parse()?,
-        )?,
+                arch,
+                instance_source.asset_pattern.as_deref(),
+            )
+            .await?;
+            provenance.commit =
+                github::get_commit_sha(instance_source.repo(), &instance_source.identifier)
+                    .map_err(|e| warn!("Failed to get the commit of the release: {:#}", e))
+                    .ok();
+            (
+                archive_file,
+                String::from(&instance_source.identifier),
+                provenance,
+            )
+        }
+        InstanceSourceType::PR => {
+            let (archive_file, provenance) = download_pr_asset(
+                &name,
+                &destination,
+                instance_type,
+                arch,
+                instance_source.identifier.parse()?,
+                instance_source.asset_pattern.as_deref(),
+            )
+            .await?;
+            let version = provenance.commit.clone().unwrap_or_default();
+            (archive_file, version, provenance)
+        }
     };
 
+    job::check_cancelled(&name)?;
     let mut executable_path = destination.clone();
     executable_path.push(instance_type.executable().unwrap());
 
     if instance_type == InstanceType::AppImage {
         fs::rename(&archive_file, &executable_path)?;
     } else if cfg!(target_os = "macos") && archive_file.to_string_lossy().contains("dmg") {
-        send_progress_message(&name, "Processing DMG file".into());
-        if let Err(e) = mac_process_dmg(&archive_file) {
+        job::stage(&name, "Processing DMG file");
+        if let Err(e) = mac_process_dmg(&archive_file, &destination) {
             return Err(anyhow!("Mac DMG postprocessing failed! {}", e));
         }
     } else {
-        send_progress_message(&name, "Extracting archive".into());
-        archive::unpack(&archive_file, &destination, !cfg!(target_os = "macos"))?;
+        job::stage(&name, "Extracting archive");
+        archive::unpack(
+            &archive_file,
+            &destination,
+            !cfg!(target_os = "macos"),
+            Some(&name),
+        )?;
     }
 
-    // TODO: Remove this after a while, only exists for backwards compatibility with pre-cmake PRs
-    if InstanceType::Windows == instance_type && !executable_path.exists() {
-        executable_path.set_file_name("EndlessSky.exe");
+    // Asset names only hint at what's inside, e.g. pre-cmake PRs still have EndlessSky.exe
+    let mut instance_type = instance_type;
+    if let Some((detected, detected_executable)) = InstanceType::detect(&destination) {
+        if detected != instance_type {
+            warn!(
+                "Expected a {:?} build, but found a {:?} one",
+                instance_type, detected
+            );
+            instance_type = detected;
+        }
+        executable_path = detected_executable;
+    } else if !executable_path.exists() {
+        warn!(
+            "Couldn't find the executable in {}",
+            destination.to_string_lossy()
+        );
     }
 
     // upload-artifact doesn't preserve permissions, so we need to set the executable bit here
@@ -90,7 +176,7 @@ pub fn install(
     chmod_x(&executable_path);
 
     info!("Done!");
-    Ok(Instance::new(
+    let mut instance = Instance::new(
         destination,
         executable_path,
         name,
@@ -98,81 +184,305 @@ pub fn install(
         instance_type,
         instance_source,
         InstanceState::Ready,
-    ))
+    );
+    instance.provenance = provenance;
+    let download_dir = recovery::download_dir(&instance.path);
+    if download_dir.exists() {
+        fs::remove_dir_all(&download_dir)?;
+    }
+    job::stage(&instance.name, "Recording data files");
+    if let Err(e) = modifications::record(&instance) {
+        warn!(
+            "Failed to record the data files of {}: {:#}",
+            instance.name, e
+        );
+    }
+    Ok(instance)
+}
+
+/// An installed instance with the build `source` would download, to install from while offline.
+/// Continuous and PR builds move on, so any build of theirs will do, like the latest release
+/// does if no tag was given.
+fn local_build(
+    source: &InstanceSource,
+    instance_type: InstanceType,
+    destination: &Path,
+) -> Option<Instance> {
+    instance::load_instances()
+        .map_err(|e| warn!("Failed to load the instances: {:#}", e))
+        .ok()?
+        .into_iter()
+        .filter(|i| i.path != destination && i.state.is_ready())
+        .find(|i| {
+            i.instance_type == instance_type
+                && i.source.r#type == source.r#type
+                && i.source.repo == source.repo
+                && i.source.arch == source.arch
+                && (source.r#type == InstanceSourceType::Continuous
+                    || source.identifier.is_empty()
+                    || i.source.identifier == source.identifier)
+        })
+}
+
+/// Installs by copying the build of another instance, leaving its user files behind.
+fn copy_local_build(
+    local: &Instance,
+    destination: PathBuf,
+    name: String,
+    instance_source: InstanceSource,
+) -> Result<Instance> {
+    job::stage(&name, format!("Copying the build of {}", local.name));
+    info!(
+        "Offline, copying the build of {} from {}",
+        local.name,
+        local.path.to_string_lossy()
+    );
+    instance_lock::check(&local.path)?;
+    let entries: Vec<PathBuf> = fs::read_dir(&local.path)?
+        .filter_map(Result::ok)
+        .filter(|e| !versions::belongs_to_user(&e.file_name()) && e.file_name() != DOWNLOAD_DIR)
+        .map(|e| e.path())
+        .collect();
+    fs_extra::copy_items(&entries, &destination, &CopyOptions::new())?;
+    let executable = local.executable.strip_prefix(&local.path).map_or_else(
+        |_| local.executable.clone(),
+        |relative| destination.join(relative),
+    );
+    let mut instance = Instance::new(
+        destination,
+        executable,
+        name,
+        local.version.clone(),
+        local.instance_type,
+        instance_source,
+        InstanceState::Ready,
+    );
+    instance.provenance = local.provenance.clone();
+    if let Err(e) = modifications::record(&instance) {
+        warn!(
+            "Failed to record the data files of {}: {:#}",
+            instance.name, e
+        );
+    }
+    Ok(instance)
 }
 
-fn download_release_asset(
+async fn download_release_asset(
     instance_name: &str,
+    repo_slug: &str,
     tag: &str,
     destination: &Path,
     instance_type: InstanceType,
-) -> Result<PathBuf> {
-    send_progress_message(instance_name, "Fetching release data".into());
-    let release = github::get_release_by_tag(tag)?;
-    let assets = github::get_release_assets(release.id)?;
-    let asset = choose_artifact(assets, instance_type)?;
-
-    info!("Downloading artifact from {}", asset.browser_download_url);
-    github::download(
-        instance_name,
-        &asset.browser_download_url,
-        asset.name(),
-        destination,
-        None,
-    )
+    arch: Arch,
+    asset_pattern: Option<&str>,
+) -> Result<(PathBuf, Provenance)> {
+    job::stage(instance_name, "Fetching release data");
+    let release = github::get_release_by_tag(repo_slug, tag)?;
+    let assets = github::get_release_assets(repo_slug, release.id)?;
+    let asset = choose_artifact(assets, instance_type, arch, asset_pattern)?;
+
+    let size = (asset.size > 0).then_some(asset.size);
+    let archive_file = match recovery::finished_download(destination, asset.name(), size) {
+        Some(archive_file) => {
+            info!("Reusing {} from the interrupted install", asset.name());
+            archive_file
+        }
+        None => {
+            info!("Downloading artifact from {}", asset.browser_download_url);
+            let download_dir = recovery::download_dir(destination);
+            fs::create_dir_all(&download_dir)?;
+            github::download_fastest(
+                instance_name,
+                endpoints::release_asset(repo_slug, &asset),
+                asset.name(),
+                &download_dir,
+                None,
+            )
+            .await?
+        }
+    };
+    let provenance = Provenance {
+        release_tag: Some(release.tag_name),
+        asset_id: Some(asset.id),
+        asset_name: Some(asset.name().into()),
+        commit: None,
+        build_date: asset.updated_at,
+    };
+    Ok((archive_file, provenance))
 }
 
-fn download_pr_asset(
+async fn download_pr_asset(
     instance_name: &str,
-    destination: &PathBuf,
+    destination: &Path,
     instance_type: InstanceType,
+    arch: Arch,
     pr_id: u16,
-) -> Result<(PathBuf, String)> {
-    send_progress_message(instance_name, "Fetching PR data".into());
+    asset_pattern: Option<&str>,
+) -> Result<(PathBuf, Provenance)> {
+    job::stage(instance_name, "Fetching PR data");
     let pr = github::get_pr(pr_id)?;
-    send_progress_message(instance_name, "Fetching CD workflow".into());
+    job::stage(instance_name, "Fetching CD workflow");
     let workflow = github::get_cd_workflow()?;
-    send_progress_message(instance_name, "Fetching CD workflow run".into());
+    job::stage(instance_name, "Fetching CD workflow run");
     let run = github::get_latest_workflow_run(workflow.id, &pr.head.branch, pr.head.repo.id)?;
-    send_progress_message(instance_name, "Fetching CD run artifacts".into());
+    job::stage(instance_name, "Fetching CD run artifacts");
     let artifacts = get_workflow_run_artifacts(run.id)?;
-    let artifact = choose_artifact(artifacts, instance_type)?;
+    let artifact = choose_artifact(artifacts, instance_type, arch, asset_pattern)?;
 
-    let unblocked_url = github::unblock_artifact_download(artifact.id);
-    let archive_path = github::download(
-        instance_name,
-        &unblocked_url,
-        &format!("{}.zip", artifact.name()),
-        destination,
-        Some(artifact.size_in_bytes),
-    )?;
+    let archive_name = format!("{}.zip", artifact.name());
+    let archive_path = match recovery::finished_download(destination, &archive_name, None) {
+        Some(archive_path) => {
+            info!("Reusing {} from the interrupted install", archive_name);
+            archive_path
+        }
+        None => {
+            let download_dir = recovery::download_dir(destination);
+            fs::create_dir_all(&download_dir)?;
+            github::download(
+                instance_name,
+                &github::unblock_artifact_download(artifact.id),
+                &archive_name,
+                &download_dir,
+                Some(artifact.size_in_bytes),
+            )
+            .await?
+        }
+    };
 
-    send_progress_message(instance_name, "Extracting artifact".into());
-    archive::unpack(&archive_path, destination, true)?;
+    job::stage(instance_name, "Extracting artifact");
+    archive::unpack(&archive_path, destination, true, Some(instance_name))?;
     fs::remove_file(archive_path)?;
 
-    let mut result_path = destination.clone();
-    result_path.push(artifact.name());
-    Ok((result_path, pr.head.sha))
+    let result_path = destination.join(artifact.name());
+    let provenance = Provenance {
+        release_tag: None,
+        asset_id: Some(artifact.id.into()),
+        asset_name: Some(artifact.name().into()),
+        commit: Some(pr.head.sha),
+        build_date: artifact.created_at,
+    };
+    Ok((result_path, provenance))
 }
 
-pub fn choose_artifact<A: Artifact>(artifacts: Vec<A>, instance_type: InstanceType) -> Result<A> {
-    for artifact in artifacts {
-        if instance_type.archive_matches(artifact.name()) {
-            info!("Choosing asset with name {}", artifact.name());
-            return if artifact.expired() {
-                Err(anyhow!(
-                    "Artifact is expired, the PR author has to trigger a new workflow"
-                ))
-            } else {
-                Ok(artifact)
-            };
-        }
+/// Files published next to builds, which are never builds themselves
+const NOT_BUILDS: [&str; 9] = [
+    ".sha256", ".sha512", ".md5", ".sig", ".asc", ".txt", ".json", ".pdb", ".zsync",
+];
+/// Marks builds with debug symbols or other extras, which the regular build is preferred to
+const EXTRAS: [&str; 3] = ["debug", "dbg", "symbols"];
+
+/// Several assets fit equally well, so the user has to pick one.
+#[derive(Debug)]
+pub struct AmbiguousAsset {
+    pub candidates: Vec<String>,
+}
+
+impl fmt::Display for AmbiguousAsset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Several assets fit equally well: {}",
+            self.candidates.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for AmbiguousAsset {}
+
+/// How well an asset fits, None if it doesn't at all. The platform weighs most, then whether
+/// it's built for `arch` rather than universal, then whether it's a regular build.
+fn score_asset(
+    name: &str,
+    instance_type: InstanceType,
+    arch: Arch,
+    regex: Option<&Regex>,
+) -> Option<u32> {
+    let lower = name.to_lowercase();
+    if NOT_BUILDS.iter().any(|e| lower.ends_with(e)) || !arch.asset_matches(name, true) {
+        return None;
+    }
+    let platform = match regex {
+        // A pattern is specific enough on its own
+        Some(regex) => regex.is_match(name).then_some(3)?,
+        None => instance_type.asset_score(name)?,
+    };
+    let arch = if arch.asset_matches(name, false) {
+        2
+    } else {
+        1
+    };
+    let regular = !EXTRAS.iter().any(|m| lower.contains(m));
+    Some(platform * 100 + arch * 10 + u32::from(regular))
+}
+
+/// A pattern matching the chosen asset in future builds too, assuming those only differ
+/// in the version numbers that are part of the name.
+pub fn pattern_for_asset(name: &str) -> String {
+    let parts: Vec<String> = VERSION_REGEX.split(name).map(regex::escape).collect();
+    format!("^{}$", parts.join(".+"))
+}
+
+/// Picks the asset that fits best, see `score_asset`. If several fit equally well,
+/// this fails with [`AmbiguousAsset`] rather than picking one at random.
+/// `pattern` replaces the game's naming for sources whose assets are named differently.
+pub fn choose_artifact<A: Artifact>(
+    artifacts: Vec<A>,
+    instance_type: InstanceType,
+    arch: Arch,
+    pattern: Option<&str>,
+) -> Result<A> {
+    let regex = pattern
+        .map(Regex::new)
+        .transpose()
+        .with_context(|| format!("Invalid asset pattern '{}'", pattern.unwrap_or_default()))?;
+    let mut scored: Vec<(u32, A)> = artifacts
+        .into_iter()
+        // Patterns may match anything, e.g. installers, so the kind of file is checked either way
+        .filter(|a| a.zipped() || instance_type.installs_from(a.name()))
+        .filter_map(|a| {
+            Some((
+                score_asset(a.name(), instance_type, arch, regex.as_ref())?,
+                a,
+            ))
+        })
+        .collect();
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    for (score, a) in &scored {
+        debug!("Asset {} scored {}", a.name(), score);
+    }
+    let best = scored.first().map(|(score, _)| *score);
+    let tied: Vec<String> = scored
+        .iter()
+        .filter(|(score, _)| Some(*score) == best)
+        .map(|(_, a)| a.name().to_string())
+        .collect();
+    if tied.len() > 1 {
+        return Err(AmbiguousAsset { candidates: tied }.into());
+    }
+    let artifact = scored
+        .into_iter()
+        .map(|(_, a)| a)
+        .next()
+        .ok_or_else(|| match pattern {
+            Some(pattern) => anyhow!(
+                "Couldn't match any asset against the pattern '{}' for {}",
+                pattern,
+                arch.resolve()
+            ),
+            None => anyhow!(
+                "Couldn't match any asset against {:#?} for {}",
+                instance_type,
+                arch.resolve()
+            ),
+        })?;
+    info!("Choosing asset with name {}", artifact.name());
+    if artifact.expired() {
+        Err(anyhow!(
+            "Artifact is expired, the PR author has to trigger a new workflow"
+        ))
+    } else {
+        Ok(artifact)
     }
-    Err(anyhow!(
-        "Couldn't match any asset against {:#?}",
-        instance_type
-    ))
 }
 
 #[cfg(unix)]
@@ -186,7 +496,9 @@ fn chmod_x(file: &PathBuf) {
     }
 }
 
-fn mac_process_dmg(archive_path: &PathBuf) -> Result<()> {
+/// Copies the app out of the disk image into `destination`, next to which the download folder is
+/// removed once the install is done.
+fn mac_process_dmg(archive_path: &PathBuf, destination: &Path) -> Result<()> {
     // Mount the disk image file
     let attach_info = dmg::Attach::new(archive_path)
         .attach()
@@ -202,16 +514,10 @@ fn mac_process_dmg(archive_path: &PathBuf) -> Result<()> {
     })?;
     app_source_path.push(stem);
     app_source_path.push("Endless Sky.app");
-    let parent = archive_path.parent().with_context(|| {
-        format!(
-            "Unable to determine parent from {}",
-            archive_path.to_string_lossy()
-        )
-    })?;
-    let app_target_path = PathBuf::from(parent);
+    let app_target_path = destination.to_path_buf();
     let mut options = CopyOptions::new();
     options.overwrite = true;
-    let _result = copy(&app_source_path, &app_target_path, &options).map_err(|my_error| {
+    let result = copy(&app_source_path, &app_target_path, &options).map_err(|my_error| {
         anyhow!(
             "Copy from {} to {} failed! {}",
             app_source_path.to_string_lossy(),
@@ -227,5 +533,55 @@ fn mac_process_dmg(archive_path: &PathBuf) -> Result<()> {
     if let Err(e) = fs::remove_file(archive_path) {
         error!("Deletion of archive file failed! {}", e);
     }
-    Ok(())
+    result.map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(name: &str, instance_type: InstanceType, pattern: Option<&str>) -> Option<u32> {
+        let regex = pattern.map(|p| Regex::new(p).unwrap());
+        score_asset(name, instance_type, Arch::X86_64, regex.as_ref())
+    }
+
+    #[test]
+    fn score_asset_prefers_the_platform_then_the_arch_then_regular_builds() {
+        let windows = InstanceType::Windows;
+        assert_eq!(
+            score("EndlessSky-win64-v0.10.2.zip", windows, None),
+            Some(221)
+        );
+        assert_eq!(
+            score("EndlessSky-win64-debug.zip", windows, None),
+            Some(220)
+        );
+        assert_eq!(score("EndlessSky-win32-v0.10.2.zip", windows, None), None);
+        assert_eq!(score("EndlessSky-win64.zip.sha256", windows, None), None);
+        assert_eq!(score("endless-sky-x86_64.tar.gz", windows, None), None);
+
+        let linux = InstanceType::Linux;
+        assert_eq!(score("endless-sky-x86_64.tar.gz", linux, None), Some(221));
+        assert_eq!(score("endless-sky.tar.gz", linux, None), Some(211));
+        assert_eq!(score("endless-sky-linux.tar.gz", linux, None), Some(311));
+        assert_eq!(score("endless-sky-arm64.tar.gz", linux, None), None);
+    }
+
+    #[test]
+    fn score_asset_with_a_pattern_ignores_the_naming() {
+        let pattern = Some(r"^fork-build\.zip$");
+        let linux = InstanceType::Linux;
+        assert_eq!(score("fork-build.zip", linux, pattern), Some(311));
+        assert_eq!(score("endless-sky.tar.gz", linux, pattern), None);
+    }
+
+    #[test]
+    fn pattern_for_asset_matches_other_versions() {
+        let pattern = Regex::new(&pattern_for_asset("EndlessSky-win64-v0.10.2.zip")).unwrap();
+        assert!(pattern.is_match("EndlessSky-win64-v0.10.2.zip"));
+        assert!(pattern.is_match("EndlessSky-win64-v0.11.0.zip"));
+        assert!(!pattern.is_match("EndlessSky-win32-v0.11.0.zip"));
+        assert!(!pattern.is_match("EndlessSky-win64-v0.11.0.zip.sha256"));
+        assert!(!pattern.is_match("EndlessSkyXwin64-v0.11.0.zip"));
+    }
 }
@@ -1,19 +1,92 @@
-use crate::instance::{get_instances_dir, InstanceType};
-use crate::style::text_button;
-use crate::{instance, Message};
+use crate::groups::{self, Groups};
+use crate::instance::{get_instances_dir, Arch, InstanceType};
+use crate::notifications::ErrorBanner;
+use crate::presets::{self, Preset};
+use crate::style::{icon_button, text_button};
+use crate::templates::{self, Template, Templates};
+use crate::{github, install, instance, logger, network, releases, style, Message};
 use core::fmt;
-use iced::widget::{Button, Column, Container, Radio, Scrollable, Text, TextInput};
-use iced::{alignment, Alignment, Command, Element, Length};
+use iced::widget::{
+    text_input, Button, Column, Container, PickList, Radio, Row, Scrollable, Text, TextInput,
+};
+use iced::{alignment, theme, Alignment, Color, Command, Element, Length};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 // Characters that shall not be allowed to enter. This does not cover all cases!
 // One should expect the install process to fail on particularly exotic characters.
-const BLACKLISTED_CHARS: [char; 10] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|', '%'];
+pub(crate) const BLACKLISTED_CHARS: [char; 10] =
+    ['/', '\\', ':', '*', '?', '"', '<', '>', '|', '%'];
 
-#[derive(Debug, Clone, Default)]
+fn name_input_id() -> text_input::Id {
+    text_input::Id::new("install-name")
+}
+
+#[derive(Debug, Clone)]
 pub struct InstallFrame {
     pub(crate) name: String,
     source: InstanceSource,
+    templates: Templates,
+    /// The last install that failed, until dismissed
+    error: Option<ErrorBanner>,
+    /// Only a choice on Linux, between running the AppImage as-is or an extracted build
+    linux_type: InstanceType,
+    /// Release tags for the version picker, from the cache until fetched
+    releases: Vec<String>,
+    fetching_releases: bool,
+    presets: Vec<Preset>,
+    /// What's typed into the repository field, which only becomes the source's repo once valid
+    repo_input: String,
+    /// The folder to import existing builds from
+    import_dir: String,
+    /// An install that stopped because several assets fit, until one is picked
+    asset_choice: Option<Box<AssetChoice>>,
+    /// Whether the asset pattern fails to compile, checked whenever it changes
+    invalid_pattern: bool,
+    /// The group to install into, empty for none
+    group: String,
+    groups: Groups,
+}
+
+/// What was entered into the install form, restored at the next start, see [`crate::session`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallDraft {
+    name: String,
+    source: InstanceSource,
+    linux_type: InstanceType,
+    group: String,
+}
+
+/// What's needed to restart an install once the user picked one of several fitting assets
+#[derive(Debug, Clone)]
+pub struct AssetChoice {
+    pub name: String,
+    pub instance_type: InstanceType,
+    pub source: InstanceSource,
+    pub template: Option<Template>,
+    pub candidates: Vec<String>,
+}
+
+impl Default for InstallFrame {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            source: InstanceSource::default(),
+            templates: Templates::load(),
+            error: None,
+            linux_type: InstanceType::AppImage,
+            releases: releases::load_cached(),
+            fetching_releases: false,
+            presets: presets::load(),
+            repo_input: String::new(),
+            import_dir: String::new(),
+            asset_choice: None,
+            invalid_pattern: false,
+            group: String::new(),
+            groups: Groups::load(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -21,7 +94,31 @@ pub enum InstallFrameMessage {
     SourceTypeChanged(InstanceSourceType),
     NameChanged(String),
     SourceIdentifierChanged(String),
+    ArchChanged(Arch),
+    LinuxTypeChanged(InstanceType),
     StartInstallation(InstanceType),
+    InstallFromTemplate(String, InstanceType),
+    DeleteTemplate(String),
+    ReloadTemplates,
+    GroupChanged(String),
+    GroupSelected(String),
+    ReloadGroups,
+    Failed(ErrorBanner),
+    ToggleErrorDetails,
+    DismissError,
+    ReleaseSelected(String),
+    ReleasesLoaded(Option<Vec<String>>),
+    PresetSelected(Preset),
+    RepoChanged(String),
+    SavePreset,
+    AssetPatternChanged(String),
+    AssetsAmbiguous(Box<AssetChoice>),
+    AssetChosen(String),
+    DismissAssetChoice,
+    ImportDirChanged(String),
+    Import,
+    /// Puts the cursor into the name field, to start setting up an install
+    Focus,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -35,6 +132,15 @@ pub enum InstanceSourceType {
 pub struct InstanceSource {
     pub(crate) identifier: String,
     pub(crate) r#type: InstanceSourceType,
+    #[serde(default)]
+    pub(crate) arch: Arch,
+    /// The repository of a fork the releases come from, see `presets`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) repo: Option<String>,
+    /// A regex that assets are matched against instead of the game's naming, for forks that
+    /// name them differently or to settle which of several fitting assets is installed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) asset_pattern: Option<String>,
 }
 
 impl Default for InstanceSource {
@@ -42,10 +148,20 @@ impl Default for InstanceSource {
         Self {
             identifier: String::new(),
             r#type: InstanceSourceType::Continuous,
+            arch: Arch::default(),
+            repo: None,
+            asset_pattern: None,
         }
     }
 }
 
+impl InstanceSource {
+    /// The GitHub repository releases are installed from, as `owner/repo`
+    pub fn repo(&self) -> &str {
+        self.repo.as_deref().unwrap_or(github::GAME_REPO)
+    }
+}
+
 impl InstanceSourceType {
     pub const ALL: [Self; 3] = [Self::Continuous, Self::Release, Self::PR];
 }
@@ -57,25 +173,83 @@ impl fmt::Display for InstanceSourceType {
 }
 
 impl InstallFrame {
+    /// What's entered into the form, unless it's untouched.
+    pub fn draft(&self) -> Option<InstallDraft> {
+        let untouched = self.name.is_empty()
+            && self.source == InstanceSource::default()
+            && self.linux_type == InstanceType::AppImage
+            && self.group.is_empty();
+        (!untouched).then(|| InstallDraft {
+            name: self.name.clone(),
+            source: self.source.clone(),
+            linux_type: self.linux_type,
+            group: self.group.clone(),
+        })
+    }
+
+    pub fn restore(&mut self, draft: InstallDraft) {
+        self.name = draft.name;
+        self.source = draft.source;
+        self.linux_type = draft.linux_type;
+        self.group = draft.group;
+        self.repo_input = self.source.repo.clone().unwrap_or_default();
+        self.check_pattern();
+    }
+
+    fn check_pattern(&mut self) {
+        self.invalid_pattern = self
+            .source
+            .asset_pattern
+            .as_deref()
+            .is_some_and(|p| Regex::new(p).is_err());
+    }
+
     pub fn update(&mut self, message: InstallFrameMessage) -> Command<Message> {
         match message {
             InstallFrameMessage::StartInstallation(instance_type) => {
-                if let Some(mut destination) = get_instances_dir() {
-                    destination.push(&self.name);
-                    return Command::perform(
-                        instance::perform_install(
-                            destination,
-                            self.name.clone(),
-                            instance_type,
-                            self.source.clone(),
-                        ),
-                        Message::Dummy,
-                    );
+                return self.install(instance_type, self.source.clone(), None);
+            }
+            InstallFrameMessage::InstallFromTemplate(template_name, instance_type) => {
+                if let Some(template) = self.templates.0.get(&template_name).cloned() {
+                    return self.install(instance_type, template.source.clone(), Some(template));
+                }
+            }
+            InstallFrameMessage::DeleteTemplate(name) => {
+                if let Err(e) = templates::delete(&name) {
+                    error!("Failed to delete template: {:#}", e);
+                }
+                self.templates = Templates::load();
+            }
+            InstallFrameMessage::ReloadTemplates => self.templates = Templates::load(),
+            InstallFrameMessage::GroupChanged(group) => {
+                if let Some(invalid) = group.chars().rfind(|c| BLACKLISTED_CHARS.contains(c)) {
+                    error!("Invalid character: '{}'", invalid);
                 } else {
-                    error!("Could not get instances directory from AppDirs");
+                    self.group = group;
+                }
+            }
+            InstallFrameMessage::GroupSelected(group) => {
+                let channel = self.groups.0.get(&group).and_then(|d| d.channel);
+                self.group = group;
+                if let Some(channel) = channel.filter(|c| *c != self.source.r#type) {
+                    return self.update(InstallFrameMessage::SourceTypeChanged(channel));
                 }
             }
-            InstallFrameMessage::SourceTypeChanged(source_type) => self.source.r#type = source_type,
+            InstallFrameMessage::ReloadGroups => self.groups = Groups::load(),
+            InstallFrameMessage::Failed(banner) => self.error = Some(banner),
+            InstallFrameMessage::ToggleErrorDetails => {
+                if let Some(banner) = &mut self.error {
+                    banner.expanded = !banner.expanded;
+                }
+            }
+            InstallFrameMessage::DismissError => self.error = None,
+            InstallFrameMessage::SourceTypeChanged(source_type) => {
+                self.source.r#type = source_type;
+                self.source.repo = None;
+                self.repo_input.clear();
+                self.source.asset_pattern = None;
+                self.invalid_pattern = false;
+            }
             InstallFrameMessage::NameChanged(name) => {
                 if let Some(invalid) = name.chars().rfind(|c| BLACKLISTED_CHARS.contains(c)) {
                     error!("Invalid character: '{}'", invalid);
@@ -86,13 +260,199 @@ impl InstallFrame {
             InstallFrameMessage::SourceIdentifierChanged(identifier) => {
                 self.source.identifier = identifier;
             }
+            InstallFrameMessage::ReleaseSelected(tag) => self.source.identifier = tag,
+            InstallFrameMessage::ReleasesLoaded(tags) => {
+                self.fetching_releases = false;
+                if let Some(tags) = tags {
+                    self.releases = tags;
+                }
+            }
+            InstallFrameMessage::PresetSelected(preset) => {
+                self.source.r#type = InstanceSourceType::Release;
+                self.source.identifier.clear();
+                // The game's own releases don't need a preset's repo
+                self.source.repo = (preset.repo != github::GAME_REPO).then_some(preset.repo);
+                self.repo_input = self.source.repo.clone().unwrap_or_default();
+                self.source.asset_pattern = preset.asset_pattern;
+                self.check_pattern();
+                if self.name.trim().is_empty() {
+                    self.name = preset.name;
+                }
+            }
+            InstallFrameMessage::RepoChanged(repo) => {
+                let trimmed = repo.trim();
+                let changed = self.source.repo.as_deref() != Some(trimmed);
+                if presets::is_valid_repo(trimmed) && trimmed != github::GAME_REPO && changed {
+                    self.source.repo = Some(trimmed.to_string());
+                    self.source.identifier.clear();
+                } else if trimmed.is_empty() || trimmed == github::GAME_REPO {
+                    self.source.repo = None;
+                    self.source.asset_pattern = None;
+                    self.invalid_pattern = false;
+                }
+                self.repo_input = repo;
+            }
+            InstallFrameMessage::SavePreset => {
+                let Some(repo) = self.source.repo.clone() else {
+                    return Command::none();
+                };
+                let preset = Preset {
+                    name: self.name.trim().to_string(),
+                    repo,
+                    description: String::new(),
+                    asset_pattern: self.source.asset_pattern.clone(),
+                };
+                match presets::save(preset) {
+                    Ok(()) => self.presets = presets::load(),
+                    Err(e) => {
+                        error!("Failed to save the preset: {:#}", e);
+                        self.error = Some(ErrorBanner::new("Saving the preset failed", &e));
+                    }
+                }
+            }
+            InstallFrameMessage::AssetPatternChanged(pattern) => {
+                self.source.asset_pattern = (!pattern.is_empty()).then_some(pattern);
+                self.check_pattern();
+            }
+            InstallFrameMessage::AssetsAmbiguous(choice) => {
+                self.error = None;
+                self.asset_choice = Some(choice);
+            }
+            InstallFrameMessage::AssetChosen(asset) => {
+                if let Some(choice) = self.asset_choice.take() {
+                    let AssetChoice {
+                        name,
+                        instance_type,
+                        mut source,
+                        template,
+                        ..
+                    } = *choice;
+                    info!("Installing {} with asset {}", name, asset);
+                    source.asset_pattern = Some(install::pattern_for_asset(&asset));
+                    self.name = name;
+                    return self.install(instance_type, source, template);
+                }
+            }
+            InstallFrameMessage::DismissAssetChoice => self.asset_choice = None,
+            InstallFrameMessage::ImportDirChanged(dir) => self.import_dir = dir,
+            InstallFrameMessage::Focus => return text_input::focus(name_input_id()),
+            InstallFrameMessage::Import if self.import_dir.trim().is_empty() => {}
+            InstallFrameMessage::Import => {
+                let dir = PathBuf::from(self.import_dir.trim());
+                self.import_dir.clear();
+                return Command::perform(instance::perform_import(dir), Message::Dummy);
+            }
+            InstallFrameMessage::ArchChanged(arch) => self.source.arch = arch,
+            InstallFrameMessage::LinuxTypeChanged(instance_type) => self.linux_type = instance_type,
         }
         Command::none()
     }
 
+    fn install(
+        &mut self,
+        instance_type: InstanceType,
+        source: InstanceSource,
+        template: Option<Template>,
+    ) -> Command<Message> {
+        self.error = None;
+        let group = self.group.trim();
+        let template = if group.is_empty() {
+            template
+        } else {
+            if let Err(e) = groups::ensure(group) {
+                error!("Failed to save group {}: {:#}", group, e);
+            }
+            let defaults = self.groups.0.get(group).cloned().unwrap_or_default();
+            self.groups = Groups::load();
+            // A template's configuration is more specific than the group's defaults
+            Some(match template {
+                Some(template) => Template {
+                    group: Some(group.to_string()),
+                    ..template
+                },
+                None => defaults.template(group, source.clone()),
+            })
+        };
+        if let Some(mut destination) = get_instances_dir() {
+            destination.push(&self.name);
+            Command::perform(
+                logger::with_job(
+                    self.name.clone(),
+                    instance::perform_install(
+                        destination,
+                        self.name.clone(),
+                        instance_type,
+                        source,
+                        template,
+                    ),
+                ),
+                Message::Dummy,
+            )
+        } else {
+            error!("Could not get instances directory from AppDirs");
+            Command::none()
+        }
+    }
+
+    /// Fetches the release tags in the background, the cached ones are shown meanwhile.
+    pub fn refresh_releases(&mut self) -> Command<Message> {
+        self.fetching_releases = true;
+        Command::perform(releases::perform_fetch(), |tags| {
+            Message::InstallFrameMessage(InstallFrameMessage::ReleasesLoaded(tags))
+        })
+    }
+
+    /// Only true on a cold cache, otherwise the cached releases can be picked from already.
+    pub fn loading_releases(&self) -> bool {
+        self.fetching_releases && self.releases.is_empty()
+    }
+
+    /// The kind of instance that runs on this platform
+    fn native_type(&self) -> InstanceType {
+        if cfg!(windows) {
+            InstanceType::Windows
+        } else if cfg!(target_os = "linux") {
+            self.linux_type
+        } else {
+            InstanceType::MacOS
+        }
+    }
+
+    /// The preset matching the chosen releases, if any
+    fn preset(&self) -> Option<&Preset> {
+        if self.source.r#type != InstanceSourceType::Release {
+            return None;
+        }
+        self.presets.iter().find(|p| p.repo == self.source.repo())
+    }
+
     pub fn view(&self) -> Element<InstallFrameMessage> {
+        let presets = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Text::new("Preset:"))
+            .push(
+                PickList::new(
+                    self.presets.clone(),
+                    self.preset().cloned(),
+                    InstallFrameMessage::PresetSelected,
+                )
+                .placeholder("Forks and total conversions"),
+            );
         let mut controls = InstanceSourceType::ALL.iter().fold(
-            Column::new().spacing(10).push(Text::new("Choose a Type:")),
+            Column::new()
+                .spacing(10)
+                .push(presets)
+                .push_maybe(
+                    self.preset()
+                        .filter(|p| !p.description.is_empty())
+                        .map(|p| {
+                            Text::new(format!("{} ({})", p.description, p.repo))
+                                .size(14)
+                                .style(theme::Text::Color(Color::from_rgb8(150, 150, 150)))
+                        }),
+                )
+                .push(Text::new("Choose a Type:")),
             |column, source_type| {
                 column.push(Radio::new(
                     format!("{source_type:?}"),
@@ -102,26 +462,156 @@ impl InstallFrame {
                 ))
             },
         );
-        if InstanceSourceType::Continuous != self.source.r#type {
+        if self.source.r#type == InstanceSourceType::Release {
+            let can_save = self.source.repo.is_some() && !self.name.trim().is_empty();
+            controls = controls.push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .push(
+                        TextInput::new(
+                            "Repository of a fork as owner/repo, empty for the game's releases",
+                            &self.repo_input,
+                        )
+                        .on_input(InstallFrameMessage::RepoChanged)
+                        .padding(10),
+                    )
+                    .push(style::tooltip(
+                        Button::new(Text::new("Save as preset"))
+                            .style(text_button())
+                            .on_press_maybe(can_save.then_some(InstallFrameMessage::SavePreset)),
+                        "Adds it to presets.json in the data folder, under the instance name",
+                    )),
+            );
+        }
+        // The cached releases are the game's own, a fork's tag has to be typed in
+        if self.source.repo.is_some() {
+            controls = controls.push(
+                TextInput::new(
+                    "Release tag, or empty for the latest",
+                    &self.source.identifier,
+                )
+                .on_input(InstallFrameMessage::SourceIdentifierChanged)
+                .padding(10),
+            );
+            let pattern = self.source.asset_pattern.as_deref().unwrap_or_default();
+            controls = controls
+                .push(
+                    TextInput::new(
+                        "Asset name pattern (regex), empty if named like the game's",
+                        pattern,
+                    )
+                    .on_input(InstallFrameMessage::AssetPatternChanged)
+                    .padding(10),
+                )
+                .push_maybe(self.invalid_pattern.then(|| {
+                    Text::new("Not a valid regular expression")
+                        .size(14)
+                        .style(theme::Text::Color(Color::from_rgb(0.8, 0.4, 0.0)))
+                }));
+        } else if InstanceSourceType::Release == self.source.r#type && !self.releases.is_empty() {
+            controls = controls.push(
+                PickList::new(
+                    self.releases.clone(),
+                    self.releases
+                        .contains(&self.source.identifier)
+                        .then(|| self.source.identifier.clone()),
+                    InstallFrameMessage::ReleaseSelected,
+                )
+                .placeholder("Choose a release")
+                .padding(10),
+            );
+        } else if InstanceSourceType::Continuous != self.source.r#type {
+            if InstanceSourceType::Release == self.source.r#type && self.loading_releases() {
+                controls = controls.push(
+                    Row::new()
+                        .spacing(8)
+                        .align_items(Alignment::Center)
+                        .push(style::spinner())
+                        .push(Text::new("Loading releases…").size(14)),
+                );
+            }
             controls = controls.push(
                 TextInput::new("Enter Version / Hash / PR Number", &self.source.identifier)
                     .on_input(InstallFrameMessage::SourceIdentifierChanged)
                     .padding(10),
             );
         }
+        controls = controls.push(
+            Row::new()
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .push(Text::new("Architecture:"))
+                .push(PickList::new(
+                    // Only Windows has 32-bit builds
+                    Arch::ALL
+                        .into_iter()
+                        .filter(|a| cfg!(windows) || *a != Arch::X86)
+                        .collect::<Vec<_>>(),
+                    Some(self.source.arch),
+                    InstallFrameMessage::ArchChanged,
+                )),
+        );
+        if cfg!(target_os = "linux") {
+            controls = controls
+                .push(Text::new("Package:"))
+                .push(Radio::new(
+                    "AppImage, kept as a single file and run directly",
+                    InstanceType::AppImage,
+                    Some(self.linux_type),
+                    InstallFrameMessage::LinuxTypeChanged,
+                ))
+                .push(Radio::new(
+                    "Extracted archive",
+                    InstanceType::Linux,
+                    Some(self.linux_type),
+                    InstallFrameMessage::LinuxTypeChanged,
+                ));
+        }
 
+        let has_name = !self.name.trim().is_empty();
+        let can_install = has_name && network::is_online();
         let mut install_button = Button::new(Text::new("Install")).style(text_button());
-        if !self.name.trim().is_empty() {
+        if can_install {
             install_button =
-                install_button.on_press(InstallFrameMessage::StartInstallation(if cfg!(windows) {
-                    InstanceType::Windows
-                } else if cfg!(target_os = "linux") {
-                    InstanceType::AppImage
-                } else {
-                    InstanceType::MacOS
-                }));
+                install_button.on_press(InstallFrameMessage::StartInstallation(self.native_type()));
         }
 
+        let templates = (!self.templates.0.is_empty()).then(|| {
+            self.templates.0.iter().fold(
+                Column::new()
+                    .spacing(5)
+                    .align_items(Alignment::End)
+                    .push(Text::new("Or install from a template:")),
+                |column, (name, template)| {
+                    let mut button = Button::new(Text::new(format!(
+                        "{} ({} {})",
+                        name, template.source.r#type, template.source.identifier
+                    )))
+                    .style(text_button());
+                    // Offline, the build is copied from an instance that has it
+                    if has_name {
+                        button = button.on_press(InstallFrameMessage::InstallFromTemplate(
+                            name.clone(),
+                            self.native_type(),
+                        ));
+                    }
+                    column.push(
+                        Row::new()
+                            .spacing(5)
+                            .align_items(Alignment::Center)
+                            .push(button)
+                            .push(style::tooltip(
+                                Button::new(style::labeled(style::delete_icon(), "Delete"))
+                                    .style(icon_button())
+                                    .on_press(InstallFrameMessage::DeleteTemplate(name.clone())),
+                                "Delete this template",
+                            )),
+                    )
+                },
+            )
+        });
+
         Container::new(Scrollable::new(
             Column::new()
                 .padding(20)
@@ -133,11 +623,74 @@ impl InstallFrame {
                 )
                 .push(
                     TextInput::new("Name (required)", &self.name)
+                        .id(name_input_id())
                         .on_input(InstallFrameMessage::NameChanged)
                         .padding(10),
                 )
+                .push(
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Alignment::Center)
+                        .push(
+                            TextInput::new("Group (optional)", &self.group)
+                                .on_input(InstallFrameMessage::GroupChanged)
+                                .padding(10),
+                        )
+                        .push_maybe((!self.groups.0.is_empty()).then(|| {
+                            PickList::new(
+                                self.groups.names(),
+                                self.groups
+                                    .0
+                                    .contains_key(&self.group)
+                                    .then(|| self.group.clone()),
+                                InstallFrameMessage::GroupSelected,
+                            )
+                            .placeholder("Existing groups")
+                            .padding(10)
+                        })),
+                )
                 .push(controls)
+                .push_maybe(self.error.as_ref().map(|banner| {
+                    banner.view(
+                        InstallFrameMessage::ToggleErrorDetails,
+                        InstallFrameMessage::DismissError,
+                    )
+                }))
+                .push_maybe(self.asset_choice.as_deref().map(asset_choice_view))
+                .push_maybe((!network::is_online()).then(|| {
+                    Text::new(
+                        "Offline, only templates of builds that are installed already can be installed",
+                    )
+                        .size(14)
+                        .style(theme::Text::Color(Color::from_rgb8(150, 150, 150)))
+                }))
                 .push(install_button)
+                .push_maybe(templates)
+                .push(
+                    Column::new()
+                        .spacing(5)
+                        .align_items(Alignment::End)
+                        .push(Text::new("Or import the builds in a folder:"))
+                        .push(
+                            Row::new()
+                                .spacing(5)
+                                .align_items(Alignment::Center)
+                                .push(
+                                    TextInput::new("Folder path", &self.import_dir)
+                                        .on_input(InstallFrameMessage::ImportDirChanged)
+                                        .on_submit(InstallFrameMessage::Import)
+                                        .padding(5),
+                                )
+                                .push(
+                                    Button::new(Text::new("Import"))
+                                        .style(text_button())
+                                        .on_press_maybe(
+                                            (!self.import_dir.trim().is_empty())
+                                                .then_some(InstallFrameMessage::Import),
+                                        ),
+                                ),
+                        ),
+                )
                 .spacing(20)
                 .align_items(Alignment::End),
         ))
@@ -145,3 +698,36 @@ impl InstallFrame {
         .into()
     }
 }
+
+fn asset_choice_view(choice: &AssetChoice) -> Element<InstallFrameMessage> {
+    choice
+        .candidates
+        .iter()
+        .fold(
+            Column::new()
+                .spacing(5)
+                .align_items(Alignment::End)
+                .push(Text::new(format!(
+                    "Several downloads fit {}, which one should be installed?",
+                    choice.name
+                )))
+                .push(
+                    Text::new("Later updates pick downloads named the same way")
+                        .size(14)
+                        .style(theme::Text::Color(Color::from_rgb8(150, 150, 150))),
+                ),
+            |column, candidate| {
+                column.push(
+                    Button::new(Text::new(candidate))
+                        .style(text_button())
+                        .on_press(InstallFrameMessage::AssetChosen(candidate.clone())),
+                )
+            },
+        )
+        .push(
+            Button::new(Text::new("Cancel"))
+                .style(text_button())
+                .on_press(InstallFrameMessage::DismissAssetChoice),
+        )
+        .into()
+}
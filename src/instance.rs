@@ -1,17 +1,50 @@
-use crate::install_frame::InstanceSource;
-use crate::music::MusicCommand;
-use crate::style::icon_button;
-use crate::{get_data_dir, install, send_message, style, update, Message};
-use anyhow::Result;
-use iced::widget::{Button, Column, ProgressBar, Row, Space, Text};
-use iced::{alignment, theme, Alignment, Element, Length};
+use crate::console::{ConsoleMessage, Stream};
+use crate::errors::MissingRuntime;
+use crate::game_logs::CappedLog;
+use crate::github::PRState;
+use crate::install_frame::{
+    AssetChoice, InstallFrameMessage, InstanceSource, InstanceSourceType, BLACKLISTED_CHARS,
+};
+use crate::instance_store::{self, StoreType};
+use crate::instances_frame::DoubleClickAction;
+use crate::job::{JobEvent, JobStatus, PendingJob};
+use crate::launch_options::LaunchOptions;
+use crate::modifications::Modifications;
+use crate::music;
+use crate::notifications::{notify, ErrorBanner, Level, Notification};
+use crate::resources::Usage;
+use crate::safe_mode::{self, PluginsAside};
+use crate::sandbox;
+use crate::settings::{LocalSettings, Settings};
+use crate::stats::Run;
+use crate::style::{icon_button, text_button};
+use crate::templates::{self, Template};
+use crate::trash::{self, Deleted};
+use crate::versions::{self, KeptVersion};
+use crate::{
+    activity, archival, archive, backups, benchmark, compare_frame, crash_summary, desktop_entry,
+    game_logs, get_data_dir, get_game_data_dir, github, groups, high_dpi, install, instance_lock,
+    is_portable, job, kiosk, libraries, logger, modifications, network, plugin_manifest,
+    plugin_profiles, profiles, read_only, runtimes, saves, scripting, send_message, stats, steam,
+    style, sync, telemetry, update, Message,
+};
+use anyhow::{Context, Result};
+use iced::widget::{
+    Button, Checkbox, Column, Container, MouseArea, PickList, ProgressBar, Row, Space, Text,
+    TextInput,
+};
+use iced::{alignment, theme, Alignment, Color, Element, Length};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
 use std::fs::File;
-use std::io::Write;
-use std::path::PathBuf;
-use std::process::Command;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{mpsc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
 use time::{format_description, OffsetDateTime};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -23,17 +56,131 @@ pub enum InstanceType {
     Unknown,
 }
 
+/// How deep into an extracted build to look for the executable
+const DETECT_MAX_DEPTH: usize = 3;
+
+/// The first bytes of ELF binaries, and the AppImage type marker following them at offset 8
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+const APPIMAGE_MAGIC: &[u8] = b"AI";
+/// The first bytes of Windows executables, and of 32-bit, 64-bit and universal Mach-O ones
+const EXECUTABLE_MAGICS: [&[u8]; 5] = [
+    ELF_MAGIC,
+    b"MZ",
+    b"\xce\xfa\xed\xfe",
+    b"\xcf\xfa\xed\xfe",
+    b"\xca\xfe\xba\xbe",
+];
+/// How deep into an instance to look for executables to pick from, deep enough for app bundles
+const EXECUTABLE_CHOICES_MAX_DEPTH: usize = 5;
+
+fn read_header(path: &Path) -> Option<[u8; 11]> {
+    let mut header = [0; 11];
+    File::open(path).ok()?.read_exact(&mut header).ok()?;
+    Some(header)
+}
+
 impl InstanceType {
+    /// Works out the kind of build in `dir` from its contents rather than from file names,
+    /// returning it along with the executable to run.
+    pub fn detect(dir: &Path) -> Option<(Self, PathBuf)> {
+        Self::detect_in(dir, 0)
+    }
+
+    /// The kind of build `path` is the executable of, if it is one.
+    fn detect_file(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy();
+        if name.eq_ignore_ascii_case("Endless Sky.exe")
+            || name.eq_ignore_ascii_case("EndlessSky.exe")
+        {
+            return Some(Self::Windows);
+        }
+        let header = read_header(path)?;
+        if !header.starts_with(ELF_MAGIC) {
+            None
+        } else if header[8..10] == *APPIMAGE_MAGIC {
+            Some(Self::AppImage)
+        } else {
+            (name == "endless-sky").then_some(Self::Linux)
+        }
+    }
+
+    fn detect_in(dir: &Path, depth: usize) -> Option<(Self, PathBuf)> {
+        let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+            .ok()?
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .collect();
+        entries.sort();
+        for path in &entries {
+            let name = path.file_name()?.to_string_lossy();
+            if path.is_dir() {
+                if name.ends_with(".app") {
+                    let executable = path
+                        .join("Contents/MacOS")
+                        .join(name.trim_end_matches(".app"));
+                    if executable.is_file() {
+                        return Some((Self::MacOS, executable));
+                    }
+                }
+                continue;
+            }
+            if let Some(instance_type) = Self::detect_file(path) {
+                return Some((instance_type, path.clone()));
+            }
+        }
+        if depth >= DETECT_MAX_DEPTH {
+            return None;
+        }
+        entries
+            .iter()
+            .filter(|p| p.is_dir())
+            .find_map(|p| Self::detect_in(p, depth + 1))
+    }
+
     pub fn archive_matches(self, archive_name: &str) -> bool {
         match self {
             Self::MacOS => archive_name.contains("mac") || archive_name.ends_with(".dmg"),
-            Self::Windows => archive_name.contains("win64"),
+            Self::Windows => ["win64", "win32", "winarm64"]
+                .iter()
+                .any(|w| archive_name.contains(w)),
             Self::Linux => archive_name.ends_with(".tar.gz"),
             Self::AppImage => archive_name.contains(".AppImage"),
             Self::Unknown => false,
         }
     }
 
+    /// Whether a downloaded file of this name can be installed as this type at all, i.e. is an
+    /// archive that can be unpacked, or the AppImage or disk image itself.
+    pub fn installs_from(self, file_name: &str) -> bool {
+        let name = file_name.to_lowercase();
+        let extensions: &[&str] = match self {
+            Self::AppImage => &[".appimage"],
+            Self::MacOS => &[".dmg", ".zip", ".tar.gz"],
+            _ => &[".zip", ".tar.gz"],
+        };
+        extensions.iter().any(|e| name.ends_with(e))
+    }
+
+    /// How well an asset fits this type, None if it doesn't at all. Assets with the usual
+    /// extension and naming of the platform beat ones that merely contain a marker.
+    pub fn asset_score(self, asset_name: &str) -> Option<u32> {
+        if !self.archive_matches(asset_name) {
+            return None;
+        }
+        let name = asset_name.to_lowercase();
+        let (extensions, markers): (&[&str], &[&str]) = match self {
+            Self::MacOS => (&[".dmg", ".zip"], &["macos", "osx"]),
+            Self::Windows => (&[".zip"], &["windows"]),
+            // Any tarball matches, e.g. a fork's source archive too
+            Self::Linux => (&[".tar.gz"], &["linux"]),
+            Self::AppImage => (&[".appimage"], &[]),
+            Self::Unknown => return None,
+        };
+        let extension = extensions.iter().any(|e| name.ends_with(e));
+        let marker = markers.iter().any(|m| name.contains(m));
+        Some(1 + u32::from(extension) + u32::from(marker))
+    }
+
     pub fn executable(self) -> Option<&'static str> {
         match self {
             Self::MacOS => Some("Endless Sky.app/Contents/MacOS/Endless Sky"),
@@ -45,6 +192,101 @@ impl InstanceType {
     }
 }
 
+/// The CPU architecture of the build to install
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Arch {
+    /// Whatever this machine runs natively
+    #[default]
+    Native,
+    X86_64,
+    Arm64,
+    /// 32-bit, only built for Windows
+    X86,
+}
+
+impl Arch {
+    pub const ALL: [Self; 4] = [Self::Native, Self::X86_64, Self::Arm64, Self::X86];
+
+    fn host() -> Self {
+        match std::env::consts::ARCH {
+            "aarch64" | "arm" => Self::Arm64,
+            "x86" => Self::X86,
+            _ => Self::X86_64,
+        }
+    }
+
+    /// Name fragments that mark assets built for this architecture
+    fn markers(self) -> &'static [&'static str] {
+        match self {
+            Self::Native => Self::host().markers(),
+            Self::X86_64 => &["x86_64", "x64", "amd64", "win64"],
+            Self::Arm64 => &["arm64", "aarch64"],
+            Self::X86 => &["win32", "i686", "i386", "x86"],
+        }
+    }
+
+    /// Whether an asset is built for this architecture. Assets without any marker are
+    /// universal builds, which only match if `allow_universal` is set.
+    pub fn asset_matches(self, asset_name: &str, allow_universal: bool) -> bool {
+        let name = asset_name.to_lowercase();
+        let marked = |arch: &Self| arch.markers().iter().any(|m| name.contains(m));
+        // Checked in this order, since "x86_64" also contains the 32-bit marker "x86"
+        match [Self::Arm64, Self::X86_64, Self::X86]
+            .iter()
+            .find(|a| marked(a))
+        {
+            Some(arch) => self.resolve() == *arch,
+            None => allow_universal,
+        }
+    }
+
+    pub fn resolve(self) -> Self {
+        match self {
+            Self::Native => Self::host(),
+            arch => arch,
+        }
+    }
+}
+
+impl fmt::Display for Arch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Native => write!(f, "Automatic ({})", Self::host()),
+            Self::X86_64 => write!(f, "x86_64"),
+            Self::Arm64 => write!(f, "ARM64"),
+            Self::X86 => write!(f, "32-bit x86"),
+        }
+    }
+}
+
+/// Where exactly the installed build came from, recorded whenever it gets installed or updated
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Provenance {
+    pub release_tag: Option<String>,
+    pub asset_id: Option<i64>,
+    pub asset_name: Option<String>,
+    pub commit: Option<String>,
+    /// RFC 3339, as reported by wherever the build was downloaded from
+    pub build_date: Option<String>,
+}
+
+impl Provenance {
+    /// Labels and values of everything that is known
+    fn entries(&self) -> Vec<(&'static str, String)> {
+        [
+            ("Release", self.release_tag.clone()),
+            ("Asset", self.asset_name.clone()),
+            ("Asset ID", self.asset_id.map(|id| id.to_string())),
+            ("Commit", self.commit.clone()),
+            ("Built", self.build_date.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(label, value)| Some((label, value?)))
+        .collect()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Instance {
     #[serde(skip)]
@@ -56,51 +298,141 @@ pub struct Instance {
     pub version: String,
     pub instance_type: InstanceType,
     pub source: InstanceSource,
+    #[serde(default)]
+    pub plugin_profile: Option<String>,
+    /// Whether the game gets its own config dir (saves, plugins, preferences) inside the instance.
+    #[serde(default)]
+    pub isolated_config: bool,
+    /// Extra arguments passed to the game, separated by whitespace
+    #[serde(default)]
+    pub launch_args: String,
+    /// Whether the Play button launches the game in debug mode, like the Debug button
+    #[serde(default)]
+    pub debug_by_default: bool,
+    /// Whether the high-DPI graphics pack is linked into the instance's plugins folder
+    #[serde(default)]
+    pub high_dpi: bool,
+    #[serde(default)]
+    pub launch_options: LaunchOptions,
+    /// Whether the instance is included in scheduled backups
+    #[serde(default)]
+    pub scheduled_backups: bool,
+    /// Set by the background update check
+    #[serde(skip)]
+    pub update_available: bool,
+    /// Whether updates found by the background check get installed without asking
+    #[serde(default)]
+    pub auto_update: bool,
+    /// Known after the first update check, only for PR instances
+    #[serde(skip)]
+    pub pr_state: Option<PRState>,
+    /// Unix timestamp of the installation, kept across updates
+    #[serde(default)]
+    pub installed: Option<i64>,
+    /// Previous builds that can be switched to, newest first
+    #[serde(default)]
+    pub kept_versions: Vec<KeptVersion>,
+    #[serde(default)]
+    pub provenance: Provenance,
+    /// Whether the provenance is expanded in the list
+    #[serde(skip)]
+    pub show_details: bool,
+    /// When the row was last clicked, to tell double-clicks apart
+    #[serde(skip)]
+    last_click: Option<Instant>,
+    /// How the data files differ from the build, once checked
+    #[serde(skip)]
+    pub modifications: Option<Modifications>,
+    /// Whether the modified files are listed
+    #[serde(skip)]
+    pub show_modifications: bool,
+    /// The recent runs, loaded from the stats while they're shown
+    #[serde(skip)]
+    pub run_history: Option<Vec<Run>>,
+    /// Sampled regularly while the game runs
+    #[serde(skip)]
+    pub usage: Option<Usage>,
+    /// A launch requested while the instance was busy, with whether to debug
+    #[serde(skip)]
+    pub queued_play: Option<bool>,
+    /// Whether the menu with secondary actions is open
+    #[serde(skip)]
+    pub menu_open: bool,
+    #[serde(skip)]
+    pub show_launch_options: bool,
+    /// The name being typed while renaming
+    #[serde(skip)]
+    pub rename: Option<String>,
+    /// The backups that can be restored, listed when the menu opens
+    #[serde(skip)]
+    pub backups: Vec<String>,
+    #[serde(skip)]
+    pub selected_backup: Option<String>,
+    /// What's broken about the instance, as found by `check_health`
+    #[serde(skip)]
+    pub problems: Vec<String>,
+    /// In bytes, measured in the background since large instances take a while
+    #[serde(skip)]
+    pub disk_size: Option<u64>,
+    /// See `groups`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// The folder being entered for an instance whose folder moved
+    #[serde(skip)]
+    pub locate: Option<String>,
+    /// Files and folders in the instance dir, relative to it, that updates leave as they are
+    #[serde(default)]
+    pub preserved: Vec<PathBuf>,
+    /// The path being entered to preserve
+    #[serde(skip)]
+    pub preserve_input: String,
+    /// The executable to run instead of the detected one, relative to the instance folder.
+    /// Picked again after updates, as long as the new build still has it.
+    #[serde(default)]
+    pub executable_override: Option<PathBuf>,
+    /// The executables in the instance to pick from, while picking one
+    #[serde(skip)]
+    pub executable_choices: Option<Vec<String>>,
 }
 
-#[derive(Debug, Clone, Default)]
-pub enum InstanceState {
-    Playing,
-    Working(Progress),
-    #[default]
-    Ready,
-}
+const NO_PROFILE: &str = "No plugin profile";
+/// How many modified data files are listed before the rest get summarized
+const MAX_LISTED_MODIFICATIONS: usize = 50;
+/// Longest time between two clicks on a row that still counts as a double-click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+/// Bytes of a game's output kept in memory for crash reports
+const MAX_COLLECTED_OUTPUT: usize = 1024 * 1024;
 
-#[derive(Debug, Clone, Default)]
-pub struct Progress {
-    status: String,
-    done: Option<u32>,
-    total: Option<u32>,
-    units: Option<String>,
-    total_approx: bool,
+/// Process ids and config dirs of the games that are currently running, by instance name
+static RUNNING: Mutex<BTreeMap<String, (u32, Option<PathBuf>)>> = Mutex::new(BTreeMap::new());
+/// Looked up once instead of reading local.json every time, e.g. on every frame
+static INSTANCES_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+/// Looked up once like [`INSTANCES_DIR`], since the instances are saved on every change
+static INSTANCE_STORE: Mutex<Option<StoreType>> = Mutex::new(None);
+
+/// The running games and their process ids, by instance name.
+pub fn running_pids() -> Vec<(String, u32)> {
+    RUNNING
+        .lock()
+        .map(|running| running.iter().map(|(n, (p, _))| (n.clone(), *p)).collect())
+        .unwrap_or_default()
 }
 
-impl Progress {
-    pub fn total(mut self, total: impl Into<Option<u32>>) -> Self {
-        self.total = total.into();
-        self
-    }
-    pub fn done(mut self, done: impl Into<Option<u32>>) -> Self {
-        self.done = done.into();
-        self
-    }
-    pub fn units<T: AsRef<str>>(mut self, units: T) -> Self {
-        self.units = Some(units.as_ref().into());
-        self
-    }
-    pub fn total_approx(mut self, total_approx: bool) -> Self {
-        self.total_approx = total_approx;
-        self
-    }
+/// A running game other than `name` that uses `config_dir`, if any.
+fn running_with_config(config_dir: &Path, name: &str) -> Option<String> {
+    RUNNING.lock().ok()?.iter().find_map(|(other, (_, dir))| {
+        (other != name && dir.as_deref() == Some(config_dir)).then(|| other.clone())
+    })
 }
 
-impl<T: AsRef<str>> From<T> for Progress {
-    fn from(status: T) -> Self {
-        Self {
-            status: status.as_ref().into(),
-            ..Default::default()
-        }
-    }
+#[derive(Debug, Clone, Default)]
+pub enum InstanceState {
+    Playing,
+    Working(JobStatus),
+    /// The last operation failed, otherwise the same as Ready
+    Failed(ErrorBanner),
+    #[default]
+    Ready,
 }
 
 impl InstanceState {
@@ -111,17 +443,97 @@ impl InstanceState {
         matches!(self, Self::Working { .. })
     }
     pub fn is_ready(&self) -> bool {
-        matches!(self, Self::Ready)
+        matches!(self, Self::Ready | Self::Failed(_))
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum InstanceMessage {
     Play(bool),
+    /// Plays once with the plugins moved aside, to tell whether one of them is the problem
+    PlayWithoutPlugins,
     Update,
     Folder,
+    ConfigFolder,
+    OpenSource,
     Delete,
+    ProfileSelected(String),
+    IsolatedConfigToggled(bool),
+    LaunchArgsChanged(String),
+    DebugByDefaultToggled(bool),
+    HighDpiToggled(bool),
+    ToggleLaunchOptions,
+    LaunchOptionsChanged(LaunchOptions),
+    OpenLogs,
+    ClearLogs,
+    SaveAsTemplate,
+    /// Makes this instance's configuration what new instances in its group start out with
+    SaveAsGroupDefaults,
+    SwitchVersion(String),
+    /// Stops the current job
+    Cancel,
+    /// Starts the step a stalled job hangs in over
+    RetryStalled,
+    /// Plays once the current job finishes, in debug mode if true
+    QueuePlay(bool),
+    UnqueuePlay,
+    ToggleDetails,
+    /// A click on the row outside of its buttons, which does the action on the second one
+    RowClicked(DoubleClickAction),
+    ToggleModifications,
+    ToggleRunHistory,
+    ModificationsChecked(Option<Modifications>),
+    HealthChecked(Vec<String>),
+    SizeMeasured(u64),
+    ToggleErrorDetails,
+    DismissError,
+    ToggleMenu,
+    /// Moves a release instance to continuous builds or vice versa
+    SwitchChannel(InstanceSourceType),
+    StartRename,
+    RenameChanged(String),
+    ConfirmRename,
+    CancelRename,
+    Clone,
+    Export,
+    /// Writes the instance's plugin list into a manifest, see [`plugin_manifest`]
+    ExportPlugins,
+    /// Compresses the instance into a single file and removes its files, see [`archival`]
+    Archive,
+    /// Times how long the game takes to load, see [`benchmark`]
+    Benchmark,
+    Verify,
+    AddToSteam,
+    /// Installs the build again, keeping the instance's settings
+    Reinstall,
+    /// Points an instance whose folder moved to its new place
+    StartLocate,
+    LocateChanged(String),
+    ConfirmLocate,
+    CancelLocate,
+    CreateShortcut,
+    AutoUpdateToggled(bool),
+    /// Lists the executables in the instance to pick from
+    PickExecutable,
+    ExecutablesListed(Vec<String>),
+    ExecutableSelected(String),
+    CancelPickExecutable,
+    /// Goes back to the executable the launcher detects by itself
+    ResetExecutable,
+    PreserveInputChanged(String),
+    /// Keeps the entered path as it is through updates
+    Preserve,
+    Unpreserve(PathBuf),
+    ScheduledBackupsToggled(bool),
+    BackUpNow,
+    BackupSelected(String),
+    RestoreBackup,
+    /// Downloads and starts the Visual C++ runtime's installer
+    InstallRuntime,
+    /// Opens a new issue about a crash, pre-filled by this URL
+    ReportCrash(String),
     StateChanged(InstanceState),
+    JobEvent(JobEvent),
 }
 
 impl Instance {
@@ -142,37 +554,146 @@ impl Instance {
             version,
             instance_type,
             source,
+            plugin_profile: None,
+            isolated_config: false,
+            launch_args: String::new(),
+            debug_by_default: false,
+            high_dpi: false,
+            launch_options: LaunchOptions::default(),
+            scheduled_backups: false,
+            update_available: false,
+            auto_update: false,
+            pr_state: None,
+            installed: Some(OffsetDateTime::now_utc().unix_timestamp()),
+            kept_versions: vec![],
+            provenance: Provenance::default(),
+            show_details: false,
+            last_click: None,
+            modifications: None,
+            show_modifications: false,
+            run_history: None,
+            usage: None,
+            queued_play: None,
+            menu_open: false,
+            show_launch_options: false,
+            rename: None,
+            backups: vec![],
+            selected_backup: None,
+            problems: vec![],
+            disk_size: None,
+            group: None,
+            locate: None,
+            preserved: vec![],
+            preserve_input: String::new(),
+            executable_override: None,
+            executable_choices: None,
+        }
+    }
+
+    pub fn logs_dir(&self) -> PathBuf {
+        self.path.join("logs")
+    }
+
+    /// The config directory the game uses when launched from this instance.
+    pub fn config_dir(&self) -> Option<PathBuf> {
+        let custom = self.launch_options.config.trim();
+        if !custom.is_empty() {
+            Some(PathBuf::from(custom))
+        } else if self.isolated_config {
+            Some(self.path.join("config"))
+        } else {
+            get_game_data_dir()
         }
     }
 
+    /// The config dir to pass to the game, if it doesn't use the default one.
+    pub fn config_arg(&self) -> Option<PathBuf> {
+        let custom = !self.launch_options.config.trim().is_empty();
+        if custom || self.isolated_config || is_portable() || profiles::current().is_some() {
+            self.config_dir()
+        } else {
+            None
+        }
+    }
+
+    fn apply_plugin_profile(&self) {
+        if let (Some(profile), Some(config_dir)) = (&self.plugin_profile, self.config_dir()) {
+            if let Err(e) = plugin_profiles::apply(profile, &config_dir, self.isolated_config) {
+                error!("Failed to apply plugin profile: {:#}", e);
+            }
+        }
+    }
+
+    fn start_playing(&self, do_debug: bool, without_plugins: bool) -> iced::Command<Message> {
+        let name1 = self.name.clone(); // (Jett voice)
+        let name2 = self.name.clone(); // "Yikes!"
+
+        iced::Command::batch(vec![
+            iced::Command::perform(dummy(), move |()| {
+                Message::InstanceMessage(
+                    name1,
+                    InstanceMessage::StateChanged(InstanceState::Playing),
+                )
+            }),
+            iced::Command::perform(
+                logger::with_job(
+                    self.name.clone(),
+                    perform_play(self.clone(), do_debug, without_plugins),
+                ),
+                move |state| Message::InstanceMessage(name2, InstanceMessage::StateChanged(state)),
+            ),
+        ])
+    }
+
     pub fn update(&mut self, message: InstanceMessage) -> iced::Command<Message> {
         match message {
-            InstanceMessage::Play(do_debug) => {
-                let name1 = self.name.clone(); // (Jett voice)
-                let name2 = self.name.clone(); // "Yikes!"
-
-                iced::Command::batch(vec![
-                    iced::Command::perform(dummy(), move |()| {
-                        Message::InstanceMessage(
-                            name1,
-                            InstanceMessage::StateChanged(InstanceState::Playing),
-                        )
-                    }),
-                    iced::Command::perform(
-                        perform_play(
-                            self.path.clone(),
-                            self.executable.clone(),
-                            self.name.clone(),
-                            do_debug,
-                        ),
-                        move |()| {
-                            Message::InstanceMessage(
-                                name2,
-                                InstanceMessage::StateChanged(InstanceState::Ready),
-                            )
-                        },
-                    ),
-                ])
+            InstanceMessage::Play(do_debug) if self.state.is_working() => {
+                self.update(InstanceMessage::QueuePlay(do_debug))
+            }
+            InstanceMessage::Play(do_debug) => self.start_playing(do_debug, false),
+            InstanceMessage::PlayWithoutPlugins => {
+                self.menu_open = false;
+                self.start_playing(self.debug_by_default, true)
+            }
+            InstanceMessage::StartLocate => {
+                let candidate = self.relink_candidate().unwrap_or_else(|| self.path.clone());
+                self.locate = Some(candidate.to_string_lossy().to_string());
+                iced::Command::none()
+            }
+            InstanceMessage::LocateChanged(path) => {
+                self.locate = Some(path);
+                iced::Command::none()
+            }
+            InstanceMessage::CancelLocate => {
+                self.locate = None;
+                iced::Command::none()
+            }
+            InstanceMessage::ConfirmLocate => match self.locate.take() {
+                Some(path) => match self.relink(PathBuf::from(path.trim())) {
+                    Ok(()) => {
+                        let instance = self.clone();
+                        iced::Command::perform(dummy(), move |()| {
+                            Message::AddInstance(Box::new(instance))
+                        })
+                    }
+                    Err(e) => {
+                        error!("Failed to relink {}: {:#}", self.name, e);
+                        notify(Notification::new(
+                            Level::Error,
+                            format!("Relinking {} failed: {}", self.name, e),
+                        ));
+                        self.locate = Some(path);
+                        iced::Command::none()
+                    }
+                },
+                None => iced::Command::none(),
+            },
+            InstanceMessage::Reinstall => {
+                self.state = InstanceState::Working(JobStatus::new("Reinstalling"));
+                iced::Command::perform(
+                    logger::with_job(self.name.clone(), perform_reinstall(self.clone())),
+                    Message::Dummy,
+                )
             }
             InstanceMessage::Update => {
                 let name = self.name.clone();
@@ -180,140 +701,1695 @@ impl Instance {
                     iced::Command::perform(dummy(), move |()| {
                         Message::InstanceMessage(
                             name,
-                            InstanceMessage::StateChanged(InstanceState::Working(
-                                "Updating".into(),
-                            )),
+                            InstanceMessage::StateChanged(InstanceState::Working(JobStatus::new(
+                                "Updating",
+                            ))),
                         )
                     }),
-                    iced::Command::perform(perform_update(self.clone()), Message::Dummy),
+                    iced::Command::perform(
+                        logger::with_job(self.name.clone(), perform_update(self.clone())),
+                        Message::Dummy,
+                    ),
                 ])
             }
             InstanceMessage::Folder => {
                 iced::Command::perform(open_folder(self.path.clone()), Message::Dummy)
             }
+            InstanceMessage::ConfigFolder => match self.config_dir() {
+                Some(dir) => {
+                    // The game only creates it on first launch
+                    if let Err(e) = fs::create_dir_all(&dir) {
+                        error!("Failed to create config folder: {}", e);
+                    }
+                    iced::Command::perform(open_folder(dir), Message::Dummy)
+                }
+                None => iced::Command::none(),
+            },
+            InstanceMessage::InstallRuntime => {
+                iced::Command::perform(runtimes::perform_install(self.source.arch), Message::Dummy)
+            }
+            InstanceMessage::ReportCrash(url) => {
+                if let Err(e) = open::that(&url) {
+                    error!("URL could not be opened: '{}': {}", url, e);
+                }
+                iced::Command::none()
+            }
+            InstanceMessage::OpenSource => {
+                if let Some(url) = self.source_url() {
+                    info!("Opening {}", url);
+                    if let Err(e) = open::that(&url) {
+                        error!("URL could not be opened: '{}': {}", url, e);
+                    }
+                }
+                iced::Command::none()
+            }
             InstanceMessage::Delete => {
-                let name = self.name.clone();
-                iced::Command::perform(delete(self.path.clone()), move |_| {
-                    Message::RemoveInstance(Some(name))
-                })
+                if let Err(e) = instance_lock::check(&self.path) {
+                    error!("Failed to delete {}: {:#}", self.name, e);
+                    notify(Notification::new(
+                        Level::Error,
+                        format!("{} is in use and can't be deleted right now", self.name),
+                    ));
+                    return iced::Command::none();
+                }
+                // Kept versions go along, so undoing brings them back as well
+                let paths = std::iter::once(self.path.clone())
+                    .chain(versions::get_versions_dir(&self.name))
+                    .collect();
+                desktop_entry::remove(&self.name);
+                iced::Command::perform(
+                    trash::perform_hold(Deleted::Instance(Box::new(self.clone())), paths),
+                    |held| held.map_or(Message::Dummy(()), Message::Deleted),
+                )
             }
-            InstanceMessage::StateChanged(state) => {
-                self.state = state;
+            InstanceMessage::ProfileSelected(profile) => {
+                self.plugin_profile = (profile != NO_PROFILE).then_some(profile);
+                self.apply_plugin_profile();
+                let instance = self.clone();
+                iced::Command::perform(dummy(), move |()| Message::AddInstance(Box::new(instance)))
+            }
+            InstanceMessage::IsolatedConfigToggled(isolated) => {
+                self.isolated_config = isolated;
+                self.apply_plugin_profile();
+                high_dpi::apply(self);
+                let instance = self.clone();
+                iced::Command::perform(dummy(), move |()| Message::AddInstance(Box::new(instance)))
+            }
+            InstanceMessage::LaunchArgsChanged(args) => {
+                self.launch_args = args;
+                let instance = self.clone();
+                iced::Command::perform(dummy(), move |()| Message::AddInstance(Box::new(instance)))
+            }
+            InstanceMessage::HighDpiToggled(true) => {
+                self.high_dpi = true;
+                self.state = InstanceState::Working(JobStatus::new("Setting up high-DPI graphics"));
+                iced::Command::perform(
+                    logger::with_job(self.name.clone(), high_dpi::perform_enable(self.clone())),
+                    Message::Dummy,
+                )
+            }
+            InstanceMessage::HighDpiToggled(false) => {
+                self.high_dpi = false;
+                if let Err(e) = high_dpi::remove(self) {
+                    error!("Failed to remove the high-DPI pack: {:#}", e);
+                }
+                let instance = self.clone();
+                iced::Command::perform(dummy(), move |()| Message::AddInstance(Box::new(instance)))
+            }
+            InstanceMessage::DebugByDefaultToggled(debug) => {
+                self.debug_by_default = debug;
+                let instance = self.clone();
+                iced::Command::perform(dummy(), move |()| Message::AddInstance(Box::new(instance)))
+            }
+            InstanceMessage::ToggleLaunchOptions => {
+                self.show_launch_options = !self.show_launch_options;
                 iced::Command::none()
             }
-        }
-    }
-
-    pub fn view(&self) -> Element<InstanceMessage> {
-        // Buttons
-        let mut debug_button = Button::new(style::debug_icon()).style(icon_button());
-        let mut play_button = Button::new(style::play_icon()).style(icon_button());
-        let mut update_button = Button::new(style::update_icon()).style(icon_button());
-        let folder_button = Button::new(style::folder_icon())
-            .style(icon_button())
-            .on_press(InstanceMessage::Folder);
-        let mut delete_button = Button::new(style::delete_icon()).style(theme::Button::Destructive);
-
-        if self.state.is_ready() {
-            debug_button = debug_button.on_press(InstanceMessage::Play(true));
-            play_button = play_button.on_press(InstanceMessage::Play(false));
-            update_button = update_button.on_press(InstanceMessage::Update);
-            delete_button = delete_button.on_press(InstanceMessage::Delete);
-        }
-
-        // Layout
-        Row::new()
-            .spacing(10)
-            .padding(10)
-            .align_items(Alignment::Start)
-            .width(Length::Fill)
-            .push(
-                Column::new()
-                    .push(Text::new(&self.name).size(24))
-                    .push(Text::new(format!("Version: {:.*}", 32, self.version)).size(10))
-                    .push(
-                        Text::new(format!(
-                            "Source: {} {}",
-                            self.source.r#type, self.source.identifier
-                        ))
-                        .size(10),
-                    ),
-            )
-            .push(Space::new(Length::Fill, Length::Shrink))
-            .push({
-                if let InstanceState::Working(progress) = &self.state {
-                    let mut status_field = Column::new().align_items(Alignment::Center).push(
-                        Text::new(&progress.status)
-                            .size(16)
-                            .horizontal_alignment(alignment::Horizontal::Center),
+            InstanceMessage::LaunchOptionsChanged(options) => {
+                self.launch_options = options;
+                let instance = self.clone();
+                iced::Command::perform(dummy(), move |()| Message::AddInstance(Box::new(instance)))
+            }
+            InstanceMessage::OpenLogs => {
+                self.menu_open = false;
+                let dir = self.logs_dir();
+                if let Err(e) = fs::create_dir_all(&dir) {
+                    error!("Failed to create logs folder: {}", e);
+                }
+                iced::Command::perform(open_folder(dir), Message::Dummy)
+            }
+            InstanceMessage::ClearLogs => {
+                self.menu_open = false;
+                if let Err(e) = game_logs::clear(&self.logs_dir()) {
+                    error!("Failed to clear logs: {:#}", e);
+                }
+                iced::Command::none()
+            }
+            InstanceMessage::SaveAsTemplate => {
+                self.menu_open = false;
+                match templates::save_instance(self) {
+                    Ok(()) => notify(Notification::new(
+                        Level::Success,
+                        format!("Saved {} as a template", self.name),
+                    )),
+                    Err(e) => error!("Failed to save template: {:#}", e),
+                }
+                iced::Command::perform(dummy(), |()| {
+                    Message::InstallFrameMessage(InstallFrameMessage::ReloadTemplates)
+                })
+            }
+            InstanceMessage::SaveAsGroupDefaults => {
+                self.menu_open = false;
+                match groups::save_defaults(self) {
+                    Ok(()) => notify(Notification::new(
+                        Level::Success,
+                        format!(
+                            "New instances in {} start out like {} now",
+                            self.group.as_deref().unwrap_or_default(),
+                            self.name
+                        ),
+                    )),
+                    Err(e) => error!("Failed to save group defaults: {:#}", e),
+                }
+                iced::Command::perform(dummy(), |()| {
+                    Message::InstallFrameMessage(InstallFrameMessage::ReloadGroups)
+                })
+            }
+            InstanceMessage::SwitchVersion(version) => {
+                if self.auto_update {
+                    // Otherwise the next update check would switch right back
+                    info!(
+                        "Turning off auto-update for {}, it stays on {}",
+                        self.name, version
                     );
-                    if let (Some(done), Some(total)) = (progress.done, progress.total) {
-                        status_field = status_field.push(
-                            ProgressBar::new(0.0..=total as f32, done as f32)
-                                .height(Length::Fixed(5.)),
-                        );
-                    }
-                    if let Some(done) = progress.done {
-                        status_field = status_field.push(
-                            Text::new(format!(
-                                "{}/{}{}{}",
-                                done,
-                                if progress.total_approx { "~" } else { "" },
-                                progress.total.map_or_else(|| "?".into(), |u| u.to_string()),
-                                progress.units.as_ref().unwrap_or(&String::new())
-                            ))
-                            .size(12)
-                            .horizontal_alignment(alignment::Horizontal::Center),
-                        );
+                    self.auto_update = false;
+                }
+                self.state = InstanceState::Working(JobStatus::new("Switching version"));
+                iced::Command::perform(
+                    logger::with_job(
+                        self.name.clone(),
+                        perform_switch_version(self.clone(), version),
+                    ),
+                    Message::Dummy,
+                )
+            }
+            InstanceMessage::Cancel => {
+                job::cancel(&self.name);
+                if let InstanceState::Working(status) = &mut self.state {
+                    status.stage = "Cancelling".into();
+                    status.progress = None;
+                }
+                iced::Command::none()
+            }
+            InstanceMessage::RetryStalled => {
+                job::retry(&self.name);
+                if let InstanceState::Working(status) = &mut self.state {
+                    status.stalled = false;
+                }
+                iced::Command::none()
+            }
+            InstanceMessage::QueuePlay(do_debug) => {
+                info!("Launching {} once it's done", self.name);
+                self.queued_play = Some(do_debug);
+                iced::Command::none()
+            }
+            InstanceMessage::UnqueuePlay => {
+                self.queued_play = None;
+                iced::Command::none()
+            }
+            InstanceMessage::ToggleModifications => {
+                self.show_modifications = !self.show_modifications;
+                iced::Command::none()
+            }
+            InstanceMessage::ToggleRunHistory => {
+                self.run_history = match self.run_history {
+                    Some(_) => None,
+                    None => Some(stats::runs(&self.name)),
+                };
+                iced::Command::none()
+            }
+            InstanceMessage::ModificationsChecked(modifications) => {
+                self.modifications = modifications;
+                iced::Command::none()
+            }
+            InstanceMessage::HealthChecked(problems) => {
+                self.set_problems(problems);
+                iced::Command::none()
+            }
+            InstanceMessage::SizeMeasured(size) => {
+                self.disk_size = Some(size);
+                iced::Command::none()
+            }
+            InstanceMessage::RowClicked(action) => {
+                let now = Instant::now();
+                let double = self
+                    .last_click
+                    .is_some_and(|last| now.duration_since(last) <= DOUBLE_CLICK_INTERVAL);
+                // A third click starts over instead of counting as another double-click
+                self.last_click = (!double).then_some(now);
+                if !double {
+                    return iced::Command::none();
+                }
+                match action {
+                    DoubleClickAction::Nothing => iced::Command::none(),
+                    DoubleClickAction::Play => {
+                        self.update(InstanceMessage::Play(self.debug_by_default))
                     }
-                    Row::new()
-                        .push(Space::with_width(Length::FillPortion(1)))
-                        .push(status_field.width(Length::FillPortion(2)))
-                } else {
-                    Row::new()
-                        .spacing(10)
-                        .push(debug_button)
-                        .push(play_button)
-                        .push(update_button)
-                        .push(folder_button)
-                        .push(delete_button)
+                    DoubleClickAction::OpenFolder => self.update(InstanceMessage::Folder),
+                    DoubleClickAction::ToggleDetails => self.update(InstanceMessage::ToggleDetails),
                 }
-            })
-            .into()
-    }
-}
-
-async fn dummy() {}
-
-pub async fn perform_install(
-    path: PathBuf,
-    name: String,
-    instance_type: InstanceType,
-    instance_source: InstanceSource,
-) {
-    send_message(Message::AddInstance(Box::new(Instance::new(
-        path.clone(),
-        "provisional".into(),
-        name.clone(),
-        instance_source.identifier.clone(),
-        instance_type,
-        instance_source.clone(),
-        InstanceState::Working(Progress::default()),
-    ))));
-    match install::install(path, name.clone(), instance_type, instance_source) {
-        Ok(instance) => {
+            }
+            InstanceMessage::ToggleDetails => {
+                self.show_details = !self.show_details;
+                iced::Command::none()
+            }
+            InstanceMessage::ToggleErrorDetails => {
+                if let InstanceState::Failed(banner) = &mut self.state {
+                    banner.expanded = !banner.expanded;
+                }
+                iced::Command::none()
+            }
+            InstanceMessage::DismissError => {
+                if let InstanceState::Failed(_) = self.state {
+                    self.state = InstanceState::Ready;
+                }
+                iced::Command::none()
+            }
+            InstanceMessage::ToggleMenu => {
+                self.menu_open = !self.menu_open;
+                if self.menu_open {
+                    let config = Settings::load().local.backups;
+                    self.backups = backups::list(&config, &self.name)
+                        .iter()
+                        .map(|archive| backups::label(archive))
+                        .collect();
+                    self.selected_backup = None;
+                }
+                iced::Command::none()
+            }
+            InstanceMessage::SwitchChannel(channel) => {
+                self.menu_open = false;
+                self.state = InstanceState::Working(JobStatus::new("Switching channel"));
+                iced::Command::perform(
+                    logger::with_job(
+                        self.name.clone(),
+                        perform_switch_channel(self.clone(), channel),
+                    ),
+                    Message::Dummy,
+                )
+            }
+            InstanceMessage::StartRename => {
+                self.menu_open = false;
+                self.rename = Some(self.name.clone());
+                iced::Command::none()
+            }
+            InstanceMessage::RenameChanged(name) => {
+                self.rename = Some(name);
+                iced::Command::none()
+            }
+            InstanceMessage::ConfirmRename => match self.rename.take() {
+                Some(new_name) if new_name != self.name => {
+                    let old_name = self.name.clone();
+                    iced::Command::perform(dummy(), move |()| {
+                        Message::RenameInstance(old_name, new_name)
+                    })
+                }
+                _ => iced::Command::none(),
+            },
+            InstanceMessage::CancelRename => {
+                self.rename = None;
+                iced::Command::none()
+            }
+            InstanceMessage::Clone => {
+                self.menu_open = false;
+                let name = self.name.clone();
+                iced::Command::perform(dummy(), move |()| Message::CloneInstance(name))
+            }
+            InstanceMessage::Export => {
+                self.menu_open = false;
+                self.state = InstanceState::Working(JobStatus::new("Exporting"));
+                let name = self.name.clone();
+                iced::Command::perform(
+                    logger::with_job(self.name.clone(), perform_export(self.clone())),
+                    move |state| {
+                        Message::InstanceMessage(name, InstanceMessage::StateChanged(state))
+                    },
+                )
+            }
+            InstanceMessage::Benchmark => {
+                self.menu_open = false;
+                self.state = InstanceState::Working(JobStatus::new("Benchmarking"));
+                let name = self.name.clone();
+                iced::Command::perform(
+                    logger::with_job(
+                        self.name.clone(),
+                        benchmark::perform_benchmark(self.clone()),
+                    ),
+                    move |state| {
+                        Message::InstanceMessage(name, InstanceMessage::StateChanged(state))
+                    },
+                )
+            }
+            InstanceMessage::ExportPlugins => {
+                self.menu_open = false;
+                iced::Command::perform(
+                    logger::with_job(
+                        self.name.clone(),
+                        plugin_manifest::perform_export(self.clone()),
+                    ),
+                    Message::Dummy,
+                )
+            }
+            InstanceMessage::Archive => {
+                self.menu_open = false;
+                self.state = InstanceState::Working(JobStatus::new("Archiving"));
+                let name = self.name.clone();
+                iced::Command::perform(
+                    logger::with_job(self.name.clone(), archival::perform_archive(self.clone())),
+                    move |result| match result {
+                        Ok(()) => Message::RemoveInstance(Some(name)),
+                        Err(e) => Message::InstanceMessage(
+                            name,
+                            InstanceMessage::StateChanged(InstanceState::Failed(ErrorBanner::new(
+                                "Archiving failed",
+                                &e,
+                            ))),
+                        ),
+                    },
+                )
+            }
+            InstanceMessage::PickExecutable => {
+                let name = self.name.clone();
+                iced::Command::perform(perform_list_executables(self.path.clone()), move |list| {
+                    Message::InstanceMessage(name, InstanceMessage::ExecutablesListed(list))
+                })
+            }
+            InstanceMessage::ExecutablesListed(list) => {
+                if list.is_empty() {
+                    notify(Notification::new(
+                        Level::Warning,
+                        format!("There are no executables in {}", self.name),
+                    ));
+                } else {
+                    self.executable_choices = Some(list);
+                }
+                iced::Command::none()
+            }
+            InstanceMessage::CancelPickExecutable => {
+                self.executable_choices = None;
+                iced::Command::none()
+            }
+            InstanceMessage::ExecutableSelected(relative) => {
+                self.executable_choices = None;
+                let relative = PathBuf::from(relative);
+                if !self.path.join(&relative).is_file() {
+                    error!(
+                        "{} isn't a file in {}",
+                        relative.to_string_lossy(),
+                        self.path.to_string_lossy()
+                    );
+                    return iced::Command::none();
+                }
+                info!(
+                    "Running {} for {} from now on",
+                    relative.to_string_lossy(),
+                    self.name
+                );
+                self.executable = self.path.join(&relative);
+                self.executable_override = Some(relative);
+                self.check_health();
+                let instance = self.clone();
+                iced::Command::perform(dummy(), move |()| Message::AddInstance(Box::new(instance)))
+            }
+            InstanceMessage::ResetExecutable => {
+                self.executable_override = None;
+                match InstanceType::detect(&self.path) {
+                    Some((_, executable)) => self.executable = executable,
+                    None => warn!(
+                        "No executable found in {}, keeping {}",
+                        self.path.to_string_lossy(),
+                        self.executable.to_string_lossy()
+                    ),
+                }
+                self.check_health();
+                let instance = self.clone();
+                iced::Command::perform(dummy(), move |()| Message::AddInstance(Box::new(instance)))
+            }
+            InstanceMessage::PreserveInputChanged(input) => {
+                self.preserve_input = input;
+                iced::Command::none()
+            }
+            InstanceMessage::Preserve => {
+                let entered = PathBuf::from(self.preserve_input.trim());
+                let relative = entered
+                    .strip_prefix(&self.path)
+                    .map(Path::to_path_buf)
+                    .unwrap_or(entered);
+                let inside = !relative.as_os_str().is_empty()
+                    && relative
+                        .components()
+                        .all(|c| matches!(c, std::path::Component::Normal(_)));
+                if !inside || !self.path.join(&relative).exists() {
+                    error!(
+                        "{} isn't a file or folder in {}",
+                        relative.to_string_lossy(),
+                        self.path.to_string_lossy()
+                    );
+                    return iced::Command::none();
+                }
+                self.preserve_input.clear();
+                if self.preserved.contains(&relative) {
+                    return iced::Command::none();
+                }
+                info!(
+                    "Preserving {} of {} on updates",
+                    relative.to_string_lossy(),
+                    self.name
+                );
+                self.preserved.push(relative);
+                let instance = self.clone();
+                iced::Command::perform(dummy(), move |()| Message::AddInstance(Box::new(instance)))
+            }
+            InstanceMessage::Unpreserve(path) => {
+                self.preserved.retain(|p| *p != path);
+                let instance = self.clone();
+                iced::Command::perform(dummy(), move |()| Message::AddInstance(Box::new(instance)))
+            }
+            InstanceMessage::AutoUpdateToggled(enabled) => {
+                self.auto_update = enabled;
+                let instance = self.clone();
+                iced::Command::perform(dummy(), move |()| Message::AddInstance(Box::new(instance)))
+            }
+            InstanceMessage::ScheduledBackupsToggled(enabled) => {
+                self.scheduled_backups = enabled;
+                let instance = self.clone();
+                iced::Command::perform(dummy(), move |()| Message::AddInstance(Box::new(instance)))
+            }
+            InstanceMessage::BackUpNow => {
+                self.menu_open = false;
+                self.state = InstanceState::Working(JobStatus::new("Backing up"));
+                let name = self.name.clone();
+                iced::Command::perform(
+                    logger::with_job(self.name.clone(), perform_backup(self.clone())),
+                    move |state| {
+                        Message::InstanceMessage(name, InstanceMessage::StateChanged(state))
+                    },
+                )
+            }
+            InstanceMessage::BackupSelected(backup) => {
+                self.selected_backup = Some(backup);
+                iced::Command::none()
+            }
+            InstanceMessage::RestoreBackup => match self.selected_backup.take() {
+                Some(backup) => {
+                    self.menu_open = false;
+                    self.state = InstanceState::Working(JobStatus::new("Restoring backup"));
+                    iced::Command::perform(
+                        logger::with_job(self.name.clone(), perform_restore(self.clone(), backup)),
+                        Message::Dummy,
+                    )
+                }
+                None => iced::Command::none(),
+            },
+            InstanceMessage::Verify => {
+                self.menu_open = false;
+                self.check_health();
+                let problems = &self.problems;
+                if problems.is_empty() {
+                    notify(Notification::new(
+                        Level::Success,
+                        format!("All files of {} are in place", self.name),
+                    ));
+                } else {
+                    notify(Notification::new(
+                        Level::Warning,
+                        format!(
+                            "{} has {} problems, see the log for details",
+                            self.name,
+                            problems.len()
+                        ),
+                    ));
+                }
+                modifications::check_all(std::iter::once(&*self))
+            }
+            InstanceMessage::CreateShortcut => {
+                self.menu_open = false;
+                match desktop_entry::create_shortcut(self) {
+                    Ok(_) => notify(Notification::new(
+                        Level::Success,
+                        format!("Created a desktop shortcut for {}", self.name),
+                    )),
+                    Err(e) => {
+                        error!("Failed to create shortcut: {:#}", e);
+                        notify(Notification::new(
+                            Level::Error,
+                            "Creating the shortcut failed, see the log for details",
+                        ));
+                    }
+                }
+                iced::Command::none()
+            }
+            InstanceMessage::AddToSteam => {
+                self.menu_open = false;
+                match steam::add_shortcut(self) {
+                    Ok(0) => notify(Notification::new(
+                        Level::Info,
+                        format!("{} already is in Steam", self.name),
+                    )),
+                    Ok(_) => notify(Notification::new(
+                        Level::Success,
+                        format!(
+                            "Added {} to Steam, it shows up once Steam is restarted",
+                            self.name
+                        ),
+                    )),
+                    Err(e) => {
+                        error!("Failed to add {} to Steam: {:#}", self.name, e);
+                        notify(Notification::new(
+                            Level::Error,
+                            format!("Adding {} to Steam failed: {}", self.name, e),
+                        ));
+                    }
+                }
+                iced::Command::none()
+            }
+            InstanceMessage::StateChanged(state) => {
+                self.state = state;
+                iced::Command::none()
+            }
+            InstanceMessage::JobEvent(event) => {
+                match event {
+                    JobEvent::StageChanged(stage) => {
+                        self.state = InstanceState::Working(JobStatus::new(stage));
+                    }
+                    JobEvent::Progress(progress) => {
+                        if let InstanceState::Working(status) = &mut self.state {
+                            status.progress = Some(progress);
+                        }
+                    }
+                    JobEvent::Finished => self.state = InstanceState::Ready,
+                    JobEvent::Failed(error) => self.state = InstanceState::Failed(error),
+                }
+                iced::Command::none()
+            }
+        }
+    }
+
+    /// Starts the launch that was queued while the instance was busy, once it's done.
+    /// Nothing gets launched if the job failed, since the build might be broken.
+    pub fn launch_queued(&mut self) -> iced::Command<Message> {
+        match (&self.state, self.queued_play) {
+            (InstanceState::Ready, Some(do_debug)) => {
+                self.queued_play = None;
+                self.update(InstanceMessage::Play(do_debug))
+            }
+            (InstanceState::Failed(_), Some(_)) => {
+                self.queued_play = None;
+                notify(Notification::new(
+                    Level::Warning,
+                    format!("Not launching {}, since it failed", self.name),
+                ));
+                iced::Command::none()
+            }
+            _ => iced::Command::none(),
+        }
+    }
+
+    /// The GitHub page of the release, PR or commit the build came from.
+    pub fn source_url(&self) -> Option<String> {
+        let repo = format!("https://github.com/{}", self.source.repo());
+        match self.source.r#type {
+            InstanceSourceType::PR => {
+                let pr = self.source.identifier.trim_start_matches('#');
+                pr.parse::<u16>().ok().map(|pr| format!("{repo}/pull/{pr}"))
+            }
+            InstanceSourceType::Release => {
+                let tag = self
+                    .provenance
+                    .release_tag
+                    .as_ref()
+                    .unwrap_or(&self.source.identifier);
+                Some(format!("{repo}/releases/tag/{tag}"))
+            }
+            InstanceSourceType::Continuous => {
+                let commit = self.provenance.commit.as_ref().unwrap_or(&self.version);
+                // Older instances could have a placeholder instead of a SHA
+                (!commit.is_empty() && commit.chars().all(|c| c.is_ascii_hexdigit()))
+                    .then(|| format!("{repo}/commit/{commit}"))
+            }
+        }
+    }
+
+    /// Renames the instance along with everything the launcher stores under its name.
+    pub fn rename(&mut self, new_name: &str) -> Result<()> {
+        instance_lock::check(&self.path)?;
+        let old_name = self.name.clone();
+        // Instances that were installed under a different folder name keep it
+        if self.path.file_name().is_some_and(|n| *n == *old_name) {
+            let new_path = self.path.with_file_name(new_name);
+            if new_path.exists() {
+                return Err(anyhow!("{} already exists", new_path.to_string_lossy()));
+            }
+            fs::rename(&self.path, &new_path)?;
+            if let Ok(relative) = self.executable.strip_prefix(&self.path) {
+                self.executable = new_path.join(relative);
+            }
+            self.path = new_path;
+        }
+        for dir in [versions::get_versions_dir, saves::get_backups_dir] {
+            if let (Some(from), Some(to)) = (dir(&old_name), dir(new_name)) {
+                if from.exists() {
+                    fs::rename(from, to)?;
+                }
+            }
+        }
+        backups::rename(&Settings::load().local.backups, &old_name, new_name)?;
+        stats::rename(&old_name, new_name);
+        benchmark::rename(&old_name, new_name);
+        activity::record(activity::Event::Renamed {
+            from: old_name.clone(),
+            to: new_name.into(),
+        });
+        desktop_entry::rename(&old_name, new_name);
+        self.name = new_name.into();
+        Ok(())
+    }
+
+    /// Where the instance's folder might have moved to, e.g. if the instances folder was changed without moving it.
+    fn relink_candidate(&self) -> Option<PathBuf> {
+        let candidate = get_instances_dir()?.join(&self.name);
+        (candidate != self.path && candidate.is_dir()).then_some(candidate)
+    }
+
+    /// Goes back to the picked executable after the build changed, if the new build still has it.
+    fn apply_executable_override(&mut self) {
+        let Some(relative) = &self.executable_override else {
+            return;
+        };
+        let executable = self.path.join(relative);
+        if executable.is_file() {
+            self.executable = executable;
+        } else {
+            warn!(
+                "{} isn't in the new build of {}, running {} instead",
+                relative.to_string_lossy(),
+                self.name,
+                self.executable.to_string_lossy()
+            );
+            self.executable_override = None;
+        }
+    }
+
+    /// Points the instance at the folder it was moved to, keeping the executable's place inside it.
+    pub fn relink(&mut self, new_path: PathBuf) -> Result<()> {
+        if !new_path.is_dir() {
+            return Err(anyhow!("{} isn't a folder", new_path.to_string_lossy()));
+        }
+        self.executable = match self.executable.strip_prefix(&self.path) {
+            Ok(relative) if new_path.join(relative).exists() => new_path.join(relative),
+            _ => InstanceType::detect(&new_path)
+                .map(|(_, executable)| executable)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Couldn't find an Endless Sky build in {}",
+                        new_path.to_string_lossy()
+                    )
+                })?,
+        };
+        info!(
+            "Relinked {} from {} to {}",
+            self.name,
+            self.path.to_string_lossy(),
+            new_path.to_string_lossy()
+        );
+        self.path = new_path;
+        self.check_health();
+        Ok(())
+    }
+
+    /// Marks the instance as broken if files are missing, so that's visible before playing fails.
+    pub fn check_health(&mut self) {
+        self.set_problems(self.verify());
+    }
+
+    fn set_problems(&mut self, problems: Vec<String>) {
+        for problem in &problems {
+            warn!("{}: {}", self.name, problem);
+        }
+        self.problems = problems;
+    }
+
+    /// Checks the instance's files and measures its size in the background, each reporting back
+    /// on its own, so the list shows up right away and fills in as results arrive.
+    pub fn inspect(&self) -> iced::Command<Message> {
+        let name = self.name.clone();
+        let instance = self.clone();
+        let health = iced::Command::perform(async move { instance.verify() }, move |problems| {
+            Message::InstanceMessage(name.clone(), InstanceMessage::HealthChecked(problems))
+        });
+        let name = self.name.clone();
+        let path = self.path.clone();
+        let size = iced::Command::perform(
+            async move {
+                fs_extra::dir::get_size(&path).unwrap_or_else(|e| {
+                    warn!("Failed to get size of {}: {}", path.to_string_lossy(), e);
+                    0
+                })
+            },
+            move |size| Message::InstanceMessage(name.clone(), InstanceMessage::SizeMeasured(size)),
+        );
+        iced::Command::batch([health, size])
+    }
+
+    /// Checks that the files the instance needs are still there, returning what's wrong.
+    fn verify(&self) -> Vec<String> {
+        let mut problems = vec![];
+        if !self.path.is_dir() {
+            problems.push(format!(
+                "The instance folder {} is missing",
+                self.path.to_string_lossy()
+            ));
+        }
+        if !self.executable.exists() {
+            problems.push(format!(
+                "The executable {} is missing",
+                self.executable.to_string_lossy()
+            ));
+        } else {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let executable = fs::metadata(&self.executable)
+                    .is_ok_and(|m| m.is_dir() || m.permissions().mode() & 0o111 != 0);
+                if !executable {
+                    problems.push(format!(
+                        "{} isn't executable",
+                        self.executable.to_string_lossy()
+                    ));
+                }
+            }
+        }
+        if self.instance_type != InstanceType::AppImage
+            && compare_frame::find_data_dir(self).is_none()
+        {
+            problems.push("The game's data folder is missing".into());
+        }
+        problems
+    }
+
+    fn menu(&self) -> Container<InstanceMessage> {
+        let idle = self.state.is_ready();
+        let item = |label, message: InstanceMessage, enabled: bool| {
+            Button::new(Text::new(label).size(12))
+                .style(text_button())
+                .on_press_maybe(enabled.then_some(message))
+        };
+        Container::new(
+            Column::new()
+                .spacing(5)
+                .push(
+                    Row::new()
+                        .spacing(5)
+                        .push(item("Open folder", InstanceMessage::Folder, true))
+                        .push(item(
+                            "Open config folder",
+                            InstanceMessage::ConfigFolder,
+                            self.config_dir().is_some(),
+                        ))
+                        .push(item(
+                            "Open source on GitHub",
+                            InstanceMessage::OpenSource,
+                            self.source_url().is_some(),
+                        )),
+                )
+                .push(
+                    Row::new()
+                        .spacing(5)
+                        .push(item("Rename…", InstanceMessage::StartRename, idle))
+                        .push(item("Clone", InstanceMessage::Clone, idle))
+                        .push(item("Export", InstanceMessage::Export, idle))
+                        .push(item("Export plugins", InstanceMessage::ExportPlugins, true))
+                        .push(item("Archive", InstanceMessage::Archive, idle))
+                        .push(item("Verify files", InstanceMessage::Verify, idle))
+                        .push(item("Benchmark launch", InstanceMessage::Benchmark, idle))
+                        .push(item(
+                            "Play without plugins",
+                            InstanceMessage::PlayWithoutPlugins,
+                            idle && self.problems.is_empty(),
+                        ))
+                        .push(item(
+                            "Create shortcut",
+                            InstanceMessage::CreateShortcut,
+                            true,
+                        ))
+                        .push(item("Add to Steam", InstanceMessage::AddToSteam, true)),
+                )
+                .push_maybe(match self.source.r#type {
+                    InstanceSourceType::Release => Some(item(
+                        "Switch to continuous builds",
+                        InstanceMessage::SwitchChannel(InstanceSourceType::Continuous),
+                        idle && network::is_online(),
+                    )),
+                    InstanceSourceType::Continuous => Some(item(
+                        "Switch to stable releases",
+                        InstanceMessage::SwitchChannel(InstanceSourceType::Release),
+                        idle && network::is_online(),
+                    )),
+                    // PRs aren't a channel that could be followed
+                    InstanceSourceType::PR => None,
+                })
+                .push(
+                    Checkbox::new("Update automatically", self.auto_update)
+                        .on_toggle(InstanceMessage::AutoUpdateToggled)
+                        .text_size(12)
+                        .size(12),
+                )
+                .push(self.executable_row(idle))
+                .push(
+                    self.preserved
+                        .iter()
+                        .fold(Column::new().spacing(2), |column, path| {
+                            column.push(
+                                Row::new()
+                                    .spacing(5)
+                                    .align_items(Alignment::Center)
+                                    .push(
+                                        Text::new(format!(
+                                            "Preserved on update: {}",
+                                            path.to_string_lossy()
+                                        ))
+                                        .size(12),
+                                    )
+                                    .push(item(
+                                        "Remove",
+                                        InstanceMessage::Unpreserve(path.clone()),
+                                        true,
+                                    )),
+                            )
+                        }),
+                )
+                .push(
+                    Row::new()
+                        .spacing(5)
+                        .align_items(Alignment::Center)
+                        .push(
+                            TextInput::new(
+                                "File or folder in the instance, e.g. images/custom",
+                                &self.preserve_input,
+                            )
+                            .on_input(InstanceMessage::PreserveInputChanged)
+                            .on_submit(InstanceMessage::Preserve)
+                            .size(12)
+                            .width(Length::Fixed(300.)),
+                        )
+                        .push(item(
+                            "Preserve on update",
+                            InstanceMessage::Preserve,
+                            !self.preserve_input.trim().is_empty(),
+                        )),
+                )
+                .push(
+                    Row::new()
+                        .spacing(5)
+                        .align_items(Alignment::Center)
+                        .push(
+                            Checkbox::new("Back up on schedule", self.scheduled_backups)
+                                .on_toggle(InstanceMessage::ScheduledBackupsToggled)
+                                .text_size(12)
+                                .size(12),
+                        )
+                        .push(item("Back up now", InstanceMessage::BackUpNow, idle))
+                        .push_maybe((!self.backups.is_empty()).then(|| {
+                            PickList::new(
+                                self.backups.clone(),
+                                self.selected_backup.clone(),
+                                InstanceMessage::BackupSelected,
+                            )
+                            .placeholder("Restore a backup")
+                            .text_size(12)
+                        }))
+                        .push_maybe(
+                            self.selected_backup
+                                .is_some()
+                                .then(|| item("Restore", InstanceMessage::RestoreBackup, idle)),
+                        ),
+                )
+                .push(
+                    Row::new()
+                        .spacing(5)
+                        .push(item("View logs", InstanceMessage::OpenLogs, true))
+                        .push(item("Clear logs", InstanceMessage::ClearLogs, true))
+                        .push(item(
+                            "Save as template",
+                            InstanceMessage::SaveAsTemplate,
+                            true,
+                        ))
+                        .push_maybe(self.group.is_some().then(|| {
+                            item(
+                                "Use as group defaults",
+                                InstanceMessage::SaveAsGroupDefaults,
+                                true,
+                            )
+                        })),
+                ),
+        )
+        .padding(5)
+        .width(Length::Fill)
+        .style(theme::Container::Box)
+    }
+
+    fn details(&self) -> Column<InstanceMessage> {
+        let size = Text::new(match self.disk_size {
+            Some(size) => format!("Size on disk: {}", stats::format_size(size)),
+            None => "Size on disk: measuring…".into(),
+        })
+        .size(10);
+        let entries = self.provenance.entries();
+        if entries.is_empty() {
+            return Column::new().push(size).push(
+                Text::new("No build details were recorded, they will be after the next update")
+                    .size(10),
+            );
+        }
+        entries
+            .into_iter()
+            .fold(Column::new().push(size), |column, (label, value)| {
+                column.push(Text::new(format!("{label}: {value}")).size(10))
+            })
+    }
+
+    /// Settings that are rarely changed, shown along with the details.
+    fn options(&self) -> Column<InstanceMessage> {
+        Column::new()
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(
+                        Checkbox::new("Separate config", self.isolated_config)
+                            .on_toggle(InstanceMessage::IsolatedConfigToggled)
+                            .text_size(12)
+                            .size(12),
+                    )
+                    .push(
+                        Checkbox::new("Always debug", self.debug_by_default)
+                            .on_toggle(InstanceMessage::DebugByDefaultToggled)
+                            .text_size(12)
+                            .size(12),
+                    )
+                    .push(
+                        Checkbox::new("High-DPI graphics", self.high_dpi)
+                            .on_toggle_maybe(
+                                (self.state.is_ready() && network::is_online())
+                                    .then_some(InstanceMessage::HighDpiToggled),
+                            )
+                            .text_size(12)
+                            .size(12),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .spacing(5)
+                    .align_items(Alignment::Center)
+                    .push(
+                        TextInput::new("Launch arguments", &self.launch_args)
+                            .on_input(InstanceMessage::LaunchArgsChanged)
+                            .size(12)
+                            .padding(3)
+                            .width(Length::Fixed(200.)),
+                    )
+                    .push(
+                        Button::new(
+                            Text::new(if self.show_launch_options {
+                                "Hide options"
+                            } else {
+                                "Launch options"
+                            })
+                            .size(12),
+                        )
+                        .style(text_button())
+                        .on_press(InstanceMessage::ToggleLaunchOptions),
+                    ),
+            )
+            .push_maybe(self.show_launch_options.then(|| {
+                self.launch_options
+                    .view()
+                    .map(InstanceMessage::LaunchOptionsChanged)
+            }))
+    }
+
+    /// Only shown if data files differ from the installed build, and lists them when clicked.
+    fn modified_label(&self) -> Option<Button<InstanceMessage>> {
+        let modifications = self.modifications.as_ref().filter(|m| !m.is_empty())?;
+        Some(
+            Button::new(
+                Text::new(format!("Modified ({} files)", modifications.count()))
+                    .size(12)
+                    .style(theme::Text::Color(Color::from_rgb8(230, 140, 20))),
+            )
+            .style(text_button())
+            .padding([0, 4])
+            .on_press(InstanceMessage::ToggleModifications),
+        )
+    }
+
+    fn modifications_list(&self) -> Column<InstanceMessage> {
+        let Some(modifications) = &self.modifications else {
+            return Column::new();
+        };
+        let lines = modifications
+            .changed
+            .iter()
+            .map(|f| format!("Changed: data/{f}"))
+            .chain(
+                modifications
+                    .added
+                    .iter()
+                    .map(|f| format!("Added: data/{f}")),
+            )
+            .chain(
+                modifications
+                    .removed
+                    .iter()
+                    .map(|f| format!("Removed: data/{f}")),
+            );
+        let column = lines
+            .take(MAX_LISTED_MODIFICATIONS)
+            .fold(Column::new(), |column, line| {
+                column.push(Text::new(line).size(10))
+            });
+        let unlisted = modifications
+            .count()
+            .saturating_sub(MAX_LISTED_MODIFICATIONS);
+        column
+            .push_maybe((unlisted > 0).then(|| Text::new(format!("…and {unlisted} more")).size(10)))
+    }
+
+    fn run_history_list(&self) -> Option<Column<InstanceMessage>> {
+        let runs = self.run_history.as_ref()?;
+        if runs.is_empty() {
+            return Some(Column::new().push(Text::new("Not played yet").size(10)));
+        }
+        let failed = runs.iter().filter(|r| !r.success).count();
+        let summary = Text::new(format!(
+            "{failed} of the last {} runs ended abnormally",
+            runs.len()
+        ))
+        .size(10);
+        Some(
+            runs.iter()
+                .fold(Column::new().push(summary), |column, run| {
+                    let line = Text::new(format!(
+                        "{}, {}, {}",
+                        run.started(),
+                        stats::format_duration(run.duration),
+                        run.status()
+                    ))
+                    .size(10);
+                    column.push(if run.success {
+                        line
+                    } else {
+                        line.style(theme::Text::Color(Color::from_rgb8(207, 34, 46)))
+                    })
+                }),
+        )
+    }
+
+    fn pr_state_label(&self) -> Option<Text> {
+        let (label, color) = match self.pr_state? {
+            PRState::Open => return None,
+            PRState::Merged => ("PR merged", Color::from_rgb8(130, 80, 223)),
+            PRState::Closed => ("PR closed", Color::from_rgb8(207, 34, 46)),
+        };
+        Some(Text::new(label).size(12).style(theme::Text::Color(color)))
+    }
+
+    /// A PR that got new commits offers rebuilding right away, since that's what testers want.
+    fn update_label(&self) -> Row<InstanceMessage> {
+        let is_pr = self.source.r#type == InstanceSourceType::PR;
+        Row::new()
+            .spacing(5)
+            .align_items(Alignment::Center)
+            .push(
+                Text::new(if is_pr {
+                    "Updated since install"
+                } else {
+                    "Update available"
+                })
+                .size(12)
+                .style(theme::Text::Color(Color::from_rgb8(0, 200, 80))),
+            )
+            .push_maybe(is_pr.then(|| {
+                Button::new(Text::new("Rebuild").size(12))
+                    .style(text_button())
+                    .padding([0, 4])
+                    .on_press_maybe(
+                        (self.state.is_ready() && network::is_online())
+                            .then_some(InstanceMessage::Update),
+                    )
+            }))
+    }
+
+    fn status_label(&self) -> Row<InstanceMessage> {
+        let (icon, label) = match &self.state {
+            InstanceState::Playing => (style::play_icon(), "Playing"),
+            InstanceState::Working(_) => (style::update_icon(), "Working"),
+            InstanceState::Failed(_) => (style::warning_icon(), "Failed"),
+            InstanceState::Ready => (style::checkmark_icon(), "Ready"),
+        };
+        Row::new()
+            .spacing(5)
+            .align_items(Alignment::Center)
+            .push(icon)
+            .push(Text::new(label).size(14))
+    }
+
+    /// Only shown while the game runs.
+    fn usage_label(&self) -> Option<Text> {
+        // Inside Flatpak, the process that's known is flatpak-spawn rather than the game
+        if !matches!(self.state, InstanceState::Playing)
+            || sandbox::current() == Some(sandbox::Sandbox::Flatpak)
+        {
+            return None;
+        }
+        let usage = self.usage?;
+        Some(
+            Text::new(format!(
+                "{} RAM, {:.0}% CPU",
+                stats::format_size(usage.memory),
+                usage.cpu
+            ))
+            .size(12)
+            .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6))),
+        )
+    }
+
+    /// The executable that gets run, which can be picked from the ones in the instance.
+    fn executable_row(&self, idle: bool) -> Row<InstanceMessage> {
+        let item = |label, message, enabled: bool| {
+            Button::new(Text::new(label).size(12))
+                .style(text_button())
+                .on_press_maybe(enabled.then_some(message))
+        };
+        let row = Row::new().spacing(5).align_items(Alignment::Center);
+        if let Some(choices) = &self.executable_choices {
+            return row
+                .push(
+                    PickList::new(
+                        choices.clone(),
+                        None::<String>,
+                        InstanceMessage::ExecutableSelected,
+                    )
+                    .placeholder("Pick the executable to run")
+                    .text_size(12),
+                )
+                .push(item("Cancel", InstanceMessage::CancelPickExecutable, true));
+        }
+        let executable = self
+            .executable
+            .strip_prefix(&self.path)
+            .unwrap_or(&self.executable)
+            .to_string_lossy();
+        row.push(
+            Text::new(if self.executable_override.is_some() {
+                format!("Runs {executable} (picked)")
+            } else {
+                format!("Runs {executable}")
+            })
+            .size(12),
+        )
+        .push(item("Change…", InstanceMessage::PickExecutable, idle))
+        .push_maybe(
+            self.executable_override
+                .is_some()
+                .then(|| item("Detect again", InstanceMessage::ResetExecutable, idle)),
+        )
+    }
+
+    fn problems_notice(&self) -> Element<InstanceMessage> {
+        if let Some(locate) = &self.locate {
+            return Row::new()
+                .spacing(5)
+                .align_items(Alignment::Center)
+                .push(
+                    TextInput::new("The instance's new folder", locate)
+                        .on_input(InstanceMessage::LocateChanged)
+                        .on_submit(InstanceMessage::ConfirmLocate)
+                        .size(12)
+                        .width(Length::Fixed(300.)),
+                )
+                .push(
+                    Button::new(Text::new("Use folder").size(12))
+                        .style(text_button())
+                        .on_press(InstanceMessage::ConfirmLocate),
+                )
+                .push(
+                    Button::new(Text::new("Cancel").size(12))
+                        .style(text_button())
+                        .on_press(InstanceMessage::CancelLocate),
+                )
+                .into();
+        }
+        let mut text = self.problems[0].clone();
+        if self.problems.len() > 1 {
+            text.push_str(&format!(" (and {} more problems)", self.problems.len() - 1));
+        }
+        let action = |label, message| {
+            Button::new(Text::new(label).size(12))
+                .style(text_button())
+                .padding([0, 4])
+                .on_press_maybe(self.state.is_ready().then_some(message))
+        };
+        Row::new()
+            .spacing(5)
+            .align_items(Alignment::Center)
+            .push(style::warning_icon())
+            .push(Text::new(text).size(12))
+            // Reinstalling would download the whole build again, while it might just have moved
+            .push_maybe(
+                (!self.path.is_dir()).then(|| action("Locate…", InstanceMessage::StartLocate)),
+            )
+            .push_maybe(
+                network::is_online().then(|| action("Reinstall", InstanceMessage::Reinstall)),
+            )
+            .into()
+    }
+
+    /// What players see in kiosk mode, see [`kiosk`].
+    fn kiosk_view(&self, can_play: bool) -> Element<InstanceMessage> {
+        let playable = self.state.is_ready() && can_play && self.problems.is_empty();
+        let status: Element<_> = match &self.state {
+            InstanceState::Playing => Text::new("Playing").size(16).into(),
+            InstanceState::Working(status) => Row::new()
+                .spacing(8)
+                .align_items(Alignment::Center)
+                .push(style::spinner())
+                .push(Text::new(status.summary()).size(16))
+                .into(),
+            _ => Button::new(style::labeled(style::play_icon(), "Play"))
+                .style(icon_button())
+                .on_press_maybe(playable.then_some(InstanceMessage::Play(self.debug_by_default)))
+                .into(),
+        };
+        Column::new()
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .padding(10)
+                    .align_items(Alignment::Center)
+                    .width(Length::Fill)
+                    .push(
+                        Column::new()
+                            .push(Text::new(&self.name).size(24))
+                            .push(Text::new(format!("Version: {:.*}", 32, self.version)).size(10)),
+                    )
+                    .push(Space::new(Length::Fill, Length::Shrink))
+                    .push(status),
+            )
+            .push_maybe(match &self.state {
+                InstanceState::Failed(banner) => Some(banner.view(
+                    InstanceMessage::ToggleErrorDetails,
+                    InstanceMessage::DismissError,
+                )),
+                _ => None,
+            })
+            .into()
+    }
+
+    /// `show_status` adds an icon and label for the state, for those who can't rely on colors.
+    pub fn view(
+        &self,
+        profiles: Vec<String>,
+        can_play: bool,
+        show_status: bool,
+        double_click: DoubleClickAction,
+    ) -> Element<InstanceMessage> {
+        if kiosk::is_enabled() {
+            return self.kiosk_view(can_play);
+        }
+        // Buttons
+        let mut debug_button =
+            Button::new(style::labeled(style::debug_icon(), "Debug")).style(icon_button());
+        let mut play_button =
+            Button::new(style::labeled(style::play_icon(), "Play")).style(icon_button());
+        let mut update_button =
+            Button::new(style::labeled(style::update_icon(), "Update")).style(icon_button());
+        let mut delete_button = Button::new(style::labeled(style::delete_icon(), "Delete"))
+            .style(theme::Button::Destructive);
+        let menu_button = Button::new(style::labeled(style::menu_icon(), "More"))
+            .style(icon_button())
+            .on_press(InstanceMessage::ToggleMenu);
+        let name: Element<_> = match &self.rename {
+            Some(new_name) => Row::new()
+                .spacing(5)
+                .align_items(Alignment::Center)
+                .push(
+                    TextInput::new("Name", new_name)
+                        .on_input(InstanceMessage::RenameChanged)
+                        .on_submit(InstanceMessage::ConfirmRename)
+                        .size(18)
+                        .width(Length::Fixed(250.)),
+                )
+                .push(
+                    Button::new(Text::new("Rename").size(12))
+                        .style(text_button())
+                        .on_press(InstanceMessage::ConfirmRename),
+                )
+                .push(
+                    Button::new(Text::new("Cancel").size(12))
+                        .style(text_button())
+                        .on_press(InstanceMessage::CancelRename),
+                )
+                .into(),
+            None => Text::new(&self.name).size(24).into(),
+        };
+        let profile_picker = (!profiles.is_empty()).then(|| {
+            let mut options = vec![NO_PROFILE.to_string()];
+            options.extend(profiles);
+            PickList::new(
+                options,
+                Some(
+                    self.plugin_profile
+                        .clone()
+                        .unwrap_or_else(|| NO_PROFILE.to_string()),
+                ),
+                InstanceMessage::ProfileSelected,
+            )
+            .text_size(12)
+        });
+        // PickLists can't be disabled, so hide it while the instance is busy
+        let version_picker = (self.state.is_ready() && !self.kept_versions.is_empty()).then(|| {
+            let versions: Vec<String> = self
+                .kept_versions
+                .iter()
+                .map(|v| v.version.clone())
+                .collect();
+            PickList::new(versions, None::<String>, InstanceMessage::SwitchVersion)
+                .placeholder("Switch to a kept version")
+                .text_size(12)
+        });
+
+        let broken = !self.problems.is_empty();
+        let double_click = match double_click {
+            // Only does what the Play button would
+            DoubleClickAction::Play if !(self.state.is_ready() && can_play && !broken) => {
+                DoubleClickAction::Nothing
+            }
+            action => action,
+        };
+        if self.state.is_ready() {
+            if can_play && !broken {
+                debug_button = debug_button.on_press(InstanceMessage::Play(true));
+                play_button = play_button.on_press(InstanceMessage::Play(self.debug_by_default));
+            }
+            if network::is_online() {
+                update_button = update_button.on_press(InstanceMessage::Update);
+            }
+            delete_button = delete_button.on_press(InstanceMessage::Delete);
+        }
+
+        // Layout
+        let row = Row::new()
+            .spacing(10)
+            .padding(10)
+            .align_items(Alignment::Start)
+            .width(Length::Fill)
+            .push(
+                Column::new()
+                    .push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .push_maybe(show_status.then(|| self.status_label()))
+                            .push(name)
+                            .push_maybe(self.group.as_ref().map(|group| {
+                                Text::new(group)
+                                    .size(12)
+                                    .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6)))
+                            }))
+                            .push_maybe(self.usage_label())
+                            .push_maybe(self.modified_label())
+                            .push_maybe(self.pr_state_label())
+                            .push_maybe(self.update_available.then(|| self.update_label())),
+                    )
+                    .push(
+                        Row::new()
+                            .spacing(5)
+                            .align_items(Alignment::Center)
+                            .push(Text::new(format!("Version: {:.*}", 32, self.version)).size(10))
+                            .push(
+                                Button::new(
+                                    Text::new(if self.show_details {
+                                        "Hide details"
+                                    } else {
+                                        "Details"
+                                    })
+                                    .size(10),
+                                )
+                                .style(text_button())
+                                .padding([0, 4])
+                                .on_press(InstanceMessage::ToggleDetails),
+                            )
+                            .push(
+                                Button::new(
+                                    Text::new(if self.run_history.is_some() {
+                                        "Hide run history"
+                                    } else {
+                                        "Run history"
+                                    })
+                                    .size(10),
+                                )
+                                .style(text_button())
+                                .padding([0, 4])
+                                .on_press(InstanceMessage::ToggleRunHistory),
+                            ),
+                    )
+                    .push_maybe(self.show_details.then(|| self.details()))
+                    .push_maybe(self.run_history_list())
+                    .push_maybe(self.show_modifications.then(|| self.modifications_list()))
+                    .push(
+                        Text::new(format!(
+                            "Source: {} {}{}",
+                            self.source.r#type,
+                            self.source.identifier,
+                            match self.source.arch {
+                                Arch::Native => String::new(),
+                                arch => format!(" ({arch})"),
+                            }
+                        ))
+                        .size(10),
+                    )
+                    .push_maybe(self.installed.map(|installed| {
+                        Text::new(format!("Installed {}", stats::format_age(installed))).size(10)
+                    }))
+                    .push_maybe(broken.then(|| self.problems_notice()))
+                    .push_maybe(match &self.state {
+                        InstanceState::Failed(banner) => Some(banner.view(
+                            InstanceMessage::ToggleErrorDetails,
+                            InstanceMessage::DismissError,
+                        )),
+                        _ => None,
+                    })
+                    .push_maybe(profile_picker)
+                    .push_maybe(version_picker)
+                    .push_maybe(self.show_details.then(|| self.options())),
+            )
+            .push(Space::new(Length::Fill, Length::Shrink))
+            .push({
+                if let InstanceState::Working(status) = &self.state {
+                    let mut status_field = Column::new().align_items(Alignment::Center).push(
+                        Row::new()
+                            .spacing(8)
+                            .align_items(Alignment::Center)
+                            .push(style::spinner())
+                            .push(Text::new(status.summary()).size(16)),
+                    );
+                    if status.stalled {
+                        status_field = status_field.push(
+                            Text::new("No progress for a while")
+                                .size(12)
+                                .style(theme::Text::Color(Color::from_rgb(0.8, 0.4, 0.0))),
+                        );
+                    }
+                    if self.queued_play.is_some() {
+                        status_field =
+                            status_field.push(Text::new("Will launch when done").size(12));
+                    }
+                    if let Some(progress) = &status.progress {
+                        if let Some(total) = progress.total {
+                            status_field = status_field.push(
+                                ProgressBar::new(0.0..=total as f32, progress.done as f32)
+                                    .height(Length::Fixed(5.)),
+                            );
+                        }
+                        status_field = status_field.push(
+                            Text::new(format!(
+                                "{}/{}{}{}",
+                                progress.done,
+                                if progress.total_approx { "~" } else { "" },
+                                progress.total.map_or_else(|| "?".into(), |u| u.to_string()),
+                                progress.units.as_ref().unwrap_or(&String::new())
+                            ))
+                            .size(12)
+                            .horizontal_alignment(alignment::Horizontal::Center),
+                        );
+                    }
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Alignment::Center)
+                        .push(Space::with_width(Length::FillPortion(1)))
+                        .push(status_field.width(Length::FillPortion(2)))
+                        .push(if self.queued_play.is_some() {
+                            Button::new(Text::new("Don't launch").size(12))
+                                .style(text_button())
+                                .on_press(InstanceMessage::UnqueuePlay)
+                        } else {
+                            Button::new(Text::new("Play when done").size(12))
+                                .style(text_button())
+                                .on_press_maybe(
+                                    can_play.then_some(InstanceMessage::QueuePlay(
+                                        self.debug_by_default,
+                                    )),
+                                )
+                        })
+                        .push_maybe((status.stalled && job::is_retryable(&self.name)).then(|| {
+                            Button::new(Text::new("Retry").size(12))
+                                .style(text_button())
+                                .on_press(InstanceMessage::RetryStalled)
+                        }))
+                        .push(
+                            Button::new(Text::new("Cancel").size(12))
+                                .style(text_button())
+                                .on_press(InstanceMessage::Cancel),
+                        )
+                } else {
+                    Row::new()
+                        .spacing(10)
+                        .push(style::tooltip(debug_button, "Play with debug mode enabled"))
+                        .push(style::tooltip(
+                            play_button,
+                            if self.debug_by_default {
+                                "Play, in debug mode since it's always enabled for this instance"
+                            } else {
+                                "Play"
+                            },
+                        ))
+                        .push(style::tooltip(
+                            update_button,
+                            "Update to the latest version",
+                        ))
+                        .push(style::tooltip(
+                            delete_button,
+                            "Delete this instance and all of its files",
+                        ))
+                        .push(style::tooltip(
+                            menu_button,
+                            "More actions, also available by right-clicking the instance or with the menu key",
+                        ))
+                }
+            });
+
+        Column::new()
+            .push({
+                let area = MouseArea::new(row).on_right_press(InstanceMessage::ToggleMenu);
+                if double_click == DoubleClickAction::Nothing {
+                    area
+                } else {
+                    area.on_press(InstanceMessage::RowClicked(double_click))
+                }
+            })
+            .push_maybe(self.menu_open.then(|| self.menu()))
+            .into()
+    }
+}
+
+async fn dummy() {}
+
+pub async fn perform_install(
+    path: PathBuf,
+    name: String,
+    instance_type: InstanceType,
+    instance_source: InstanceSource,
+    template: Option<Template>,
+) {
+    job::register(PendingJob::Install {
+        path: path.clone(),
+        name: name.clone(),
+        instance_type,
+        source: instance_source.clone(),
+        template: template.clone().map(Box::new),
+    });
+    send_message(Message::AddInstance(Box::new(Instance::new(
+        path.clone(),
+        "provisional".into(),
+        name.clone(),
+        instance_source.identifier.clone(),
+        instance_type,
+        instance_source.clone(),
+        InstanceState::Working(JobStatus::new("Installing")),
+    ))));
+    let result = async {
+        fs::create_dir_all(&path)?;
+        let _lock = instance_lock::acquire(&path, "installing")?;
+        install::install(
+            path.clone(),
+            name.clone(),
+            instance_type,
+            instance_source.clone(),
+        )
+        .await
+    }
+    .await;
+    match result {
+        Ok(mut instance) => {
+            if let Some(template) = template {
+                template.apply(&mut instance);
+                instance.apply_plugin_profile();
+            }
+            if let Err(e) = desktop_entry::extract_icon(&instance) {
+                warn!("Failed to extract the icon of {}: {:#}", name, e);
+            }
+            scripting::fire(
+                "on_install_complete",
+                vec![name.clone().into(), instance.version.clone().into()],
+            );
+            activity::record(activity::Event::Installed {
+                instance: name.clone(),
+                version: instance.version.clone(),
+            });
             send_message(Message::AddInstance(Box::new(instance)));
+            stats::record_install();
+            telemetry::record_install(instance_type);
+            notify(
+                Notification::new(Level::Success, format!("Installed {name}")).action(
+                    "Play",
+                    Message::InstanceMessage(name.clone(), InstanceMessage::Play(false)),
+                ),
+            );
+        }
+        Err(e) if job::is_cancelled(&e) => {
+            notify(Notification::new(
+                Level::Info,
+                format!("Cancelled installing {name}"),
+            ));
+            delete(path).await;
+            send_message(Message::RemoveInstance(Some(name.clone())));
+        }
+        Err(e) if e.is::<install::AmbiguousAsset>() => {
+            warn!("Install needs a choice: {:#}", e);
+            let candidates = e
+                .downcast::<install::AmbiguousAsset>()
+                .map(|ambiguous| ambiguous.candidates)
+                .unwrap_or_default();
+            delete(path).await;
+            send_message(Message::RemoveInstance(Some(name.clone())));
+            send_message(Message::InstallFrameMessage(
+                InstallFrameMessage::AssetsAmbiguous(Box::new(AssetChoice {
+                    name: name.clone(),
+                    instance_type,
+                    source: instance_source,
+                    template,
+                    candidates,
+                })),
+            ));
         }
         Err(e) => {
             error!("Install failed: {:#}", e);
-            send_message(Message::RemoveInstance(Some(name)));
+            notify(Notification::new(
+                Level::Error,
+                format!("Installing {name} failed"),
+            ));
+            send_message(Message::InstallFrameMessage(InstallFrameMessage::Failed(
+                ErrorBanner::new(format!("Installing {name} failed"), &e),
+            )));
+            send_message(Message::RemoveInstance(Some(name.clone())));
         }
     }
+    job::complete(&name);
 }
 
 pub async fn open_folder(path: PathBuf) {
     info!("Opening {} in file explorer", path.to_string_lossy());
-    if let Err(e) = open::that(path.as_path()) {
+    if let Err(e) = sandbox::open(&path) {
         error!("Failed to open path: {}", e);
     }
 }
@@ -328,31 +2404,644 @@ pub async fn delete(path: PathBuf) -> Option<PathBuf> {
     }
 }
 
+/// Takes a snapshot of the instance's saves and keeps the current build if configured, then updates it.
+pub async fn snapshot_and_update(instance: Instance) -> Result<Instance> {
+    let source = instance.source.clone();
+    snapshot_and_update_to(instance, source).await
+}
+
+/// Like `snapshot_and_update`, but installs the latest build of `source`, which may be another channel.
+/// The instance only takes on the new source if that succeeds.
+async fn snapshot_and_update_to(
+    mut instance: Instance,
+    source: InstanceSource,
+) -> Result<Instance> {
+    let _lock = instance_lock::acquire(&instance.path, "updating")?;
+    let settings = Settings::load();
+    if let Some(config_dir) = instance.config_dir() {
+        job::stage(&instance.name, "Backing up saves");
+        if let Err(e) = saves::snapshot(&config_dir, &instance.name, settings.save_snapshots) {
+            error!("Failed to snapshot saves: {:#}", e);
+        }
+    }
+    job::stage(&instance.name, "Keeping current version");
+    if let Err(e) = versions::store(&mut instance, settings.keep_versions) {
+        error!("Failed to keep the current version: {:#}", e);
+    }
+    job::stage(&instance.name, "Creating restore point");
+    let prepared = versions::restore_point(&instance).and_then(|restore_point| {
+        match versions::PreservedFiles::stash(&instance) {
+            Ok(preserved) => Ok((restore_point, preserved)),
+            Err(e) => {
+                restore_point.discard();
+                Err(e)
+            }
+        }
+    });
+    let (restore_point, preserved) = match prepared {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            // Keeps the version stored above, the build itself wasn't touched yet
+            send_message(Message::AddInstance(Box::new(instance)));
+            return Err(e);
+        }
+    };
+    let previous = instance.clone();
+    instance.source = source;
+    let updated = match update::update_instance(instance).await {
+        Ok(updated) => {
+            restore_point.discard();
+            // The update itself went through, so it isn't failed over this
+            if let Err(e) = preserved.put_back(&previous.path) {
+                warn!("Failed to put back the preserved files: {:#}", e);
+                notify(Notification::new(Level::Warning, format!("{e:#}")).sticky());
+            }
+            updated
+        }
+        // A failed update may have removed the build already, e.g. before the download failed
+        Err(e) => {
+            job::stage(&previous.name, "Restoring previous version");
+            // The restore point has the preserved files too
+            preserved.discard();
+            restore_point
+                .restore(&previous.path)
+                .context("Failed to restore the previous version")?;
+            // Keeps the version that might have been stored above
+            send_message(Message::AddInstance(Box::new(previous)));
+            return Err(e);
+        }
+    };
+    let previous_version = previous.version.clone();
+    // Only the build changes, the instance's settings are kept
+    let mut updated = Instance {
+        executable: updated.executable,
+        instance_type: updated.instance_type,
+        version: updated.version,
+        source: updated.source,
+        provenance: updated.provenance,
+        state: updated.state,
+        update_available: false,
+        ..previous
+    };
+    updated.apply_executable_override();
+    if updated.version == previous_version {
+        // Nothing changed, so there's no point in keeping a copy
+        versions::forget(&mut updated, &previous_version);
+    }
+    // The preserved files were put back, which is worth pointing out
+    updated.modifications = modifications::check(&updated);
+    Ok(updated)
+}
+
+/// Whether `path` looks like a program, going by its name first so data files don't get read.
+fn is_executable(path: &Path) -> bool {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+    if !matches!(extension.as_deref(), None | Some("exe" | "appimage")) {
+        return false;
+    }
+    read_header(path).is_some_and(|header| EXECUTABLE_MAGICS.iter().any(|m| header.starts_with(m)))
+}
+
+fn list_executables(dir: &Path, instance_dir: &Path, depth: usize, found: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.filter_map(Result::ok).map(|e| e.path()) {
+        if path.is_dir() {
+            if depth < EXECUTABLE_CHOICES_MAX_DEPTH {
+                list_executables(&path, instance_dir, depth + 1, found);
+            }
+        } else if is_executable(&path) {
+            if let Ok(relative) = path.strip_prefix(instance_dir) {
+                found.push(relative.to_string_lossy().into());
+            }
+        }
+    }
+}
+
+/// The executables in the instance folder, relative to it, for picking the one to run.
+pub async fn perform_list_executables(instance_dir: PathBuf) -> Vec<String> {
+    let mut found = vec![];
+    list_executables(&instance_dir, &instance_dir, 0, &mut found);
+    found.sort();
+    found
+}
+
+/// Installs the instance's build again over a broken one, keeping everything but the build itself.
+pub async fn perform_reinstall(instance: Instance) {
+    let name = instance.name.clone();
+    let result = async {
+        fs::create_dir_all(&instance.path)?;
+        let _lock = instance_lock::acquire(&instance.path, "reinstalling")?;
+        install::install(
+            instance.path.clone(),
+            name.clone(),
+            instance.instance_type,
+            instance.source.clone(),
+        )
+        .await
+    }
+    .await;
+    match result {
+        Ok(installed) => {
+            let mut repaired = instance;
+            repaired.executable = installed.executable;
+            repaired.instance_type = installed.instance_type;
+            repaired.version = installed.version;
+            repaired.provenance = installed.provenance;
+            repaired.modifications = None;
+            repaired.state = InstanceState::Ready;
+            repaired.apply_executable_override();
+            repaired.check_health();
+            send_message(Message::AddInstance(Box::new(repaired)));
+            notify(Notification::new(
+                Level::Success,
+                format!("Reinstalled {name}"),
+            ));
+        }
+        Err(e) if job::is_cancelled(&e) => {
+            notify(Notification::new(
+                Level::Info,
+                format!("Cancelled reinstalling {name}"),
+            ));
+            job::send(&name, JobEvent::Finished);
+        }
+        Err(e) => {
+            error!("Failed to reinstall instance: {:#}", e);
+            job::send(
+                &name,
+                JobEvent::Failed(ErrorBanner::new("Reinstalling failed", &e)),
+            );
+        }
+    }
+    job::complete(&name);
+}
+
 pub async fn perform_update(instance: Instance) {
+    let source = instance.source.clone();
+    perform_update_to(instance, source).await;
+}
+
+async fn perform_update_to(instance: Instance, source: InstanceSource) {
     let name = instance.name.clone();
-    match update::update_instance(instance).await {
-        Ok(instance) => send_message(Message::AddInstance(Box::new(instance))),
+    let from = instance.version.clone();
+    job::register(PendingJob::Update { name: name.clone() });
+    let _slot = update::acquire_slot(&name).await;
+    match snapshot_and_update_to(instance, source).await {
+        Ok(instance) => {
+            activity::record(activity::Event::Updated {
+                instance: name.clone(),
+                from,
+                to: instance.version.clone(),
+            });
+            if let Err(e) = desktop_entry::extract_icon(&instance) {
+                warn!("Failed to extract the icon of {}: {:#}", name, e);
+            }
+            scripting::fire(
+                "on_update_complete",
+                vec![name.clone().into(), instance.version.clone().into()],
+            );
+            send_message(Message::AddInstance(Box::new(instance)));
+            job::send(&name, JobEvent::Finished);
+            stats::record_update();
+            notify(Notification::new(Level::Success, format!("Updated {name}")));
+        }
+        Err(e) if job::is_cancelled(&e) => {
+            notify(Notification::new(
+                Level::Info,
+                format!("Cancelled updating {name}"),
+            ));
+            job::send(&name, JobEvent::Finished);
+        }
         Err(e) => {
             error!("Failed to update instance: {:#}", e);
-            send_message(Message::InstanceMessage(
-                name,
-                InstanceMessage::StateChanged(InstanceState::Ready),
+            notify(Notification::new(
+                Level::Error,
+                format!("Updating {name} failed"),
+            ));
+            job::send(
+                &name,
+                JobEvent::Failed(ErrorBanner::new("Updating failed", &e)),
+            );
+        }
+    }
+    job::complete(&name);
+}
+
+/// Moves the instance over to stable releases or continuous builds, installing the latest build of that channel.
+pub async fn perform_switch_channel(instance: Instance, channel: InstanceSourceType) {
+    let name = instance.name.clone();
+    let identifier = match channel {
+        InstanceSourceType::Release => match github::get_latest_release(instance.source.repo()) {
+            Ok(tag) => tag,
+            Err(e) => {
+                error!("Failed to get the latest release: {:#}", e);
+                job::send(
+                    &name,
+                    JobEvent::Failed(ErrorBanner::new("Switching channels failed", &e)),
+                );
+                return;
+            }
+        },
+        _ => String::new(),
+    };
+    info!(
+        "Switching {} from {} to {}",
+        name, instance.source.r#type, channel
+    );
+    // Forks only publish releases
+    let is_release = channel == InstanceSourceType::Release;
+    let source = InstanceSource {
+        identifier,
+        r#type: channel,
+        arch: instance.source.arch,
+        repo: is_release.then(|| instance.source.repo.clone()).flatten(),
+        asset_pattern: is_release
+            .then(|| instance.source.asset_pattern.clone())
+            .flatten(),
+    };
+    perform_update_to(instance, source).await;
+}
+
+pub async fn perform_switch_version(instance: Instance, version: String) {
+    let name = instance.name.clone();
+    let from = instance.version.clone();
+    match versions::switch(instance, &version) {
+        Ok(mut instance) => {
+            activity::record(activity::Event::SwitchedVersion {
+                instance: name.clone(),
+                from,
+                to: version.clone(),
+            });
+            instance.state = InstanceState::Ready;
+            instance.modifications = modifications::check(&instance);
+            send_message(Message::AddInstance(Box::new(instance)));
+            notify(Notification::new(
+                Level::Success,
+                format!("Switched {name} to {version:.32}"),
+            ));
+        }
+        Err(e) => {
+            error!("Failed to switch version: {:#}", e);
+            job::send(
+                &name,
+                JobEvent::Failed(ErrorBanner::new("Switching versions failed", &e)),
+            );
+        }
+    }
+}
+
+/// Registers the builds in `dir` as instances. Extracted builds in its subfolders and AppImages
+/// directly inside it are copied into instance folders of their own, since updating, reinstalling
+/// or deleting an instance replaces or trashes everything in its folder. Builds inside the
+/// instances dir already stay where they are.
+fn import_builds(dir: &Path) -> Result<Vec<Instance>> {
+    let instances_dir =
+        get_instances_dir().ok_or_else(|| anyhow!("Failed to get Instances dir"))?;
+    let existing = load_instances()?;
+    let paths: Vec<PathBuf> = existing.iter().map(|i| i.path.clone()).collect();
+    let mut names: Vec<String> = existing.into_iter().map(|i| i.name).collect();
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+
+    let mut imported = vec![];
+    for entry in entries {
+        let stem = entry
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .replace(BLACKLISTED_CHARS, "_");
+        let mut name = stem.clone();
+        let mut n = 2;
+        while names.contains(&name) {
+            name = format!("{stem} ({n})");
+            n += 1;
+        }
+
+        let (path, instance_type, executable) = if entry.is_dir() {
+            if paths.contains(&entry) {
+                debug!("{} already is an instance", entry.to_string_lossy());
+                continue;
+            }
+            let Some((instance_type, executable)) = InstanceType::detect(&entry) else {
+                continue;
+            };
+            if entry.starts_with(&instances_dir) {
+                (entry, instance_type, executable)
+            } else {
+                let path = instances_dir.join(&name);
+                if path.exists() {
+                    warn!(
+                        "Not importing {}, since {} already exists",
+                        entry.to_string_lossy(),
+                        path.to_string_lossy()
+                    );
+                    continue;
+                }
+                info!(
+                    "Copying {} to {}",
+                    entry.to_string_lossy(),
+                    path.to_string_lossy()
+                );
+                let options = fs_extra::dir::CopyOptions::new().content_only(true);
+                if let Err(e) = fs::create_dir_all(&path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|()| Ok(fs_extra::dir::copy(&entry, &path, &options)?))
+                {
+                    error!("Failed to copy {}: {:#}", entry.to_string_lossy(), e);
+                    let _ = fs::remove_dir_all(&path);
+                    continue;
+                }
+                let executable = executable
+                    .strip_prefix(&entry)
+                    .map(|relative| path.join(relative))
+                    .unwrap_or(executable);
+                (path, instance_type, executable)
+            }
+        } else {
+            if InstanceType::detect_file(&entry) != Some(InstanceType::AppImage) {
+                continue;
+            }
+            let path = instances_dir.join(&name);
+            if path.exists() {
+                warn!(
+                    "Not importing {}, since {} already exists",
+                    entry.to_string_lossy(),
+                    path.to_string_lossy()
+                );
+                continue;
+            }
+            let executable = path.join(InstanceType::AppImage.executable().unwrap_or_default());
+            info!(
+                "Copying {} to {}",
+                entry.to_string_lossy(),
+                executable.to_string_lossy()
+            );
+            if let Err(e) = fs::create_dir_all(&path).and_then(|()| fs::copy(&entry, &executable)) {
+                error!("Failed to copy {}: {}", entry.to_string_lossy(), e);
+                let _ = fs::remove_dir_all(&path);
+                continue;
+            }
+            (path, InstanceType::AppImage, executable)
+        };
+        info!(
+            "Importing {} as {} ({:?})",
+            path.to_string_lossy(),
+            name,
+            instance_type
+        );
+        let mut instance = Instance::new(
+            path,
+            executable,
+            name.clone(),
+            "unknown".into(),
+            instance_type,
+            InstanceSource::default(),
+            InstanceState::Ready,
+        );
+        instance.check_health();
+        names.push(name);
+        imported.push(instance);
+    }
+    Ok(imported)
+}
+
+pub async fn perform_import(dir: PathBuf) {
+    match import_builds(&dir) {
+        Ok(imported) if imported.is_empty() => notify(Notification::new(
+            Level::Warning,
+            format!("Found no builds in {}", dir.to_string_lossy()),
+        )),
+        Ok(imported) => {
+            let count = imported.len();
+            for instance in imported {
+                send_message(Message::AddInstance(Box::new(instance)));
+            }
+            notify(Notification::new(
+                Level::Success,
+                format!("Imported {count} instances"),
+            ));
+        }
+        Err(e) => {
+            error!("Failed to import {}: {:#}", dir.to_string_lossy(), e);
+            notify(Notification::new(
+                Level::Error,
+                "Importing failed, see the log for details",
+            ));
+        }
+    }
+}
+
+/// Copies the instance into a new one named `name`, without its kept versions.
+pub async fn perform_clone(instance: Instance, name: String) {
+    let Some(path) = get_instances_dir().map(|d| d.join(&name)) else {
+        error!("Could not get instances directory from AppDirs");
+        return;
+    };
+    let mut clone = instance.clone();
+    clone.name = name.clone();
+    clone.path = path.clone();
+    if let Ok(relative) = instance.executable.strip_prefix(&instance.path) {
+        clone.executable = path.join(relative);
+    }
+    clone.kept_versions = vec![];
+    clone.update_available = false;
+    clone.installed = Some(OffsetDateTime::now_utc().unix_timestamp());
+    clone.menu_open = false;
+    clone.state = InstanceState::Working(JobStatus::new("Copying"));
+    send_message(Message::AddInstance(Box::new(clone.clone())));
+
+    info!(
+        "Copying {} to {}",
+        instance.path.to_string_lossy(),
+        path.to_string_lossy()
+    );
+    let options = fs_extra::dir::CopyOptions::new().content_only(true);
+    let result = (|| -> Result<()> {
+        instance_lock::check(&instance.path)?;
+        fs::create_dir_all(&path)?;
+        fs_extra::dir::copy(&instance.path, &path, &options)?;
+        Ok(())
+    })();
+    match result {
+        Ok(()) => {
+            clone.state = InstanceState::Ready;
+            if let Err(e) = desktop_entry::extract_icon(&clone) {
+                warn!("Failed to extract the icon of {}: {:#}", name, e);
+            }
+            send_message(Message::AddInstance(Box::new(clone)));
+            activity::record(activity::Event::Cloned {
+                from: instance.name.clone(),
+                to: name.clone(),
+            });
+            notify(Notification::new(
+                Level::Success,
+                format!("Cloned {} as {name}", instance.name),
+            ));
+        }
+        Err(e) => {
+            error!("Failed to clone {}: {:#}", instance.name, e);
+            notify(Notification::new(
+                Level::Error,
+                format!("Cloning {} failed, see the log for details", instance.name),
+            ));
+            delete(path).await;
+            send_message(Message::RemoveInstance(Some(name)));
+        }
+    }
+}
+
+/// Packs the instance into a .tar.gz archive in the exports folder, then opens that folder.
+pub async fn perform_export(instance: Instance) -> InstanceState {
+    let result = (|| -> Result<PathBuf> {
+        instance_lock::check(&instance.path)?;
+        let dir = get_data_dir()
+            .ok_or_else(|| anyhow!("Failed to get app save dir"))?
+            .join("exports");
+        fs::create_dir_all(&dir)?;
+        let timestamp = OffsetDateTime::now_utc().format(&format_description::parse(
+            "[year]-[month]-[day] [hour]-[minute]-[second]",
+        )?)?;
+        let archive = dir.join(format!("{} {timestamp}.tar.gz", instance.name));
+        archive::pack(&instance.path, &instance.name, &archive)?;
+        Ok(dir)
+    })();
+    match result {
+        Ok(dir) => {
+            notify(Notification::new(
+                Level::Success,
+                format!("Exported {}", instance.name),
+            ));
+            open_folder(dir).await;
+            InstanceState::Ready
+        }
+        Err(e) => {
+            error!("Failed to export {}: {:#}", instance.name, e);
+            InstanceState::Failed(ErrorBanner::new("Exporting failed", &e))
+        }
+    }
+}
+
+/// Archives the instance into the configured backup folder.
+pub async fn perform_backup(instance: Instance) -> InstanceState {
+    let config = Settings::load().local.backups;
+    match backups::create(&config, &instance) {
+        Ok(_) => {
+            notify(Notification::new(
+                Level::Success,
+                format!("Backed up {}", instance.name),
+            ));
+            InstanceState::Ready
+        }
+        Err(e) => {
+            error!("Failed to back up {}: {:#}", instance.name, e);
+            InstanceState::Failed(ErrorBanner::new("Backing up failed", &e))
+        }
+    }
+}
+
+/// Replaces the instance's files with those in the backup labelled `backup`.
+pub async fn perform_restore(instance: Instance, backup: String) {
+    let name = instance.name.clone();
+    let config = Settings::load().local.backups;
+    let result = backups::list(&config, &name)
+        .into_iter()
+        .find(|archive| backups::label(archive) == backup)
+        .ok_or_else(|| anyhow!("The backup {backup} doesn't exist anymore"))
+        .and_then(|archive| backups::restore(instance, &archive));
+    match result {
+        Ok(mut instance) => {
+            instance.state = InstanceState::Ready;
+            send_message(Message::AddInstance(Box::new(instance)));
+            activity::record(activity::Event::RestoredBackup {
+                instance: name.clone(),
+                backup: backup.clone(),
+            });
+            notify(Notification::new(
+                Level::Success,
+                format!("Restored {name} from {backup}"),
             ));
         }
+        Err(e) => {
+            error!("Failed to restore {}: {:#}", name, e);
+            job::send(
+                &name,
+                JobEvent::Failed(ErrorBanner::new("Restoring the backup failed", &e)),
+            );
+        }
     }
 }
 
-pub async fn perform_play(path: PathBuf, executable: PathBuf, name: String, do_debug: bool) {
-    send_message(Message::MusicMessage(MusicCommand::WeakPause));
-    if let Err(e) = play(path, executable, name, do_debug).await {
-        error!("Failed to run game: {:#}", e);
+/// Returns the state the instance is in after the game exited. `without_plugins` moves the
+/// plugins aside for this run, see [`safe_mode`].
+pub async fn perform_play(
+    instance: Instance,
+    do_debug: bool,
+    without_plugins: bool,
+) -> InstanceState {
+    let aside = if without_plugins {
+        // Moving the plugins away from under another game would change what it sees
+        let move_aside = |dir: PathBuf| match running_with_config(&dir, &instance.name) {
+            Some(other) => Err(anyhow!(
+                "{} is running with the same config folder, close it first",
+                other
+            )),
+            None => safe_mode::move_aside(&dir),
+        };
+        match instance.config_dir().map(move_aside) {
+            Some(Ok(aside)) => aside,
+            Some(Err(e)) => {
+                error!("Failed to play {} without plugins: {:#}", instance.name, e);
+                return InstanceState::Failed(ErrorBanner::new(
+                    "The plugins couldn't be moved aside",
+                    &e,
+                ));
+            }
+            None => None,
+        }
+    } else {
+        instance.apply_plugin_profile();
+        high_dpi::apply(&instance);
+        None
+    };
+    let music = instance
+        .launch_options
+        .music
+        .or(Settings::load().music_while_playing);
+    send_message(Message::MusicMessage(music::game_started(
+        &instance.name,
+        music,
+    )));
+    let state = match play(&instance, do_debug).await {
+        Ok(()) => InstanceState::Ready,
+        Err(e) => {
+            error!("Failed to run game: {:#}", e);
+            InstanceState::Failed(ErrorBanner::new("The game failed to start", &e))
+        }
+    };
+    if let Some(Err(e)) = aside.map(PluginsAside::put_back) {
+        error!(
+            "Failed to put back the plugins of {}: {:#}",
+            instance.name, e
+        );
+        notify(Notification::new(Level::Error, format!("{e:#}")).sticky());
     }
-    send_message(Message::MusicMessage(MusicCommand::WeakPlay));
+    sync::perform_sync(false).await;
+    send_message(Message::MusicMessage(music::game_exited(&instance.name)));
+    state
 }
 
-pub async fn play(path: PathBuf, executable: PathBuf, name: String, do_debug: bool) -> Result<()> {
-    let mut log_path = path;
-    log_path.push("logs");
+pub async fn play(instance: &Instance, do_debug: bool) -> Result<()> {
+    let name = &instance.name;
+    let executable = &instance.executable;
+    // AppImages bring their libraries along
+    if instance.instance_type == InstanceType::Linux {
+        libraries::check(executable)?;
+    }
+    let log_path = instance.logs_dir();
     fs::create_dir_all(&log_path)?;
 
     let time = OffsetDateTime::now_utc().format(&format_description::parse(
@@ -360,11 +3049,11 @@ pub async fn play(path: PathBuf, executable: PathBuf, name: String, do_debug: bo
     )?)?;
     let mut out_path = log_path.clone();
     out_path.push(format!("{time}.out"));
-    let mut out = File::create(out_path)?;
+    let out = File::create(&out_path)?;
 
     let mut err_path = log_path.clone();
     err_path.push(format!("{time}.err"));
-    let mut err = File::create(err_path)?;
+    let err = File::create(&err_path)?;
 
     info!(
         "Launching {} via executable {}",
@@ -372,42 +3061,412 @@ pub async fn play(path: PathBuf, executable: PathBuf, name: String, do_debug: bo
         executable.to_string_lossy()
     );
 
-    let mut cmd = Command::new(&executable);
-    let output = if do_debug {
-        cmd.arg("-d").output()
+    let lock = instance_lock::acquire(&instance.path, "playing")?;
+    let mut cmd = Command::new(executable);
+    cmd.args(instance.launch_options.args());
+    cmd.args(instance.launch_args.split_whitespace());
+    instance.launch_options.prepare(&mut cmd, executable);
+    if let Some(config_dir) = instance.config_arg() {
+        fs::create_dir_all(&config_dir)?;
+        cmd.arg("--config").arg(config_dir);
+    }
+    if do_debug {
+        cmd.arg("-d");
+    }
+    let mut cmd = sandbox::host_command(cmd);
+    cmd.stdin(Stdio::null());
+    // Output gets written to the logfiles either way, but only streamed to the console if attached
+    let mut logfiles = None;
+    let settings = Settings::load();
+    if settings.detach_games {
+        info!(
+            "Launching {} detached, its output only goes to the logfiles",
+            name
+        );
+        cmd.stdout(out).stderr(err);
+        detach(&mut cmd);
     } else {
-        cmd.output()
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+        logfiles = Some((out, err));
+    }
+    let started = Instant::now();
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Failed to start {}", executable.to_string_lossy()))?;
+    debug!("{} is running as process {}", name, child.id());
+    instance.launch_options.apply(child.id());
+    if let Err(e) = lock.hand_over(child.id()) {
+        warn!(
+            "Failed to hand the lock of {} over to the game: {:#}",
+            name, e
+        );
+    }
+    if let Ok(mut running) = RUNNING.lock() {
+        running.insert(name.clone(), (child.id(), instance.config_dir()));
+    }
+    send_message(Message::ConsoleMessage(ConsoleMessage::Started(
+        name.clone(),
+    )));
+    let (stdout, stderr) = match logfiles {
+        Some((out, err)) => (
+            child.stdout.take().map(|s| {
+                let out = CappedLog::new(out, &out_path, settings.game_log_limit);
+                stream_output(name.clone(), Stream::Stdout, s, out)
+            }),
+            child.stderr.take().map(|s| {
+                let err = CappedLog::new(err, &err_path, settings.game_log_limit);
+                stream_output(name.clone(), Stream::Stderr, s, err)
+            }),
+        ),
+        None => (None, None),
     };
-    match output {
-        Ok(output) => {
-            info!("{} exited with {}", name, output.status);
-            out.write_all(&output.stdout)?;
-            err.write_all(&output.stderr)?;
-            info!(
-                "Logfiles have been written to {}",
-                log_path.to_string_lossy()
+    // Dropping the sender once the game exits stops the reminders
+    let _reminder =
+        (settings.play_reminder > 0).then(|| remind(name.clone(), settings.play_reminder));
+    let status = child.wait();
+    if let Ok(mut running) = RUNNING.lock() {
+        running.remove(name);
+    }
+    let stdout = stdout.and_then(|h| h.join().ok()).unwrap_or_default();
+    let stderr = stderr.and_then(|h| h.join().ok()).unwrap_or_default();
+    let status = status?;
+
+    let run = stats::record_launch(name, started.elapsed(), status);
+    telemetry::record_launch(instance.instance_type);
+    info!("{} exited with {}", name, status);
+    scripting::fire(
+        "on_game_exit",
+        vec![
+            name.clone().into(),
+            i64::from(status.code().unwrap_or(-1)).into(),
+        ],
+    );
+    info!(
+        "Logfiles have been written to {}",
+        log_path.to_string_lossy()
+    );
+    if let Err(e) = game_logs::prune(
+        &log_path,
+        settings.keep_log_sessions,
+        settings.keep_log_days,
+    ) {
+        warn!("Failed to remove old logfiles: {:#}", e);
+    }
+    // Nothing gets collected for detached games, their logfiles have it all
+    let has_output = !stdout.is_empty() || !stderr.is_empty();
+    if !status.success() && has_output {
+        error!("Stdout was: {}", stdout);
+        error!("Stderr was: {}", stderr);
+    }
+    if instance.instance_type == InstanceType::Windows
+        && !status.success()
+        && runtimes::is_missing(status.code(), &stderr)
+    {
+        notify(
+            Notification::new(
+                Level::Error,
+                format!("{name} needs the Visual C++ runtime, which isn't installed"),
+            )
+            .action(
+                "Install it",
+                Message::InstanceMessage(name.clone(), InstanceMessage::InstallRuntime),
+            )
+            .sticky(),
+        );
+        return Err(MissingRuntime.into());
+    }
+    if !status.success() {
+        // Detached games only wrote their output to the logfile
+        let stderr = if has_output {
+            stderr
+        } else {
+            fs::read_to_string(&err_path).unwrap_or_default()
+        };
+        debug!(
+            "Crash summary:\n{}",
+            crash_summary::summary(instance, &run, &stderr)
+        );
+        notify(
+            Notification::new(Level::Error, format!("{name} {}", run.status()))
+                .action(
+                    "Report on GitHub",
+                    Message::InstanceMessage(
+                        name.clone(),
+                        InstanceMessage::ReportCrash(crash_summary::issue_url(
+                            instance, &run, &stderr,
+                        )),
+                    ),
+                )
+                .sticky(),
+        );
+    }
+    Ok(())
+}
+
+/// Notifies every `hours` of the session until the returned sender is dropped.
+fn remind(name: String, hours: u64) -> mpsc::Sender<()> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut played = 0;
+        while let Err(mpsc::RecvTimeoutError::Timeout) =
+            receiver.recv_timeout(Duration::from_secs(hours * 60 * 60))
+        {
+            played += hours;
+            info!("{} has been running for {} hours", name, played);
+            notify(
+                Notification::new(
+                    Level::Info,
+                    format!(
+                        "You've been flying for {played} hour{} in {name}",
+                        if played == 1 { "" } else { "s" }
+                    ),
+                )
+                .sticky(),
+            );
+        }
+    });
+    sender
+}
+
+/// Keeps the game from being taken down together with the launcher.
+#[cfg(unix)]
+fn detach(cmd: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    // Keeps hangups and Ctrl+C in the launcher's terminal from reaching the game
+    cmd.process_group(0);
+}
+
+#[cfg(windows)]
+fn detach(cmd: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const DETACHED_PROCESS: u32 = 0x0000_0008;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+    cmd.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+}
+
+/// Forwards the lines of a game's output to its console and logfile as they come in.
+/// Returns everything that was read once the stream is closed.
+fn stream_output(
+    name: String,
+    stream: Stream,
+    reader: impl Read + Send + 'static,
+    mut file: CappedLog,
+) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut collected = String::new();
+        let mut buffer = vec![];
+        loop {
+            buffer.clear();
+            match reader.read_until(b'\n', &mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            if let Err(e) = file.write_line(&buffer) {
+                warn!("Failed to write game output to logfile: {}", e);
+            }
+            let line = String::from_utf8_lossy(&buffer)
+                .trim_end_matches(['\r', '\n'])
+                .to_string();
+            collected.push_str(&line);
+            collected.push('\n');
+            // Only the end matters for crash reports, and the logfile has the rest
+            if collected.len() > 2 * MAX_COLLECTED_OUTPUT {
+                let mut start = collected.len() - MAX_COLLECTED_OUTPUT;
+                while !collected.is_char_boundary(start) {
+                    start += 1;
+                }
+                collected.drain(..start);
+            }
+            send_message(Message::ConsoleMessage(ConsoleMessage::Line(
+                name.clone(),
+                stream,
+                line,
+            )));
+        }
+        if let Err(e) = file.finish() {
+            warn!(
+                "Failed to write the end of the game output to logfile: {}",
+                e
             );
-            if !output.status.success() {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                error!("Stdout was: {}", stdout);
-                error!("Stderr was: {}", stderr);
+        }
+        collected
+    })
+}
+
+pub fn get_instances_dir() -> Option<PathBuf> {
+    let mut cached = INSTANCES_DIR.lock().unwrap_or_else(PoisonError::into_inner);
+    if cached.is_none() {
+        *cached = LocalSettings::load()
+            .instances_dir
+            .or_else(|| Some(get_data_dir()?.join("instances")));
+    }
+    cached.clone()
+}
+
+/// How the instances are stored, see `instance_store`.
+fn get_instance_store() -> StoreType {
+    *INSTANCE_STORE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_or_insert_with(|| LocalSettings::load().instance_store)
+}
+
+/// Makes [`get_instances_dir`] and [`get_instance_store`] look up their settings again, once the
+/// settings changed.
+pub fn forget_instances_dir() {
+    *INSTANCES_DIR.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    *INSTANCE_STORE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Bytes available on the volume `path` is on, which is the one with the longest matching mount point.
+pub fn free_space(path: &Path) -> Option<u64> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    sysinfo::Disks::new_with_refreshed_list()
+        .list()
+        .iter()
+        .filter(|d| path.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(sysinfo::Disk::available_space)
+}
+
+/// The instances dir along with the free space on its volume
+pub async fn perform_check_free_space() -> Option<(PathBuf, Option<u64>)> {
+    let dir = get_instances_dir()?;
+    let free_space = free_space(&dir);
+    Some((dir, free_space))
+}
+
+/// Moves a directory, falling back to copying if it's on another filesystem.
+fn move_dir(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::create_dir_all(to)?;
+    let options = fs_extra::dir::CopyOptions::new().content_only(true);
+    fs_extra::dir::move_dir(from, to, &options)?;
+    Ok(())
+}
+
+/// Moves all instances into `target` and makes it the new instances dir.
+/// If moving an instance fails, the already moved ones keep their new paths,
+/// but the instances dir stays the same.
+fn move_instances(instances: Vec<Instance>, target: &Path) -> Result<()> {
+    let old_dir = get_instances_dir().ok_or_else(|| anyhow!("Failed to get Instances dir"))?;
+    fs::create_dir_all(target)?;
+
+    let mut moved = BTreeMap::new();
+    let mut result = Ok(());
+    for mut instance in instances {
+        if result.is_ok() {
+            let new_path = target.join(&instance.name);
+            info!("Moving {} to {}", instance.name, new_path.to_string_lossy());
+            result = if new_path.exists() {
+                Err(anyhow!("{} already exists", new_path.to_string_lossy()))
+            } else {
+                move_dir(&instance.path, &new_path)
+            };
+            if result.is_ok() {
+                if let Ok(relative) = instance.executable.strip_prefix(&instance.path) {
+                    instance.executable = new_path.join(relative);
+                }
+                instance.path = new_path;
+                if !instance.executable.exists() {
+                    warn!(
+                        "The executable of {} is missing after moving it: {}",
+                        instance.name,
+                        instance.executable.to_string_lossy()
+                    );
+                }
             }
         }
+        moved.insert(instance.name.clone(), instance);
+    }
 
-        Err(e) => error!("Error starting process: {}", e),
-    };
+    if result.is_ok() {
+        let mut settings = Settings::load();
+        settings.local.instances_dir = Some(target.to_path_buf());
+        settings.save()?;
+        instance_store::remove(&old_dir)?;
+    }
+    save_instances(moved)?;
+    result
+}
+
+/// Makes `target` the instances dir for new instances, while the existing ones stay where they
+/// are. They're listed in `target` from now on, along with any that were listed there already.
+fn use_instances_dir(instances: Vec<Instance>, target: &Path) -> Result<()> {
+    let old_dir = get_instances_dir().ok_or_else(|| anyhow!("Failed to get Instances dir"))?;
+    fs::create_dir_all(target)?;
+    let mut listed: BTreeMap<String, Instance> =
+        instance_store::load(target, get_instance_store())?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|i| (i.name.clone(), i))
+            .collect();
+    for instance in instances {
+        if let Some(existing) = listed.get(&instance.name) {
+            if existing.path != instance.path {
+                warn!(
+                    "{} is listed in {} already, so the one in {} isn't",
+                    instance.name,
+                    target.to_string_lossy(),
+                    instance.path.to_string_lossy()
+                );
+            }
+            continue;
+        }
+        listed.insert(instance.name.clone(), instance);
+    }
+
+    let mut settings = Settings::load();
+    settings.local.instances_dir = Some(target.to_path_buf());
+    settings.save()?;
+    save_instances(listed)?;
+    if old_dir != target {
+        instance_store::remove(&old_dir)?;
+    }
     Ok(())
 }
 
-pub fn get_instances_dir() -> Option<PathBuf> {
-    let mut dir = get_data_dir()?;
-    dir.push("instances");
-    Some(dir)
+pub async fn perform_use_instances_dir(instances: Vec<Instance>, target: PathBuf) {
+    match use_instances_dir(instances, &target) {
+        Ok(()) => notify(Notification::new(
+            Level::Success,
+            format!(
+                "New instances go into {}, the existing ones stay where they are",
+                target.to_string_lossy()
+            ),
+        )),
+        Err(e) => {
+            error!("Failed to switch the instances folder: {:#}", e);
+            notify(Notification::new(
+                Level::Error,
+                "Switching the instances folder failed, see the log for details",
+            ));
+        }
+    }
+    send_message(Message::ReloadInstances);
 }
 
-#[derive(Serialize, Deserialize)]
-struct InstancesContainer(Vec<Instance>);
+pub async fn perform_move_instances(instances: Vec<Instance>, target: PathBuf) {
+    match move_instances(instances, &target) {
+        Ok(()) => notify(Notification::new(
+            Level::Success,
+            format!("Moved instances to {}", target.to_string_lossy()),
+        )),
+        Err(e) => {
+            error!("Failed to move instances: {:#}", e);
+            notify(Notification::new(
+                Level::Error,
+                "Moving instances failed, see the log for details",
+            ));
+        }
+    }
+    send_message(Message::ReloadInstances);
+}
 
 pub fn perform_save_instances(instances: BTreeMap<String, Instance>) {
     if let Err(e) = save_instances(instances) {
@@ -416,33 +3475,57 @@ pub fn perform_save_instances(instances: BTreeMap<String, Instance>) {
 }
 
 fn save_instances(instances: BTreeMap<String, Instance>) -> Result<()> {
-    let mut instances_file =
+    if !read_only::is_persistent() {
+        return Ok(());
+    }
+    let instances_dir =
         get_instances_dir().ok_or_else(|| anyhow!("Failed to get Instances dir"))?;
-    instances_file.push("instances.json");
-    debug!("Saving to {}", instances_file.to_string_lossy());
-
-    let file = File::create(instances_file)?;
+    instance_store::save(
+        &instances_dir,
+        get_instance_store(),
+        &instances.into_values().collect::<Vec<Instance>>(),
+    )
+}
 
-    serde_json::to_writer_pretty(
-        file,
-        &InstancesContainer(instances.values().cloned().collect()),
-    )?;
-    Ok(())
+/// Drive letters and mount points change when a portable launcher gets moved around,
+/// so look for instances that went missing in the current instances dir.
+fn rebase_portable(instance: &mut Instance) {
+    let Some(dir) = get_instances_dir().map(|d| d.join(&instance.name)) else {
+        return;
+    };
+    if instance.path.exists() || !dir.exists() {
+        return;
+    }
+    if let Ok(relative) = instance.executable.strip_prefix(&instance.path) {
+        instance.executable = dir.join(relative);
+    }
+    info!(
+        "Moved {} from {} to {}",
+        instance.name,
+        instance.path.to_string_lossy(),
+        dir.to_string_lossy()
+    );
+    instance.path = dir;
 }
 
 pub fn load_instances() -> Result<Vec<Instance>> {
-    let mut instances_file =
+    let instances_dir =
         get_instances_dir().ok_or_else(|| anyhow!("Failed to get Instances dir"))?;
-    instances_file.push("instances.json");
-    debug!("Loading from {}", instances_file.to_string_lossy());
-
-    if instances_file.exists() {
-        let file = File::open(instances_file)?;
 
-        let container: InstancesContainer = serde_json::from_reader(file)?;
-        Ok(container.0)
+    if let Some(mut instances) = instance_store::load(&instances_dir, get_instance_store())? {
+        if is_portable() {
+            instances.iter_mut().for_each(rebase_portable);
+        }
+        // Instances from before install dates were recorded get the age of their folder
+        for instance in instances.iter_mut().filter(|i| i.installed.is_none()) {
+            instance.installed = fs::metadata(&instance.path)
+                .and_then(|m| m.created().or(m.modified()))
+                .ok()
+                .map(|time| OffsetDateTime::from(time).unix_timestamp());
+        }
+        Ok(instances)
     } else {
-        warn!("instances.json doesn't exist (yet?), commencing without loading Instances");
+        warn!("No instances were saved (yet?), commencing without loading Instances");
         Ok(vec![])
     }
 }
@@ -0,0 +1,132 @@
+//! Lock files in instance folders, so two operations, or two launcher processes, can't change
+//! the same instance at once.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Kept out of backups of the build, see `versions::PRESERVED`
+pub const FILE_NAME: &str = ".eslauncher2.lock";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Holder {
+    pid: u32,
+    /// What the holder is doing, for the error shown to whoever has to wait
+    operation: String,
+}
+
+/// Held while an operation changes an instance folder. Dropping it removes the lock file.
+#[must_use]
+pub struct InstanceLock {
+    path: PathBuf,
+    operation: String,
+}
+
+#[cfg(target_os = "linux")]
+fn is_alive(pid: u32) -> bool {
+    pid == process::id() || Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn is_alive(pid: u32) -> bool {
+    pid == process::id()
+        || process::Command::new("kill")
+            .arg("-0")
+            .arg(pid.to_string())
+            .stdout(process::Stdio::null())
+            .stderr(process::Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+}
+
+#[cfg(windows)]
+fn is_alive(pid: u32) -> bool {
+    pid == process::id()
+        || process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {pid}"), "/NH"])
+            .output()
+            .is_ok_and(|o| String::from_utf8_lossy(&o.stdout).contains(&pid.to_string()))
+}
+
+/// The holder of the lock on `dir`, unless there is none or it has exited without cleaning up.
+fn holder(dir: &Path) -> Option<Holder> {
+    let text = fs::read_to_string(dir.join(FILE_NAME)).ok()?;
+    let holder: Holder = serde_json::from_str(&text).ok()?;
+    is_alive(holder.pid).then_some(holder)
+}
+
+fn busy(dir: &Path, holder: &Holder) -> anyhow::Error {
+    anyhow!(
+        "{} is in use ({}, process {})",
+        dir.to_string_lossy(),
+        holder.operation,
+        holder.pid
+    )
+}
+
+/// Fails if an operation is changing `dir`, for operations that only read from it.
+pub fn check(dir: &Path) -> Result<()> {
+    match holder(dir) {
+        Some(holder) => Err(busy(dir, &holder)),
+        None => Ok(()),
+    }
+}
+
+/// Locks `dir` for an operation that changes it, failing if another one already holds the lock.
+pub fn acquire(dir: &Path, operation: &str) -> Result<InstanceLock> {
+    let path = dir.join(FILE_NAME);
+    let holder = Holder {
+        pid: process::id(),
+        operation: operation.into(),
+    };
+    // The second attempt follows the removal of a stale lock
+    for _ in 0..2 {
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => {
+                serde_json::to_writer(file, &holder)?;
+                debug!("Locked {} for {}", dir.to_string_lossy(), operation);
+                return Ok(InstanceLock {
+                    path,
+                    operation: operation.into(),
+                });
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                if let Some(holder) = self::holder(dir) {
+                    return Err(busy(dir, &holder));
+                }
+                warn!("Removing stale lock {}", path.to_string_lossy());
+                fs::remove_file(&path)?;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(anyhow!("Failed to lock {}", dir.to_string_lossy()))
+}
+
+impl InstanceLock {
+    /// Passes the lock on to another process, e.g. the game,
+    /// so it stays valid while that runs even if the launcher exits.
+    pub fn hand_over(&self, pid: u32) -> Result<()> {
+        let holder = Holder {
+            pid,
+            operation: self.operation.clone(),
+        };
+        fs::write(&self.path, serde_json::to_string(&holder)?)?;
+        Ok(())
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        match fs::remove_file(&self.path) {
+            // Operations that replace the whole folder take the lock file with them
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                warn!("Failed to remove {}: {}", self.path.to_string_lossy(), e);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,441 @@
+//! Where the list of instances is kept. By default that's instances.json in the instances folder,
+//! which is rewritten as a whole on every change. Alternatively each instance gets a file of its
+//! own, or a row in an SQLite database, so a change only rewrites the instances that changed.
+//! Other stores can be added by implementing [`InstanceStore`].
+//!
+//! Several launchers may use the same instances folder, e.g. one started from the command line
+//! while the GUI is open, so every access holds a lock on a file in there.
+
+use crate::instance::Instance;
+use anyhow::{Context, Result};
+use fs4::fs_std::FileExt;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// Serializes access to the stores, since instances are saved from jobs running in parallel
+static LOCK: Mutex<()> = Mutex::new(());
+/// Inside the instances folder, hidden so it isn't taken for an instance
+pub const PER_INSTANCE_DIR: &str = ".instances";
+/// Inside the instances folder
+pub const DATABASE_FILE: &str = "instances.sqlite";
+/// Locked by whichever launcher is using the stores in the instances folder
+const LOCK_FILE: &str = ".instances.lock";
+/// How long to wait for another launcher to finish with the database, on top of the lock file
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StoreType {
+    #[default]
+    SingleFile,
+    PerInstance,
+    Sqlite,
+}
+
+impl StoreType {
+    pub const ALL: [Self; 3] = [Self::SingleFile, Self::PerInstance, Self::Sqlite];
+}
+
+impl fmt::Display for StoreType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SingleFile => write!(f, "instances.json"),
+            Self::PerInstance => write!(f, "A file per instance"),
+            Self::Sqlite => write!(f, "SQLite database"),
+        }
+    }
+}
+
+trait InstanceStore {
+    /// None if nothing was stored here yet.
+    fn load(&self) -> Result<Option<Vec<Instance>>>;
+    fn save(&self, instances: &[Instance]) -> Result<()>;
+    /// Removes what's stored, e.g. after switching to another store.
+    fn remove(&self) -> Result<()>;
+}
+
+/// Writes to a temporary file first, so a crash while saving doesn't leave half a file behind.
+fn write_atomically(path: &Path, data: &[u8]) -> Result<()> {
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, data)?;
+    fs::rename(&temp, path).with_context(|| format!("Failed to replace {}", path.to_string_lossy()))
+}
+
+struct SingleFile {
+    file: PathBuf,
+}
+
+impl InstanceStore for SingleFile {
+    fn load(&self) -> Result<Option<Vec<Instance>>> {
+        if !self.file.exists() {
+            return Ok(None);
+        }
+        debug!("Loading from {}", self.file.to_string_lossy());
+        Ok(Some(serde_json::from_reader(File::open(&self.file)?)?))
+    }
+
+    fn save(&self, instances: &[Instance]) -> Result<()> {
+        debug!("Saving to {}", self.file.to_string_lossy());
+        write_atomically(&self.file, &serde_json::to_vec_pretty(instances)?)
+    }
+
+    fn remove(&self) -> Result<()> {
+        if self.file.exists() {
+            fs::remove_file(&self.file)?;
+        }
+        Ok(())
+    }
+}
+
+struct PerInstance {
+    dir: PathBuf,
+}
+
+/// The file an instance is stored in. Names can hold anything, e.g. when imported or set from the
+/// command line, so only safe characters are kept. A hash of the whole name keeps names apart that
+/// only differ in the replaced characters or in case, which some filesystems ignore.
+fn file_name(instance_name: &str) -> String {
+    let readable: String = instance_name
+        .chars()
+        .take(64)
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    // FNV-1a, which unlike the std hashers is the same in every build
+    let hash = instance_name
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("{}-{hash:016x}.json", readable.trim())
+}
+
+impl PerInstance {
+    fn files(&self) -> Result<Vec<PathBuf>> {
+        let mut files: Vec<PathBuf> = fs::read_dir(&self.dir)?
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "json"))
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+}
+
+impl InstanceStore for PerInstance {
+    fn load(&self) -> Result<Option<Vec<Instance>>> {
+        if !self.dir.is_dir() {
+            return Ok(None);
+        }
+        debug!("Loading from {}", self.dir.to_string_lossy());
+        let mut instances = vec![];
+        for file in self.files()? {
+            // One broken file only costs that instance
+            match File::open(&file)
+                .map_err(anyhow::Error::from)
+                .and_then(|f| Ok(serde_json::from_reader(f)?))
+            {
+                Ok(instance) => instances.push(instance),
+                Err(e) => error!("Failed to load {}: {:#}", file.to_string_lossy(), e),
+            }
+        }
+        Ok(Some(instances))
+    }
+
+    fn save(&self, instances: &[Instance]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let mut kept = BTreeSet::new();
+        for instance in instances {
+            let file = self.dir.join(file_name(&instance.name));
+            let data = serde_json::to_vec_pretty(instance)?;
+            if fs::read(&file).ok().as_ref() != Some(&data) {
+                debug!("Saving to {}", file.to_string_lossy());
+                write_atomically(&file, &data)?;
+            }
+            kept.insert(file);
+        }
+        for file in self.files()?.into_iter().filter(|f| !kept.contains(f)) {
+            fs::remove_file(file)?;
+        }
+        Ok(())
+    }
+
+    fn remove(&self) -> Result<()> {
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir)?;
+        }
+        Ok(())
+    }
+}
+
+/// The instances as JSON, keyed by name, so changes are tracked by comparing those.
+struct Sqlite {
+    file: PathBuf,
+}
+
+impl Sqlite {
+    fn connect(&self) -> Result<Connection> {
+        let connection = Connection::open(&self.file)
+            .with_context(|| format!("Failed to open {}", self.file.to_string_lossy()))?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS instances (name TEXT PRIMARY KEY, data TEXT NOT NULL)",
+            [],
+        )?;
+        Ok(connection)
+    }
+}
+
+impl InstanceStore for Sqlite {
+    fn load(&self) -> Result<Option<Vec<Instance>>> {
+        if !self.file.exists() {
+            return Ok(None);
+        }
+        debug!("Loading from {}", self.file.to_string_lossy());
+        let connection = self.connect()?;
+        let mut statement = connection.prepare("SELECT name, data FROM instances ORDER BY name")?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut instances = vec![];
+        for row in rows {
+            let (name, data) = row?;
+            // One broken row only costs that instance
+            match serde_json::from_str(&data) {
+                Ok(instance) => instances.push(instance),
+                Err(e) => error!("Failed to load {} from the database: {:#}", name, e),
+            }
+        }
+        Ok(Some(instances))
+    }
+
+    fn save(&self, instances: &[Instance]) -> Result<()> {
+        let mut connection = self.connect()?;
+        let transaction = connection.transaction()?;
+        let stored: BTreeMap<String, String> = {
+            let mut statement = transaction.prepare("SELECT name, data FROM instances")?;
+            let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        for instance in instances {
+            let data = serde_json::to_string_pretty(instance)?;
+            if stored.get(&instance.name) != Some(&data) {
+                debug!(
+                    "Saving {} to {}",
+                    instance.name,
+                    self.file.to_string_lossy()
+                );
+                transaction.execute(
+                    "INSERT OR REPLACE INTO instances (name, data) VALUES (?1, ?2)",
+                    params![instance.name, data],
+                )?;
+            }
+        }
+        let kept: BTreeSet<&str> = instances.iter().map(|i| i.name.as_str()).collect();
+        for name in stored.keys().filter(|n| !kept.contains(n.as_str())) {
+            transaction.execute("DELETE FROM instances WHERE name = ?1", params![name])?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn remove(&self) -> Result<()> {
+        // Left behind by a launcher that crashed while writing
+        for file in [
+            self.file.clone(),
+            self.file.with_extension("sqlite-journal"),
+            self.file.with_extension("sqlite-wal"),
+            self.file.with_extension("sqlite-shm"),
+        ] {
+            if file.exists() {
+                fs::remove_file(file)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Keeps other launchers away from the stores in `instances_dir` until it's dropped.
+struct Guard {
+    // Released when the file is closed
+    _file: Option<File>,
+    _process: MutexGuard<'static, ()>,
+}
+
+fn lock(instances_dir: &Path) -> Result<Guard> {
+    let process = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    // Nothing to keep others away from yet
+    if !instances_dir.is_dir() {
+        return Ok(Guard {
+            _file: None,
+            _process: process,
+        });
+    }
+    let path = instances_dir.join(LOCK_FILE);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.to_string_lossy()))?;
+    file.lock_exclusive()
+        .with_context(|| format!("Failed to lock {}", path.to_string_lossy()))?;
+    Ok(Guard {
+        _file: Some(file),
+        _process: process,
+    })
+}
+
+fn open(store_type: StoreType, instances_dir: &Path) -> Box<dyn InstanceStore> {
+    match store_type {
+        StoreType::SingleFile => Box::new(SingleFile {
+            file: instances_dir.join("instances.json"),
+        }),
+        StoreType::PerInstance => Box::new(PerInstance {
+            dir: instances_dir.join(PER_INSTANCE_DIR),
+        }),
+        StoreType::Sqlite => Box::new(Sqlite {
+            file: instances_dir.join(DATABASE_FILE),
+        }),
+    }
+}
+
+/// The instances stored in `instances_dir` as `current`, None if there are none yet. Instances
+/// only found in another store, e.g. after an older launcher saved them, are loaded from there.
+pub fn load(instances_dir: &Path, current: StoreType) -> Result<Option<Vec<Instance>>> {
+    let _guard = lock(instances_dir)?;
+    if let Some(instances) = open(current, instances_dir).load()? {
+        return Ok(Some(instances));
+    }
+    for other in StoreType::ALL.into_iter().filter(|t| *t != current) {
+        if let Some(instances) = open(other, instances_dir).load()? {
+            info!("Loading the instances from {} instead", other);
+            return Ok(Some(instances));
+        }
+    }
+    Ok(None)
+}
+
+pub fn save(instances_dir: &Path, store_type: StoreType, instances: &[Instance]) -> Result<()> {
+    let _guard = lock(instances_dir)?;
+    open(store_type, instances_dir).save(instances)
+}
+
+/// Removes the stored instances, after they moved to another instances folder.
+pub fn remove(instances_dir: &Path) -> Result<()> {
+    let guard = lock(instances_dir)?;
+    for store_type in StoreType::ALL {
+        open(store_type, instances_dir).remove()?;
+    }
+    drop(guard);
+    // Another launcher may still have it open, which is fine
+    let _ = fs::remove_file(instances_dir.join(LOCK_FILE));
+    Ok(())
+}
+
+/// Moves the stored instances from one store into the other.
+pub fn switch(instances_dir: &Path, from: StoreType, to: StoreType) -> Result<()> {
+    let _guard = lock(instances_dir)?;
+    let instances = open(from, instances_dir).load()?.unwrap_or_default();
+    open(to, instances_dir).save(&instances)?;
+    open(from, instances_dir).remove()?;
+    info!(
+        "Moved {} instances from {} to {}",
+        instances.len(),
+        from,
+        to
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::TempDir;
+    use crate::install_frame::InstanceSource;
+    use crate::instance::{InstanceState, InstanceType};
+
+    fn instance(name: &str, version: &str) -> Instance {
+        Instance::new(
+            PathBuf::from(name),
+            PathBuf::from(name).join("endless-sky"),
+            name.into(),
+            version.into(),
+            InstanceType::Linux,
+            InstanceSource::default(),
+            InstanceState::Ready,
+        )
+    }
+
+    fn names(instances: Option<Vec<Instance>>) -> Vec<(String, String)> {
+        let mut names: Vec<_> = instances
+            .unwrap()
+            .into_iter()
+            .map(|i| (i.name, i.version))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn every_store_keeps_the_instances() {
+        let dir = TempDir::new("instance-stores");
+        for store_type in StoreType::ALL {
+            let store = open(store_type, dir.path());
+            assert!(store.load().unwrap().is_none());
+            store
+                .save(&[instance("A/B?", "1"), instance("Other", "1")])
+                .unwrap();
+            store
+                .save(&[instance("A/B?", "2"), instance("New", "1")])
+                .unwrap();
+            assert_eq!(
+                names(store.load().unwrap()),
+                [("A/B?".into(), "2".into()), ("New".into(), "1".into())],
+                "{store_type}"
+            );
+            store.remove().unwrap();
+            assert!(store.load().unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn switching_moves_the_instances() {
+        let dir = TempDir::new("instance-store-switch");
+        save(dir.path(), StoreType::SingleFile, &[instance("A", "1")]).unwrap();
+        switch(dir.path(), StoreType::SingleFile, StoreType::Sqlite).unwrap();
+        assert!(!dir.path().join("instances.json").exists());
+        assert_eq!(
+            names(load(dir.path(), StoreType::Sqlite).unwrap()),
+            [("A".into(), "1".into())]
+        );
+        // E.g. after an older launcher saved them
+        assert_eq!(
+            names(load(dir.path(), StoreType::PerInstance).unwrap()),
+            [("A".into(), "1".into())]
+        );
+        remove(dir.path()).unwrap();
+        assert!(load(dir.path(), StoreType::Sqlite).unwrap().is_none());
+    }
+
+    #[test]
+    fn other_launchers_are_kept_out() {
+        let dir = TempDir::new("instance-store-lock");
+        let guard = lock(dir.path()).unwrap();
+        // Stands in for another launcher, since locks are per open file
+        let other = File::open(dir.path().join(LOCK_FILE)).unwrap();
+        assert!(!other.try_lock_exclusive().unwrap());
+        drop(guard);
+        assert!(other.try_lock_exclusive().unwrap());
+    }
+}
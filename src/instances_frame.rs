@@ -1,34 +1,318 @@
-use crate::instance::{load_instances, Instance};
-use crate::Message;
-use iced::widget::{Column, Container, Scrollable, Text};
-use iced::{alignment, theme, Alignment, Color, Element, Length};
+use crate::archival::Archival;
+use crate::housekeeping::Housekeeping;
+use crate::install_frame::BLACKLISTED_CHARS;
+use crate::instance::{self, get_instances_dir, load_instances, Instance, InstanceMessage};
+use crate::inventory::ExportFormat;
+use crate::navigation::Navigation;
+use crate::settings::{LocalSettings, Settings};
+use crate::stats::Stats;
+use crate::style::{self, ThemeChoice};
+use crate::{desktop_entry, kiosk, Message};
+use anyhow::Result;
+use iced::widget::{scrollable, Button, Column, Container, PickList, Row, Scrollable, Space, Text};
+use iced::{alignment, theme, Alignment, Color, Command, Element, Length};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
 use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InstanceSort {
+    #[default]
+    Name,
+    InstallDate,
+    LastPlayed,
+    Version,
+    DiskSize,
+}
+
+impl InstanceSort {
+    pub const ALL: [Self; 5] = [
+        Self::Name,
+        Self::InstallDate,
+        Self::LastPlayed,
+        Self::Version,
+        Self::DiskSize,
+    ];
+}
+
+impl fmt::Display for InstanceSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name => write!(f, "Name"),
+            Self::InstallDate => write!(f, "Install date"),
+            Self::LastPlayed => write!(f, "Last played"),
+            Self::Version => write!(f, "Version"),
+            Self::DiskSize => write!(f, "Disk size"),
+        }
+    }
+}
+
+/// What double-clicking an instance row does, besides its buttons
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DoubleClickAction {
+    Nothing,
+    #[default]
+    Play,
+    OpenFolder,
+    ToggleDetails,
+}
+
+impl DoubleClickAction {
+    pub const ALL: [Self; 4] = [
+        Self::Nothing,
+        Self::Play,
+        Self::OpenFolder,
+        Self::ToggleDetails,
+    ];
+}
+
+impl fmt::Display for DoubleClickAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Nothing => write!(f, "Nothing"),
+            Self::Play => write!(f, "Play"),
+            Self::OpenFolder => write!(f, "Open folder"),
+            Self::ToggleDetails => write!(f, "Show details"),
+        }
+    }
+}
+
+/// What instances can be sorted by that is too slow to look up while rendering, by instance name.
+/// Disk sizes are measured per instance instead, see `Instance::inspect`.
+#[derive(Debug, Clone, Default)]
+pub struct SortKeys {
+    last_played: BTreeMap<String, i64>,
+}
+
+/// Only gathers the keys `sort` needs.
+async fn load_sort_keys(sort: InstanceSort) -> SortKeys {
+    let mut keys = SortKeys::default();
+    if sort == InstanceSort::LastPlayed {
+        keys.last_played = Stats::load()
+            .instances
+            .into_iter()
+            .filter_map(|(name, stats)| Some((name, stats.last_played?)))
+            .collect();
+    }
+    keys
+}
+
+/// Splits a version into its numeric and other parts, so that e.g. 0.10.2 sorts after 0.9.16.
+fn version_key(version: &str) -> Vec<(u64, String)> {
+    version
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| match part.trim_start_matches('v').parse() {
+            Ok(number) => (number, String::new()),
+            Err(_) => (0, part.to_string()),
+        })
+        .collect()
+}
 
 #[derive(Debug, Clone)]
 pub struct InstancesFrame {
     pub instances: BTreeMap<String, Instance>,
+    sort_keys: SortKeys,
+    pub housekeeping: Housekeeping,
+    pub archival: Archival,
+    /// How far the list is scrolled down, in pixels
+    pub scroll_offset: f32,
+    /// The instance keyboard navigation is on
+    selected: Option<String>,
+}
+
+fn scrollable_id() -> scrollable::Id {
+    scrollable::Id::new("instances")
 }
 
 impl Default for InstancesFrame {
     fn default() -> Self {
+        // Checking files and measuring sizes is left to `inspect_all`, so the list shows up quickly
         let instances = match load_instances() {
-            Ok(vec) => {
-                let mut map = BTreeMap::new();
-                for i in vec {
-                    map.insert(i.name.clone(), i);
-                }
-                map
-            }
+            Ok(vec) => vec.into_iter().map(|i| (i.name.clone(), i)).collect(),
             Err(e) => {
                 error!("Failed to load instances: {:#}", e);
                 BTreeMap::new()
             }
         };
-        Self { instances }
+        Self {
+            instances,
+            sort_keys: SortKeys::default(),
+            housekeeping: Housekeeping::default(),
+            archival: Archival::default(),
+            scroll_offset: LocalSettings::load().instances_scroll,
+            selected: None,
+        }
     }
 }
 impl InstancesFrame {
-    pub fn view(&self) -> Element<Message> {
+    /// Does nothing for sorts that don't need keys, so it's cheap to call after any change.
+    pub fn refresh_sort_keys(&self, sort: InstanceSort) -> Command<Message> {
+        if sort != InstanceSort::LastPlayed {
+            return Command::none();
+        }
+        Command::perform(load_sort_keys(sort), Message::SortKeysLoaded)
+    }
+
+    pub fn inspect_all(&self) -> Command<Message> {
+        Command::batch(self.instances.values().map(Instance::inspect))
+    }
+
+    /// Scrolls the list back to where it was, since it starts at the top whenever it's rebuilt.
+    pub fn restore_scroll(&self) -> Command<Message> {
+        scrollable::scroll_to(
+            scrollable_id(),
+            scrollable::AbsoluteOffset {
+                x: 0.0,
+                y: self.scroll_offset,
+            },
+        )
+    }
+
+    /// Moves the selection through the list, or controls the selected instance.
+    pub fn navigate(&mut self, navigation: Navigation, settings: &Settings) -> Command<Message> {
+        let names: Vec<String> = self
+            .sorted(settings.instance_sort)
+            .into_iter()
+            .map(|i| i.name.clone())
+            .collect();
+        let position = self
+            .selected
+            .as_ref()
+            .and_then(|name| names.iter().position(|n| n == name));
+        let step = match navigation {
+            Navigation::SelectNext => Some(position.map_or(0, |p| (p + 1).min(names.len() - 1))),
+            Navigation::SelectPrevious => Some(position.map_or(0, |p| p.saturating_sub(1))),
+            _ => None,
+        };
+        if let Some(index) = step.filter(|_| !names.is_empty()) {
+            self.selected = Some(names[index].clone());
+            // Rows differ in height, so this only gets close to the selected one
+            return scrollable::snap_to(
+                scrollable_id(),
+                scrollable::RelativeOffset {
+                    x: 0.0,
+                    y: index as f32 / (names.len() - 1).max(1) as f32,
+                },
+            );
+        }
+        let Some(instance) = self.selected.as_ref().and_then(|n| self.instances.get(n)) else {
+            return Command::none();
+        };
+        let can_play = self.can_play(settings);
+        let message = match navigation {
+            Navigation::Activate
+                if can_play && instance.state.is_ready() && instance.problems.is_empty() =>
+            {
+                InstanceMessage::Play(instance.debug_by_default)
+            }
+            Navigation::ToggleDetails => InstanceMessage::ToggleDetails,
+            Navigation::ToggleMenu => InstanceMessage::ToggleMenu,
+            Navigation::Escape if instance.menu_open => InstanceMessage::ToggleMenu,
+            Navigation::Escape => {
+                self.selected = None;
+                return Command::none();
+            }
+            _ => return Command::none(),
+        };
+        let name = instance.name.clone();
+        Command::perform(async {}, move |()| Message::InstanceMessage(name, message))
+    }
+
+    pub fn set_sort_keys(&mut self, keys: SortKeys) {
+        self.sort_keys = keys;
+    }
+
+    /// Name sorts alphabetically, everything else newest or largest first.
+    fn sorted(&self, sort: InstanceSort) -> Vec<&Instance> {
+        let mut instances: Vec<&Instance> = self.instances.values().collect();
+        let keys = &self.sort_keys;
+        // The sort is stable, so ties stay in alphabetical order
+        match sort {
+            InstanceSort::Name => {}
+            InstanceSort::InstallDate => {
+                instances.sort_by_key(|i| Reverse(i.installed));
+            }
+            InstanceSort::LastPlayed => {
+                instances.sort_by_key(|i| Reverse(keys.last_played.get(&i.name)));
+            }
+            InstanceSort::Version => instances.sort_by_key(|i| Reverse(version_key(&i.version))),
+            InstanceSort::DiskSize => instances.sort_by_key(|i| Reverse(i.disk_size)),
+        }
+        instances
+    }
+
+    pub fn any_working(&self) -> bool {
+        self.instances.values().any(|i| i.state.is_working())
+    }
+
+    pub fn any_playing(&self) -> bool {
+        self.instances
+            .values()
+            .any(|i| matches!(i.state, instance::InstanceState::Playing))
+    }
+
+    /// A name for a copy of `name` that neither an instance nor a folder uses yet.
+    pub fn copy_name(&self, name: &str) -> String {
+        let instances_dir = get_instances_dir();
+        (1..)
+            .map(|i| match i {
+                1 => format!("{name} copy"),
+                i => format!("{name} copy {i}"),
+            })
+            .find(|candidate| {
+                !self.instances.contains_key(candidate)
+                    && !instances_dir
+                        .as_ref()
+                        .is_some_and(|dir| dir.join(candidate).exists())
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn rename(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        if new_name.is_empty() {
+            return Err(anyhow!("The name can't be empty"));
+        }
+        if let Some(invalid) = new_name.chars().find(|c| BLACKLISTED_CHARS.contains(c)) {
+            return Err(anyhow!("Invalid character: '{}'", invalid));
+        }
+        if self.instances.contains_key(new_name) {
+            return Err(anyhow!("An instance named {} already exists", new_name));
+        }
+        let mut instance = self
+            .instances
+            .remove(old_name)
+            .ok_or_else(|| anyhow!("Failed to find internal Instance with name {}", old_name))?;
+        // Even a partial rename may have moved the instance folder, so save either way
+        let result = instance.rename(new_name);
+        self.instances.insert(instance.name.clone(), instance);
+        instance::perform_save_instances(self.instances.clone());
+        result?;
+        desktop_entry::write(&self.instances[new_name])?;
+        info!("Renamed {} to {}", old_name, new_name);
+        Ok(())
+    }
+
+    /// Unless allowed, games can't be launched while another one is running.
+    pub fn can_play(&self, settings: &Settings) -> bool {
+        settings.allow_concurrent_games || !self.instances.values().any(|i| i.state.is_playing())
+    }
+
+    /// The instances that have an update available and aren't busy.
+    pub fn updatable(&self) -> Vec<String> {
+        self.instances
+            .values()
+            .filter(|i| i.update_available && i.state.is_ready())
+            .map(|i| i.name.clone())
+            .collect()
+    }
+
+    pub fn view(&self, profiles: &[String], settings: &Settings) -> Element<Message> {
+        let can_play = self.can_play(settings);
+        let show_status = settings.theme == ThemeChoice::HighContrast;
+        let updatable = self.updatable();
+        let kiosk = kiosk::is_enabled();
         let instances_column = Column::new()
             .padding(20)
             .spacing(5)
@@ -41,17 +325,17 @@ impl InstancesFrame {
                         .horizontal_alignment(alignment::Horizontal::Center)
                         .width(Length::Fill),
                 )
-                .push(
+                .push_maybe((!kiosk).then(|| {
                     Text::new("ESLauncher allows you to install multiple instances of Endless Sky. Instances are installations which ESLauncher automatically updates. Install your first instance by typing a name like 'newest' in the box to the right and choosing which version of the game to install.")
                         .size(16)
                          .style(theme::Text::Color(Color::from_rgb8(150, 150, 150)))
                         .horizontal_alignment(alignment::Horizontal::Center)
-                        .width(Length::Fill),
-                )
+                        .width(Length::Fill)
+                }))
                 .into()
         } else {
-            self.instances
-                .values()
+            self.sorted(settings.instance_sort)
+                .into_iter()
                 .fold(instances_column, |column, instance| {
                     column
                         .push(
@@ -66,26 +350,110 @@ impl InstancesFrame {
                                 },
                             )),
                         )
-                        .push(instance.view().map(move |message| {
-                            Message::InstanceMessage(instance.name.clone(), message)
-                        }))
+                        .push({
+                            let row = Container::new(
+                                instance
+                                    .view(
+                                        profiles.to_vec(),
+                                        can_play,
+                                        show_status,
+                                        settings.double_click_action,
+                                    )
+                                    .map(move |message| {
+                                        Message::InstanceMessage(instance.name.clone(), message)
+                                    }),
+                            );
+                            if self.selected.as_ref() == Some(&instance.name) {
+                                row.style(style::focus_outline())
+                            } else {
+                                row
+                            }
+                        })
                 })
                 .into()
         };
-        Container::new(Scrollable::new(
-            Column::new()
-                .push(
-                    Text::new("Instances")
-                        .size(26)
-                        .horizontal_alignment(alignment::Horizontal::Center)
-                        .width(Length::Fill),
-                )
-                .push(instances_list)
-                .spacing(20)
-                .width(Length::Fill),
-        ))
+        Container::new(
+            Scrollable::new(
+                Column::new()
+                    .push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .push(Space::new(Length::Fill, Length::Shrink))
+                            .push(Text::new("Instances").size(26))
+                            .push(
+                                Row::new()
+                                    .spacing(5)
+                                    .align_items(Alignment::Center)
+                                    .width(Length::Fill)
+                                    .push(Space::new(Length::Fill, Length::Shrink))
+                                    .push_maybe((updatable.len() > 1 && !kiosk).then(|| {
+                                        Button::new(Text::new("Update all").size(14))
+                                            .on_press(Message::UpdateInstances(updatable))
+                                    }))
+                                    .push_maybe((!kiosk).then(|| {
+                                        PickList::new(
+                                            ExportFormat::ALL,
+                                            None::<ExportFormat>,
+                                            Message::ExportInstanceList,
+                                        )
+                                        .placeholder("Export list")
+                                        .text_size(14)
+                                    }))
+                                    .push_maybe((!kiosk).then(|| Text::new("Sort by").size(14)))
+                                    .push_maybe((!kiosk).then(|| {
+                                        PickList::new(
+                                            InstanceSort::ALL,
+                                            Some(settings.instance_sort),
+                                            Message::InstanceSortChanged,
+                                        )
+                                        .text_size(14)
+                                    })),
+                            ),
+                    )
+                    .push_maybe(
+                        self.housekeeping
+                            .view(settings.housekeeping_days)
+                            .filter(|_| !kiosk)
+                            .map(|view| view.map(Message::HousekeepingMessage)),
+                    )
+                    .push(instances_list)
+                    .push_maybe(
+                        self.archival
+                            .view()
+                            .filter(|_| !kiosk)
+                            .map(|view| view.map(Message::ArchivalMessage)),
+                    )
+                    .spacing(20)
+                    .width(Length::Fill),
+            )
+            .id(scrollable_id())
+            .on_scroll(|viewport| Message::InstancesScrolled(viewport.absolute_offset().y)),
+        )
         .width(Length::FillPortion(3))
         .padding(30)
         .into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_key_sorts_numbers_numerically() {
+        assert!(version_key("0.10.2") > version_key("0.9.16"));
+        assert!(version_key("v0.10.0") > version_key("0.9.16"));
+        assert!(version_key("0.10.2-alpha") > version_key("0.10.2"));
+        assert_eq!(
+            version_key("v0.10.2-rc1"),
+            vec![
+                (0, String::new()),
+                (10, String::new()),
+                (2, String::new()),
+                (0, "rc1".to_string()),
+            ]
+        );
+        assert!(version_key("").is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+//! Exports the list of instances as a Markdown table or CSV, for sharing setups in forum posts
+//! or keeping a record of what was tested on which build.
+
+use crate::get_data_dir;
+use crate::install_frame::InstanceSourceType;
+use crate::instance::{self, Instance};
+use crate::notifications::{notify, Level, Notification};
+use crate::stats::{self, Stats};
+use anyhow::Result;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use time::{format_description, OffsetDateTime};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Csv,
+}
+
+impl ExportFormat {
+    pub const ALL: [Self; 2] = [Self::Markdown, Self::Csv];
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Markdown => "Markdown table",
+            Self::Csv => "CSV",
+        })
+    }
+}
+
+const COLUMNS: [&str; 4] = ["Name", "Version", "Source", "Playtime"];
+
+fn source(instance: &Instance) -> String {
+    let source = &instance.source;
+    let mut text = match source.r#type {
+        InstanceSourceType::Continuous => "Continuous".into(),
+        InstanceSourceType::Release => format!("Release {}", source.identifier),
+        InstanceSourceType::PR => format!("PR #{}", source.identifier),
+    };
+    if let Some(repo) = &source.repo {
+        text.push_str(&format!(" ({repo})"));
+    }
+    text
+}
+
+fn rows(instances: &[Instance]) -> Vec<[String; 4]> {
+    let stats = Stats::load();
+    instances
+        .iter()
+        .map(|instance| {
+            let playtime = stats
+                .instances
+                .get(&instance.name)
+                .map_or(0, |s| s.playtime);
+            [
+                instance.name.clone(),
+                instance.version.clone(),
+                source(instance),
+                stats::format_duration(playtime),
+            ]
+        })
+        .collect()
+}
+
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|")
+}
+
+/// Quotes values where needed, following RFC 4180.
+fn csv_cell(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn render(format: ExportFormat, instances: &[Instance]) -> String {
+    let rows = rows(instances);
+    match format {
+        ExportFormat::Markdown => {
+            let line = |cells: &[String]| format!("| {} |\n", cells.join(" | "));
+            let mut text = line(&COLUMNS.map(String::from));
+            text.push_str(&line(&COLUMNS.map(|_| "---".into())));
+            for row in rows {
+                text.push_str(&line(&row.map(|cell| markdown_cell(&cell))));
+            }
+            text
+        }
+        ExportFormat::Csv => {
+            let line = |cells: &[String]| format!("{}\r\n", cells.join(","));
+            let mut text = line(&COLUMNS.map(String::from));
+            for row in rows {
+                text.push_str(&line(&row.map(|cell| csv_cell(&cell))));
+            }
+            text
+        }
+    }
+}
+
+fn export(format: ExportFormat, mut instances: Vec<Instance>) -> Result<PathBuf> {
+    instances.sort_by(|a, b| a.name.cmp(&b.name));
+    let dir = get_data_dir()
+        .ok_or_else(|| anyhow!("Failed to get app save dir"))?
+        .join("exports");
+    fs::create_dir_all(&dir)?;
+    let timestamp = OffsetDateTime::now_utc().format(&format_description::parse(
+        "[year]-[month]-[day] [hour]-[minute]-[second]",
+    )?)?;
+    let path = dir.join(format!("Instances {timestamp}.{}", format.extension()));
+    fs::write(&path, render(format, &instances))?;
+    info!("Exported the instance list to {}", path.to_string_lossy());
+    Ok(dir)
+}
+
+/// Writes the list into the exports folder, then opens that folder.
+pub async fn perform_export(format: ExportFormat, instances: Vec<Instance>) {
+    match export(format, instances) {
+        Ok(dir) => {
+            notify(Notification::new(
+                Level::Success,
+                format!("Exported the instance list as {format}"),
+            ));
+            instance::open_folder(dir).await;
+        }
+        Err(e) => {
+            error!("Failed to export the instance list: {:#}", e);
+            notify(Notification::new(
+                Level::Error,
+                "Exporting the instance list failed, see the log for details",
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_cell_quotes_where_needed() {
+        assert_eq!(csv_cell("Endless Sky"), "Endless Sky");
+        assert_eq!(csv_cell(""), "");
+        assert_eq!(csv_cell("a,b"), "\"a,b\"");
+        assert_eq!(csv_cell("the \"fork\""), "\"the \"\"fork\"\"\"");
+        assert_eq!(csv_cell("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn markdown_cell_escapes_pipes() {
+        assert_eq!(markdown_cell("a|b"), "a\\|b");
+    }
+}
@@ -1,4 +1,5 @@
 use crate::github::Artifact;
+use crate::http;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
@@ -6,8 +7,11 @@ use serde::{Deserialize, Serialize};
 struct SHA1(String);
 
 #[derive(Deserialize, Serialize)]
-struct Build {
-    artifacts: Vec<BuildArtifact>,
+pub struct Build {
+    pub artifacts: Vec<BuildArtifact>,
+    /// When the build started, in milliseconds since the epoch
+    #[serde(default)]
+    pub timestamp: Option<i64>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -29,16 +33,15 @@ impl Artifact for BuildArtifact {
 pub fn get_latest_sha() -> Result<String> {
     let url = "https://ci.mcofficer.me/job/EndlessSky-continuous-bitar/lastSuccessfulBuild/api/xml?xpath=/*/*/lastBuiltRevision/SHA1";
 
-    let res = ureq::get(url).call()?;
+    let res = http::agent().get(url).call()?;
     let sha: SHA1 = serde_xml_rs::from_str(&res.into_string()?)?;
     info!("Got new version from Jenkins: {}", sha.0);
     Ok(sha.0)
 }
 
-pub fn get_latest_artifacts() -> Result<Vec<BuildArtifact>> {
-    let url = "https://ci.mcofficer.me/job/EndlessSky-continuous-bitar/lastBuild/api/json?tree=artifacts[*]";
+pub fn get_latest_build() -> Result<Build> {
+    let url = "https://ci.mcofficer.me/job/EndlessSky-continuous-bitar/lastBuild/api/json?tree=artifacts[*],timestamp";
 
-    let res = ureq::get(url).call()?;
-    let build: Build = res.into_json()?;
-    Ok(build.artifacts)
+    let res = http::agent().get(url).call()?;
+    Ok(res.into_json()?)
 }
@@ -0,0 +1,336 @@
+use crate::install_frame::InstanceSource;
+use crate::instance::InstanceType;
+use crate::notifications::ErrorBanner;
+use crate::templates::Template;
+use crate::{get_data_dir, read_only, send_message, Message};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::{self, File};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Serializes read-modify-write cycles of jobs.json
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// Names of the jobs that were asked to stop at their next [`check_cancelled`]
+static CANCELLED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+/// Names of the jobs that were asked to start what they're waiting for in [`watch`] over
+static RETRY: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+/// When each job last showed signs of life, see [`beat`]
+static HEARTBEATS: Mutex<BTreeMap<String, Heartbeat>> = Mutex::new(BTreeMap::new());
+/// How often [`watch`] checks whether the job it waits for should stop
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the GUI checks for stalled jobs
+pub const WATCHDOG_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy)]
+struct Heartbeat {
+    last: Instant,
+    /// Whether the job waits in [`watch`], where it can be retried
+    watched: bool,
+}
+
+/// The error jobs stop with when they get cancelled
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Typed events sent from background jobs (installs, updates) to the GUI,
+/// keyed by the name of the instance they work on.
+#[derive(Debug, Clone)]
+pub enum JobEvent {
+    /// A new step started, which resets any progress of the previous one
+    StageChanged(String),
+    /// Progress within the current stage
+    Progress(Progress),
+    Finished,
+    Failed(ErrorBanner),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    pub done: u32,
+    pub total: Option<u32>,
+    pub units: Option<String>,
+    pub total_approx: bool,
+}
+
+impl Progress {
+    pub fn new(done: u32) -> Self {
+        Self {
+            done,
+            ..Default::default()
+        }
+    }
+    pub fn total(mut self, total: impl Into<Option<u32>>) -> Self {
+        self.total = total.into();
+        self
+    }
+    pub fn units<T: AsRef<str>>(mut self, units: T) -> Self {
+        self.units = Some(units.as_ref().into());
+        self
+    }
+    pub fn total_approx(mut self, total_approx: bool) -> Self {
+        self.total_approx = total_approx;
+        self
+    }
+}
+
+/// What a working instance displays: the current stage and how far along it is
+#[derive(Debug, Clone, Default)]
+pub struct JobStatus {
+    pub stage: String,
+    pub progress: Option<Progress>,
+    /// The job hasn't shown signs of life for a while, see [`stalled_for`]
+    pub stalled: bool,
+}
+
+impl JobStatus {
+    pub fn new(stage: impl Into<String>) -> Self {
+        Self {
+            stage: stage.into(),
+            progress: None,
+            stalled: false,
+        }
+    }
+
+    /// The stage, followed by the percentage done if the total is known, like "Downloading 42%".
+    pub fn summary(&self) -> String {
+        match &self.progress {
+            Some(Progress {
+                done,
+                total: Some(total),
+                ..
+            }) if *total > 0 => {
+                let percent = (u64::from(*done) * 100 / u64::from(*total)).min(100);
+                format!("{} {}%", self.stage, percent)
+            }
+            _ => self.stage.clone(),
+        }
+    }
+}
+
+pub fn send(job: &str, event: JobEvent) {
+    if matches!(event, JobEvent::Finished | JobEvent::Failed(_)) {
+        forget_heartbeat(job);
+    } else {
+        beat(job);
+    }
+    send_message(Message::JobEvent(job.into(), event));
+}
+
+/// Records that the job is still making progress, without telling the GUI. Stages and progress
+/// count too, this is for steps that report progress more rarely than they make it.
+pub fn beat(job: &str) {
+    if let Ok(mut heartbeats) = HEARTBEATS.lock() {
+        let heartbeat = heartbeats.entry(job.into()).or_insert(Heartbeat {
+            last: Instant::now(),
+            watched: false,
+        });
+        heartbeat.last = Instant::now();
+    }
+}
+
+/// The job waits for something that may take arbitrarily long, like other updates, so it isn't
+/// considered stalled until it reports again.
+pub fn expect_wait(job: &str) {
+    forget_heartbeat(job);
+}
+
+/// Stops watching for the job stalling, e.g. once the instance isn't working anymore.
+pub fn forget_heartbeat(job: &str) {
+    if let Ok(mut heartbeats) = HEARTBEATS.lock() {
+        heartbeats.remove(job);
+    }
+}
+
+/// How long the job has shown no signs of life, if it's longer than `timeout`.
+pub fn stalled_for(job: &str, timeout: Duration) -> Option<Duration> {
+    let heartbeats = HEARTBEATS.lock().ok()?;
+    Some(heartbeats.get(job)?.last.elapsed()).filter(|elapsed| *elapsed > timeout)
+}
+
+/// Whether what the job waits for can be started over with [`retry`].
+pub fn is_retryable(job: &str) -> bool {
+    HEARTBEATS
+        .lock()
+        .is_ok_and(|heartbeats| heartbeats.get(job).is_some_and(|h| h.watched))
+}
+
+fn set_watched(job: &str, watched: bool) {
+    if let Ok(mut heartbeats) = HEARTBEATS.lock() {
+        if let Some(heartbeat) = heartbeats.get_mut(job) {
+            heartbeat.watched = watched;
+        }
+    }
+}
+
+/// Asks the job to give up on what it waits for in [`watch`] and start it over.
+pub fn retry(job: &str) {
+    info!("Retrying the current step of {}", job);
+    beat(job);
+    if let Ok(mut retry) = RETRY.lock() {
+        retry.insert(job.into());
+    }
+}
+
+fn take_retry(job: &str) -> bool {
+    RETRY.lock().is_ok_and(|mut retry| retry.remove(job))
+}
+
+/// Waits for `future`, e.g. the next chunk of a download, while still reacting to the job being
+/// cancelled, which hung network requests would otherwise keep it from. None means the job was
+/// asked to [`retry`] instead.
+pub async fn watch<F: Future>(job: &str, future: F) -> Result<Option<F::Output>> {
+    beat(job);
+    set_watched(job, true);
+    let mut future = std::pin::pin!(future);
+    let result = loop {
+        match tokio::time::timeout(WATCH_INTERVAL, &mut future).await {
+            Ok(output) => break Ok(Some(output)),
+            Err(_) => {
+                if let Err(e) = check_cancelled(job) {
+                    break Err(e);
+                }
+                if take_retry(job) {
+                    break Ok(None);
+                }
+            }
+        }
+    };
+    set_watched(job, false);
+    result
+}
+
+pub fn stage(job: &str, stage: impl Into<String>) {
+    send(job, JobEvent::StageChanged(stage.into()));
+}
+
+pub fn progress(job: &str, progress: Progress) {
+    send(job, JobEvent::Progress(progress));
+}
+
+/// A job that gets persisted while it runs, so it can be resumed if the launcher is closed midway.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PendingJob {
+    Install {
+        path: PathBuf,
+        name: String,
+        instance_type: InstanceType,
+        source: InstanceSource,
+        #[serde(default)]
+        template: Option<Box<Template>>,
+    },
+    Update {
+        name: String,
+    },
+}
+
+impl PendingJob {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Install { name, .. } | Self::Update { name } => name,
+        }
+    }
+}
+
+fn jobs_file() -> Option<PathBuf> {
+    Some(get_data_dir()?.join("jobs.json"))
+}
+
+fn load_pending() -> Vec<PendingJob> {
+    jobs_file()
+        .filter(|p| p.exists())
+        .and_then(|p| File::open(p).ok())
+        .and_then(|f| match serde_json::from_reader(f) {
+            Ok(jobs) => Some(jobs),
+            Err(e) => {
+                warn!("Failed to deserialize jobs.json: {}", e);
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+fn save_pending(jobs: &[PendingJob]) -> Result<()> {
+    let path = jobs_file().ok_or_else(|| anyhow!("Failed to get app save dir"))?;
+    serde_json::to_writer_pretty(File::create(path)?, jobs)?;
+    Ok(())
+}
+
+fn modify_pending(f: impl FnOnce(&mut Vec<PendingJob>)) {
+    if !read_only::is_persistent() {
+        return;
+    }
+    let _guard = LOCK.lock();
+    let mut jobs = load_pending();
+    f(&mut jobs);
+    if let Err(e) = save_pending(&jobs) {
+        error!("Failed to save jobs.json: {:#}", e);
+    }
+}
+
+/// Asks a running job to stop. It does so at its next [`check_cancelled`].
+pub fn cancel(job: &str) {
+    info!("Cancelling {}", job);
+    if let Ok(mut cancelled) = CANCELLED.lock() {
+        cancelled.insert(job.into());
+    }
+}
+
+/// Fails with [`Cancelled`] if the job was asked to stop.
+pub fn check_cancelled(job: &str) -> Result<()> {
+    match CANCELLED.lock() {
+        Ok(cancelled) if cancelled.contains(job) => Err(Cancelled.into()),
+        _ => Ok(()),
+    }
+}
+
+pub fn is_cancelled(error: &anyhow::Error) -> bool {
+    error.chain().any(|e| e.is::<Cancelled>())
+}
+
+fn clear_cancelled(job: &str) {
+    if let Ok(mut cancelled) = CANCELLED.lock() {
+        cancelled.remove(job);
+    }
+    take_retry(job);
+}
+
+/// Remembers a job until [`complete`] is called for it.
+pub fn register(job: PendingJob) {
+    clear_cancelled(job.name());
+    modify_pending(|jobs| {
+        jobs.retain(|j| j.name() != job.name());
+        jobs.push(job);
+    });
+}
+
+pub fn complete(name: &str) {
+    clear_cancelled(name);
+    forget_heartbeat(name);
+    modify_pending(|jobs| jobs.retain(|j| j.name() != name));
+}
+
+/// Returns the jobs that didn't finish during the last run and forgets about them.
+pub fn take_unfinished() -> Vec<PendingJob> {
+    let _guard = LOCK.lock();
+    let jobs = load_pending();
+    if let Some(path) = jobs_file().filter(|p| p.exists()) {
+        if let Err(e) = fs::remove_file(path) {
+            error!("Failed to remove jobs.json: {}", e);
+        }
+    }
+    jobs
+}
@@ -0,0 +1,70 @@
+//! Kiosk mode, for LAN parties or exhibitions where players should only be able to start the
+//! instances that are there. Only the instance list is shown, with nothing but a Play button per
+//! instance, and messages that would change anything are dropped.
+
+use crate::instance::InstanceMessage;
+use crate::instances_frame::DoubleClickAction;
+use crate::{Message, Tab};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Whether players may do what `message` does, e.g. playing or dismissing an error.
+fn allows_instance_message(message: &InstanceMessage) -> bool {
+    matches!(
+        message,
+        InstanceMessage::Play(_)
+            | InstanceMessage::QueuePlay(_)
+            | InstanceMessage::UnqueuePlay
+            | InstanceMessage::RowClicked(DoubleClickAction::Play | DoubleClickAction::Nothing)
+            | InstanceMessage::ToggleErrorDetails
+            | InstanceMessage::DismissError
+            | InstanceMessage::ReportCrash(_)
+            // Sent by the launcher itself
+            | InstanceMessage::StateChanged(_)
+            | InstanceMessage::JobEvent(_)
+            | InstanceMessage::ModificationsChecked(_)
+            | InstanceMessage::HealthChecked(_)
+            | InstanceMessage::SizeMeasured(_)
+    )
+}
+
+/// Whether kiosk mode drops `message`, because it would change instances, plugins, saves or
+/// settings, or show what isn't meant to be seen.
+pub fn blocks(message: &Message) -> bool {
+    if !is_enabled() {
+        return false;
+    }
+    match message {
+        Message::InstanceMessage(_, message) => !allows_instance_message(message),
+        Message::TabSelected(tab) => *tab != Tab::Instances,
+        Message::InstallFrameMessage(_)
+        | Message::PluginMessage(..)
+        | Message::ProfilesMessage(_)
+        | Message::ManifestMessage(_)
+        | Message::BulkPluginMessage(_)
+        | Message::SavesMessage(_)
+        | Message::SettingsMessage(_)
+        | Message::HousekeepingMessage(_)
+        | Message::ArchivalMessage(_)
+        | Message::UpdateInstances(_)
+        | Message::MoveInstances(_)
+        | Message::UseInstancesDir(_)
+        | Message::RenameInstance(..)
+        | Message::CloneInstance(_)
+        | Message::UndoDelete(_)
+        | Message::ResumeJobs(_)
+        | Message::OpenInstancesDir
+        | Message::ExportInstanceList(_)
+        | Message::InstanceSortChanged(_) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,445 @@
+//! The game's command-line flags as per-instance settings, so they don't have to be typed as raw arguments.
+
+use crate::music::MusicWhilePlaying;
+use crate::sandbox::{self, Sandbox};
+use iced::widget::{Checkbox, Column, PickList, Row, Text, TextInput};
+use iced::{Alignment, Element, Length};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// Makes Mesa render on the discrete GPU, e.g. of AMD and Intel hybrid laptops (PRIME)
+const MESA_DISCRETE_GPU_ENV: [(&str, &str); 1] = [("DRI_PRIME", "1")];
+/// Makes the proprietary Nvidia driver render on the discrete GPU (render offload). Mesa systems
+/// break with these, so they're only set when the driver is loaded.
+const NVIDIA_DISCRETE_GPU_ENV: [(&str, &str); 3] = [
+    ("__NV_PRIME_RENDER_OFFLOAD", "1"),
+    ("__GLX_VENDOR_LIBRARY_NAME", "nvidia"),
+    ("__VK_LAYER_NV_optimus", "NVIDIA_only"),
+];
+/// Only exists while the proprietary Nvidia driver is loaded
+const NVIDIA_DRIVER_FILE: &str = "/proc/driver/nvidia/version";
+
+/// Where Windows keeps the per-application GPU choice of its graphics settings
+const GPU_PREFERENCES_KEY: &str = r"HKCU\Software\Microsoft\DirectX\UserGpuPreferences";
+/// The value asking Windows for the high performance GPU
+const HIGH_PERFORMANCE_GPU: &str = "GpuPreference=2;";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DisplayMode {
+    /// Whatever the game's preferences say
+    #[default]
+    Default,
+    Fullscreen,
+    Windowed,
+}
+
+impl DisplayMode {
+    pub const ALL: [Self; 3] = [Self::Default, Self::Fullscreen, Self::Windowed];
+}
+
+impl fmt::Display for DisplayMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "From preferences"),
+            Self::Fullscreen => write!(f, "Fullscreen"),
+            Self::Windowed => write!(f, "Windowed"),
+        }
+    }
+}
+
+/// The scheduling priority of the game process, as a nice value or Windows priority class
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Priority {
+    Low,
+    BelowNormal,
+    #[default]
+    Normal,
+    AboveNormal,
+    High,
+}
+
+impl Priority {
+    pub const ALL: [Self; 5] = [
+        Self::Low,
+        Self::BelowNormal,
+        Self::Normal,
+        Self::AboveNormal,
+        Self::High,
+    ];
+
+    /// Raising it below 0 needs root on most systems
+    fn niceness(self) -> i32 {
+        match self {
+            Self::Low => 19,
+            Self::BelowNormal => 10,
+            Self::Normal => 0,
+            Self::AboveNormal => -5,
+            Self::High => -10,
+        }
+    }
+
+    fn windows_class(self) -> &'static str {
+        match self {
+            Self::Low => "Idle",
+            Self::BelowNormal => "BelowNormal",
+            Self::Normal => "Normal",
+            Self::AboveNormal => "AboveNormal",
+            Self::High => "High",
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Low => write!(f, "Low"),
+            Self::BelowNormal => write!(f, "Below normal"),
+            Self::Normal => write!(f, "Normal"),
+            Self::AboveNormal => write!(f, "Above normal"),
+            Self::High => write!(f, "High"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaunchOptions {
+    pub display: DisplayMode,
+    /// Overrides the config dir, empty to use the default or the instance's own one
+    pub config: String,
+    /// Where the game looks for its data, images and sounds, empty for the bundled ones
+    pub resources: String,
+    /// Simulation steps per second, empty for the game's default
+    pub ticks: String,
+    pub priority: Priority,
+    /// The CPU cores the game may run on, like `0-3,8`, empty for all of them
+    pub affinity: String,
+    /// Render on the discrete GPU of laptops with two, instead of the integrated one
+    pub discrete_gpu: bool,
+    /// What the launcher's music does while this instance is being played
+    pub music: MusicWhilePlaying,
+}
+
+/// Runs a tool that changes the game process, e.g. `renice`, logging its failures.
+fn run_tool(program: &str, args: &[String]) {
+    match Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .output()
+    {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => warn!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => warn!("Failed to run {}: {}", program, e),
+    }
+}
+
+/// The GPU choice Windows has for `executable`, None if there is none.
+fn gpu_preference(executable: &Path) -> Option<String> {
+    let output = Command::new("reg")
+        .args(["query", GPU_PREFERENCES_KEY, "/v"])
+        .arg(executable)
+        .stdin(Stdio::null())
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    // The value is the last column of the line naming the executable
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|l| l.contains("REG_SZ"))
+        .and_then(|l| l.split_whitespace().last())
+        .map(String::from)
+}
+
+impl LaunchOptions {
+    /// The flags to pass to the game, except for `--config`, which depends on more than these options.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec![];
+        match self.display {
+            DisplayMode::Default => {}
+            DisplayMode::Fullscreen => args.push("--fullscreen".into()),
+            DisplayMode::Windowed => args.push("--windowed".into()),
+        }
+        if !self.resources.trim().is_empty() {
+            args.push("--resources".into());
+            args.push(self.resources.trim().into());
+        }
+        if !self.ticks.is_empty() {
+            args.push("--ticks".into());
+            args.push(self.ticks.clone());
+        }
+        args
+    }
+
+    /// Makes the game render on the discrete GPU if requested, before it gets started.
+    /// Windows remembers the choice per executable, so it stays in effect for launches outside the
+    /// launcher, until the option is turned off again.
+    pub fn prepare(&self, cmd: &mut Command, executable: &Path) {
+        if cfg!(windows) {
+            let current = gpu_preference(executable);
+            let executable = executable.to_string_lossy().to_string();
+            if self.discrete_gpu && current.as_deref() != Some(HIGH_PERFORMANCE_GPU) {
+                run_tool(
+                    "reg",
+                    &[
+                        "add".into(),
+                        GPU_PREFERENCES_KEY.into(),
+                        "/v".into(),
+                        executable,
+                        "/t".into(),
+                        "REG_SZ".into(),
+                        "/d".into(),
+                        HIGH_PERFORMANCE_GPU.into(),
+                        "/f".into(),
+                    ],
+                );
+            } else if !self.discrete_gpu && current.as_deref() == Some(HIGH_PERFORMANCE_GPU) {
+                run_tool(
+                    "reg",
+                    &[
+                        "delete".into(),
+                        GPU_PREFERENCES_KEY.into(),
+                        "/v".into(),
+                        executable,
+                        "/f".into(),
+                    ],
+                );
+            }
+        } else if self.discrete_gpu {
+            cmd.envs(MESA_DISCRETE_GPU_ENV);
+            if Path::new(NVIDIA_DRIVER_FILE).exists() {
+                cmd.envs(NVIDIA_DISCRETE_GPU_ENV);
+            }
+        }
+    }
+
+    /// The cores listed in `affinity`, None if there are none or it isn't a valid list. Cores this
+    /// machine doesn't have make the list invalid too.
+    fn cores(&self) -> Option<Vec<u32>> {
+        let available = thread::available_parallelism().map_or(1, |n| n.get());
+        let available = u32::try_from(available).unwrap_or(u32::MAX);
+        let mut cores = vec![];
+        for part in self.affinity.split(',').map(str::trim) {
+            let (first, last): (u32, u32) = match part.split_once('-') {
+                Some((first, last)) => (first.trim().parse().ok()?, last.trim().parse().ok()?),
+                None => (part.parse().ok()?, part.parse().ok()?),
+            };
+            if first > last || last >= available {
+                return None;
+            }
+            cores.extend(first..=last);
+        }
+        (!cores.is_empty()).then_some(cores)
+    }
+
+    /// Applies the priority and CPU affinity to the running game.
+    /// Both are set from outside the process, since the launcher has no unsafe code to do so itself.
+    pub fn apply(&self, pid: u32) {
+        let cores = self.cores();
+        if cores.is_none() && !self.affinity.trim().is_empty() {
+            warn!("Ignoring invalid CPU affinity '{}'", self.affinity);
+        }
+        if cfg!(windows) {
+            let mut script = format!("$p = Get-Process -Id {pid}");
+            if self.priority != Priority::Normal {
+                script.push_str(&format!(
+                    "; $p.PriorityClass = '{}'",
+                    self.priority.windows_class()
+                ));
+            }
+            if let Some(cores) = &cores {
+                let mask = cores
+                    .iter()
+                    .filter(|c| **c < 64)
+                    .fold(0u64, |mask, c| mask | (1 << c));
+                script.push_str(&format!("; $p.ProcessorAffinity = {mask}"));
+            }
+            if self.priority != Priority::Normal || cores.is_some() {
+                run_tool(
+                    "powershell",
+                    &["-NoProfile".into(), "-Command".into(), script],
+                );
+            }
+            return;
+        }
+        if sandbox::current() == Some(Sandbox::Flatpak)
+            && (self.priority != Priority::Normal || cores.is_some())
+        {
+            // The game runs on the host, where its process ID isn't the one seen in here
+            warn!("Priority and CPU affinity can't be changed from inside Flatpak");
+            return;
+        }
+        if self.priority != Priority::Normal {
+            run_tool(
+                "renice",
+                &[
+                    "-n".into(),
+                    self.priority.niceness().to_string(),
+                    "-p".into(),
+                    pid.to_string(),
+                ],
+            );
+        }
+        if cores.is_some() {
+            if cfg!(target_os = "linux") {
+                run_tool(
+                    "taskset",
+                    &[
+                        "-a".into(),
+                        "-p".into(),
+                        "-c".into(),
+                        self.affinity.trim().into(),
+                        pid.to_string(),
+                    ],
+                );
+            } else {
+                warn!("CPU affinity isn't supported on this platform");
+            }
+        }
+    }
+
+    /// Every change produces the complete new options.
+    pub fn view(&self) -> Element<LaunchOptions> {
+        let options = self.clone();
+        let display = PickList::new(DisplayMode::ALL, Some(self.display), move |display| {
+            LaunchOptions {
+                display,
+                ..options.clone()
+            }
+        })
+        .text_size(12);
+        let options = self.clone();
+        let config =
+            TextInput::new("Default", &self.config).on_input(move |config| LaunchOptions {
+                config,
+                ..options.clone()
+            });
+        let options = self.clone();
+        let resources =
+            TextInput::new("Bundled", &self.resources).on_input(move |resources| LaunchOptions {
+                resources,
+                ..options.clone()
+            });
+        let options = self.clone();
+        let ticks = TextInput::new("Default", &self.ticks).on_input(move |ticks| {
+            // Only numbers make sense here, so anything else gets ignored
+            if ticks.chars().all(|c| c.is_ascii_digit()) {
+                LaunchOptions {
+                    ticks,
+                    ..options.clone()
+                }
+            } else {
+                options.clone()
+            }
+        });
+
+        let options = self.clone();
+        let priority = PickList::new(Priority::ALL, Some(self.priority), move |priority| {
+            LaunchOptions {
+                priority,
+                ..options.clone()
+            }
+        })
+        .text_size(12);
+        let options = self.clone();
+        let affinity =
+            TextInput::new("All cores, or e.g. 0-3,8", &self.affinity).on_input(move |affinity| {
+                if affinity
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || c == ',' || c == '-')
+                {
+                    LaunchOptions {
+                        affinity,
+                        ..options.clone()
+                    }
+                } else {
+                    options.clone()
+                }
+            });
+        let options = self.clone();
+        let music = PickList::new(MusicWhilePlaying::ALL, Some(self.music), move |music| {
+            LaunchOptions {
+                music,
+                ..options.clone()
+            }
+        })
+        .text_size(12);
+
+        Column::new()
+            .spacing(3)
+            .push(field("Display", display))
+            .push(field("Config folder", input(config)))
+            .push(field("Resources folder", input(resources)))
+            .push(field("Ticks per second", input(ticks)))
+            .push(field("Priority", priority))
+            .push(field("CPU cores", input(affinity)))
+            .push(field("Music", music))
+            // Macs switch GPUs by themselves
+            .push_maybe((!cfg!(target_os = "macos")).then(|| {
+                let options = self.clone();
+                field(
+                    "Discrete GPU",
+                    Checkbox::new("", self.discrete_gpu)
+                        .on_toggle(move |discrete_gpu| LaunchOptions {
+                            discrete_gpu,
+                            ..options.clone()
+                        })
+                        .size(14),
+                )
+            }))
+            .into()
+    }
+}
+
+fn input(input: TextInput<LaunchOptions>) -> TextInput<LaunchOptions> {
+    input.size(12).padding(3).width(Length::Fixed(250.))
+}
+
+fn field<'a>(
+    label: &'a str,
+    control: impl Into<Element<'a, LaunchOptions>>,
+) -> Row<'a, LaunchOptions> {
+    Row::new()
+        .spacing(5)
+        .align_items(Alignment::Center)
+        .push(Text::new(label).size(12).width(Length::Fixed(110.)))
+        .push(control)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cores(affinity: &str) -> Option<Vec<u32>> {
+        LaunchOptions {
+            affinity: affinity.into(),
+            ..LaunchOptions::default()
+        }
+        .cores()
+    }
+
+    #[test]
+    fn cores_reads_lists_and_ranges() {
+        // Every machine has at least core 0
+        assert_eq!(cores("0"), Some(vec![0]));
+        assert_eq!(cores(" 0 - 0 , 0"), Some(vec![0, 0]));
+    }
+
+    #[test]
+    fn cores_rejects_invalid_lists() {
+        assert_eq!(cores(""), None);
+        assert_eq!(cores("  "), None);
+        assert_eq!(cores("a"), None);
+        assert_eq!(cores("0,"), None);
+        assert_eq!(cores("1-0"), None);
+        assert_eq!(cores("0-"), None);
+        assert_eq!(cores(&u32::MAX.to_string()), None);
+    }
+}
@@ -0,0 +1,195 @@
+//! Checks that the shared libraries a Linux build needs are installed before launching it, since
+//! otherwise the game dies right away and leaves nothing but an empty logfile behind.
+
+use crate::sandbox::{self, Sandbox};
+use anyhow::Result;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Distro {
+    Debian,
+    Fedora,
+    Arch,
+    Suse,
+}
+
+impl Distro {
+    fn install_command(self) -> &'static str {
+        match self {
+            Self::Debian => "sudo apt install",
+            Self::Fedora => "sudo dnf install",
+            Self::Arch => "sudo pacman -S",
+            Self::Suse => "sudo zypper install",
+        }
+    }
+}
+
+/// The packages providing the libraries the game links against, by the start of their soname:
+/// Debian and Ubuntu, Fedora, Arch, openSUSE
+const PACKAGES: [(&str, [&str; 4]); 10] = [
+    (
+        "libSDL2-2.0",
+        ["libsdl2-2.0-0", "SDL2", "sdl2", "libSDL2-2_0-0"],
+    ),
+    ("libGLEW", ["libglew2.2", "glew", "glew", "libGLEW2_2"]),
+    (
+        "libGL.",
+        ["libgl1", "mesa-libGL", "libglvnd", "Mesa-libGL1"],
+    ),
+    (
+        "libopenal",
+        ["libopenal1", "openal-soft", "openal", "libopenal1"],
+    ),
+    ("libmad", ["libmad0", "libmad", "libmad", "libmad0"]),
+    (
+        "libpng16",
+        ["libpng16-16", "libpng", "libpng", "libpng16-16"],
+    ),
+    (
+        "libjpeg.so.8",
+        [
+            "libjpeg-turbo8",
+            "libjpeg-turbo",
+            "libjpeg-turbo",
+            "libjpeg8",
+        ],
+    ),
+    (
+        "libjpeg.so.62",
+        [
+            "libjpeg62-turbo",
+            "libjpeg-turbo",
+            "libjpeg-turbo",
+            "libjpeg62",
+        ],
+    ),
+    (
+        "libuuid",
+        ["libuuid1", "libuuid", "util-linux-libs", "libuuid1"],
+    ),
+    (
+        "libminizip",
+        ["libminizip1", "minizip-compat", "minizip", "libminizip1"],
+    ),
+];
+
+/// The libraries a build needs but can't find, with how to install them on this system.
+#[derive(Debug)]
+pub struct MissingLibraries {
+    pub libraries: Vec<String>,
+    distro: Option<Distro>,
+}
+
+impl MissingLibraries {
+    fn package(&self, library: &str) -> Option<&'static str> {
+        let index = match self.distro? {
+            Distro::Debian => 0,
+            Distro::Fedora => 1,
+            Distro::Arch => 2,
+            Distro::Suse => 3,
+        };
+        PACKAGES
+            .iter()
+            .find(|(prefix, _)| library.starts_with(prefix))
+            .map(|(_, packages)| packages[index])
+    }
+
+    /// How to install the missing libraries, as specific as the distribution allows.
+    pub fn hint(&self) -> String {
+        let Some(distro) = self.distro else {
+            return "Install the packages providing them with your distribution's package manager, then try again.".into();
+        };
+        let mut packages: Vec<&str> = vec![];
+        let mut unknown: Vec<&str> = vec![];
+        for library in &self.libraries {
+            match self.package(library) {
+                Some(package) if !packages.contains(&package) => packages.push(package),
+                Some(_) => {}
+                None => unknown.push(library),
+            }
+        }
+        let mut hint = String::new();
+        if !packages.is_empty() {
+            hint = format!(
+                "Install them with `{} {}`",
+                distro.install_command(),
+                packages.join(" ")
+            );
+        }
+        if !unknown.is_empty() {
+            if !hint.is_empty() {
+                hint.push_str(", and ");
+            }
+            hint.push_str(&format!(
+                "search your distribution's packages for {}",
+                unknown.join(", ")
+            ));
+        }
+        hint + ", then try again."
+    }
+}
+
+impl fmt::Display for MissingLibraries {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Missing shared libraries: {}", self.libraries.join(", "))
+    }
+}
+
+impl std::error::Error for MissingLibraries {}
+
+/// The distribution the game runs on, which is the host's when the launcher is a Flatpak.
+fn detect_distro() -> Option<Distro> {
+    let path = if sandbox::current() == Some(Sandbox::Flatpak) {
+        "/run/host/os-release"
+    } else {
+        "/etc/os-release"
+    };
+    let os_release = fs::read_to_string(path).ok()?;
+    let ids: Vec<String> = os_release
+        .lines()
+        .filter_map(|line| {
+            line.strip_prefix("ID=")
+                .or_else(|| line.strip_prefix("ID_LIKE="))
+        })
+        .flat_map(|value| value.trim_matches('"').split_whitespace())
+        .map(str::to_lowercase)
+        .collect();
+    ids.iter().find_map(|id| match id.as_str() {
+        "debian" | "ubuntu" => Some(Distro::Debian),
+        "fedora" | "rhel" => Some(Distro::Fedora),
+        "arch" => Some(Distro::Arch),
+        "suse" | "opensuse" => Some(Distro::Suse),
+        _ => None,
+    })
+}
+
+/// Fails with [`MissingLibraries`] if `executable` links against libraries that aren't installed.
+/// If `ldd` isn't available, the check is skipped rather than keeping the game from launching.
+pub fn check(executable: &Path) -> Result<()> {
+    let mut cmd = Command::new("ldd");
+    cmd.arg(executable);
+    let output = match sandbox::host_command(cmd).output() {
+        Ok(output) => output,
+        Err(e) => {
+            debug!("Skipping the library check, ldd failed to run: {}", e);
+            return Ok(());
+        }
+    };
+    let libraries: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| line.contains("=> not found"))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(String::from)
+        .collect();
+    if libraries.is_empty() {
+        return Ok(());
+    }
+    Err(MissingLibraries {
+        libraries,
+        distro: detect_distro(),
+    }
+    .into())
+}
@@ -1,11 +1,19 @@
+use std::cell::RefCell;
 use std::fs;
 use std::fs::File;
+use std::future::{self, Future};
+use std::path::PathBuf;
+use std::sync::OnceLock;
 
+use core::fmt;
 use log::{Level, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
 use simplelog::{
     ColorChoice, CombinedLogger, Config, ConfigBuilder, LevelFilter, SharedLogger, TermLogger,
     TerminalMode, WriteLogger,
 };
+use time::format_description::well_known::Rfc3339;
+use time::{format_description, OffsetDateTime};
 
 use crate::{get_data_dir, Message};
 
@@ -18,22 +26,110 @@ const BLACKLIST: [&str; 6] = [
     "glow_glyph",
 ];
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub const ALL: [Self; 5] = [
+        Self::Error,
+        Self::Warn,
+        Self::Info,
+        Self::Debug,
+        Self::Trace,
+    ];
+
+    pub fn filter(self) -> LevelFilter {
+        match self {
+            Self::Error => LevelFilter::Error,
+            Self::Warn => LevelFilter::Warn,
+            Self::Info => LevelFilter::Info,
+            Self::Debug => LevelFilter::Debug,
+            Self::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// A log record as forwarded to the UI
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub timestamp: OffsetDateTime,
+    pub level: Level,
+    pub module: String,
+    /// The instance/job that was being worked on when the record was emitted
+    pub job: Option<String>,
+    pub message: String,
+}
+
+impl LogEntry {
+    pub fn time(&self) -> String {
+        format_description::parse("[hour]:[minute]:[second]")
+            .ok()
+            .and_then(|f| self.timestamp.format(&f).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Where this session's log is written, which keeps the records the UI has let go of
+static LOGFILE: OnceLock<PathBuf> = OnceLock::new();
+
+thread_local! {
+    static JOB: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+fn current_job() -> Option<String> {
+    JOB.with(|job| job.borrow().clone())
+}
+
+/// Tags all records logged while `future` is being polled with `job`.
+/// Works across executor threads since the tag is set anew on every poll.
+pub async fn with_job<F: Future>(job: String, future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    future::poll_fn(move |cx| {
+        let previous = JOB.with(|j| j.replace(Some(job.clone())));
+        let result = future.as_mut().poll(cx);
+        JOB.with(|j| *j.borrow_mut() = previous);
+        result
+    })
+    .await
+}
+
+/// Like [`with_job`], for blocking work that was moved off the executor.
+pub fn in_job<T>(job: String, f: impl FnOnce() -> T) -> T {
+    let previous = JOB.with(|j| j.replace(Some(job)));
+    let result = f();
+    JOB.with(|j| *j.borrow_mut() = previous);
+    result
+}
+
 struct ChanneledLogger {}
 
 impl Log for ChanneledLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= Level::Info
+        metadata.level() <= log::max_level()
     }
 
     fn log(&self, record: &Record) {
         if self.enabled(record.metadata()) && should_log(record) {
-            let line = format!(
-                "{:<7} [{}] {}",
-                record.metadata().level(),
-                record.module_path().unwrap_or("unknown"),
-                record.args()
-            );
-            crate::send_message(Message::Log(line));
+            crate::send_message(Message::Log(LogEntry {
+                timestamp: OffsetDateTime::now_utc(),
+                level: record.metadata().level(),
+                module: record.module_path().unwrap_or("unknown").to_string(),
+                job: current_job(),
+                message: record.args().to_string(),
+            }));
         }
     }
 
@@ -42,7 +138,7 @@ impl Log for ChanneledLogger {
 
 impl SharedLogger for ChanneledLogger {
     fn level(&self) -> LevelFilter {
-        LevelFilter::Warn
+        LevelFilter::Trace
     }
 
     fn config(&self) -> Option<&Config> {
@@ -90,36 +186,97 @@ fn open_logfile() -> Option<File> {
             );
             None
         }
-        Ok(f) => Some(f),
+        Ok(f) => {
+            LOGFILE.set(path).ok();
+            Some(f)
+        }
     }
 }
 
-pub fn init() {
+/// The latest `limit` records of this session's logfile from before `before`, oldest first.
+/// Lines without a timestamp continue the record before them.
+pub fn read_older(
+    before: OffsetDateTime,
+    limit: usize,
+) -> anyhow::Result<Vec<(OffsetDateTime, String)>> {
+    let path = LOGFILE
+        .get()
+        .ok_or_else(|| anyhow!("There's no logfile this session"))?;
+    let mut records: Vec<(OffsetDateTime, String)> = vec![];
+    for line in fs::read_to_string(path)?.lines() {
+        let timestamp = line
+            .split_once(' ')
+            .and_then(|(time, _)| OffsetDateTime::parse(time, &Rfc3339).ok());
+        match (timestamp, records.last_mut()) {
+            (Some(timestamp), _) if timestamp >= before => break,
+            (Some(timestamp), _) => records.push((timestamp, line.to_string())),
+            (None, Some((_, record))) => {
+                record.push('\n');
+                record.push_str(line);
+            }
+            (None, None) => {}
+        }
+    }
+    let skip = records.len().saturating_sub(limit);
+    Ok(records.split_off(skip))
+}
+
+pub async fn perform_read_older(
+    before: OffsetDateTime,
+    limit: usize,
+) -> Vec<(OffsetDateTime, String)> {
+    read_older(before, limit).unwrap_or_else(|e| {
+        error!("Failed to read older log records: {:#}", e);
+        vec![]
+    })
+}
+
+/// Sets which records get forwarded to the UI and written to the terminal and logfile.
+pub fn set_level(level: LogLevel) {
+    log::set_max_level(level.filter());
+}
+
+pub fn init(level: LogLevel) {
     let channeled = ChanneledLogger {};
 
-    let config = ConfigBuilder::new()
+    let mut builder = ConfigBuilder::new();
+    builder
         .add_filter_ignore_str("iced_wgpu::renderer") // STOP
         .add_filter_ignore_str("wgpu_native::device") // SPAMMING
         .add_filter_ignore_str("wgpu_native::command") // AAAAAH
         .add_filter_ignore_str("gfx_backend_metal::device") // spammy thing on mac
-        .add_filter_ignore_str("hyper::proto")
-        .build();
+        .add_filter_ignore_str("hyper::proto");
+    let config = builder.build();
+    // Full timestamps, so `read_older` can tell which records the UI has let go of
+    let file_config = builder.set_time_format_rfc3339().build();
 
     let mut loggers: Vec<Box<dyn SharedLogger>> = vec![
         Box::new(channeled),
         TermLogger::new(
-            LevelFilter::Debug,
-            config.clone(),
+            LevelFilter::Trace,
+            config,
             TerminalMode::Mixed,
             ColorChoice::Auto,
         ),
     ];
 
     if let Some(file) = open_logfile() {
-        loggers.push(WriteLogger::new(LevelFilter::Debug, config, file));
+        loggers.push(WriteLogger::new(LevelFilter::Trace, file_config, file));
     }
 
     CombinedLogger::init(loggers).unwrap();
+    set_level(level);
 
     log::info!("Initialized logger");
 }
+
+/// Only logs to the terminal, for processes that mustn't replace the logfile, like the crash dialog.
+pub fn init_terminal() {
+    TermLogger::init(
+        LevelFilter::Info,
+        ConfigBuilder::new().build(),
+        TerminalMode::Mixed,
+        ColorChoice::Auto,
+    )
+    .ok();
+}
@@ -8,42 +8,132 @@ extern crate anyhow;
 #[macro_use]
 extern crate version;
 
+use lazy_static::lazy_static;
 use std::hash::Hash;
 use std::path::PathBuf;
-use std::sync::mpsc::Sender;
+use std::sync::mpsc::{self, Sender};
 use std::thread;
 use std::time::Duration;
+use time::OffsetDateTime;
 
 use iced::advanced::subscription::EventStream;
 use iced::advanced::Hasher;
-use iced::widget::{Button, Column, Container, Row, Scrollable, Space, Text};
+use iced::widget::pane_grid::{self, PaneGrid};
+use iced::widget::{Button, Column, Container, PickList, Row, Scrollable, Space, Text};
 use iced::{
-    alignment, font, Alignment, Application, Command, Element, Font, Length, Subscription, Theme,
+    alignment, event, font, theme, window, Alignment, Application, Color, Command, Element, Event,
+    Font, Length, Subscription, Theme,
 };
 use iced_aw::{TabLabel, Tabs};
-use std::collections::VecDeque;
-use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::{Mutex, OnceLock, PoisonError};
 
+use crate::archival::ArchivalMessage;
+use crate::compare_frame::CompareMessage;
+use crate::console::ConsoleMessage;
+use crate::housekeeping::HousekeepingMessage;
 use crate::install_frame::InstallFrameMessage;
-use crate::instance::{Instance, InstanceMessage, InstanceState, Progress};
+use crate::instance::{Instance, InstanceMessage, InstanceState};
+use crate::instances_frame::{InstanceSort, SortKeys};
+use crate::job::{JobEvent, PendingJob};
+use crate::logger::{LogEntry, LogLevel};
 use crate::music::{MusicCommand, MusicState};
+use crate::navigation::Navigation;
+use crate::news::NewsMessage;
+use crate::notifications::{Level, Notification, NotificationMessage};
+use crate::palette::{Action, Palette, PaletteMessage};
+use crate::plugin_bulk::BulkMessage;
+use crate::plugin_manifest::ManifestMessage;
+use crate::plugin_profiles::ProfilesMessage;
 use crate::plugins_frame::PluginMessage;
-use crate::settings::Settings;
+use crate::saves_frame::SavesMessage;
+use crate::screenshots_frame::ScreenshotsMessage;
+use crate::session::Session;
+use crate::settings::{Settings, WindowGeometry};
+use crate::settings_frame::SettingsMessage;
+use crate::stats::StatsMessage;
 use crate::style::{icon_button, log_container, tab_bar};
 
+mod activity;
+mod archival;
 mod archive;
+mod backups;
+mod bandwidth;
+mod benchmark;
+mod cache;
+mod cli;
+mod compare_frame;
+mod console;
+mod crash;
+mod crash_summary;
+mod desktop_entry;
+mod diagnostics;
+#[cfg(test)]
+mod driver;
+mod endpoints;
+mod errors;
+mod game_logs;
 mod github;
+mod groups;
+mod high_dpi;
+mod housekeeping;
+mod http;
 mod install;
 mod install_frame;
 mod instance;
+mod instance_lock;
+mod instance_store;
 mod instances_frame;
+mod inventory;
 mod jenkins;
+mod job;
+mod kiosk;
+mod launch_options;
+mod libraries;
 mod logger;
+mod markdown;
+mod modifications;
 mod music;
+mod navigation;
+mod network;
+mod news;
+mod notifications;
+mod palette;
+mod plugin_bulk;
+mod plugin_index;
+mod plugin_manifest;
+mod plugin_metadata;
+mod plugin_pool;
+mod plugin_profiles;
 mod plugins_frame;
+mod presets;
+mod profiles;
+mod quarantine;
+mod read_only;
+mod recovery;
+mod releases;
+mod resources;
+mod runtimes;
+mod safe_mode;
+mod sandbox;
+mod saves;
+mod saves_frame;
+mod screenshots_frame;
+mod scripting;
+mod secrets;
+mod session;
 mod settings;
+mod settings_frame;
+mod stats;
+mod steam;
 mod style;
+mod sync;
+mod telemetry;
+mod templates;
+mod trash;
 mod update;
+mod versions;
 
 // Yes, this is terrible abuse of globals.
 // I spent hours and hours trying to find a better solution:
@@ -58,33 +148,185 @@ mod update;
 //      send() is async and thus can't be used in the logger.
 //      try_send() requires the sender to be mutable (??) and thus can't be used inside log().
 //
-// Bottom line, this is terrible design with comparatively bad performance, but also the only solution that
+// Bottom line, this is terrible design, but also the only solution that
 // - delivers messages immediately
 // - Doesn't block the receiving thread and thus screws over iced's internals
 // - Doesn't randomly break after some messages
-// so here it will stay.
+// so here it will stay. MESSAGE_WAKER at least spares the receiver from polling the queue,
+// and it is drained completely each time, so bursts of log lines don't trickle in one by one.
 static MESSAGE_QUEUE: Mutex<VecDeque<Message>> = Mutex::new(VecDeque::new());
 
+lazy_static! {
+    static ref MESSAGE_WAKER: tokio::sync::Notify = tokio::sync::Notify::new();
+}
+
+/// How long the receiver waits for a wake-up before checking the queue anyway,
+/// in case the queue was locked when it last looked
+const MESSAGE_RECHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+const ALL_JOBS: &str = "All jobs";
+/// Records kept in memory for the log pane, enough for a long build
+const LOG_BUFFER_LIMIT: usize = 5000;
+/// Records loaded from the logfile per click on "Load older lines"
+const OLDER_LOG_PAGE: usize = 500;
+
 pub fn main() -> iced::Result {
-    ESLauncher::run(iced::Settings::default())
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let [flag, report] = args.as_slice() {
+        if flag == crash::FLAG {
+            logger::init_terminal();
+            let mut settings = iced::Settings::with_flags(PathBuf::from(report));
+            settings.window.size = iced::Size::new(700., 500.);
+            return crash::CrashDialog::run(settings);
+        }
+    }
+    if let Err(e) = profiles::check_requested() {
+        // Nothing is set up yet, the data dir depends on the profile
+        eprintln!("{e}");
+        std::process::exit(2);
+    }
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+    crash::install_hook();
+    if profiles::should_pick() {
+        let mut settings = iced::Settings::with_flags(());
+        settings.window.size = iced::Size::new(360., 420.);
+        return profiles::ProfilePicker::run(settings);
+    }
+    let mut settings = iced::Settings::with_flags(StartupFlags {
+        verbose: args.iter().any(|arg| arg == "--verbose" || arg == "-v"),
+        quiet: args.iter().any(|arg| arg == "--quiet"),
+        kiosk: if args.iter().any(|arg| arg == "--no-kiosk") {
+            Some(false)
+        } else {
+            args.iter().any(|arg| arg == "--kiosk").then_some(true)
+        },
+        headless: false,
+    });
+    // Closing is handled in update, so the window geometry can be saved first
+    settings.window.exit_on_close_request = false;
+    if let Some(geometry) = Settings::load().local.window {
+        settings.window.size = iced::Size::new(geometry.width, geometry.height);
+        if let (Some(x), Some(y)) = (geometry.x, geometry.y) {
+            settings.window.position =
+                window::Position::Specific(iced::Point::new(x as f32, y as f32));
+        }
+    }
+    ESLauncher::run(settings)
+}
+
+/// Global options passed on the command line
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StartupFlags {
+    verbose: bool,
+    /// Quiet mode for this session, without changing the setting
+    quiet: bool,
+    /// Kiosk mode on or off for this session, without changing the setting
+    kiosk: Option<bool>,
+    /// Without a window, see `driver`: there's no logger, music or check for a newer launcher
+    headless: bool,
 }
 
 #[derive(Debug)]
 struct ESLauncher {
     music_sender: Sender<MusicCommand>,
+    /// Whether music is playing, which differs from the saved state while in quiet mode
+    music_state: MusicState,
     install_frame: install_frame::InstallFrame,
     instances_frame: instances_frame::InstancesFrame,
     plugins_frame: plugins_frame::PluginsFrameState,
+    news_frame: news::NewsFrame,
+    notifications: notifications::Notifications,
+    profiles_frame: plugin_profiles::ProfilesFrame,
+    manifest_frame: plugin_manifest::ManifestFrame,
+    bulk_plugin_frame: plugin_bulk::BulkFrame,
+    saves_frame: saves_frame::SavesFrame,
+    screenshots_frame: screenshots_frame::ScreenshotsFrame,
+    stats_frame: stats::StatsFrame,
+    compare_frame: compare_frame::CompareFrame,
+    console_frame: console::ConsoleFrame,
+    settings_frame: settings_frame::SettingsFrame,
     message_receiver: MessageReceiver,
-    log_buffer: Vec<String>,
+    /// The latest records, older ones are only kept in the session's logfile
+    log_buffer: VecDeque<LogEntry>,
+    /// How many records were dropped from `log_buffer` to keep it bounded
+    log_dropped: usize,
+    /// Records loaded back from the logfile on request, oldest first
+    older_log: Vec<(OffsetDateTime, String)>,
+    /// Whether `older_log` reaches back to the start of the session
+    older_log_complete: bool,
+    /// Only show log entries of this job
+    log_filter: Option<String>,
     active_tab: Tab,
     settings: Settings,
+    /// Only saved when closing, since it changes constantly while resizing
+    window_geometry: Option<WindowGeometry>,
+    /// The tabs above and the log below, with a split that can be dragged
+    panes: pane_grid::State<MainPane>,
+    /// None until the network has been probed for the first time
+    online: Option<bool>,
+    /// The instances dir along with the bytes free on its volume, for the footer
+    instances_dir: Option<(PathBuf, Option<u64>)>,
+    /// A close request held back, with the work closing would interrupt, until confirmed
+    close_request: Option<(window::Id, Vec<String>)>,
+    palette: Palette,
+}
+
+/// How often the free space shown in the footer gets refreshed
+const FREE_SPACE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MainPane {
+    Tabs,
+    Log,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Tab {
     Instances,
     Plugins,
+    Saves,
+    Screenshots,
+    Statistics,
+    Compare,
+    Console,
+    Settings,
+}
+
+impl Tab {
+    /// In the order they're shown in
+    const ALL: [Self; 8] = [
+        Self::Instances,
+        Self::Plugins,
+        Self::Saves,
+        Self::Screenshots,
+        Self::Statistics,
+        Self::Compare,
+        Self::Console,
+        Self::Settings,
+    ];
+
+    fn title(&self) -> &'static str {
+        match self {
+            Self::Instances => "Instances",
+            Self::Plugins => "Plugins",
+            Self::Saves => "Saves",
+            Self::Screenshots => "Screenshots",
+            Self::Statistics => "Statistics",
+            Self::Compare => "Compare",
+            Self::Console => "Console",
+            Self::Settings => "Settings",
+        }
+    }
+
+    /// The tab next to this one, wrapping around at the ends.
+    fn next(&self, forward: bool) -> Self {
+        let index = Self::ALL.iter().position(|t| t == self).unwrap_or_default();
+        let len = Self::ALL.len();
+        let next = if forward { index + 1 } else { index + len - 1 };
+        Self::ALL[next % len].clone()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -92,24 +334,106 @@ pub enum Message {
     InstallFrameMessage(InstallFrameMessage),
     InstanceMessage(String, InstanceMessage),
     PluginMessage(String, PluginMessage),
+    ProfilesMessage(ProfilesMessage),
+    ManifestMessage(ManifestMessage),
+    BulkPluginMessage(BulkMessage),
+    NewsMessage(NewsMessage),
+    NotificationMessage(NotificationMessage),
+    SavesMessage(SavesMessage),
+    ScreenshotsMessage(ScreenshotsMessage),
+    StatsMessage(StatsMessage),
+    CompareMessage(CompareMessage),
+    ConsoleMessage(ConsoleMessage),
+    SettingsMessage(SettingsMessage),
     AddInstance(Box<Instance>),
     RemoveInstance(Option<String>),
+    /// An instance or plugin was moved into the holding area and can still be restored
+    Deleted(Box<trash::Held>),
+    UndoDelete(Box<trash::Held>),
+    Restored(Option<trash::Deleted>),
+    RenameInstance(String, String),
+    CloneInstance(String),
+    InstanceSortChanged(InstanceSort),
+    ExportInstanceList(inventory::ExportFormat),
+    SortKeysLoaded(SortKeys),
     Dummy(()),
     FontLoaded(Result<(), font::Error>),
     MusicMessage(MusicCommand),
+    /// Looks for the game's soundtrack again, or stops using it if that's disabled
+    RefreshSoundtrack,
     TabSelected(Tab),
+    Navigation(Navigation),
+    PaletteMessage(PaletteMessage),
+    /// How far the instance list is scrolled down now
+    InstancesScrolled(f32),
     PluginFrameLoaded(Vec<plugins_frame::Plugin>),
-    Log(String),
+    RefreshPluginIndex,
+    /// The plugins whose index entries changed
+    PluginIndexRefreshed(Option<Vec<String>>),
+    /// The changed plugins, loaded again
+    PluginsReloaded(Vec<String>, Vec<plugins_frame::Plugin>),
+    CheckForUpdates,
+    CheckNetwork,
+    NetworkChecked(bool),
+    CheckFreeSpace,
+    FreeSpaceChecked(Option<(PathBuf, Option<u64>)>),
+    OpenInstancesDir,
+    SampleResources,
+    ResourcesSampled(Vec<(String, resources::Usage)>),
+    HousekeepingMessage(HousekeepingMessage),
+    ArchivalMessage(ArchivalMessage),
+    /// Backs up the instances whose scheduled backup is due
+    RunScheduledBackups,
+    UpdatesChecked(Vec<update::UpdateStatus>),
+    UpdateInstances(Vec<String>),
+    ResumeJobs(Vec<PendingJob>),
+    MoveInstances(PathBuf),
+    /// Installs new instances into this dir, keeping the existing ones where they are
+    UseInstancesDir(PathBuf),
+    ReloadInstances,
+    Log(LogEntry),
+    LoadOlderLog,
+    OlderLogLoaded(Vec<(OffsetDateTime, String)>),
+    JobEvent(String, JobEvent),
+    /// Marks jobs that haven't shown signs of life for a while as stalled
+    CheckStalledJobs,
+    LogFilterChanged(String),
+    WindowResized(u32, u32),
+    WindowMoved(i32, i32),
+    WindowCloseRequested(window::Id),
+    WindowFocusChanged(bool),
+    /// Closes the window despite the work that gets interrupted
+    ConfirmClose,
+    CancelClose,
+    PaneResized(pane_grid::ResizeEvent),
+    ToggleLogMaximized,
 }
 
 impl Application for ESLauncher {
     type Executor = iced::executor::Default;
     type Message = Message;
-    type Flags = ();
+    type Flags = StartupFlags;
 
-    fn new(_flag: ()) -> (Self, Command<Message>) {
-        logger::init();
+    fn new(flags: StartupFlags) -> (Self, Command<Message>) {
+        let mut settings = Settings::load();
+        // The logger can only be set up once per process, which tests run many launchers in
+        if !flags.headless {
+            logger::init(if flags.verbose {
+                LogLevel::Debug
+            } else {
+                settings.log_level
+            });
+        }
+        style::set_button_labels(settings.button_labels);
         info!("Starting ESLauncher2 v{}", version!());
+        if let Some(data_dir) = get_data_dir_override() {
+            info!("  using data dir {}", data_dir.to_string_lossy());
+        } else if is_portable() {
+            info!("  running in portable mode");
+        }
+        if let Some(profile) = profiles::current() {
+            info!("  using profile {}", profile);
+        }
         if cfg!(target_os = "macos") {
             info!("  running on target environment macos");
         } else if cfg!(target_os = "windows") {
@@ -120,25 +444,175 @@ impl Application for ESLauncher {
             info!("  running on target environment other");
         }
 
-        let settings = Settings::load();
-        let music_sender = music::spawn(settings.music_state);
-
-        check_for_update();
+        secrets::migrate(&mut settings);
+        let quiet = flags.quiet || settings.quiet_mode;
+        style::set_quiet(quiet);
+        let music_state = if quiet {
+            info!("  running in quiet mode");
+            MusicState::Paused
+        } else {
+            settings.music_state
+        };
+        let kiosk = flags.kiosk.unwrap_or(settings.kiosk_mode);
+        kiosk::set_enabled(kiosk);
+        if kiosk {
+            info!("  running in kiosk mode");
+        }
+        let music_sender = if flags.headless {
+            mpsc::channel().0
+        } else {
+            check_for_update();
+            music::spawn(music_state, settings.local.audio_device.clone())
+        };
 
         let (plugins_frame_state, plugins_frame_cmd) = plugins_frame::PluginsFrameState::new();
+        let (news_frame, news_frame_cmd) = news::NewsFrame::new();
+        let mut notifications = notifications::Notifications::default();
+        if let Some(banner) = read_only::banner() {
+            warn!("{}", banner);
+            notifications.push(Notification::new(Level::Warning, banner).sticky());
+        }
+        if let Some(dir) = instance::get_instances_dir().filter(|dir| !read_only::is_writable(dir))
+        {
+            warn!(
+                "The instances folder {} isn't writable",
+                dir.to_string_lossy()
+            );
+            notifications.push(
+                Notification::new(
+                    Level::Warning,
+                    format!(
+                        "{} can't be written to, so instances can't be installed or updated there",
+                        dir.to_string_lossy()
+                    ),
+                )
+                .sticky(),
+            );
+        }
+        let unfinished = job::take_unfinished();
+        let mut leftover_dirs: Vec<PathBuf> = unfinished
+            .iter()
+            .filter_map(|job| match job {
+                PendingJob::Install { path, .. } => Some(path.clone()),
+                PendingJob::Update { .. } => None,
+            })
+            .collect();
+        if !unfinished.is_empty() {
+            let names: Vec<&str> = unfinished.iter().map(PendingJob::name).collect();
+            warn!(
+                "Found unfinished jobs from the last run: {}",
+                names.join(", ")
+            );
+            notifications.push(
+                Notification::new(
+                    Level::Warning,
+                    format!(
+                        "The launcher was closed while working on {}{}",
+                        names.join(", "),
+                        if leftover_dirs
+                            .iter()
+                            .any(|p| recovery::has_finished_downloads(p))
+                        {
+                            ", resuming reuses what was downloaded already"
+                        } else {
+                            ""
+                        }
+                    ),
+                )
+                .action("Resume", Message::ResumeJobs(unfinished))
+                .sticky(),
+            );
+        }
+        let session = Session::load();
+        let mut install_frame = install_frame::InstallFrame::default();
+        if let Some(draft) = session.install.clone() {
+            install_frame.restore(draft);
+        }
+        let releases_cmd = install_frame.refresh_releases();
+        let mut instances_frame = instances_frame::InstancesFrame::default();
+        if let Some(instance) = session
+            .expanded_instance
+            .as_ref()
+            .and_then(|name| instances_frame.instances.get_mut(name))
+        {
+            instance.show_details = true;
+        }
+        // Selected like by hand, since some tabs load what they show on selection
+        let tab_cmd = match session.tab.clone() {
+            Some(tab) if tab != Tab::Instances => {
+                Command::perform(async {}, move |()| Message::TabSelected(tab))
+            }
+            _ => Command::none(),
+        };
+        let sort_keys_cmd = instances_frame.refresh_sort_keys(settings.instance_sort);
+        let modifications_cmd = modifications::check_all(instances_frame.instances.values());
+        let inspect_cmd = instances_frame.inspect_all();
+        let scroll_cmd = instances_frame.restore_scroll();
+        leftover_dirs.extend(instances_frame.instances.values().map(|i| i.path.clone()));
+        let config_dirs = instances_frame
+            .instances
+            .values()
+            .filter_map(Instance::config_dir)
+            .collect();
+        let cache_limit = settings.cache_limit;
         (
             Self {
                 music_sender,
-                install_frame: install_frame::InstallFrame::default(),
-                instances_frame: instances_frame::InstancesFrame::default(),
+                music_state,
+                install_frame,
+                instances_frame,
                 plugins_frame: plugins_frame_state,
+                news_frame,
+                notifications,
+                profiles_frame: plugin_profiles::ProfilesFrame::new(),
+                manifest_frame: plugin_manifest::ManifestFrame::default(),
+                bulk_plugin_frame: plugin_bulk::BulkFrame::default(),
+                saves_frame: saves_frame::SavesFrame::default(),
+                screenshots_frame: screenshots_frame::ScreenshotsFrame::default(),
+                stats_frame: stats::StatsFrame::default(),
+                compare_frame: compare_frame::CompareFrame::default(),
+                console_frame: console::ConsoleFrame::default(),
+                settings_frame: settings_frame::SettingsFrame::new(),
                 message_receiver: MessageReceiver {},
-                log_buffer: vec![],
+                log_buffer: VecDeque::new(),
+                log_dropped: 0,
+                older_log: vec![],
+                older_log_complete: false,
+                log_filter: session.log_filter,
                 active_tab: Tab::Instances,
+                window_geometry: settings.local.window,
+                panes: pane_grid::State::with_configuration(pane_grid::Configuration::Split {
+                    axis: pane_grid::Axis::Horizontal,
+                    ratio: settings.local.log_split,
+                    a: Box::new(pane_grid::Configuration::Pane(MainPane::Tabs)),
+                    b: Box::new(pane_grid::Configuration::Pane(MainPane::Log)),
+                }),
                 settings,
+                online: None,
+                instances_dir: None,
+                close_request: None,
+                palette: Palette::default(),
             },
             Command::batch(vec![
                 plugins_frame_cmd,
+                news_frame_cmd,
+                Command::perform(sync::perform_sync(false), Message::Dummy),
+                // Checks for updates once the network turns out to be available
+                Command::perform(network::probe(), Message::NetworkChecked),
+                sort_keys_cmd,
+                modifications_cmd,
+                inspect_cmd,
+                scroll_cmd,
+                tab_cmd,
+                releases_cmd,
+                // Catches up on backups that were due while the launcher wasn't running
+                Command::perform(async {}, |()| Message::RunScheduledBackups),
+                Command::perform(cache::perform_collect(cache_limit), Message::Dummy),
+                Command::perform(trash::purge_leftovers(), Message::Dummy),
+                Command::perform(recovery::clean_leftovers(leftover_dirs), Message::Dummy),
+                Command::perform(safe_mode::recover(config_dirs), Message::Dummy),
+                Command::perform(async {}, |()| Message::CheckFreeSpace),
+                Command::perform(async {}, |()| Message::RefreshSoundtrack),
                 font::load(include_bytes!("../assets/IcoMoon-Free.ttf").as_slice())
                     .map(Message::FontLoaded),
                 font::load(include_bytes!("../assets/DejaVuSansMono.ttf").as_slice())
@@ -154,12 +628,30 @@ impl Application for ESLauncher {
     type Theme = Theme;
 
     fn update(&mut self, message: Self::Message) -> Command<Message> {
+        if kiosk::blocks(&message) {
+            debug!("Ignoring {:?} in kiosk mode", message);
+            return Command::none();
+        }
         match message {
             Message::InstallFrameMessage(msg) => return self.install_frame.update(msg),
             Message::InstanceMessage(name, msg) => {
                 match self.instances_frame.instances.get_mut(&name) {
                     None => error!("Failed to find internal Instance with name {}", &name),
-                    Some(instance) => return instance.update(msg),
+                    Some(instance) => {
+                        // A game exiting changes when the instance was last played
+                        let refresh = instance.state.is_playing()
+                            && matches!(&msg, InstanceMessage::StateChanged(s) if !s.is_playing());
+                        let command = instance.update(msg);
+                        // E.g. the job the launch was queued behind just finished
+                        let launch = instance.launch_queued();
+                        let sort = if refresh {
+                            self.instances_frame
+                                .refresh_sort_keys(self.settings.instance_sort)
+                        } else {
+                            Command::none()
+                        };
+                        return Command::batch([command, launch, sort]);
+                    }
                 }
             }
             Message::PluginMessage(name, msg) => {
@@ -168,17 +660,92 @@ impl Application for ESLauncher {
                 {
                     match plugins.iter_mut().find(|p| p.name == name) {
                         None => error!("Failed to find internal Plug-In with name {}", name),
-                        Some(p) => return p.update(msg),
+                        Some(p) => {
+                            let command = p.update(msg);
+                            self.plugins_frame.check_dependencies();
+                            return command;
+                        }
                     }
                 }
             }
-            Message::AddInstance(instance) => {
+            Message::ProfilesMessage(msg) => return self.profiles_frame.update(msg),
+            Message::ManifestMessage(msg) => {
+                return self
+                    .manifest_frame
+                    .update(msg, &self.instances_frame.instances)
+            }
+            Message::BulkPluginMessage(msg) => {
+                return self
+                    .bulk_plugin_frame
+                    .update(msg, &self.instances_frame.instances)
+            }
+            Message::NewsMessage(msg) => return self.news_frame.update(msg),
+            Message::NotificationMessage(msg) => return self.notifications.update(msg),
+            Message::SavesMessage(msg) => {
+                return self
+                    .saves_frame
+                    .update(msg, &self.instances_frame.instances)
+            }
+            Message::ScreenshotsMessage(msg) => {
+                return self
+                    .screenshots_frame
+                    .update(msg, &self.instances_frame.instances)
+            }
+            Message::StatsMessage(msg) => return self.stats_frame.update(msg),
+            Message::ConsoleMessage(msg) => return self.console_frame.update(msg),
+            Message::CompareMessage(msg) => {
+                return self
+                    .compare_frame
+                    .update(msg, &self.instances_frame.instances)
+            }
+            Message::SettingsMessage(msg) => {
+                return self.settings_frame.update(&mut self.settings, msg)
+            }
+            Message::AddInstance(mut instance) => {
+                // Checking files and measuring the size is only worth it when the build changed
+                let mut changed = true;
+                // Jobs replace the instance before they finish, the queued launch has to stay
+                if let Some(previous) = self.instances_frame.instances.get(&instance.name) {
+                    instance.queued_play = instance.queued_play.or(previous.queued_play);
+                    changed = instance.path != previous.path
+                        || instance.executable != previous.executable
+                        || instance.version != previous.version;
+                    // Shown until measured again below
+                    instance.disk_size = instance.disk_size.or(previous.disk_size);
+                    if !changed {
+                        instance.problems.clone_from(&previous.problems);
+                    }
+                }
                 let is_ready = instance.state.is_ready();
+                let name = instance.name.clone();
+                // Only an instance that's new, or just finished installing, can bring stats along
+                let is_new = self
+                    .instances_frame
+                    .instances
+                    .get(&name)
+                    .is_none_or(|previous| !previous.state.is_ready());
                 self.instances_frame
                     .instances
-                    .insert(instance.name.clone(), *instance);
+                    .insert(name.clone(), *instance);
                 if is_ready {
                     instance::perform_save_instances(self.instances_frame.instances.clone());
+                    if let Err(e) = desktop_entry::write(&self.instances_frame.instances[&name]) {
+                        error!("Failed to write desktop entry: {:#}", e);
+                    }
+                    let instance = &self.instances_frame.instances[&name];
+                    return Command::batch([
+                        if is_new {
+                            self.instances_frame
+                                .refresh_sort_keys(self.settings.instance_sort)
+                        } else {
+                            Command::none()
+                        },
+                        if changed {
+                            instance.inspect()
+                        } else {
+                            Command::none()
+                        },
+                    ]);
                 };
             }
             Message::RemoveInstance(option) => {
@@ -187,22 +754,567 @@ impl Application for ESLauncher {
                     instance::perform_save_instances(self.instances_frame.instances.clone());
                 }
             }
+            Message::Deleted(held) => {
+                let reload = match &held.deleted {
+                    trash::Deleted::Instance(instance) => {
+                        self.instances_frame.instances.remove(&instance.name);
+                        instance::perform_save_instances(self.instances_frame.instances.clone());
+                        Command::none()
+                    }
+                    trash::Deleted::Plugin(_) => {
+                        Command::perform(plugins_frame::load_plugins(), Message::PluginFrameLoaded)
+                    }
+                    trash::Deleted::Pilot(_) => self
+                        .saves_frame
+                        .update(SavesMessage::Refresh, &self.instances_frame.instances),
+                };
+                self.notifications.push(
+                    Notification::new(Level::Info, format!("Deleted {}", held.deleted))
+                        .action("Undo", Message::UndoDelete(held.clone())),
+                );
+                return Command::batch(vec![
+                    reload,
+                    Command::perform(trash::purge_later(held), Message::Dummy),
+                ]);
+            }
+            Message::UndoDelete(held) => {
+                return Command::perform(trash::perform_restore(held), Message::Restored);
+            }
+            Message::Restored(deleted) => match deleted {
+                Some(trash::Deleted::Instance(instance)) => {
+                    self.notifications.push(Notification::new(
+                        Level::Success,
+                        format!("Restored {}", instance.name),
+                    ));
+                    let icon_instance = (*instance).clone();
+                    return Command::batch(vec![
+                        Command::perform(async {}, move |()| Message::AddInstance(instance)),
+                        Command::perform(
+                            async move {
+                                if let Err(e) = desktop_entry::extract_icon(&icon_instance) {
+                                    warn!(
+                                        "Failed to extract the icon of {}: {:#}",
+                                        icon_instance.name, e
+                                    );
+                                }
+                            },
+                            Message::Dummy,
+                        ),
+                    ]);
+                }
+                Some(trash::Deleted::Plugin(name)) => {
+                    self.notifications.push(Notification::new(
+                        Level::Success,
+                        format!("Restored {name}"),
+                    ));
+                    return Command::perform(
+                        plugins_frame::load_plugins(),
+                        Message::PluginFrameLoaded,
+                    );
+                }
+                Some(trash::Deleted::Pilot(name)) => {
+                    self.notifications.push(Notification::new(
+                        Level::Success,
+                        format!("Restored {name}"),
+                    ));
+                    return self
+                        .saves_frame
+                        .update(SavesMessage::Refresh, &self.instances_frame.instances);
+                }
+                None => {}
+            },
+            Message::RenameInstance(old_name, new_name) => {
+                if let Err(e) = self.instances_frame.rename(&old_name, new_name.trim()) {
+                    error!("Failed to rename {}: {:#}", old_name, e);
+                    self.notifications.push(Notification::new(
+                        Level::Error,
+                        format!("Renaming {old_name} failed: {e}"),
+                    ));
+                }
+                return self
+                    .instances_frame
+                    .refresh_sort_keys(self.settings.instance_sort);
+            }
+            Message::CloneInstance(name) => {
+                if let Some(instance) = self.instances_frame.instances.get(&name) {
+                    let clone_name = self.instances_frame.copy_name(&name);
+                    return Command::perform(
+                        logger::with_job(
+                            clone_name.clone(),
+                            instance::perform_clone(instance.clone(), clone_name),
+                        ),
+                        Message::Dummy,
+                    );
+                }
+            }
+            Message::ExportInstanceList(format) => {
+                let instances = self.instances_frame.instances.values().cloned().collect();
+                return Command::perform(
+                    inventory::perform_export(format, instances),
+                    Message::Dummy,
+                );
+            }
+            Message::InstanceSortChanged(sort) => {
+                self.settings.instance_sort = sort;
+                if let Err(e) = self.settings.save() {
+                    error!("Failed to save settings.json: {:#?}", e);
+                };
+                return self.instances_frame.refresh_sort_keys(sort);
+            }
+            Message::SortKeysLoaded(keys) => self.instances_frame.set_sort_keys(keys),
             Message::MusicMessage(cmd) => {
-                self.music_sender.send(cmd).ok();
-                self.settings.music_state = match cmd {
+                self.music_sender.send(cmd.clone()).ok();
+                self.music_state = match cmd {
                     MusicCommand::Pause => MusicState::Paused,
                     MusicCommand::Play => MusicState::Playing,
-                    _ => self.settings.music_state,
+                    _ => return Command::none(),
                 };
+                self.settings.music_state = self.music_state;
                 if let Err(e) = self.settings.save() {
                     error!("Failed to save settings.json: {:#?}", e);
                 };
             }
-            Message::TabSelected(active_tab) => self.active_tab = active_tab,
+            Message::RefreshSoundtrack => {
+                if !self.settings.game_soundtrack {
+                    return Command::perform(async {}, |()| {
+                        Message::MusicMessage(MusicCommand::SetSoundtrack(vec![]))
+                    });
+                }
+                let instances = self.instances_frame.instances.values().cloned().collect();
+                return Command::perform(music::perform_find_soundtrack(instances), |tracks| {
+                    Message::MusicMessage(MusicCommand::SetSoundtrack(tracks))
+                });
+            }
+            Message::InstancesScrolled(offset) => self.instances_frame.scroll_offset = offset,
+            Message::TabSelected(active_tab) => {
+                self.active_tab = active_tab;
+                if self.active_tab == Tab::Instances {
+                    return self.instances_frame.restore_scroll();
+                }
+                if self.active_tab == Tab::Statistics {
+                    return self
+                        .stats_frame
+                        .update(StatsMessage::Refresh(self.instance_paths()));
+                }
+                if self.active_tab == Tab::Settings {
+                    return Command::batch([
+                        self.settings_frame.refresh_cache_size(),
+                        self.settings_frame.refresh_audio_devices(),
+                    ]);
+                }
+            }
+            Message::Navigation(Navigation::CommandPalette) => return self.palette.toggle(),
+            Message::Navigation(
+                navigation @ (Navigation::SelectNext
+                | Navigation::SelectPrevious
+                | Navigation::Escape),
+            ) if self.palette.is_open() => {
+                self.palette.navigate(navigation, self.palette_actions());
+            }
+            Message::PaletteMessage(msg) => {
+                if let Some(action) = self.palette.update(msg, self.palette_actions()) {
+                    return self.run_action(action);
+                }
+            }
+            Message::Navigation(Navigation::FocusNext) => return iced::widget::focus_next(),
+            Message::Navigation(Navigation::FocusPrevious) => {
+                return iced::widget::focus_previous();
+            }
+            Message::Navigation(navigation @ (Navigation::NextTab | Navigation::PreviousTab)) => {
+                let tab = self.active_tab.next(navigation == Navigation::NextTab);
+                return self.update(Message::TabSelected(tab));
+            }
+            Message::Navigation(navigation) => {
+                if self.active_tab == Tab::Instances {
+                    return self.instances_frame.navigate(navigation, &self.settings);
+                }
+            }
+            Message::PluginFrameLoaded(plugins)
+                if plugins.is_empty() && self.plugins_frame.is_offline() =>
+            {
+                debug!("Keeping the plugins from the cached index");
+            }
             Message::PluginFrameLoaded(plugins) => {
                 self.plugins_frame = plugins_frame::PluginsFrameState::from(plugins);
             }
-            Message::Log(line) => self.log_buffer.push(line),
+            Message::RefreshPluginIndex => {
+                return Command::perform(
+                    plugin_index::perform_refresh(),
+                    Message::PluginIndexRefreshed,
+                );
+            }
+            Message::PluginIndexRefreshed(Some(changed)) => match &self.plugins_frame {
+                // The full load underway picks the changes up anyway
+                plugins_frame::PluginsFrameState::Loading => {}
+                frame if frame.is_offline() => {
+                    return Command::perform(
+                        plugins_frame::load_plugins(),
+                        Message::PluginFrameLoaded,
+                    );
+                }
+                plugins_frame::PluginsFrameState::Ready { .. } => {
+                    return Command::perform(plugins_frame::load_changed(changed), |(c, p)| {
+                        Message::PluginsReloaded(c, p)
+                    });
+                }
+            },
+            Message::PluginIndexRefreshed(None) => {}
+            Message::PluginsReloaded(changed, plugins) => {
+                self.plugins_frame.merge(&changed, plugins);
+            }
+            Message::CheckForUpdates if !network::is_online() => {
+                debug!("Skipping the update check while offline");
+            }
+            Message::CheckForUpdates => {
+                let instances = self
+                    .instances_frame
+                    .instances
+                    .values()
+                    .filter(|i| i.state.is_ready())
+                    .cloned()
+                    .collect();
+                return Command::perform(
+                    update::check_for_updates(instances),
+                    Message::UpdatesChecked,
+                );
+            }
+            Message::HousekeepingMessage(msg) => {
+                return self.instances_frame.housekeeping.update(msg);
+            }
+            Message::ArchivalMessage(msg) => {
+                return self
+                    .instances_frame
+                    .archival
+                    .update(msg, &self.instances_frame.instances);
+            }
+            Message::RunScheduledBackups => {
+                let config = self.settings.local.backups.clone();
+                if !config.is_scheduled() {
+                    return Command::none();
+                }
+                let instances = self
+                    .instances_frame
+                    .instances
+                    .values()
+                    .filter(|i| i.scheduled_backups && i.state.is_ready())
+                    .cloned()
+                    .collect();
+                return Command::perform(
+                    backups::perform_scheduled(instances, config),
+                    Message::Dummy,
+                );
+            }
+            Message::CheckNetwork => {
+                return Command::perform(network::probe(), Message::NetworkChecked);
+            }
+            Message::SampleResources => {
+                return Command::perform(
+                    resources::perform_sample(instance::running_pids()),
+                    Message::ResourcesSampled,
+                );
+            }
+            Message::ResourcesSampled(usages) => {
+                for instance in self.instances_frame.instances.values_mut() {
+                    instance.usage = usages
+                        .iter()
+                        .find(|(name, _)| *name == instance.name)
+                        .map(|(_, usage)| *usage);
+                }
+            }
+            Message::CheckFreeSpace => {
+                return Command::perform(
+                    instance::perform_check_free_space(),
+                    Message::FreeSpaceChecked,
+                );
+            }
+            Message::FreeSpaceChecked(instances_dir) => self.instances_dir = instances_dir,
+            Message::OpenInstancesDir => {
+                if let Some((dir, _)) = &self.instances_dir {
+                    return Command::perform(instance::open_folder(dir.clone()), Message::Dummy);
+                }
+            }
+            Message::NetworkChecked(online) => {
+                let was_online = self.online.replace(online);
+                if online && was_online != Some(true) {
+                    let mut commands = vec![Command::perform(
+                        telemetry::perform_submit(),
+                        Message::Dummy,
+                    )];
+                    // Only the cached index could be shown while offline
+                    if was_online == Some(false) && self.plugins_frame.is_offline() {
+                        commands.push(Command::perform(
+                            plugins_frame::load_plugins(),
+                            Message::PluginFrameLoaded,
+                        ));
+                    }
+                    if self.settings.update_check_interval > 0 {
+                        commands.push(Command::perform(async {}, |()| Message::CheckForUpdates));
+                    }
+                    if self.settings.housekeeping_days > 0 {
+                        let instances = self
+                            .instances_frame
+                            .instances
+                            .values()
+                            .filter(|i| i.state.is_ready())
+                            .cloned()
+                            .collect();
+                        commands.push(Command::perform(
+                            housekeeping::find_stale(instances, self.settings.housekeeping_days),
+                            |stale| Message::HousekeepingMessage(HousekeepingMessage::Found(stale)),
+                        ));
+                    }
+                    return Command::batch(commands);
+                }
+            }
+            Message::UpdatesChecked(statuses) => {
+                let mut newly_available = vec![];
+                let mut auto_update = vec![];
+                for status in statuses {
+                    let Some(instance) = self.instances_frame.instances.get_mut(&status.name)
+                    else {
+                        continue;
+                    };
+                    if status.update_available && instance.auto_update {
+                        // Retried on every check, e.g. after the game was closed
+                        auto_update.push(instance.name.clone());
+                    } else if status.update_available && !instance.update_available {
+                        newly_available.push(instance.name.clone());
+                    }
+                    instance.update_available = status.update_available;
+                    instance.pr_state = status.pr_state;
+                }
+                for name in &newly_available {
+                    scripting::fire("on_update_available", vec![name.clone().into()]);
+                }
+                if !newly_available.is_empty() {
+                    info!("Updates available for {}", newly_available.join(", "));
+                    let text = match newly_available.as_slice() {
+                        [name] => format!("Update available for {name}"),
+                        names => format!("Update available for {} instances", names.len()),
+                    };
+                    self.notifications.push(
+                        Notification::new(Level::Info, text)
+                            .action("Update", Message::UpdateInstances(newly_available)),
+                    );
+                }
+                if !auto_update.is_empty() {
+                    info!("Updating {} automatically", auto_update.join(", "));
+                    return Command::perform(async {}, move |()| {
+                        Message::UpdateInstances(auto_update)
+                    });
+                }
+            }
+            Message::MoveInstances(target) => {
+                if self
+                    .instances_frame
+                    .instances
+                    .values()
+                    .any(|i| !i.state.is_ready())
+                {
+                    error!("Can't move instances while some of them are busy");
+                    self.notifications.push(Notification::new(
+                        Level::Error,
+                        "Wait for all instances to finish before moving them",
+                    ));
+                } else {
+                    return Command::perform(
+                        instance::perform_move_instances(
+                            self.instances_frame.instances.values().cloned().collect(),
+                            target,
+                        ),
+                        Message::Dummy,
+                    );
+                }
+            }
+            Message::UseInstancesDir(target) => {
+                return Command::perform(
+                    instance::perform_use_instances_dir(
+                        self.instances_frame.instances.values().cloned().collect(),
+                        target,
+                    ),
+                    Message::Dummy,
+                );
+            }
+            Message::ReloadInstances => {
+                let scroll_offset = self.instances_frame.scroll_offset;
+                self.instances_frame = instances_frame::InstancesFrame::default();
+                self.instances_frame.scroll_offset = scroll_offset;
+                self.settings = Settings::load();
+                self.settings_frame.reset();
+                return Command::batch(vec![
+                    self.instances_frame
+                        .refresh_sort_keys(self.settings.instance_sort),
+                    modifications::check_all(self.instances_frame.instances.values()),
+                    self.instances_frame.inspect_all(),
+                    self.instances_frame.restore_scroll(),
+                    // The instances dir may have changed
+                    Command::perform(async {}, |()| Message::CheckFreeSpace),
+                ]);
+            }
+            Message::ResumeJobs(jobs) => {
+                return Command::batch(jobs.into_iter().filter_map(|pending| match pending {
+                    PendingJob::Install {
+                        path,
+                        name,
+                        instance_type,
+                        source,
+                        template,
+                    } => {
+                        info!("Resuming installation of {}", name);
+                        Some(Command::perform(
+                            logger::with_job(
+                                name.clone(),
+                                instance::perform_install(
+                                    path,
+                                    name,
+                                    instance_type,
+                                    source,
+                                    template.map(|t| *t),
+                                ),
+                            ),
+                            Message::Dummy,
+                        ))
+                    }
+                    PendingJob::Update { name } => {
+                        info!("Resuming update of {}", name);
+                        let instance = self.instances_frame.instances.get_mut(&name)?;
+                        Some(instance.update(InstanceMessage::Update))
+                    }
+                }));
+            }
+            Message::UpdateInstances(_) if !network::is_online() => {
+                self.notifications.push(Notification::new(
+                    Level::Warning,
+                    "Updating needs a network connection",
+                ));
+            }
+            Message::UpdateInstances(names) => {
+                return Command::batch(names.into_iter().filter_map(|name| {
+                    let instance = self.instances_frame.instances.get_mut(&name)?;
+                    instance
+                        .state
+                        .is_ready()
+                        .then(|| instance.update(InstanceMessage::Update))
+                }));
+            }
+            Message::JobEvent(name, event) => match self.instances_frame.instances.get_mut(&name) {
+                None => error!("Failed to find internal Instance with name {}", &name),
+                Some(instance) => return instance.update(InstanceMessage::JobEvent(event)),
+            },
+            Message::Log(entry) => {
+                self.log_buffer.push_back(entry);
+                if self.log_buffer.len() > LOG_BUFFER_LIMIT {
+                    self.log_buffer.pop_front();
+                    self.log_dropped += 1;
+                }
+            }
+            Message::LoadOlderLog => {
+                let before = self
+                    .older_log
+                    .first()
+                    .map(|(timestamp, _)| *timestamp)
+                    .or_else(|| self.log_buffer.front().map(|e| e.timestamp));
+                if let Some(before) = before {
+                    return Command::perform(
+                        logger::perform_read_older(before, OLDER_LOG_PAGE),
+                        Message::OlderLogLoaded,
+                    );
+                }
+            }
+            Message::OlderLogLoaded(mut records) => {
+                self.older_log_complete = records.len() < OLDER_LOG_PAGE;
+                records.append(&mut self.older_log);
+                self.older_log = records;
+            }
+            Message::LogFilterChanged(job) => {
+                self.log_filter = (job != ALL_JOBS).then_some(job);
+            }
+            Message::WindowResized(width, height) => {
+                // Minimizing resizes the window to nothing on some platforms
+                if width > 0 && height > 0 {
+                    let geometry = self.window_geometry();
+                    geometry.width = width as f32;
+                    geometry.height = height as f32;
+                }
+            }
+            Message::WindowMoved(x, y) => {
+                // Windows moves minimized windows far off-screen
+                if x > -10000 && y > -10000 {
+                    let geometry = self.window_geometry();
+                    geometry.x = Some(x);
+                    geometry.y = Some(y);
+                }
+            }
+            Message::PaneResized(pane_grid::ResizeEvent { split, ratio }) => {
+                // Neither side may disappear completely
+                self.panes.resize(split, ratio.clamp(0.1, 0.95));
+            }
+            Message::ToggleLogMaximized => {
+                if self.panes.maximized().is_some() {
+                    self.panes.restore();
+                } else if let Some(pane) = self.log_pane() {
+                    self.panes.maximize(pane);
+                }
+            }
+            Message::WindowFocusChanged(focused) => {
+                if let Some(cmd) = music::focus_changed(focused) {
+                    self.music_sender.send(cmd).ok();
+                }
+            }
+            Message::WindowCloseRequested(id) => {
+                let interrupted = self.interrupted_work();
+                if interrupted.is_empty() {
+                    return self.close(id);
+                }
+                info!("Asking before closing, since that would interrupt work");
+                self.close_request = Some((id, interrupted));
+            }
+            Message::ConfirmClose => {
+                if let Some((id, _)) = self.close_request.take() {
+                    return self.close(id);
+                }
+            }
+            Message::CancelClose => self.close_request = None,
+            Message::CheckStalledJobs => {
+                let timeout = Duration::from_secs(self.settings.stall_timeout * 60);
+                for instance in self.instances_frame.instances.values_mut() {
+                    let InstanceState::Working(status) = &mut instance.state else {
+                        job::forget_heartbeat(&instance.name);
+                        continue;
+                    };
+                    match job::stalled_for(&instance.name, timeout) {
+                        Some(elapsed) if !status.stalled => {
+                            status.stalled = true;
+                            warn!(
+                                "{} made no progress for {} while at '{}'",
+                                instance.name,
+                                stats::format_duration(elapsed.as_secs()),
+                                status.stage
+                            );
+                            self.notifications.push(
+                                Notification::new(
+                                    Level::Warning,
+                                    format!(
+                                        "{} seems stuck at {}",
+                                        instance.name,
+                                        status.stage.to_lowercase()
+                                    ),
+                                )
+                                .action(
+                                    "Cancel",
+                                    Message::InstanceMessage(
+                                        instance.name.clone(),
+                                        InstanceMessage::Cancel,
+                                    ),
+                                )
+                                .sticky(),
+                            );
+                        }
+                        Some(_) => {}
+                        None => status.stalled = false,
+                    }
+                }
+            }
             Message::Dummy(()) => (),
             Message::FontLoaded(_) => (),
         }
@@ -220,10 +1332,329 @@ impl Application for ESLauncher {
     /// the first the Subscription never stops returning values (unless something catastrophic happens),
     /// so the cloned Recipe just gets dropped without being turned into a Subscription.
     fn subscription(&self) -> Subscription<Message> {
-        Subscription::from_recipe(self.message_receiver.clone())
+        let mut subscriptions = vec![
+            Subscription::from_recipe(self.message_receiver.clone()),
+            navigation::listen(),
+            event::listen_with(|event, _| match event {
+                Event::Window(_, window::Event::Resized { width, height }) => {
+                    Some(Message::WindowResized(width, height))
+                }
+                Event::Window(_, window::Event::Moved { x, y }) => Some(Message::WindowMoved(x, y)),
+                Event::Window(_, window::Event::Focused) => Some(Message::WindowFocusChanged(true)),
+                Event::Window(_, window::Event::Unfocused) => {
+                    Some(Message::WindowFocusChanged(false))
+                }
+                Event::Window(id, window::Event::CloseRequested) => {
+                    Some(Message::WindowCloseRequested(id))
+                }
+                _ => None,
+            }),
+        ];
+        if !self.notifications.is_empty() {
+            subscriptions.push(
+                iced::time::every(Duration::from_secs(1))
+                    .map(|_| Message::NotificationMessage(NotificationMessage::Tick)),
+            );
+        }
+        if (self.instances_frame.any_working() || self.install_frame.loading_releases())
+            && !style::is_quiet()
+        {
+            // Only redraws, to animate the spinners
+            subscriptions
+                .push(iced::time::every(style::SPINNER_INTERVAL).map(|_| Message::Dummy(())));
+        }
+        if self.instances_frame.any_working() && self.settings.stall_timeout > 0 {
+            subscriptions
+                .push(iced::time::every(job::WATCHDOG_INTERVAL).map(|_| Message::CheckStalledJobs));
+        }
+        subscriptions.push(
+            iced::time::every(plugin_index::REFRESH_INTERVAL).map(|_| Message::RefreshPluginIndex),
+        );
+        if self.settings.local.backups.is_scheduled() {
+            subscriptions.push(
+                iced::time::every(backups::CHECK_INTERVAL).map(|_| Message::RunScheduledBackups),
+            );
+        }
+        subscriptions.push(iced::time::every(FREE_SPACE_INTERVAL).map(|_| Message::CheckFreeSpace));
+        if self.instances_frame.any_playing() {
+            subscriptions.push(
+                iced::time::every(resources::SAMPLE_INTERVAL).map(|_| Message::SampleResources),
+            );
+        }
+        if !network::is_online() {
+            subscriptions
+                .push(iced::time::every(network::RETRY_INTERVAL).map(|_| Message::CheckNetwork));
+        }
+        if self.settings.update_check_interval > 0 {
+            subscriptions.push(
+                iced::time::every(Duration::from_secs(
+                    self.settings.update_check_interval * 60,
+                ))
+                .map(|_| Message::CheckForUpdates),
+            );
+        }
+        Subscription::batch(subscriptions)
     }
 
     fn view(&self) -> Element<'_, Self::Message> {
+        let panes = PaneGrid::new(&self.panes, |_, pane, _| {
+            pane_grid::Content::new(match pane {
+                MainPane::Tabs => self.view_tabs(),
+                MainPane::Log => self.view_log(),
+            })
+        })
+        .on_resize(10, Message::PaneResized);
+
+        let offline_notice = (!network::is_online()).then(|| {
+            Row::new()
+                .spacing(5)
+                .align_items(Alignment::Center)
+                .push(style::warning_icon())
+                .push(Text::new("Offline, installing and updating are disabled").size(13))
+                .push(
+                    Button::new(Text::new("Retry").size(13))
+                        .style(style::text_button())
+                        .on_press(Message::CheckNetwork),
+                )
+        });
+        let instances_dir = self.instances_dir.as_ref().map(|(dir, free_space)| {
+            let mut text = format!("Instances: {}", dir.to_string_lossy());
+            if let Some(free_space) = free_space {
+                text.push_str(&format!(" ({} free)", stats::format_size(*free_space)));
+            }
+            style::tooltip(
+                Button::new(Text::new(text).size(13))
+                    .style(style::text_button())
+                    .on_press(Message::OpenInstancesDir),
+                "Open the folder your instances are installed in",
+            )
+        });
+        let music_controls = Row::new()
+            .width(Length::Fill)
+            .align_items(Alignment::Center)
+            .spacing(10)
+            .padding(8)
+            .push_maybe(instances_dir)
+            .push_maybe(offline_notice)
+            .push(Space::new(Length::Fill, Length::Shrink))
+            .push(style::tooltip(
+                Button::new(match self.music_state {
+                    MusicState::Playing => style::labeled(style::pause_icon(), "Pause"),
+                    MusicState::Paused => style::labeled(style::play_icon(), "Play"),
+                })
+                .style(icon_button())
+                .on_press(Message::MusicMessage(match self.music_state {
+                    MusicState::Playing => MusicCommand::Pause,
+                    MusicState::Paused => MusicCommand::Play,
+                })),
+                "Pause or resume the music",
+            ))
+            .push(Text::new("Endless Sky Prototype by JimmyZenith").size(13));
+
+        Container::new(
+            Column::new()
+                .align_items(Alignment::Start)
+                .push_maybe(self.view_close_request())
+                .push_maybe(
+                    self.palette
+                        .view(self.palette_actions())
+                        .map(|palette| palette.map(Message::PaletteMessage)),
+                )
+                .push(
+                    Container::new(panes)
+                        .width(Length::Fill)
+                        .height(Length::Fill)
+                        .center_x()
+                        .center_y(),
+                )
+                .push(music_controls.height(Length::Shrink)),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
+    fn theme(&self) -> Self::Theme {
+        self.settings.theme.theme()
+    }
+}
+
+impl ESLauncher {
+    /// Everything the command palette offers right now.
+    fn palette_actions(&self) -> Vec<Action> {
+        let mut actions = vec![Action::new(
+            "Install a new instance",
+            Message::InstallFrameMessage(InstallFrameMessage::Focus),
+        )
+        .in_tab(Tab::Instances)];
+        if self.instances_frame.can_play(&self.settings) {
+            actions.extend(
+                self.instances_frame
+                    .instances
+                    .values()
+                    .filter(|i| i.state.is_ready() && i.problems.is_empty())
+                    .map(|i| {
+                        Action::new(
+                            format!("Play {}", i.name),
+                            Message::InstanceMessage(
+                                i.name.clone(),
+                                InstanceMessage::Play(i.debug_by_default),
+                            ),
+                        )
+                    }),
+            );
+        }
+        let updatable = self.instances_frame.updatable();
+        if !updatable.is_empty() {
+            actions.push(Action::new(
+                format!("Update all ({})", updatable.len()),
+                Message::UpdateInstances(updatable),
+            ));
+        }
+        actions.push(Action::new("Check for updates", Message::CheckForUpdates));
+        actions.push(Action::go_to("Open settings", Tab::Settings));
+        actions.push(Action::new(
+            if self.panes.maximized().is_some() {
+                "Restore the log"
+            } else {
+                "Open the log"
+            },
+            Message::ToggleLogMaximized,
+        ));
+        actions.push(Action::new(
+            "Open the instances folder",
+            Message::OpenInstancesDir,
+        ));
+        actions.extend(
+            Tab::ALL
+                .iter()
+                .filter(|tab| **tab != Tab::Settings)
+                .map(|tab| Action::go_to(format!("Go to {}", tab.title()), tab.clone())),
+        );
+        actions.retain(|action| {
+            let tab = action.tab.clone().map(Message::TabSelected);
+            let blocked = [tab.as_ref(), action.message.as_ref()]
+                .into_iter()
+                .flatten()
+                .any(kiosk::blocks);
+            !blocked
+        });
+        actions
+    }
+
+    /// Selects the action's tab first, so what its message does happens there.
+    fn run_action(&mut self, action: Action) -> Command<Message> {
+        let mut commands = vec![];
+        if let Some(tab) = action.tab {
+            commands.push(self.update(Message::TabSelected(tab)));
+        }
+        if let Some(message) = action.message {
+            commands.push(self.update(message));
+        }
+        Command::batch(commands)
+    }
+
+    /// What closing the launcher would stop: running jobs, and games that aren't detached.
+    fn interrupted_work(&self) -> Vec<String> {
+        let mut work: Vec<String> = self
+            .instances_frame
+            .instances
+            .values()
+            .filter_map(|instance| match &instance.state {
+                InstanceState::Working(status) => {
+                    Some(format!("{}: {}", instance.name, status.summary()))
+                }
+                InstanceState::Playing if !self.settings.detach_games => {
+                    Some(format!("{}: the game gets closed", instance.name))
+                }
+                _ => None,
+            })
+            .collect();
+        if let plugins_frame::PluginsFrameState::Ready { plugins, .. } = &self.plugins_frame {
+            work.extend(
+                plugins
+                    .iter()
+                    .filter(|p| matches!(p.state, plugins_frame::PluginState::Working))
+                    .map(|p| format!("Plug-In {}: installing", p.name)),
+            );
+        }
+        work
+    }
+
+    /// Saves what's only saved when closing, then closes the window.
+    fn close(&mut self, id: window::Id) -> Command<Message> {
+        let log_split = match self.panes.layout() {
+            pane_grid::Node::Split { ratio, .. } => *ratio,
+            pane_grid::Node::Pane(_) => self.settings.local.log_split,
+        };
+        let scroll = self.instances_frame.scroll_offset;
+        if self.window_geometry.is_some()
+            || log_split != self.settings.local.log_split
+            || scroll != self.settings.local.instances_scroll
+        {
+            self.settings.local.window = self.window_geometry;
+            self.settings.local.log_split = log_split;
+            self.settings.local.instances_scroll = scroll;
+            if let Err(e) = self.settings.save() {
+                error!("Failed to save settings.json: {:#?}", e);
+            }
+        }
+        if let Err(e) = self.session().save() {
+            error!("Failed to save session.json: {:#}", e);
+        }
+        window::close(id)
+    }
+
+    /// Asks whether to close anyway, while a close request is held back.
+    fn view_close_request(&self) -> Option<Element<Message>> {
+        let (_, interrupted) = self.close_request.as_ref()?;
+        let list = interrupted
+            .iter()
+            .fold(Column::new().spacing(3), |column, work| {
+                column.push(Text::new(format!("• {work}")).size(14))
+            });
+        Some(
+            Container::new(
+                Column::new()
+                    .spacing(10)
+                    .push(Text::new("Closing the launcher now interrupts:"))
+                    .push(list)
+                    .push(
+                        Row::new()
+                            .spacing(10)
+                            .push(Space::with_width(Length::Fill))
+                            .push(
+                                Button::new(Text::new("Keep running"))
+                                    .style(style::text_button())
+                                    .on_press(Message::CancelClose),
+                            )
+                            .push(
+                                Button::new(Text::new("Close anyway"))
+                                    .style(theme::Button::Destructive)
+                                    .on_press(Message::ConfirmClose),
+                            ),
+                    ),
+            )
+            .padding(10)
+            .width(Length::Fill)
+            .style(theme::Container::Box)
+            .into(),
+        )
+    }
+
+    fn view_tabs(&self) -> Element<Message> {
+        if kiosk::is_enabled() {
+            return Column::new()
+                .push(
+                    self.instances_frame
+                        .view(&self.profiles_frame.profiles.names(), &self.settings),
+                )
+                .push_maybe(
+                    (!self.notifications.is_empty())
+                        .then(|| self.notifications.view().map(Message::NotificationMessage)),
+                )
+                .into();
+        }
         let tabs = Tabs::new(Message::TabSelected)
             .push::<Element<'_, Message>>(
                 Tab::Instances,
@@ -231,9 +1662,18 @@ impl Application for ESLauncher {
                 iced::widget::column([
                     iced::widget::horizontal_rule(2).into(),
                     Row::new()
-                        .push(self.instances_frame.view())
+                        .push(
+                            self.instances_frame
+                                .view(&self.profiles_frame.profiles.names(), &self.settings),
+                        )
                         .push(iced::widget::vertical_rule(2))
-                        .push(self.install_frame.view().map(Message::InstallFrameMessage))
+                        .push(
+                            Column::new()
+                                .width(Length::FillPortion(2))
+                                .push(self.install_frame.view().map(Message::InstallFrameMessage))
+                                .push(iced::widget::horizontal_rule(2))
+                                .push(self.news_frame.view().map(Message::NewsMessage)),
+                        )
                         .spacing(10)
                         .padding(iced::Padding {
                             top: 0.0,
@@ -250,34 +1690,197 @@ impl Application for ESLauncher {
                 TabLabel::Text("Plugins".into()),
                 iced::widget::column([
                     iced::widget::horizontal_rule(2).into(),
+                    self.profiles_frame.view().map(Message::ProfilesMessage),
+                    self.manifest_frame
+                        .view(self.instances_frame.instances.keys().cloned().collect())
+                        .map(Message::ManifestMessage),
+                    self.bulk_plugin_frame
+                        .view(
+                            self.plugins_frame.names(),
+                            self.instances_frame.instances.keys().cloned().collect(),
+                        )
+                        .map(Message::BulkPluginMessage),
                     self.plugins_frame.view().into(),
                 ]),
             )
+            .push(
+                Tab::Saves,
+                TabLabel::Text("Saves".into()),
+                iced::widget::column([
+                    iced::widget::horizontal_rule(2).into(),
+                    self.saves_frame
+                        .view(self.instances_frame.instances.keys().cloned().collect())
+                        .map(Message::SavesMessage),
+                ]),
+            )
+            .push(
+                Tab::Screenshots,
+                TabLabel::Text("Screenshots".into()),
+                iced::widget::column([
+                    iced::widget::horizontal_rule(2).into(),
+                    self.screenshots_frame
+                        .view(self.instances_frame.instances.keys().cloned().collect())
+                        .map(Message::ScreenshotsMessage),
+                ]),
+            )
+            .push(
+                Tab::Statistics,
+                TabLabel::Text("Statistics".into()),
+                iced::widget::column([
+                    iced::widget::horizontal_rule(2).into(),
+                    self.stats_frame
+                        .view(self.instance_paths())
+                        .map(Message::StatsMessage),
+                ]),
+            )
+            .push(
+                Tab::Compare,
+                TabLabel::Text("Compare".into()),
+                iced::widget::column([
+                    iced::widget::horizontal_rule(2).into(),
+                    self.compare_frame
+                        .view(self.instances_frame.instances.keys().cloned().collect())
+                        .map(Message::CompareMessage),
+                ]),
+            )
+            .push(
+                Tab::Console,
+                TabLabel::Text("Console".into()),
+                iced::widget::column([
+                    iced::widget::horizontal_rule(2).into(),
+                    self.console_frame
+                        .view(self.instances_frame.instances.keys().cloned().collect())
+                        .map(Message::ConsoleMessage),
+                ]),
+            )
+            .push(
+                Tab::Settings,
+                TabLabel::Text("Settings".into()),
+                iced::widget::column([
+                    iced::widget::horizontal_rule(2).into(),
+                    self.settings_frame
+                        .view(&self.settings)
+                        .map(Message::SettingsMessage),
+                ]),
+            )
             .set_active_tab(&self.active_tab)
             .tab_bar_style(tab_bar());
 
-        let logbox = self.log_buffer.iter().fold(
-            Column::new()
-                .spacing(1)
-                .padding(15)
-                .align_items(Alignment::Start),
-            |column, log| {
-                column.push(
-                    Container::new(
-                        Text::new(log)
+        Column::new()
+            .align_items(Alignment::Center)
+            .push(tabs.height(Length::Fill))
+            .push_maybe(
+                (!self.notifications.is_empty())
+                    .then(|| self.notifications.view().map(Message::NotificationMessage)),
+            )
+            .into()
+    }
+
+    fn log_pane(&self) -> Option<pane_grid::Pane> {
+        self.panes
+            .iter()
+            .find(|(_, pane)| **pane == MainPane::Log)
+            .map(|(id, _)| *id)
+    }
+
+    fn view_log(&self) -> Element<Message> {
+        let jobs: BTreeSet<&String> = self
+            .log_buffer
+            .iter()
+            .filter_map(|e| e.job.as_ref())
+            .collect();
+        let log_filter = (!jobs.is_empty()).then(|| {
+            let mut options = vec![ALL_JOBS.to_string()];
+            options.extend(jobs.into_iter().cloned());
+            PickList::new(
+                options,
+                Some(
+                    self.log_filter
+                        .clone()
+                        .unwrap_or_else(|| ALL_JOBS.to_string()),
+                ),
+                Message::LogFilterChanged,
+            )
+            .text_size(12)
+        });
+        let maximized = self.panes.maximized().is_some();
+        // Records from the logfile aren't tagged with their job, so they can't be filtered
+        let can_load_older =
+            self.log_dropped > 0 && !self.older_log_complete && self.log_filter.is_none();
+        let header = Row::new()
+            .padding([0, 15])
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push_maybe(log_filter)
+            .push(Space::new(Length::Fill, Length::Shrink))
+            .push_maybe(can_load_older.then(|| {
+                style::tooltip(
+                    Button::new(Text::new("Load older lines").size(12))
+                        .style(style::text_button())
+                        .on_press(Message::LoadOlderLog),
+                    "Only the latest lines are kept here, the whole session is in the logfile",
+                )
+            }))
+            .push(style::tooltip(
+                Button::new(Text::new(if maximized { "Restore" } else { "Expand" }).size(12))
+                    .style(style::text_button())
+                    .on_press(Message::ToggleLogMaximized),
+                if maximized {
+                    "Show the tabs again"
+                } else {
+                    "Let the log take up the whole window, e.g. to follow a long build"
+                },
+            ));
+
+        let older = self
+            .older_log
+            .iter()
+            .filter(|_| self.log_filter.is_none())
+            .fold(
+                Column::new()
+                    .spacing(1)
+                    .padding(15)
+                    .align_items(Alignment::Start),
+                |column, (_, record)| {
+                    column.push(
+                        Text::new(record.as_str())
                             .size(11)
                             .font(Font::with_name("DejaVu Sans Mono"))
-                            .horizontal_alignment(alignment::Horizontal::Left),
+                            .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6))),
+                    )
+                },
+            );
+        let logbox = self
+            .log_buffer
+            .iter()
+            .filter(|e| self.log_filter.is_none() || e.job == self.log_filter)
+            .fold(older, |column, entry| {
+                let cell = |text: String, width: Length| {
+                    Text::new(text)
+                        .size(11)
+                        .font(Font::with_name("DejaVu Sans Mono"))
+                        .horizontal_alignment(alignment::Horizontal::Left)
+                        .width(width)
+                };
+                column.push(
+                    Container::new(
+                        Row::new()
+                            .spacing(8)
+                            .push(cell(entry.time(), Length::Fixed(55.)))
+                            .push(cell(entry.level.to_string(), Length::Fixed(40.)))
+                            .push(cell(entry.module.clone(), Length::Fixed(170.)))
+                            .push(cell(
+                                entry.job.clone().unwrap_or_default(),
+                                Length::Fixed(100.),
+                            ))
+                            .push(cell(entry.message.clone(), Length::Fill)),
                     )
-                    .style(log_container(log))
+                    .style(log_container(entry.level))
                     .width(Length::Fill),
                 )
-            },
-        );
+            });
 
-        let content = Column::new()
-            .align_items(Alignment::Center)
-            .push(tabs.height(Length::FillPortion(3)))
+        Column::new()
             .push(
                 iced::widget::container(iced::widget::horizontal_rule(2)).padding(iced::Padding {
                     top: 0.0,
@@ -286,55 +1889,46 @@ impl Application for ESLauncher {
                     left: 10.0,
                 }),
             )
+            .push(header)
             .push(
                 Scrollable::new(logbox)
                     .width(Length::Fill)
-                    .height(Length::FillPortion(1)),
-            ); // TODO: Autoscroll this to bottom. https://github.com/hecrj/iced/issues/307
+                    .height(Length::Fill),
+            ) // TODO: Autoscroll this to bottom. https://github.com/hecrj/iced/issues/307
+            .into()
+    }
 
-        let music_controls = Row::new()
-            .width(Length::Fill)
-            .align_items(Alignment::Center)
-            .padding(8)
-            .push(Space::new(Length::Fill, Length::Shrink))
-            .push(
-                Button::new(match self.settings.music_state {
-                    MusicState::Playing => style::pause_icon(),
-                    MusicState::Paused => style::play_icon(),
-                })
-                .style(icon_button())
-                .on_press(Message::MusicMessage(
-                    match self.settings.music_state {
-                        MusicState::Playing => MusicCommand::Pause,
-                        MusicState::Paused => MusicCommand::Play,
-                    },
-                )),
-            )
-            .push(Text::new("Endless Sky Prototype by JimmyZenith").size(13));
+    fn window_geometry(&mut self) -> &mut WindowGeometry {
+        let size = window::Settings::default().size;
+        self.window_geometry.get_or_insert(WindowGeometry {
+            width: size.width,
+            height: size.height,
+            x: None,
+            y: None,
+        })
+    }
 
-        Container::new(
-            Column::new()
-                .align_items(Alignment::Start)
-                .push(
-                    Container::new(content)
-                        .width(Length::Fill)
-                        .height(Length::Fill)
-                        .center_x()
-                        .center_y(),
-                )
-                .push(music_controls.height(Length::Shrink)),
-        )
-        .width(Length::Fill)
-        .height(Length::Fill)
-        .into()
+    /// The UI state to restore at the next start.
+    fn session(&self) -> Session {
+        Session {
+            tab: Some(self.active_tab.clone()),
+            log_filter: self.log_filter.clone(),
+            expanded_instance: self
+                .instances_frame
+                .instances
+                .values()
+                .find(|i| i.show_details)
+                .map(|i| i.name.clone()),
+            install: self.install_frame.draft(),
+        }
     }
 
-    fn theme(&self) -> Self::Theme {
-        iced::Theme::custom("LightModified".into(), {
-            let mut palette = iced::theme::Palette::LIGHT;
-            palette.primary = iced::Color::from_rgb(0.2, 0.2, 0.2);
-            palette
-        })
+    fn instance_paths(&self) -> Vec<(String, PathBuf)> {
+        self.instances_frame
+            .instances
+            .values()
+            .map(|i| (i.name.clone(), i.path.clone()))
+            .collect()
     }
 }
 
@@ -349,8 +1943,91 @@ fn check_for_update() {
     );
 }
 
+/// The directory of the executable, if running in portable mode.
+/// Portable mode is enabled by `--portable` or a `portable.txt` next to the executable.
+fn get_portable_dir() -> Option<PathBuf> {
+    static PORTABLE_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+    PORTABLE_DIR
+        .get_or_init(|| {
+            let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+            (std::env::args().any(|arg| arg == "--portable")
+                || exe_dir.join("portable.txt").exists())
+            .then_some(exe_dir)
+        })
+        .clone()
+}
+
+fn is_portable() -> bool {
+    get_portable_dir().is_some()
+}
+
+/// Looked up once by [`get_data_dir_override`], unless the test driver set it before
+static DATA_DIR_OVERRIDE: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Set by `--data-dir <dir>` or `ESLAUNCHER2_DATA_DIR`, takes precedence over portable mode.
+fn get_data_dir_override() -> Option<PathBuf> {
+    DATA_DIR_OVERRIDE
+        .get_or_init(|| {
+            let mut args = std::env::args();
+            while let Some(arg) = args.next() {
+                if arg == "--data-dir" {
+                    return args.next().map(PathBuf::from);
+                }
+                if let Some(dir) = arg.strip_prefix("--data-dir=") {
+                    return Some(dir.into());
+                }
+            }
+            std::env::var_os("ESLAUNCHER2_DATA_DIR")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+        })
+        .clone()
+}
+
+/// The data dir of the default profile, which has the other profiles in it. A temporary one if
+/// that can't be written to, see `read_only`.
+fn get_base_data_dir() -> Option<PathBuf> {
+    let data_dir = if let Some(data_dir) = get_data_dir_override() {
+        data_dir
+    } else if let Some(portable_dir) = get_portable_dir() {
+        portable_dir.join("data")
+    } else {
+        platform_dirs::AppDirs::new(Some("ESLauncher2"), false)?.data_dir
+    };
+    Some(read_only::resolve(data_dir))
+}
+
 fn get_data_dir() -> Option<PathBuf> {
-    Some(platform_dirs::AppDirs::new(Some("ESLauncher2"), false)?.data_dir)
+    match profiles::current() {
+        Some(profile) => profiles::dir(&profile),
+        None => get_base_data_dir(),
+    }
+}
+
+/// Set by the test driver, so tests never touch the game's own saves and preferences
+static GAME_DATA_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Where Endless Sky keeps its data when it isn't pointed anywhere else.
+fn get_default_game_data_dir() -> Option<PathBuf> {
+    if let Some(dir) = GAME_DATA_DIR_OVERRIDE.get() {
+        return Some(dir.clone());
+    }
+    Some(platform_dirs::AppDirs::new(Some("endless-sky"), false)?.data_dir)
+}
+
+/// The directory Endless Sky itself stores saves, plugins and preferences in.
+/// In portable mode, the game gets pointed at a directory next to the launcher instead,
+/// and profiles other than the default one have their own in their data dir.
+fn get_game_data_dir() -> Option<PathBuf> {
+    if profiles::current().is_some() {
+        return Some(get_data_dir()?.join("endless-sky"));
+    }
+    if GAME_DATA_DIR_OVERRIDE.get().is_none() {
+        if let Some(portable_dir) = get_portable_dir() {
+            return Some(portable_dir.join("endless-sky"));
+        }
+    }
+    get_default_game_data_dir()
 }
 
 #[derive(Debug, Clone)]
@@ -367,25 +2044,42 @@ impl iced::advanced::subscription::Recipe for MessageReceiver {
         self: Box<Self>,
         _input: EventStream,
     ) -> futures::stream::BoxStream<'static, Self::Output> {
-        Box::pin(futures::stream::unfold(0, |state| async move {
-            let mut interval = tokio::time::interval(Duration::from_millis(10));
-            loop {
-                interval.tick().await;
-                if let Some(msg) = MESSAGE_QUEUE
-                    .try_lock()
-                    .ok()
-                    .and_then(|mut q| q.pop_front())
-                {
-                    return Some((msg, state));
+        use futures::StreamExt;
+        Box::pin(
+            futures::stream::unfold(0, |state| async move {
+                loop {
+                    let messages = take_messages();
+                    if !messages.is_empty() {
+                        return Some((messages, state));
+                    }
+                    // Timing out just means checking again
+                    let _ =
+                        tokio::time::timeout(MESSAGE_RECHECK_INTERVAL, MESSAGE_WAKER.notified())
+                            .await;
                 }
-            }
-        }))
+            })
+            .flat_map(futures::stream::iter),
+        )
     }
 }
 
+/// Everything sent with [`send_message`] since the last call. Senders only hold the lock while
+/// pushing, so waiting for it doesn't take long, and nothing is missed while one is.
+fn take_messages() -> Vec<Message> {
+    MESSAGE_QUEUE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .drain(..)
+        .collect()
+}
+
 pub fn send_message(message: Message) {
     match crate::MESSAGE_QUEUE.lock() {
-        Ok(mut queue) => queue.push_back(message),
+        Ok(mut queue) => {
+            queue.push_back(message);
+            // Stores a wake-up if the receiver isn't waiting right now
+            MESSAGE_WAKER.notify_one();
+        }
         Err(e) => {
             // Don't use an error log here because that may cause an endless loop of logs
             eprintln!(
@@ -394,10 +2088,3 @@ pub fn send_message(message: Message) {
         }
     }
 }
-
-pub fn send_progress_message(instance_name: &str, progress: Progress) {
-    send_message(Message::InstanceMessage(
-        instance_name.into(),
-        InstanceMessage::StateChanged(InstanceState::Working(progress)),
-    ));
-}
@@ -0,0 +1,100 @@
+//! Renders the Markdown of release notes, as far as they use it: headings, lists, code blocks
+//! and rules. Inline formatting is reduced to its text, since a Text can only have one style.
+
+use iced::widget::{horizontal_rule, Column, Row, Space, Text};
+use iced::{theme, Color, Element, Font, Length};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// Spaces per nesting level of a list
+const INDENT: usize = 2;
+const INDENT_WIDTH: f32 = 15.;
+
+lazy_static! {
+    static ref IMAGE: Regex = Regex::new(r"!\[([^\]]*)\]\([^)]*\)").unwrap();
+    static ref LINK: Regex = Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap();
+    static ref EMPHASIS: Regex = Regex::new(r"\*\*|__|`|~~").unwrap();
+    static ref COMMENT: Regex = Regex::new(r"(?s)<!--.*?-->").unwrap();
+    static ref ORDERED: Regex = Regex::new(r"^(\d+)[.)] (.*)$").unwrap();
+}
+
+/// The text of a line without its inline formatting, e.g. links become their label.
+fn plain(text: &str) -> String {
+    let text = IMAGE.replace_all(text, "$1");
+    let text = LINK.replace_all(&text, "$1");
+    EMPHASIS.replace_all(&text, "").trim().to_string()
+}
+
+fn list_item<'a, M: 'a>(indent: usize, marker: String, text: &str) -> Element<'a, M> {
+    Row::new()
+        .spacing(5)
+        .push(Space::with_width(INDENT_WIDTH * (indent / INDENT) as f32))
+        .push(Text::new(marker).size(14))
+        .push(Text::new(plain(text)).size(14).width(Length::Fill))
+        .into()
+}
+
+fn code_block<'a, M: 'a>(lines: &[&str]) -> Element<'a, M> {
+    Text::new(lines.join("\n"))
+        .size(13)
+        .font(Font::with_name("DejaVu Sans Mono"))
+        .into()
+}
+
+pub fn view<'a, M: 'a>(markdown: &str) -> Element<'a, M> {
+    let markdown = COMMENT.replace_all(markdown, "");
+    let mut column = Column::new().spacing(5);
+    let mut code: Option<Vec<&str>> = None;
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            match code.take() {
+                Some(lines) => column = column.push(code_block(&lines)),
+                None => code = Some(vec![]),
+            }
+            continue;
+        }
+        if let Some(lines) = &mut code {
+            lines.push(line);
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+        if trimmed.is_empty() {
+            continue;
+        } else if let Some(heading) = trimmed.strip_prefix('#') {
+            let level = heading.chars().take_while(|c| *c == '#').count() + 1;
+            let size = match level {
+                1 => 24,
+                2 => 20,
+                _ => 17,
+            };
+            column = column.push(Text::new(plain(heading.trim_start_matches('#'))).size(size));
+        } else if trimmed.chars().all(|c| matches!(c, '-' | '*' | '_')) && trimmed.len() >= 3 {
+            column = column.push(horizontal_rule(1));
+        } else if let Some(item) = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|marker| trimmed.strip_prefix(marker))
+        {
+            column = column.push(list_item(indent, "•".into(), item));
+        } else if let Some(captures) = ORDERED.captures(trimmed) {
+            column = column.push(list_item(
+                indent,
+                format!("{}.", &captures[1]),
+                &captures[2],
+            ));
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            column = column.push(
+                Text::new(plain(quote))
+                    .size(14)
+                    .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6))),
+            );
+        } else {
+            column = column.push(Text::new(plain(trimmed)).size(14));
+        }
+    }
+    // An unterminated code block still has its lines shown
+    if let Some(lines) = code {
+        column = column.push(code_block(&lines));
+    }
+    column.into()
+}
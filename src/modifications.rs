@@ -0,0 +1,166 @@
+//! Detects changes to an instance's data files since its build was installed, by comparing
+//! them to checksums recorded at install time. Only files whose size or modification time
+//! changed since then are checksummed again, so checking all instances at startup is quick.
+
+use crate::compare_frame::{checksum, files, find_data_dir};
+use crate::instance::{Instance, InstanceMessage};
+use crate::Message;
+use anyhow::Result;
+use iced::Command;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// Part of the build, so kept versions, clones and restore points have the right one
+const FILE_NAME: &str = ".eslauncher2-manifest.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    /// Checksums of the data files, keyed by their path relative to the data dir
+    data: BTreeMap<String, u32>,
+    /// Of the data files when they were recorded, missing in older manifests
+    #[serde(default)]
+    stamps: BTreeMap<String, Stamp>,
+}
+
+/// Changes whenever a file is written to, short of tools that restore the modification time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Stamp {
+    size: u64,
+    /// Nanoseconds since the Unix epoch
+    modified: u64,
+}
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(Stamp {
+        size: metadata.len(),
+        modified: modified.as_nanos() as u64,
+    })
+}
+
+/// Data files that differ from the installed build, by their path relative to the data dir.
+#[derive(Debug, Clone, Default)]
+pub struct Modifications {
+    pub changed: Vec<String>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl Modifications {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.added.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn count(&self) -> usize {
+        self.changed.len() + self.added.len() + self.removed.len()
+    }
+}
+
+/// Remembers the data files of a freshly installed build. AppImages have nothing to record,
+/// since their data files can't be changed anyway.
+pub fn record(instance: &Instance) -> Result<()> {
+    let Some(data_dir) = find_data_dir(instance) else {
+        return Ok(());
+    };
+    let mut manifest = Manifest {
+        data: BTreeMap::new(),
+        stamps: BTreeMap::new(),
+    };
+    for (file, path) in files(&data_dir)? {
+        manifest.data.insert(file.clone(), checksum(&path)?);
+        if let Some(stamp) = stamp(&path) {
+            manifest.stamps.insert(file, stamp);
+        }
+    }
+    serde_json::to_writer(File::create(instance.path.join(FILE_NAME))?, &manifest)?;
+    debug!(
+        "Recorded {} data files of {}",
+        manifest.data.len(),
+        instance.name
+    );
+    Ok(())
+}
+
+/// None if there's nothing to compare with, e.g. for builds installed before manifests were recorded.
+pub fn check(instance: &Instance) -> Option<Modifications> {
+    let data_dir = find_data_dir(instance)?;
+    let text = fs::read_to_string(instance.path.join(FILE_NAME)).ok()?;
+    let manifest: Manifest = match serde_json::from_str(&text) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            warn!("Failed to read the manifest of {}: {}", instance.name, e);
+            return None;
+        }
+    };
+    let current = match files(&data_dir) {
+        Ok(current) => current,
+        Err(e) => {
+            warn!(
+                "Failed to read the data files of {}: {:#}",
+                instance.name, e
+            );
+            return None;
+        }
+    };
+    let mut modifications = Modifications::default();
+    let mut checksummed = 0;
+    for (file, path) in &current {
+        let Some(original) = manifest.data.get(file) else {
+            modifications.added.push(file.clone());
+            continue;
+        };
+        if stamp(path).is_some_and(|stamp| manifest.stamps.get(file) == Some(&stamp)) {
+            continue;
+        }
+        checksummed += 1;
+        match checksum(path) {
+            Ok(sum) if sum == *original => {}
+            Ok(_) => modifications.changed.push(file.clone()),
+            Err(e) => {
+                warn!("Failed to read {}: {}", path.to_string_lossy(), e);
+                modifications.changed.push(file.clone());
+            }
+        }
+    }
+    modifications.removed = manifest
+        .data
+        .keys()
+        .filter(|file| !current.contains_key(*file))
+        .cloned()
+        .collect();
+    debug!(
+        "Checksummed {} of the {} data files of {}",
+        checksummed,
+        current.len(),
+        instance.name
+    );
+    if !modifications.is_empty() {
+        info!(
+            "{} data files of {} differ from its build",
+            modifications.count(),
+            instance.name
+        );
+    }
+    Some(modifications)
+}
+
+pub async fn perform_check(instance: Instance) -> Option<Modifications> {
+    check(&instance)
+}
+
+/// Checks the given instances in the background, each reporting back on its own.
+pub fn check_all<'a>(instances: impl Iterator<Item = &'a Instance>) -> Command<Message> {
+    Command::batch(instances.map(|instance| {
+        let name = instance.name.clone();
+        Command::perform(perform_check(instance.clone()), move |modifications| {
+            Message::InstanceMessage(
+                name.clone(),
+                InstanceMessage::ModificationsChecked(modifications),
+            )
+        })
+    }))
+}
@@ -1,20 +1,49 @@
+use crate::compare_frame::find_data_dir;
+use crate::get_data_dir;
+use crate::instance::Instance;
 use anyhow::{Context, Result};
-use rodio::Sink;
+use rodio::cpal::traits::HostTrait;
+use rodio::{cpal, Decoder, Device, DeviceTrait, OutputStream, Sink};
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::hash::{BuildHasher, Hasher};
 use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 const SONG: &[u8] = include_bytes!("../assets/endless-prototype.ogg");
+/// How often to check whether playback got stuck, e.g. because the output device went away
+const STALL_CHECK_INTERVAL: Duration = Duration::from_secs(3);
+/// How often to check whether the system default changed, which means listing the devices
+const DEVICE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const FULL_VOLUME: f32 = 0.95;
+/// The volume music is turned down to while a game that ducks it is running
+const DUCKED_VOLUME: f32 = 0.25;
 
-#[derive(Clone, Copy, Debug)]
+/// What each running game wants the music to do, by instance name
+static GAMES: Mutex<BTreeMap<String, MusicWhilePlaying>> = Mutex::new(BTreeMap::new());
+static LAUNCHER_FOCUSED: AtomicBool = AtomicBool::new(true);
+
+#[derive(Clone, Debug)]
 pub enum MusicCommand {
     Pause,
     Play,
     WeakPause,
     WeakPlay,
+    /// Turns the music down until the next `WeakPlay`, unless it's paused
+    Duck,
+    /// Plays through the output device with this name, or the system default if None
+    SetDevice(Option<String>),
+    /// Shuffles these tracks instead of the usual ones, unless empty
+    SetSoundtrack(Vec<PathBuf>),
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
@@ -24,66 +53,413 @@ pub enum MusicState {
     Paused,
 }
 
-pub fn spawn(initial_state: MusicState) -> Sender<MusicCommand> {
+/// What happens to the music while a game is running
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MusicWhilePlaying {
+    /// Only for instances, whatever the settings say
+    #[default]
+    Default,
+    Pause,
+    Duck,
+    KeepPlaying,
+    /// Pause only while the launcher is out of focus, e.g. while the game window is in front
+    PauseWhileAway,
+}
+
+impl MusicWhilePlaying {
+    pub const ALL: [Self; 5] = [
+        Self::Default,
+        Self::Pause,
+        Self::Duck,
+        Self::KeepPlaying,
+        Self::PauseWhileAway,
+    ];
+    /// The choices for the settings, which have nothing to defer to
+    pub const GLOBAL: [Self; 4] = [
+        Self::Pause,
+        Self::Duck,
+        Self::KeepPlaying,
+        Self::PauseWhileAway,
+    ];
+
+    /// This, or `global` if it's left to the settings.
+    pub fn or(self, global: Self) -> Self {
+        match self {
+            Self::Default => global,
+            _ => self,
+        }
+    }
+}
+
+impl fmt::Display for MusicWhilePlaying {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "As in the settings"),
+            Self::Pause => write!(f, "Pause"),
+            Self::Duck => write!(f, "Play quieter"),
+            Self::KeepPlaying => write!(f, "Keep playing"),
+            Self::PauseWhileAway => write!(f, "Pause while away"),
+        }
+    }
+}
+
+/// What the music should do for the running games: pausing wins over ducking, which wins over
+/// playing on.
+fn wanted(games: &BTreeMap<String, MusicWhilePlaying>) -> MusicCommand {
+    let away = !LAUNCHER_FOCUSED.load(Ordering::Relaxed);
+    let pause = games.values().any(|behavior| match behavior {
+        MusicWhilePlaying::Default | MusicWhilePlaying::Pause => true,
+        MusicWhilePlaying::PauseWhileAway => away,
+        MusicWhilePlaying::Duck | MusicWhilePlaying::KeepPlaying => false,
+    });
+    if pause {
+        MusicCommand::WeakPause
+    } else if games.values().any(|b| *b == MusicWhilePlaying::Duck) {
+        MusicCommand::Duck
+    } else {
+        MusicCommand::WeakPlay
+    }
+}
+
+fn update_games(f: impl FnOnce(&mut BTreeMap<String, MusicWhilePlaying>)) -> MusicCommand {
+    let mut games = GAMES.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut games);
+    wanted(&games)
+}
+
+/// What to tell the music thread once the game of `instance` started.
+pub fn game_started(instance: &str, behavior: MusicWhilePlaying) -> MusicCommand {
+    update_games(|games| {
+        games.insert(instance.into(), behavior);
+    })
+}
+
+/// What to tell the music thread once the game of `instance` exited.
+pub fn game_exited(instance: &str) -> MusicCommand {
+    update_games(|games| {
+        games.remove(instance);
+    })
+}
+
+/// What to tell the music thread after the launcher window gained or lost focus, if anything.
+pub fn focus_changed(focused: bool) -> Option<MusicCommand> {
+    LAUNCHER_FOCUSED.store(focused, Ordering::Relaxed);
+    let games = GAMES.lock().unwrap_or_else(|e| e.into_inner());
+    let away_only = games
+        .values()
+        .any(|b| *b == MusicWhilePlaying::PauseWhileAway);
+    away_only.then(|| wanted(&games))
+}
+
+pub fn spawn(initial_state: MusicState, device: Option<String>) -> Sender<MusicCommand> {
     let (tx, rx) = mpsc::channel();
     thread::spawn(move || {
-        if let Err(e) = play(&rx, initial_state) {
+        if let Err(e) = play(&rx, initial_state, device) {
             error!("Music thread crashed: {:#}", e);
         }
     });
     tx
 }
 
-fn play(rx: &Receiver<MusicCommand>, initial_state: MusicState) -> Result<()> {
-    let (_stream, stream_handle) =
-        rodio::OutputStream::try_default().context("Failed to get output stream")?;
+/// Only opened once music should actually play, so systems without audio don't need one
+struct Output {
+    // Playback stops once this is dropped
+    _stream: OutputStream,
+    sink: Sink,
+    device: String,
+}
+
+impl Output {
+    fn open(device: Option<&str>) -> Result<Self> {
+        let device = find_device(device).ok_or_else(|| anyhow!("No audio output device"))?;
+        let name = device.name().unwrap_or_default();
+        let (stream, stream_handle) =
+            OutputStream::try_from_device(&device).context("Failed to get output stream")?;
+        let sink = Sink::try_new(&stream_handle).context("Failed to create Sink")?;
+        debug!("Playing music through {}", name);
+        Ok(Self {
+            _stream: stream,
+            sink,
+            device: name,
+        })
+    }
+}
+
+/// The output device with the given name, falling back to the system default if it's gone.
+fn find_device(name: Option<&str>) -> Option<Device> {
+    let host = cpal::default_host();
+    if let Some(name) = name {
+        let device = host
+            .output_devices()
+            .ok()
+            .and_then(|mut devices| devices.find(|d| d.name().is_ok_and(|n| n == name)));
+        if device.is_some() {
+            return device;
+        }
+        debug!("Audio output {} isn't available, using the default", name);
+    }
+    host.default_output_device()
+}
+
+/// The names of the output devices music can be played through.
+pub fn output_devices() -> Vec<String> {
+    match cpal::default_host().output_devices() {
+        Ok(devices) => devices.filter_map(|d| d.name().ok()).collect(),
+        Err(e) => {
+            warn!("Failed to list audio outputs: {}", e);
+            vec![]
+        }
+    }
+}
+
+pub async fn perform_list_devices() -> Vec<String> {
+    output_devices()
+}
+
+/// Ogg files in the music folder of the data dir, played instead of the built-in song
+fn user_tracks() -> Vec<PathBuf> {
+    let Some(dir) = get_data_dir().map(|d| d.join("music")) else {
+        return vec![];
+    };
+    let mut tracks: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("ogg")))
+                .collect()
+        })
+        .unwrap_or_default();
+    tracks.sort();
+    tracks
+}
+
+/// Music files in the sounds folder of the game's data, recursively
+fn collect_tracks(dir: &Path, tracks: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.filter_map(Result::ok).map(|e| e.path()) {
+        if path.is_dir() {
+            collect_tracks(&path, tracks);
+        } else if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("mp3") || e.eq_ignore_ascii_case("ogg"))
+        {
+            tracks.push(path);
+        }
+    }
+}
 
-    let sink = Sink::try_new(&stream_handle).context("Failed to create Sink")?;
+/// The game's soundtrack, from the first of `instances` that has one. AppImages can't be
+/// looked into, so they're skipped.
+pub fn find_soundtrack(instances: &[Instance]) -> Vec<PathBuf> {
+    instances
+        .iter()
+        .filter_map(find_data_dir)
+        .filter_map(|data| Some(data.parent()?.join("sounds")))
+        .map(|sounds| {
+            let mut tracks = vec![];
+            collect_tracks(&sounds, &mut tracks);
+            tracks
+        })
+        .find(|tracks| !tracks.is_empty())
+        .unwrap_or_default()
+}
+
+pub async fn perform_find_soundtrack(instances: Vec<Instance>) -> Vec<PathBuf> {
+    let tracks = find_soundtrack(&instances);
+    if tracks.is_empty() {
+        warn!("None of the instances has music to play, keeping the launcher's own");
+    } else {
+        info!("Found {} tracks of the game's soundtrack", tracks.len());
+    }
+    tracks
+}
+
+/// Fisher-Yates, with the random seed every `RandomState` comes with
+fn shuffle(tracks: &mut [PathBuf]) {
+    let mut hasher = RandomState::new().build_hasher();
+    for i in (1..tracks.len()).rev() {
+        hasher.write_usize(i);
+        let j = (hasher.finish() % (i as u64 + 1)) as usize;
+        tracks.swap(i, j);
+    }
+}
+
+fn append_file(sink: &Sink, track: &Path) -> Result<()> {
+    debug!("Playing {}", track.to_string_lossy());
+    let file =
+        File::open(track).with_context(|| format!("Failed to open {}", track.to_string_lossy()))?;
+    sink.append(Decoder::new(BufReader::new(file))?);
+    Ok(())
+}
+
+/// Queues the next track of the soundtrack, shuffled anew once all of it was played.
+fn queue_from_soundtrack(sink: &Sink, soundtrack: &mut [PathBuf], index: &mut usize) -> Result<()> {
+    if *index >= soundtrack.len() {
+        *index = 0;
+    }
+    if *index == 0 {
+        shuffle(soundtrack);
+    }
+    let track = &soundtrack[*index];
+    *index += 1;
+    append_file(sink, track)
+}
+
+/// Queues the track after `index`, which gets decoded bit by bit while playing.
+fn queue_next(sink: &Sink, index: &mut usize) -> Result<()> {
+    let tracks = user_tracks();
+    if tracks.is_empty() {
+        sink.append(Decoder::new(Cursor::new(SONG))?);
+        return Ok(());
+    }
+    let track = &tracks[*index % tracks.len()];
+    *index += 1;
+    append_file(sink, track)
+}
+
+fn play(
+    rx: &Receiver<MusicCommand>,
+    initial_state: MusicState,
+    mut device: Option<String>,
+) -> Result<()> {
+    let mut output: Option<Output> = None;
+    // Set once opening the output failed, so it's only retried when the user presses play
+    let mut unavailable = false;
+    let mut track = 0;
+    let mut soundtrack: Vec<PathBuf> = vec![];
+    let mut last_device_check = Instant::now();
+    let mut last_stall_check = (Instant::now(), Duration::ZERO);
 
     let mut state = initial_state;
     loop {
-        if let Ok(cmd) = rx.try_recv() {
-            match cmd {
+        match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(cmd) => match cmd {
                 MusicCommand::Pause => {
                     state = MusicState::Paused;
-                    fade(&sink, true);
+                    if let Some(output) = &output {
+                        fade(&output.sink, true);
+                    }
                 }
                 MusicCommand::Play => {
                     state = MusicState::Playing;
-                    fade(&sink, false);
+                    unavailable = false;
+                    if let Some(output) = &output {
+                        fade(&output.sink, false);
+                    }
+                }
+                MusicCommand::WeakPause => {
+                    if let Some(output) = &output {
+                        fade(&output.sink, true);
+                    }
                 }
-                MusicCommand::WeakPause => fade(&sink, true),
                 MusicCommand::WeakPlay => {
-                    if state == MusicState::Playing {
-                        fade(&sink, false);
+                    if let (MusicState::Playing, Some(output)) = (state, &output) {
+                        fade(&output.sink, false);
                     }
                 }
-            }
+                MusicCommand::Duck => {
+                    if let (MusicState::Playing, Some(output)) = (state, &output) {
+                        fade_to(&output.sink, DUCKED_VOLUME);
+                    }
+                }
+                // The soundtrack is shuffled, so compared regardless of order
+                MusicCommand::SetSoundtrack(tracks)
+                    if tracks.len() != soundtrack.len()
+                        || tracks.iter().any(|t| !soundtrack.contains(t)) =>
+                {
+                    soundtrack = tracks;
+                    track = 0;
+                    // Moves on to the new music right away
+                    if let Some(output) = &output {
+                        output.sink.skip_one();
+                    }
+                }
+                MusicCommand::SetSoundtrack(_) => {}
+                MusicCommand::SetDevice(name) => {
+                    device = name;
+                    unavailable = false;
+                    // Reopened below, on the new device
+                    output = None;
+                }
+            },
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
         }
 
-        if state == MusicState::Playing && sink.empty() {
-            let source = rodio::Decoder::new(BufReader::new(Cursor::new(SONG)))?;
-            sink.append(source);
+        if state != MusicState::Playing {
+            continue;
+        }
+        // Paused ones are left alone, since reopening starts playing again
+        if last_stall_check.0.elapsed() >= STALL_CHECK_INTERVAL {
+            let playing = output
+                .as_ref()
+                .filter(|o| !o.sink.is_paused() && !o.sink.empty());
+            let position = playing.map_or(Duration::ZERO, |o| o.sink.get_pos());
+            // The stream stops pulling samples once its device is gone
+            if playing.is_some() && position == last_stall_check.1 {
+                info!("Music stopped playing, reopening the audio output");
+                output = None;
+            }
+            last_stall_check = (Instant::now(), position);
+        }
+        if last_device_check.elapsed() >= DEVICE_CHECK_INTERVAL {
+            last_device_check = Instant::now();
+            if let Some(current) = output.as_ref().filter(|o| !o.sink.is_paused()) {
+                let wanted = find_device(device.as_deref()).and_then(|d| d.name().ok());
+                if wanted.as_deref() != Some(current.device.as_str()) {
+                    info!("Audio output changed, moving music to {:?}", wanted);
+                    output = None;
+                }
+            }
+        }
+        if output.is_none() && !unavailable {
+            match Output::open(device.as_deref()) {
+                Ok(opened) => output = Some(opened),
+                Err(e) => {
+                    warn!("No music, since there is no audio output: {:#}", e);
+                    unavailable = true;
+                }
+            }
+        }
+        if let Some(output) = output.as_ref().filter(|o| o.sink.empty()) {
+            let queued = if soundtrack.is_empty() {
+                queue_next(&output.sink, &mut track)
+            } else {
+                queue_from_soundtrack(&output.sink, &mut soundtrack, &mut track)
+            };
+            if let Err(e) = queued {
+                // Falls back to the built-in song, so a broken file doesn't silence everything
+                warn!("Failed to play music: {:#}", e);
+                output.sink.append(Decoder::new(Cursor::new(SONG))?);
+            }
         }
-
-        thread::sleep(Duration::from_millis(100));
     }
 }
 
 fn fade(sink: &Sink, out: bool) {
-    let mut range: Vec<i32> = (1..20).collect();
-    if out {
-        range.reverse();
-    } else {
-        sink.play();
+    if !out {
+        fade_to(sink, FULL_VOLUME);
+    } else if !sink.is_paused() {
+        fade_to(sink, 0.);
+        sink.pause();
     }
+}
 
-    for i in range {
-        sink.set_volume(i as f32 / 20.);
-        thread::sleep(Duration::from_millis(20));
+/// Changes the volume gradually, starting from wherever it is, e.g. when ducked.
+fn fade_to(sink: &Sink, volume: f32) {
+    if sink.is_paused() {
+        sink.set_volume(0.);
+        sink.play();
     }
-
-    if out {
-        sink.pause();
+    let from = sink.volume();
+    if (from - volume).abs() < 0.01 {
+        return;
+    }
+    for i in 1..=19 {
+        sink.set_volume(from + (volume - from) * i as f32 / 19.);
+        thread::sleep(Duration::from_millis(20));
     }
 }
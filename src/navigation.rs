@@ -0,0 +1,66 @@
+//! Keyboard navigation, so the launcher can be used without a mouse. Text fields take the focus
+//! with Tab, while instances are selected with the arrow keys and controlled with shortcuts,
+//! since buttons can't take the keyboard focus. Ctrl+K opens the command palette.
+
+use crate::Message;
+use iced::keyboard::key::Named;
+use iced::keyboard::{self, Key, Modifiers};
+use iced::{event, Event, Subscription};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Navigation {
+    FocusNext,
+    FocusPrevious,
+    NextTab,
+    PreviousTab,
+    SelectNext,
+    SelectPrevious,
+    /// Plays the selected instance
+    Activate,
+    ToggleDetails,
+    ToggleMenu,
+    /// Closes the command palette or the menu of the selected instance, or clears the selection
+    Escape,
+    /// Opens or closes the command palette
+    CommandPalette,
+}
+
+pub fn listen() -> Subscription<Message> {
+    event::listen_with(|event, status| {
+        let Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) = event else {
+            return None;
+        };
+        let navigation = navigation(&key, modifiers)?;
+        // E.g. Space while typing into a text field, except for what works while typing into the
+        // command palette
+        if status == event::Status::Captured
+            && !matches!(navigation, Navigation::CommandPalette | Navigation::Escape)
+        {
+            return None;
+        }
+        Some(Message::Navigation(navigation))
+    })
+}
+
+fn navigation(key: &Key, modifiers: Modifiers) -> Option<Navigation> {
+    let key = match key {
+        Key::Character(c) if modifiers.command() && c.as_str() == "k" => {
+            return Some(Navigation::CommandPalette);
+        }
+        Key::Named(key) => key,
+        _ => return None,
+    };
+    Some(match (key, modifiers.control(), modifiers.shift()) {
+        (Named::Tab, true, false) => Navigation::NextTab,
+        (Named::Tab, true, true) => Navigation::PreviousTab,
+        (Named::Tab, false, false) => Navigation::FocusNext,
+        (Named::Tab, false, true) => Navigation::FocusPrevious,
+        (Named::ArrowDown, false, _) => Navigation::SelectNext,
+        (Named::ArrowUp, false, _) => Navigation::SelectPrevious,
+        (Named::Enter, false, false) => Navigation::Activate,
+        (Named::Space, false, false) => Navigation::ToggleDetails,
+        (Named::ContextMenu, ..) | (Named::F10, false, true) => Navigation::ToggleMenu,
+        (Named::Escape, ..) => Navigation::Escape,
+        _ => return None,
+    })
+}
@@ -0,0 +1,44 @@
+//! Detects whether the network is available, so that features depending on it can be disabled
+//! instead of failing over and over.
+
+use crate::http;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Nearly everything the launcher downloads comes from GitHub
+const PROBE_HOST: &str = "api.github.com:443";
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often to check whether the network is back while offline
+pub const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Assumed to be available until a probe says otherwise
+static ONLINE: AtomicBool = AtomicBool::new(true);
+
+pub fn is_online() -> bool {
+    ONLINE.load(Ordering::Relaxed)
+}
+
+fn reachable() -> bool {
+    // Behind a proxy, that's the only thing that has to be reachable
+    let host = http::proxy_address().unwrap_or_else(|| PROBE_HOST.into());
+    let Ok(addresses) = host.to_socket_addrs() else {
+        return false;
+    };
+    addresses
+        .into_iter()
+        .any(|address| TcpStream::connect_timeout(&address, PROBE_TIMEOUT).is_ok())
+}
+
+/// Checks whether GitHub can be reached and switches between online and offline mode accordingly.
+pub async fn probe() -> bool {
+    let online = reachable();
+    if ONLINE.swap(online, Ordering::Relaxed) != online {
+        if online {
+            info!("The network is available again, leaving offline mode");
+        } else {
+            warn!("The network is unavailable, switching to offline mode");
+        }
+    }
+    online
+}
@@ -0,0 +1,262 @@
+use crate::style::text_button;
+use crate::{github, http, markdown, Message};
+use anyhow::Result;
+use iced::widget::{Button, Column, Container, Row, Scrollable, Space, Text};
+use iced::{alignment, theme, Alignment, Color, Command, Element, Length};
+use serde::Deserialize;
+
+const REPO: &str = "endless-sky/endless-sky";
+const FEED_URL: &str = "https://github.com/endless-sky/endless-sky/releases.atom";
+const MAX_ITEMS: usize = 10;
+
+#[derive(Debug, Deserialize)]
+struct Feed {
+    #[serde(rename = "entry", default)]
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Entry {
+    title: String,
+    updated: String,
+    link: Link,
+}
+
+#[derive(Debug, Deserialize)]
+struct Link {
+    href: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewsItem {
+    pub title: String,
+    pub link: String,
+    /// Just the date part of the Atom timestamp
+    pub date: String,
+}
+
+impl NewsItem {
+    /// The release's tag, if the item is a release, which all of them currently are
+    fn tag(&self) -> Option<&str> {
+        self.link
+            .rsplit_once("/releases/tag/")
+            .map(|(_, tag)| tag)
+            .filter(|tag| !tag.is_empty())
+    }
+}
+
+pub fn fetch() -> Result<Vec<NewsItem>> {
+    let res = http::agent().get(FEED_URL).call()?;
+    let feed: Feed = serde_xml_rs::from_str(&res.into_string()?)?;
+    Ok(feed
+        .entries
+        .into_iter()
+        .take(MAX_ITEMS)
+        .map(|e| NewsItem {
+            title: e.title.trim().to_string(),
+            link: e.link.href,
+            date: e.updated.chars().take(10).collect(),
+        })
+        .collect())
+}
+
+pub async fn perform_fetch() -> Option<Vec<NewsItem>> {
+    match fetch() {
+        Ok(items) => Some(items),
+        Err(e) => {
+            error!("Failed to fetch news: {:#}", e);
+            None
+        }
+    }
+}
+
+/// The Markdown release notes of a release.
+pub fn fetch_notes(tag: &str) -> Result<String> {
+    Ok(github::get_release_by_tag(REPO, tag)?
+        .body
+        .unwrap_or_default())
+}
+
+pub async fn perform_fetch_notes(tag: String) -> Option<String> {
+    match fetch_notes(&tag) {
+        Ok(notes) => Some(notes),
+        Err(e) => {
+            error!("Failed to fetch the release notes of {}: {:#}", tag, e);
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum NewsMessage {
+    Loaded(Option<Vec<NewsItem>>),
+    Refresh,
+    Open(String),
+    Read(NewsItem),
+    /// The link of the release, and its notes if they could be fetched
+    NotesLoaded(String, Option<String>),
+    Back,
+}
+
+#[derive(Debug, Clone, Default)]
+enum NewsState {
+    #[default]
+    Loading,
+    Ready(Vec<NewsItem>),
+    Error,
+}
+
+#[derive(Debug, Clone)]
+struct ReleaseNotes {
+    item: NewsItem,
+    /// None while loading, Some(None) if they couldn't be fetched
+    notes: Option<Option<String>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NewsFrame {
+    state: NewsState,
+    /// The release whose notes are shown instead of the list
+    reading: Option<ReleaseNotes>,
+}
+
+impl NewsFrame {
+    pub fn new() -> (Self, Command<Message>) {
+        (
+            Self::default(),
+            Command::perform(perform_fetch(), |items| {
+                Message::NewsMessage(NewsMessage::Loaded(items))
+            }),
+        )
+    }
+
+    pub fn update(&mut self, message: NewsMessage) -> Command<Message> {
+        match message {
+            NewsMessage::Loaded(Some(items)) => self.state = NewsState::Ready(items),
+            NewsMessage::Loaded(None) => self.state = NewsState::Error,
+            NewsMessage::Refresh => {
+                let (frame, command) = Self::new();
+                *self = frame;
+                return command;
+            }
+            NewsMessage::Open(link) => {
+                if let Err(e) = open::that(&link) {
+                    error!("Failed to open {}: {}", link, e);
+                }
+            }
+            NewsMessage::Read(item) => {
+                let Some(tag) = item.tag().map(String::from) else {
+                    return self.update(NewsMessage::Open(item.link));
+                };
+                let link = item.link.clone();
+                self.reading = Some(ReleaseNotes { item, notes: None });
+                return Command::perform(perform_fetch_notes(tag), move |notes| {
+                    Message::NewsMessage(NewsMessage::NotesLoaded(link.clone(), notes))
+                });
+            }
+            NewsMessage::NotesLoaded(link, notes) => {
+                // Unless another release was opened meanwhile
+                if let Some(reading) = self.reading.as_mut().filter(|r| r.item.link == link) {
+                    reading.notes = Some(notes);
+                }
+            }
+            NewsMessage::Back => self.reading = None,
+        }
+        Command::none()
+    }
+
+    pub fn view(&self) -> Element<NewsMessage> {
+        if let Some(reading) = &self.reading {
+            return reading.view();
+        }
+        let header = Row::new()
+            .align_items(Alignment::Center)
+            .push(Text::new("News").size(26))
+            .push(Space::new(Length::Fill, Length::Shrink))
+            .push(
+                Button::new(Text::new("Refresh"))
+                    .style(text_button())
+                    .on_press(NewsMessage::Refresh),
+            );
+
+        let content: Element<_> = match &self.state {
+            NewsState::Loading => status_text("Loading news…"),
+            NewsState::Error => status_text("Failed to load news"),
+            NewsState::Ready(items) if items.is_empty() => status_text("No news"),
+            NewsState::Ready(items) => items
+                .iter()
+                .fold(Column::new().spacing(5), |column, item| {
+                    column.push(
+                        Button::new(
+                            Column::new().push(Text::new(&item.title)).push(
+                                Text::new(&item.date)
+                                    .size(12)
+                                    .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6))),
+                            ),
+                        )
+                        .width(Length::Fill)
+                        .style(theme::Button::Text)
+                        .on_press(NewsMessage::Read(item.clone())),
+                    )
+                })
+                .into(),
+        };
+
+        Container::new(
+            Column::new()
+                .padding(20)
+                .spacing(10)
+                .push(header)
+                .push(Scrollable::new(content)),
+        )
+        .into()
+    }
+}
+
+impl ReleaseNotes {
+    fn view(&self) -> Element<NewsMessage> {
+        let header = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(
+                Button::new(Text::new("Back"))
+                    .style(text_button())
+                    .on_press(NewsMessage::Back),
+            )
+            .push(Text::new(&self.item.title).size(26).width(Length::Fill))
+            .push(
+                Button::new(Text::new("Open on GitHub"))
+                    .style(text_button())
+                    .on_press(NewsMessage::Open(self.item.link.clone())),
+            );
+        let content = match &self.notes {
+            None => status_text("Loading release notes…"),
+            Some(None) => status_text("Failed to load the release notes"),
+            Some(Some(notes)) if notes.trim().is_empty() => {
+                status_text("This release has no notes")
+            }
+            Some(Some(notes)) => markdown::view(notes),
+        };
+        Container::new(
+            Column::new()
+                .padding(20)
+                .spacing(10)
+                .push(header)
+                .push(
+                    Text::new(&self.item.date)
+                        .size(12)
+                        .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6))),
+                )
+                .push(Scrollable::new(content)),
+        )
+        .into()
+    }
+}
+
+fn status_text<'a>(text: &'a str) -> Element<'a, NewsMessage> {
+    Text::new(text)
+        .style(theme::Text::Color(Color::from_rgb8(150, 150, 150)))
+        .horizontal_alignment(alignment::Horizontal::Center)
+        .width(Length::Fill)
+        .into()
+}
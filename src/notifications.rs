@@ -0,0 +1,210 @@
+use crate::errors;
+use crate::style::{icon_button, notification_container, text_button};
+use crate::{send_message, style, Message};
+use iced::widget::{Button, Column, Container, Row, Space, Text};
+use iced::{Alignment, Command, Element, Length};
+use std::time::{Duration, Instant};
+
+/// How many notifications are shown at once, older ones get dropped
+const MAX_NOTIFICATIONS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Level {
+    /// Errors stay around longer, since they usually need attention
+    fn timeout(self) -> Duration {
+        match self {
+            Self::Error => Duration::from_secs(30),
+            _ => Duration::from_secs(8),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    id: usize,
+    level: Level,
+    text: String,
+    action: Option<(String, Box<Message>)>,
+    /// Sticky notifications stay until they are dismissed or activated
+    sticky: bool,
+    created: Instant,
+}
+
+impl Notification {
+    pub fn new(level: Level, text: impl Into<String>) -> Self {
+        Self {
+            id: 0,
+            level,
+            text: text.into(),
+            action: None,
+            sticky: false,
+            created: Instant::now(),
+        }
+    }
+
+    /// Adds a button that dispatches `message` when clicked.
+    pub fn action(mut self, label: impl Into<String>, message: Message) -> Self {
+        self.action = Some((label.into(), Box::new(message)));
+        self
+    }
+
+    pub fn sticky(mut self) -> Self {
+        self.sticky = true;
+        self
+    }
+}
+
+/// Shows a notification from anywhere, including worker threads.
+pub fn notify(notification: Notification) {
+    send_message(Message::NotificationMessage(NotificationMessage::Push(
+        notification,
+    )));
+}
+
+#[derive(Debug, Clone)]
+pub enum NotificationMessage {
+    Push(Notification),
+    Activate(usize),
+    Dismiss(usize),
+    Tick,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Notifications {
+    items: Vec<Notification>,
+    next_id: usize,
+}
+
+impl Notifications {
+    pub fn push(&mut self, mut notification: Notification) {
+        notification.id = self.next_id;
+        notification.created = Instant::now();
+        self.next_id += 1;
+        self.items.push(notification);
+        if self.items.len() > MAX_NOTIFICATIONS {
+            self.items.remove(0);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn update(&mut self, message: NotificationMessage) -> Command<Message> {
+        match message {
+            NotificationMessage::Push(notification) => self.push(notification),
+            NotificationMessage::Activate(id) => {
+                if let Some(index) = self.items.iter().position(|n| n.id == id) {
+                    if let Some((_, message)) = self.items.remove(index).action {
+                        return Command::perform(async {}, move |()| *message);
+                    }
+                }
+            }
+            NotificationMessage::Dismiss(id) => self.items.retain(|n| n.id != id),
+            NotificationMessage::Tick => self
+                .items
+                .retain(|n| n.sticky || n.created.elapsed() < n.level.timeout()),
+        }
+        Command::none()
+    }
+
+    pub fn view(&self) -> Element<NotificationMessage> {
+        self.items
+            .iter()
+            .rev()
+            .fold(
+                Column::new().spacing(5).padding([5, 10]),
+                |column, notification| {
+                    let row = Row::new()
+                        .spacing(10)
+                        .align_items(Alignment::Center)
+                        .push(Text::new(&notification.text).size(14))
+                        .push(Space::new(Length::Fill, Length::Shrink))
+                        .push_maybe(notification.action.as_ref().map(|(label, _)| {
+                            Button::new(Text::new(label).size(14))
+                                .style(text_button())
+                                .on_press(NotificationMessage::Activate(notification.id))
+                        }))
+                        .push(style::tooltip(
+                            Button::new(Text::new("✕").size(14))
+                                .style(icon_button())
+                                .on_press(NotificationMessage::Dismiss(notification.id)),
+                            "Dismiss",
+                        ));
+                    column.push(
+                        Container::new(row)
+                            .padding([5, 10])
+                            .width(Length::Fill)
+                            .style(notification_container(notification.level)),
+                    )
+                },
+            )
+            .into()
+    }
+}
+
+/// A failed operation, shown inline next to whatever it failed on until dismissed.
+#[derive(Debug, Clone)]
+pub struct ErrorBanner {
+    pub summary: String,
+    /// The whole error chain, hidden until requested
+    pub details: String,
+    pub expanded: bool,
+    /// What the user can do about it, if anything specific
+    pub hint: Option<String>,
+}
+
+impl ErrorBanner {
+    pub fn new(summary: impl Into<String>, error: &anyhow::Error) -> Self {
+        Self {
+            summary: summary.into(),
+            details: format!("{error:#}"),
+            expanded: false,
+            hint: errors::hint(error),
+        }
+    }
+
+    pub fn view<M: Clone + 'static>(&self, on_toggle: M, on_dismiss: M) -> Element<M> {
+        let row = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Text::new(&self.summary).size(14))
+            .push(Space::new(Length::Fill, Length::Shrink))
+            .push(
+                Button::new(
+                    Text::new(if self.expanded {
+                        "Hide details"
+                    } else {
+                        "Details"
+                    })
+                    .size(12),
+                )
+                .style(text_button())
+                .on_press(on_toggle),
+            )
+            .push(style::tooltip(
+                Button::new(Text::new("✕").size(14))
+                    .style(icon_button())
+                    .on_press(on_dismiss),
+                "Dismiss",
+            ));
+        Container::new(
+            Column::new()
+                .spacing(5)
+                .push(row)
+                .push_maybe(self.hint.as_deref().map(|hint| Text::new(hint).size(12)))
+                .push_maybe(self.expanded.then(|| Text::new(&self.details).size(12))),
+        )
+        .padding([5, 10])
+        .width(Length::Fill)
+        .style(notification_container(Level::Error))
+        .into()
+    }
+}
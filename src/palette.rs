@@ -0,0 +1,219 @@
+//! The command palette, which lists what the launcher can do and narrows it down as you type, so
+//! e.g. playing an instance doesn't take finding it in the list first. Opened with Ctrl+K.
+
+use crate::navigation::Navigation;
+use crate::style::text_button;
+use crate::{Message, Tab};
+use iced::widget::{text_input, Button, Column, Container, Text, TextInput};
+use iced::{theme, Command, Element, Length};
+
+/// How many matches are listed at once
+const MAX_SHOWN: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct Action {
+    pub label: String,
+    /// Selected before the message is sent, e.g. so a text field on it can take the focus
+    pub tab: Option<Tab>,
+    pub message: Option<Message>,
+}
+
+impl Action {
+    pub fn new(label: impl Into<String>, message: Message) -> Self {
+        Self {
+            label: label.into(),
+            tab: None,
+            message: Some(message),
+        }
+    }
+
+    pub fn go_to(label: impl Into<String>, tab: Tab) -> Self {
+        Self {
+            label: label.into(),
+            tab: Some(tab),
+            message: None,
+        }
+    }
+
+    pub fn in_tab(mut self, tab: Tab) -> Self {
+        self.tab = Some(tab);
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PaletteMessage {
+    QueryChanged(String),
+    /// Runs the selected match
+    Submit,
+    /// Runs the match at this position
+    Run(usize),
+}
+
+#[derive(Debug, Default)]
+pub struct Palette {
+    open: bool,
+    query: String,
+    /// Position of the selected match
+    selected: usize,
+}
+
+fn input_id() -> text_input::Id {
+    text_input::Id::new("palette")
+}
+
+/// How well `label` matches `query`, if it contains all of its characters in order. Runs of
+/// characters and ones at the start of words count more.
+fn score(label: &str, query: &str) -> Option<u32> {
+    let label: Vec<char> = label.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = position + label[position..].iter().position(|l| *l == c)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 2;
+        }
+        if found == 0 || !label[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+impl Palette {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) -> Command<Message> {
+        if self.open {
+            self.open = false;
+            return Command::none();
+        }
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+        text_input::focus(input_id())
+    }
+
+    /// The actions matching the query, best first.
+    fn matches(&self, actions: Vec<Action>) -> Vec<Action> {
+        let mut matches: Vec<(u32, Action)> = actions
+            .into_iter()
+            .filter_map(|action| Some((score(&action.label, &self.query)?, action)))
+            .collect();
+        // The sort is stable, so equally good matches keep their order
+        matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        matches
+            .into_iter()
+            .take(MAX_SHOWN)
+            .map(|(_, action)| action)
+            .collect()
+    }
+
+    /// Moves the selection through the matches, or closes the palette.
+    pub fn navigate(&mut self, navigation: Navigation, actions: Vec<Action>) {
+        let count = self.matches(actions).len();
+        match navigation {
+            Navigation::SelectNext if count > 0 => self.selected = (self.selected + 1) % count,
+            Navigation::SelectPrevious if count > 0 => {
+                self.selected = (self.selected + count - 1) % count;
+            }
+            Navigation::Escape => self.open = false,
+            _ => {}
+        }
+    }
+
+    /// The action to run, if one was picked.
+    pub fn update(&mut self, message: PaletteMessage, actions: Vec<Action>) -> Option<Action> {
+        let index = match message {
+            PaletteMessage::QueryChanged(query) => {
+                self.query = query;
+                self.selected = 0;
+                return None;
+            }
+            PaletteMessage::Submit => self.selected,
+            PaletteMessage::Run(index) => index,
+        };
+        let action = self.matches(actions).into_iter().nth(index)?;
+        self.open = false;
+        Some(action)
+    }
+
+    /// Only shown while open.
+    pub fn view(&self, actions: Vec<Action>) -> Option<Element<PaletteMessage>> {
+        if !self.open {
+            return None;
+        }
+        let matches = self.matches(actions);
+        let list = if matches.is_empty() {
+            Column::new().push(Text::new("Nothing matches").size(14))
+        } else {
+            matches.into_iter().enumerate().fold(
+                Column::new().spacing(2),
+                |column, (index, action)| {
+                    column.push(
+                        Button::new(Text::new(action.label))
+                            .width(Length::Fill)
+                            .style(if index == self.selected {
+                                theme::Button::Primary
+                            } else {
+                                text_button()
+                            })
+                            .on_press(PaletteMessage::Run(index)),
+                    )
+                },
+            )
+        };
+        Some(
+            Container::new(
+                Column::new()
+                    .spacing(10)
+                    .push(
+                        TextInput::new("Type a command, e.g. play or settings", &self.query)
+                            .id(input_id())
+                            .on_input(PaletteMessage::QueryChanged)
+                            .on_submit(PaletteMessage::Submit)
+                            .padding(10),
+                    )
+                    .push(list)
+                    .push(
+                        Text::new("↑↓ to select, Enter to run, Esc to close")
+                            .size(12)
+                            .style(theme::Text::Color(iced::Color::from_rgb(0.6, 0.6, 0.6))),
+                    ),
+            )
+            .padding(10)
+            .width(Length::Fill)
+            .style(theme::Container::Box)
+            .into(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_needs_all_characters_in_order() {
+        assert_eq!(score("Play Endless", "xyz"), None);
+        assert_eq!(score("Play Endless", "ep"), None);
+        assert_eq!(score("Play Endless", ""), Some(0));
+        assert_eq!(score("Play Endless", "PLAY"), score("Play Endless", "play"));
+    }
+
+    #[test]
+    fn score_prefers_word_starts_and_runs() {
+        // Both at the start of words
+        assert_eq!(score("Play Endless", "pe"), Some(8));
+        // One run, but in the middle of a word
+        assert_eq!(score("Open settings", "pe"), Some(4));
+        assert_eq!(score("Play Endless", "p e"), score("Play Endless", "pe"));
+        assert!(score("Settings", "set") > score("Reset", "set"));
+    }
+}
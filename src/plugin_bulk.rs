@@ -0,0 +1,308 @@
+//! Installs, updates or removes one plugin in many instances at once, to keep plugins in sync
+//! between instances with their own config dir. The plugin is installed or updated through ESPIM
+//! in the first of the instances, then stored in the plugin pool and linked into the others.
+
+use crate::instance::Instance;
+use crate::notifications::{notify, Level, Notification};
+use crate::plugins_frame::{self, load_plugins};
+use crate::style::text_button;
+use crate::{activity, plugin_metadata, plugin_pool, send_message, Message};
+use anyhow::{Context, Result};
+use iced::widget::{Button, Checkbox, Column, PickList, Row, Text};
+use iced::{Alignment, Command, Element};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkAction {
+    /// Also updates where the plugin is installed already
+    Install,
+    Remove,
+}
+
+#[derive(Debug, Clone)]
+pub enum BulkMessage {
+    PluginSelected(String),
+    AllInstancesToggled(bool),
+    InstanceToggled(String, bool),
+    Apply(BulkAction),
+    Done,
+}
+
+/// Shown on the plugins tab, above the list of plugins.
+#[derive(Debug, Clone)]
+pub struct BulkFrame {
+    plugin: Option<String>,
+    all_instances: bool,
+    selected: BTreeSet<String>,
+    working: bool,
+}
+
+impl Default for BulkFrame {
+    fn default() -> Self {
+        Self {
+            plugin: None,
+            all_instances: true,
+            selected: BTreeSet::new(),
+            working: false,
+        }
+    }
+}
+
+/// Makes sure the latest version is installed in `plugins_dir`, returning its folder in the pool.
+async fn pool_latest(name: &str, plugins_dir: &Path) -> Result<PathBuf> {
+    match espim::retrieve_plugins() {
+        Ok(mut index) => {
+            if let Some(plugin) = index
+                .iter_mut()
+                .find(|p| p.name().eq_ignore_ascii_case(name) && p.is_available())
+            {
+                let installed = plugins_frame::find_in(plugins_dir, name)
+                    .and_then(|folder| plugin_metadata::load(&folder))
+                    .and_then(|meta| meta.version);
+                if installed.as_deref() != plugin.versions().1 {
+                    // Spreading the previous version instead would only look like it worked
+                    plugins_frame::install(plugin, plugins_dir)
+                        .with_context(|| format!("Failed to install the latest {name}"))?;
+                }
+            }
+        }
+        // A plugin installed by hand can still be spread
+        Err(e) => warn!(
+            "Failed to get the plugin index, using the installed copy: {:#}",
+            e
+        ),
+    }
+    let folder = plugins_frame::find_in(plugins_dir, name)
+        .with_context(|| format!("{name} isn't installed, so there's nothing to copy"))?;
+    plugin_pool::add(&folder)
+}
+
+/// Does `action` in each of the plugins folders, returning the instances it failed for.
+async fn apply(
+    action: BulkAction,
+    name: &str,
+    plugins_dirs: BTreeMap<PathBuf, Vec<String>>,
+) -> Result<Vec<String>> {
+    let pooled = match (action, plugins_dirs.keys().next()) {
+        (BulkAction::Install, Some(first)) => Some(pool_latest(name, first).await?),
+        _ => None,
+    };
+    let mut failed = vec![];
+    for (plugins_dir, instances) in plugins_dirs {
+        let result = match &pooled {
+            Some(pooled) => {
+                plugins_frame::replace_with_pooled(name, pooled, &plugins_dir).map(|_| ())
+            }
+            None => plugins_frame::find_in(&plugins_dir, name)
+                .map_or(Ok(()), |folder| plugins_frame::take_out(name, &folder)),
+        };
+        if let Err(e) = result {
+            error!(
+                "Failed to change {} in {}: {:#}",
+                name,
+                plugins_dir.to_string_lossy(),
+                e
+            );
+            failed.extend(instances);
+        }
+    }
+    Ok(failed)
+}
+
+async fn perform_apply(
+    action: BulkAction,
+    name: String,
+    instances: Vec<Instance>,
+    unselected: Vec<Instance>,
+) {
+    let mut plugins_dirs: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+    for instance in &instances {
+        match instance.config_dir() {
+            Some(dir) => plugins_dirs
+                .entry(dir.join("plugins"))
+                .or_default()
+                .push(instance.name.clone()),
+            None => warn!("Failed to get the config dir of {}", instance.name),
+        }
+    }
+    // A plugins folder is shared by all instances using the same config dir, changing it for the
+    // selected ones would change it for the others as well
+    let mut left_out = vec![];
+    for other in &unselected {
+        let Some(dir) = other.config_dir().map(|d| d.join("plugins")) else {
+            continue;
+        };
+        if let Some(shared) = plugins_dirs
+            .keys()
+            .find(|d| plugins_frame::same_dir(d, &dir))
+            .cloned()
+        {
+            let names = plugins_dirs.remove(&shared).unwrap_or_default();
+            warn!(
+                "Leaving out {} since {} uses the same plugins folder",
+                names.join(", "),
+                other.name
+            );
+            left_out.extend(names);
+        }
+    }
+    if !left_out.is_empty() {
+        notify(
+            Notification::new(
+                Level::Warning,
+                format!(
+                    "Left out {}, since instances that aren't selected use the same plugins folder",
+                    left_out.join(", ")
+                ),
+            )
+            .sticky(),
+        );
+    }
+    let names: Vec<String> = instances
+        .into_iter()
+        .map(|i| i.name)
+        .filter(|n| !left_out.contains(n))
+        .collect();
+    if names.is_empty() {
+        return;
+    }
+    let (verb, preposition) = match action {
+        BulkAction::Install => ("Installed", "in"),
+        BulkAction::Remove => ("Removed", "from"),
+    };
+    match apply(action, &name, plugins_dirs).await {
+        Ok(failed) => {
+            let done: Vec<String> = names.into_iter().filter(|n| !failed.contains(n)).collect();
+            if !done.is_empty() {
+                activity::record(match action {
+                    BulkAction::Install => activity::Event::PluginAddedTo {
+                        plugin: name.clone(),
+                        instances: done.clone(),
+                    },
+                    BulkAction::Remove => activity::Event::PluginRemovedFrom {
+                        plugin: name.clone(),
+                        instances: done.clone(),
+                    },
+                });
+            }
+            if failed.is_empty() {
+                notify(Notification::new(
+                    Level::Success,
+                    format!("{verb} {name} {preposition} {} instances", done.len()),
+                ));
+            } else {
+                notify(
+                    Notification::new(
+                        Level::Warning,
+                        format!(
+                            "{verb} {name} {preposition} {} instances, but not {preposition} {}, see the log for details",
+                            done.len(),
+                            failed.join(", ")
+                        ),
+                    )
+                    .sticky(),
+                );
+            }
+        }
+        Err(e) => {
+            error!("Failed to change {} in the instances: {:#}", name, e);
+            notify(Notification::new(
+                Level::Error,
+                format!("Changing {name} in the instances failed, see the log for details"),
+            ));
+        }
+    }
+    send_message(Message::PluginFrameLoaded(load_plugins().await));
+}
+
+impl BulkFrame {
+    pub fn update(
+        &mut self,
+        message: BulkMessage,
+        instances: &BTreeMap<String, Instance>,
+    ) -> Command<Message> {
+        match message {
+            BulkMessage::PluginSelected(name) => self.plugin = Some(name),
+            BulkMessage::AllInstancesToggled(all) => self.all_instances = all,
+            BulkMessage::InstanceToggled(name, true) => {
+                self.selected.insert(name);
+            }
+            BulkMessage::InstanceToggled(name, false) => {
+                self.selected.remove(&name);
+            }
+            BulkMessage::Apply(action) => {
+                let Some(name) = self.plugin.clone() else {
+                    return Command::none();
+                };
+                let (targets, unselected): (Vec<Instance>, Vec<Instance>) = instances
+                    .values()
+                    .cloned()
+                    .partition(|i| self.all_instances || self.selected.contains(&i.name));
+                if targets.is_empty() {
+                    return Command::none();
+                }
+                self.working = true;
+                return Command::perform(perform_apply(action, name, targets, unselected), |()| {
+                    Message::BulkPluginMessage(BulkMessage::Done)
+                });
+            }
+            BulkMessage::Done => self.working = false,
+        }
+        Command::none()
+    }
+
+    pub fn view(&self, plugins: Vec<String>, instances: Vec<String>) -> Element<BulkMessage> {
+        let ready = !self.working
+            && self.plugin.is_some()
+            && (self.all_instances || instances.iter().any(|i| self.selected.contains(i)));
+        let action = |label, action| {
+            Button::new(Text::new(label))
+                .style(text_button())
+                .on_press_maybe(ready.then_some(BulkMessage::Apply(action)))
+        };
+        let controls = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(
+                PickList::new(plugins, self.plugin.clone(), BulkMessage::PluginSelected)
+                    .placeholder("Plugin to keep in sync"),
+            )
+            .push(
+                Checkbox::new("All instances", self.all_instances)
+                    .on_toggle(BulkMessage::AllInstancesToggled),
+            )
+            .push(action(
+                if self.working {
+                    "Working…"
+                } else {
+                    "Install or update in instances"
+                },
+                BulkAction::Install,
+            ))
+            .push(action("Remove from instances", BulkAction::Remove));
+        Column::new()
+            .spacing(5)
+            .padding(iced::Padding {
+                top: 0.0,
+                right: 30.0,
+                bottom: 0.0,
+                left: 30.0,
+            })
+            .push(controls)
+            .push_maybe((!self.all_instances).then(|| {
+                instances
+                    .into_iter()
+                    .fold(Column::new().spacing(3), |column, name| {
+                        let selected = self.selected.contains(&name);
+                        column.push(
+                            Checkbox::new(name.clone(), selected)
+                                .on_toggle(move |on| BulkMessage::InstanceToggled(name.clone(), on))
+                                .size(14)
+                                .text_size(14),
+                        )
+                    })
+            }))
+            .into()
+    }
+}
@@ -0,0 +1,120 @@
+//! A copy of the plugin index on disk, so the plugin list shows up right away and offline. It's
+//! refreshed with conditional requests, which cost next to nothing while the index is unchanged,
+//! and only the plugins whose entries changed are loaded again.
+
+use crate::{get_data_dir, http, network};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+/// What ESPIM reads the plugins from
+const URL: &str = "https://raw.githubusercontent.com/EndlessSkyCommunity/endless-sky-plugins/master/generated/plugins.json";
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// The parts of an entry shown while the index can't be reached.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct IndexEntry {
+    pub name: String,
+    pub version: Option<String>,
+    pub homepage: Option<String>,
+    pub short_description: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CachedIndex {
+    /// Sent back with the next request, which is answered with 304 if nothing changed
+    etag: Option<String>,
+    fetched_at: i64,
+    /// The entries as they were fetched by plugin name, compared as a whole to find changes
+    entries: BTreeMap<String, Value>,
+}
+
+impl CachedIndex {
+    fn path() -> Option<PathBuf> {
+        Some(get_data_dir()?.join("plugin_index.json"))
+    }
+
+    pub fn load() -> Self {
+        Self::path()
+            .filter(|p| p.exists())
+            .and_then(|p| File::open(p).ok())
+            .and_then(|f| match serde_json::from_reader(f) {
+                Ok(index) => Some(index),
+                Err(e) => {
+                    warn!("Failed to deserialize plugin_index.json: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = Self::path().ok_or_else(|| anyhow!("Failed to get app save dir"))?;
+        serde_json::to_writer(File::create(path)?, self)?;
+        Ok(())
+    }
+
+    pub fn entries(&self) -> Vec<IndexEntry> {
+        self.entries
+            .values()
+            .filter_map(|value| serde_json::from_value(value.clone()).ok())
+            .collect()
+    }
+}
+
+/// Fetches the index if it changed since the last fetch, returning the names of the plugins that
+/// were added, changed or removed. None means it's unchanged.
+pub fn refresh() -> Result<Option<Vec<String>>> {
+    let cached = CachedIndex::load();
+    let mut request = http::agent().get(URL);
+    if let Some(etag) = cached.etag.as_ref().filter(|_| !cached.entries.is_empty()) {
+        request = request.set("If-None-Match", etag);
+    }
+    let response = request.call()?;
+    if response.status() == 304 {
+        debug!("The plugin index is unchanged");
+        return Ok(None);
+    }
+    let etag = response.header("ETag").map(String::from);
+    let fetched: Vec<Value> = response.into_json()?;
+    let entries: BTreeMap<String, Value> = fetched
+        .into_iter()
+        .filter_map(|entry| Some((entry.get("name")?.as_str()?.to_string(), entry)))
+        .collect();
+    let changed: Vec<String> = entries
+        .iter()
+        .filter(|(name, entry)| cached.entries.get(*name) != Some(entry))
+        .map(|(name, _)| name)
+        .chain(cached.entries.keys().filter(|n| !entries.contains_key(*n)))
+        .cloned()
+        .collect();
+    info!("{} entries of the plugin index changed", changed.len());
+    CachedIndex {
+        etag,
+        fetched_at: OffsetDateTime::now_utc().unix_timestamp(),
+        entries,
+    }
+    .save()?;
+    Ok(Some(changed))
+}
+
+/// The names of the plugins that changed, if any did.
+pub async fn perform_refresh() -> Option<Vec<String>> {
+    if !network::is_online() {
+        return None;
+    }
+    match refresh() {
+        Ok(changed) => changed.filter(|c| !c.is_empty()),
+        Err(e) => {
+            warn!("Failed to refresh the plugin index: {:#}", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! Plugin manifests list the plugins of an instance by name, version and where to get them, so a
+//! set of plugins can be shared without the plugins themselves. Importing one installs what's
+//! missing from the plugin index into the shared plugins folder or the one of an instance.
+
+use crate::instance::{self, Instance};
+use crate::notifications::{notify, Level, Notification};
+use crate::plugins_frame::{self, load_plugins};
+use crate::style::text_button;
+use crate::{get_data_dir, plugin_metadata, send_message, Message};
+use anyhow::{Context, Result};
+use iced::widget::{Button, PickList, Row, Text, TextInput};
+use iced::{Alignment, Command, Element};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub name: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    /// The plugin's homepage, for getting plugins that aren't in the index
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub plugins: Vec<ManifestEntry>,
+}
+
+fn export(instance: &Instance) -> Result<PathBuf> {
+    let plugins_dir = instance
+        .config_dir()
+        .ok_or_else(|| anyhow!("Failed to get the config dir of {}", instance.name))?
+        .join("plugins");
+    // Without the index the manifest still lists names and versions
+    let index = espim::retrieve_plugins()
+        .map_err(|e| {
+            warn!(
+                "Failed to get the plugin index, sources are left out: {:#}",
+                e
+            )
+        })
+        .unwrap_or_default();
+    let mut plugins: Vec<ManifestEntry> = plugin_metadata::load_installed(&plugins_dir)
+        .into_iter()
+        .map(|meta| ManifestEntry {
+            source: index
+                .iter()
+                .find(|p| p.name().eq_ignore_ascii_case(&meta.name))
+                .and_then(|p| p.homepage()),
+            name: meta.name,
+            version: meta.version,
+        })
+        .collect();
+    plugins.sort_by_key(|p| p.name.to_lowercase());
+    let dir = get_data_dir()
+        .ok_or_else(|| anyhow!("Failed to get app save dir"))?
+        .join("exports");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{} plugins.json", instance.name));
+    serde_json::to_writer_pretty(File::create(&path)?, &Manifest { plugins })?;
+    info!(
+        "Exported the plugins of {} to {}",
+        instance.name,
+        path.to_string_lossy()
+    );
+    Ok(dir)
+}
+
+/// Writes the instance's plugins into the exports folder, then opens that folder.
+pub async fn perform_export(instance: Instance) {
+    match export(&instance) {
+        Ok(dir) => {
+            notify(Notification::new(
+                Level::Success,
+                format!("Exported the plugins of {}", instance.name),
+            ));
+            instance::open_folder(dir).await;
+        }
+        Err(e) => {
+            error!("Failed to export the plugins of {}: {:#}", instance.name, e);
+            notify(Notification::new(
+                Level::Error,
+                "Exporting the plugins failed, see the log for details",
+            ));
+        }
+    }
+}
+
+/// What an import left to do by hand.
+#[derive(Debug, Default)]
+struct Leftovers {
+    /// Not in the plugin index
+    missing: Vec<ManifestEntry>,
+    /// In the index, but installing them failed
+    failed: Vec<String>,
+}
+
+async fn import(path: PathBuf, plugins_dir: &Path) -> Result<Leftovers> {
+    let manifest: Manifest = serde_json::from_reader(
+        File::open(&path).with_context(|| format!("Failed to open {}", path.to_string_lossy()))?,
+    )
+    .context("Failed to read the plugin manifest")?;
+    let installed = plugin_metadata::load_installed(&plugins_dir.to_path_buf());
+    let mut index = espim::retrieve_plugins()?;
+    let mut leftovers = Leftovers::default();
+    for entry in manifest.plugins {
+        if installed
+            .iter()
+            .any(|p| p.name.eq_ignore_ascii_case(&entry.name))
+        {
+            debug!("{} is installed already", entry.name);
+            continue;
+        }
+        let Some(plugin) = index
+            .iter_mut()
+            .find(|p| p.name().eq_ignore_ascii_case(&entry.name) && p.is_available())
+        else {
+            warn!(
+                "{} isn't in the plugin index, get it from {}",
+                entry.name,
+                entry.source.as_deref().unwrap_or("its author")
+            );
+            leftovers.missing.push(entry);
+            continue;
+        };
+        let available = plugin.versions().1.map(String::from);
+        if entry.version.is_some() && entry.version != available {
+            warn!(
+                "The manifest lists {} {}, installing {} instead",
+                entry.name,
+                entry.version.as_deref().unwrap_or_default(),
+                available.as_deref().unwrap_or("the available version")
+            );
+        }
+        info!("Installing {} from the manifest", entry.name);
+        if let Err(e) = plugins_frame::install(plugin, plugins_dir) {
+            error!("Failed to install {}: {:#}", entry.name, e);
+            leftovers.failed.push(entry.name);
+        }
+    }
+    Ok(leftovers)
+}
+
+async fn perform_import(path: PathBuf, plugins_dir: Option<PathBuf>) {
+    let result = match plugins_dir {
+        Some(plugins_dir) => import(path, &plugins_dir).await,
+        None => Err(anyhow!("Failed to get the plugins folder")),
+    };
+    match result {
+        Ok(leftovers) if leftovers.missing.is_empty() && leftovers.failed.is_empty() => {
+            notify(Notification::new(
+                Level::Success,
+                "Installed the plugins of the manifest",
+            ));
+        }
+        Ok(leftovers) => {
+            if !leftovers.missing.is_empty() {
+                let names: Vec<&str> = leftovers.missing.iter().map(|p| p.name.as_str()).collect();
+                notify(
+                    Notification::new(
+                        Level::Warning,
+                        format!(
+                            "Not in the plugin index, see the log for where to get them: {}",
+                            names.join(", ")
+                        ),
+                    )
+                    .sticky(),
+                );
+            }
+            if !leftovers.failed.is_empty() {
+                notify(
+                    Notification::new(
+                        Level::Error,
+                        format!(
+                            "Failed to install {}, see the log for details",
+                            leftovers.failed.join(", ")
+                        ),
+                    )
+                    .sticky(),
+                );
+            }
+        }
+        Err(e) => {
+            error!("Failed to import the plugin manifest: {:#}", e);
+            notify(Notification::new(
+                Level::Error,
+                "Importing the plugin manifest failed, see the log for details",
+            ));
+        }
+    }
+    send_message(Message::PluginFrameLoaded(load_plugins().await));
+}
+
+/// Where a manifest is imported into.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Target {
+    #[default]
+    Shared,
+    Instance(String),
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Shared => write!(f, "Shared plugins folder"),
+            Self::Instance(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ManifestMessage {
+    PathChanged(String),
+    TargetSelected(Target),
+    Import,
+    Imported,
+}
+
+/// Imports a manifest into a plugins folder, shown on the plugins tab.
+#[derive(Debug, Clone, Default)]
+pub struct ManifestFrame {
+    path: String,
+    target: Target,
+    importing: bool,
+}
+
+impl ManifestFrame {
+    pub fn update(
+        &mut self,
+        message: ManifestMessage,
+        instances: &BTreeMap<String, Instance>,
+    ) -> Command<Message> {
+        match message {
+            ManifestMessage::PathChanged(path) => self.path = path,
+            ManifestMessage::TargetSelected(target) => self.target = target,
+            ManifestMessage::Import => {
+                self.importing = true;
+                let path = PathBuf::from(self.path.trim());
+                let plugins_dir = match &self.target {
+                    Target::Shared => plugins_frame::plugins_dir(),
+                    // Like in the view, a removed instance falls back to the shared folder
+                    Target::Instance(name) => instances
+                        .get(name)
+                        .map_or_else(plugins_frame::plugins_dir, |instance| {
+                            instance.config_dir().map(|dir| dir.join("plugins"))
+                        }),
+                };
+                return Command::perform(perform_import(path, plugins_dir), |()| {
+                    Message::ManifestMessage(ManifestMessage::Imported)
+                });
+            }
+            ManifestMessage::Imported => {
+                self.importing = false;
+                self.path.clear();
+            }
+        }
+        Command::none()
+    }
+
+    pub fn view(&self, instances: Vec<String>) -> Element<ManifestMessage> {
+        // An instance that was removed since falls back to the shared folder
+        let target = match &self.target {
+            Target::Instance(name) if !instances.contains(name) => Target::Shared,
+            target => target.clone(),
+        };
+        let targets: Vec<Target> = std::iter::once(Target::Shared)
+            .chain(instances.into_iter().map(Target::Instance))
+            .collect();
+        Row::new()
+            .spacing(10)
+            .padding(iced::Padding {
+                top: 0.0,
+                right: 30.0,
+                bottom: 0.0,
+                left: 30.0,
+            })
+            .align_items(Alignment::Center)
+            .push(
+                TextInput::new("Path of a plugin manifest", &self.path)
+                    .on_input(ManifestMessage::PathChanged)
+                    .padding(10),
+            )
+            .push(PickList::new(
+                targets,
+                Some(target),
+                ManifestMessage::TargetSelected,
+            ))
+            .push(
+                Button::new(Text::new(if self.importing {
+                    "Importing…"
+                } else {
+                    "Import manifest"
+                }))
+                .style(text_button())
+                .on_press_maybe(
+                    (!self.importing && !self.path.trim().is_empty())
+                        .then_some(ManifestMessage::Import),
+                ),
+            )
+            .into()
+    }
+}
@@ -0,0 +1,196 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The subset of a plugin's `plugin.txt` that the launcher cares about.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginMetadata {
+    pub name: String,
+    pub folder_name: String,
+    pub version: Option<String>,
+    pub requires: Vec<String>,
+    pub optional: Vec<String>,
+    pub conflicts: Vec<String>,
+}
+
+/// Splits a line of Endless Sky's data format into tokens.
+/// Tokens are separated by whitespace and may be quoted with `"` or `` ` ``; `#` starts a comment.
+pub fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '#' {
+            break;
+        } else if c == '"' || c == '`' {
+            chars.next();
+            tokens.push(chars.by_ref().take_while(|&n| n != c).collect());
+        } else {
+            let mut token = String::new();
+            while let Some(&n) = chars.peek() {
+                if n.is_whitespace() {
+                    break;
+                }
+                token.push(n);
+                chars.next();
+            }
+            tokens.push(token);
+        }
+    }
+    tokens
+}
+
+pub fn parse(text: &str, folder_name: &str) -> PluginMetadata {
+    let mut meta = PluginMetadata {
+        name: folder_name.to_string(),
+        folder_name: folder_name.to_string(),
+        ..Default::default()
+    };
+    let mut in_dependencies = false;
+    for line in text.lines() {
+        let tokens = tokenize(line);
+        if tokens.is_empty() {
+            continue;
+        }
+        let indented = line.starts_with(char::is_whitespace);
+        if !indented {
+            in_dependencies = tokens[0] == "dependencies";
+            match (tokens[0].as_str(), tokens.get(1)) {
+                ("name", Some(name)) => meta.name.clone_from(name),
+                ("version", Some(version)) => meta.version = Some(version.clone()),
+                _ => {}
+            }
+        } else if in_dependencies {
+            if let Some(value) = tokens.get(1) {
+                match tokens[0].as_str() {
+                    "requires" => meta.requires.push(value.clone()),
+                    "optional" => meta.optional.push(value.clone()),
+                    "conflicts" => meta.conflicts.push(value.clone()),
+                    _ => {}
+                }
+            }
+        }
+    }
+    meta
+}
+
+/// Reads the metadata of a single plugin folder.
+/// Plugins without a `plugin.txt` are still listed, using their folder name.
+pub fn load(plugin_dir: &Path) -> Option<PluginMetadata> {
+    let folder_name = plugin_dir.file_name()?.to_string_lossy().to_string();
+    let text = fs::read_to_string(plugin_dir.join("plugin.txt")).unwrap_or_default();
+    Some(parse(&text, &folder_name))
+}
+
+/// Returns the metadata of all plugins in the given plugins folder.
+pub fn load_installed(plugins_dir: &PathBuf) -> Vec<PluginMetadata> {
+    match fs::read_dir(plugins_dir) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.is_dir())
+            .filter_map(|p| load(&p))
+            .collect(),
+        Err(e) => {
+            debug!(
+                "Failed to read plugins dir {}: {}",
+                plugins_dir.to_string_lossy(),
+                e
+            );
+            vec![]
+        }
+    }
+}
+
+/// Checks a plugin against the other installed plugins,
+/// returning a human-readable description of each problem found.
+pub fn check(plugin: &PluginMetadata, installed: &[PluginMetadata]) -> Vec<String> {
+    let is_installed = |name: &str| installed.iter().any(|p| p.name.eq_ignore_ascii_case(name));
+    let mut problems = vec![];
+    for dependency in &plugin.requires {
+        if !is_installed(dependency) {
+            problems.push(format!("Requires missing plugin '{dependency}'"));
+        }
+    }
+    for conflict in &plugin.conflicts {
+        if is_installed(conflict) {
+            problems.push(format!("Conflicts with installed plugin '{conflict}'"));
+        }
+    }
+    for other in installed {
+        if other.name != plugin.name
+            && other
+                .conflicts
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(&plugin.name))
+        {
+            problems.push(format!(
+                "Installed plugin '{}' conflicts with it",
+                other.name
+            ));
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_handles_quotes_and_comments() {
+        assert_eq!(
+            tokenize("name \"Some Plugin\" `back ticks` # comment"),
+            vec!["name", "Some Plugin", "back ticks"]
+        );
+        assert_eq!(tokenize("\tversion 1.2.3"), vec!["version", "1.2.3"]);
+        assert!(tokenize("# only a comment").is_empty());
+        assert_eq!(tokenize("\"unterminated quote"), vec!["unterminated quote"]);
+    }
+
+    #[test]
+    fn parse_reads_name_version_and_dependencies() {
+        let text = "name \"Better Ships\"\n\
+                    version 2.0\n\
+                    dependencies\n\
+                    \trequires \"Base Pack\"\n\
+                    \toptional Extras\n\
+                    \tconflicts \"Worse Ships\"\n\
+                    description \"requires nothing\"\n\
+                    \trequires \"Not A Dependency\"\n";
+        let meta = parse(text, "better-ships");
+        assert_eq!(
+            meta,
+            PluginMetadata {
+                name: "Better Ships".into(),
+                folder_name: "better-ships".into(),
+                version: Some("2.0".into()),
+                requires: vec!["Base Pack".into()],
+                optional: vec!["Extras".into()],
+                conflicts: vec!["Worse Ships".into()],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_falls_back_to_the_folder_name() {
+        let meta = parse("", "some-folder");
+        assert_eq!(meta.name, "some-folder");
+        assert_eq!(meta.version, None);
+    }
+
+    #[test]
+    fn check_reports_missing_and_conflicting_plugins() {
+        let plugin = parse("name A\ndependencies\n\trequires B\n\tconflicts C\n", "a");
+        let c = parse("name C\n", "c");
+        let d = parse("name D\ndependencies\n\tconflicts a\n", "d");
+        assert_eq!(
+            check(&plugin, &[c, d]),
+            vec![
+                "Requires missing plugin 'B'",
+                "Conflicts with installed plugin 'C'",
+                "Installed plugin 'D' conflicts with it",
+            ]
+        );
+    }
+}
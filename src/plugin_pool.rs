@@ -0,0 +1,356 @@
+use crate::get_data_dir;
+use crate::plugin_metadata::{self, PluginMetadata};
+use anyhow::Result;
+use fs_extra::dir::{copy, CopyOptions};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+/// Marks plugin folders that were assembled from hard links to the pool,
+/// so they can be told apart from plugins the user put there manually.
+/// Holds the pool entry they were linked from.
+const POOL_MARKER: &str = ".eslauncher2-pool";
+/// Inside the pool, holds a folder per plugin with a folder per version in it. Plugins pooled
+/// before versions were kept apart are directly in the pool instead.
+const VERSIONS_DIR: &str = ".versions";
+/// Inside the pool, which plugins folders each entry was linked into
+const LINKS_FILE: &str = "links.json";
+
+/// Serializes access to the links file
+static LINKS: Mutex<()> = Mutex::new(());
+
+type Links = BTreeMap<PathBuf, BTreeSet<PathBuf>>;
+
+pub fn get_pool_dir() -> Option<PathBuf> {
+    let mut dir = get_data_dir()?;
+    dir.push("plugin_pool");
+    Some(dir)
+}
+
+fn pool_dir() -> Result<PathBuf> {
+    get_pool_dir().ok_or_else(|| anyhow!("Failed to get plugin pool dir"))
+}
+
+/// The name of the folder an entry is in, which can hold anything, e.g. `1.0/beta`.
+fn version_key(version: Option<&str>) -> String {
+    let key: String = version
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    // Which would point somewhere else
+    if key.chars().all(|c| c == '.') {
+        "unversioned".into()
+    } else {
+        key
+    }
+}
+
+/// What tells versions apart. Entries are in folders named after their version, so the folder
+/// name is left out.
+fn metadata(plugin_dir: &Path) -> Option<PluginMetadata> {
+    let mut metadata = plugin_metadata::load(plugin_dir)?;
+    metadata.folder_name.clear();
+    Some(metadata)
+}
+
+/// The name of the plugin folder the entry gets linked in as.
+pub fn folder_name(entry: &Path) -> Option<String> {
+    let parent = entry.parent()?;
+    let name = if parent.parent()?.file_name()? == VERSIONS_DIR {
+        parent.file_name()?
+    } else {
+        entry.file_name()?
+    };
+    Some(name.to_string_lossy().to_string())
+}
+
+/// Stores a plugin folder in the pool, unless the same version is already there, returning its
+/// entry. Other versions stay, since other instances may be linked to them. The ones nothing
+/// is linked to anymore are removed.
+pub fn add(plugin_dir: &Path) -> Result<PathBuf> {
+    add_to(&pool_dir()?, plugin_dir)
+}
+
+fn add_to(pool: &Path, plugin_dir: &Path) -> Result<PathBuf> {
+    let name = plugin_dir
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid plugin dir {}", plugin_dir.to_string_lossy()))?;
+    if let (Ok(plugin), Ok(pool)) = (fs::canonicalize(plugin_dir), fs::canonicalize(pool)) {
+        if plugin.starts_with(pool) {
+            return Ok(plugin);
+        }
+    }
+    let pooling = metadata(plugin_dir);
+    let legacy = pool.join(name);
+    if legacy.exists() && metadata(&legacy) == pooling {
+        return Ok(legacy);
+    }
+
+    let versions = pool.join(VERSIONS_DIR).join(name);
+    let key = version_key(pooling.as_ref().and_then(|m| m.version.as_deref()));
+    // Folders with the same version can still differ, e.g. in their dependencies
+    let mut attempt = 1;
+    let entry = loop {
+        let entry = match attempt {
+            1 => versions.join(&key),
+            _ => versions.join(format!("{key}~{attempt}")),
+        };
+        if !entry.exists() {
+            break entry;
+        }
+        if metadata(&entry) == pooling {
+            return Ok(entry);
+        }
+        attempt += 1;
+    };
+    info!(
+        "Adding {} to the plugin pool as {}",
+        plugin_dir.to_string_lossy(),
+        entry.to_string_lossy()
+    );
+    fs::create_dir_all(&entry)?;
+    if let Err(e) = copy(plugin_dir, &entry, &CopyOptions::new().content_only(true)) {
+        let _ = fs::remove_dir_all(&entry);
+        return Err(anyhow!("Failed to copy plugin into pool: {}", e));
+    }
+    prune(pool, &versions, &entry);
+    Ok(entry)
+}
+
+/// The entry that was linked to `plugin_dir`, if it came from the pool.
+fn linked_entry(plugin_dir: &Path) -> Option<PathBuf> {
+    if plugin_dir.symlink_metadata().ok()?.file_type().is_symlink() {
+        return fs::read_link(plugin_dir).ok();
+    }
+    let entry = fs::read_to_string(plugin_dir.join(POOL_MARKER)).ok()?;
+    (!entry.is_empty()).then(|| PathBuf::from(entry))
+}
+
+fn load_links(pool: &Path) -> Links {
+    File::open(pool.join(LINKS_FILE))
+        .ok()
+        .and_then(|f| serde_json::from_reader(f).ok())
+        .unwrap_or_default()
+}
+
+fn save_links(pool: &Path, links: &Links) -> Result<()> {
+    serde_json::to_writer_pretty(File::create(pool.join(LINKS_FILE))?, links)?;
+    Ok(())
+}
+
+/// Removes the entries in `versions` other than `keep` that nothing is linked to anymore.
+fn prune(pool: &Path, versions: &Path, keep: &Path) {
+    let _guard = LINKS.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut links = load_links(pool);
+    let entries = fs::read_dir(versions)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|e| e != keep);
+    for entry in entries {
+        let linked: BTreeSet<PathBuf> = links
+            .remove(&entry)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|dir| linked_entry(dir).as_ref() == Some(&entry))
+            .collect();
+        if !linked.is_empty() {
+            links.insert(entry, linked);
+            continue;
+        }
+        debug!("Removing {} from the plugin pool", entry.to_string_lossy());
+        if let Err(e) = fs::remove_dir_all(&entry) {
+            warn!("Failed to remove {}: {}", entry.to_string_lossy(), e);
+        }
+    }
+    if let Err(e) = save_links(pool, &links) {
+        warn!("Failed to save the plugin pool's links: {:#}", e);
+    }
+}
+
+/// The newest pooled version of the plugin in the folder `name`.
+fn find(pool: &Path, name: &str) -> Option<PathBuf> {
+    let newest = fs::read_dir(pool.join(VERSIONS_DIR).join(name))
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+        .max()
+        .map(|(_, entry)| entry);
+    newest.or_else(|| Some(pool.join(name)).filter(|legacy| legacy.exists()))
+}
+
+/// Makes the newest pooled version of a plugin available in the given plugins folder.
+pub fn link(name: &str, plugins_dir: &Path) -> Result<()> {
+    let pool = pool_dir()?;
+    let entry = find(&pool, name).ok_or_else(|| anyhow!("Plugin {} is not in the pool", name))?;
+    link_entry(&pool, &entry, plugins_dir).map(|_| ())
+}
+
+/// Makes a pooled plugin available in the given plugins folder, returning its folder there.
+/// Uses a symlink where possible and falls back to hard-linking every file.
+pub fn link_pooled(entry: &Path, plugins_dir: &Path) -> Result<PathBuf> {
+    link_entry(&pool_dir()?, entry, plugins_dir)
+}
+
+fn link_entry(pool: &Path, entry: &Path, plugins_dir: &Path) -> Result<PathBuf> {
+    let name = folder_name(entry)
+        .ok_or_else(|| anyhow!("Invalid pool entry {}", entry.to_string_lossy()))?;
+    let target = plugins_dir.join(&name);
+    if !entry.exists() {
+        return Err(anyhow!("Plugin {} is not in the pool", name));
+    }
+    if target.symlink_metadata().is_ok() {
+        debug!("{} already exists, not linking", target.to_string_lossy());
+        return Ok(target);
+    }
+    fs::create_dir_all(plugins_dir)?;
+
+    #[cfg(unix)]
+    let symlinked = std::os::unix::fs::symlink(entry, &target);
+    #[cfg(windows)]
+    let symlinked = std::os::windows::fs::symlink_dir(entry, &target);
+    #[cfg(not(any(unix, windows)))]
+    let symlinked: std::io::Result<()> = Err(std::io::ErrorKind::Unsupported.into());
+
+    if let Err(e) = symlinked {
+        debug!(
+            "Failed to symlink plugin {}: {}, hard-linking instead",
+            name, e
+        );
+        hard_link_tree(entry, &target)?;
+        fs::write(target.join(POOL_MARKER), entry.to_string_lossy().as_bytes())?;
+    }
+
+    let _guard = LINKS.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut links = load_links(pool);
+    links
+        .entry(entry.to_path_buf())
+        .or_default()
+        .insert(target.clone());
+    save_links(pool, &links)?;
+    Ok(target)
+}
+
+fn hard_link_tree(source: &Path, target: &Path) -> Result<()> {
+    fs::create_dir_all(target)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let dest = target.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            hard_link_tree(&entry.path(), &dest)?;
+        } else {
+            fs::hard_link(entry.path(), dest)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if the plugin folder was created by [`link`].
+pub fn is_linked(plugin_dir: &Path) -> bool {
+    plugin_dir
+        .symlink_metadata()
+        .is_ok_and(|m| m.file_type().is_symlink())
+        || plugin_dir.join(POOL_MARKER).exists()
+}
+
+/// Removes a pooled plugin from the given plugins folder, leaving the pool untouched.
+pub fn unlink(plugin_dir: &Path) -> Result<()> {
+    if plugin_dir.symlink_metadata()?.file_type().is_symlink() {
+        // Directory symlinks on windows have to be removed like directories
+        if cfg!(windows) {
+            fs::remove_dir(plugin_dir)?;
+        } else {
+            fs::remove_file(plugin_dir)?;
+        }
+    } else if plugin_dir.join(POOL_MARKER).exists() {
+        fs::remove_dir_all(plugin_dir)?;
+    } else {
+        return Err(anyhow!(
+            "{} is not linked from the plugin pool",
+            plugin_dir.to_string_lossy()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::TempDir;
+
+    fn plugin(dir: &Path, version: &str) -> PathBuf {
+        let plugin = dir.join("source").join("better-ships");
+        fs::create_dir_all(plugin.join("data")).unwrap();
+        fs::write(
+            plugin.join("plugin.txt"),
+            format!("name \"Better Ships\"\nversion {version}\n"),
+        )
+        .unwrap();
+        fs::write(plugin.join("data").join("ships.txt"), version).unwrap();
+        plugin
+    }
+
+    fn linked_version(plugins_dir: &Path) -> String {
+        fs::read_to_string(
+            plugins_dir
+                .join("better-ships")
+                .join("data")
+                .join("ships.txt"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn versions_are_pooled_apart() {
+        let dir = TempDir::new("pool-versions");
+        let pool = dir.path().join("pool");
+        let old = add_to(&pool, &plugin(dir.path(), "1.0")).unwrap();
+        assert_eq!(add_to(&pool, &plugin(dir.path(), "1.0")).unwrap(), old);
+        assert_eq!(folder_name(&old).as_deref(), Some("better-ships"));
+
+        let instance = dir.path().join("instance");
+        link_entry(&pool, &old, &instance).unwrap();
+        let new = add_to(&pool, &plugin(dir.path(), "2.0")).unwrap();
+        assert_ne!(new, old);
+        // Still linked, so still there
+        assert_eq!(linked_version(&instance), "1.0");
+        assert_eq!(find(&pool, "better-ships"), Some(new.clone()));
+
+        let other = dir.path().join("other");
+        link_entry(&pool, &new, &other).unwrap();
+        assert_eq!(linked_version(&other), "2.0");
+        assert_eq!(linked_version(&instance), "1.0");
+    }
+
+    #[test]
+    fn unlinked_versions_are_removed() {
+        let dir = TempDir::new("pool-prune");
+        let pool = dir.path().join("pool");
+        let instance = dir.path().join("instance");
+        let old = add_to(&pool, &plugin(dir.path(), "1.0")).unwrap();
+        link_entry(&pool, &old, &instance).unwrap();
+        unlink(&instance.join("better-ships")).unwrap();
+
+        let new = add_to(&pool, &plugin(dir.path(), "2.0")).unwrap();
+        assert!(!old.exists());
+        assert!(new.exists());
+    }
+
+    #[test]
+    fn version_keys_are_safe_folder_names() {
+        assert_eq!(version_key(Some("1.2.3-beta")), "1.2.3-beta");
+        assert_eq!(version_key(Some("1.0/../x y")), "1.0_.._x_y");
+        assert_eq!(version_key(Some("..")), "unversioned");
+        assert_eq!(version_key(None), "unversioned");
+    }
+}
@@ -0,0 +1,249 @@
+use crate::plugin_metadata::PluginMetadata;
+use crate::style::text_button;
+use crate::{get_data_dir, get_game_data_dir, plugin_metadata, plugin_pool, style, Message};
+use anyhow::{Context, Result};
+use iced::widget::{Button, Column, Row, Space, Text, TextInput};
+use iced::{theme, Alignment, Color, Command, Element, Length};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::path::Path;
+
+/// Named sets of plugins, stored as the plugins' folder names.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PluginProfiles(pub BTreeMap<String, Vec<String>>);
+
+impl PluginProfiles {
+    pub fn load() -> Self {
+        let Some(mut profiles_file) = get_data_dir() else {
+            return Self::default();
+        };
+        profiles_file.push("plugin_profiles.json");
+        if !profiles_file.exists() {
+            return Self::default();
+        }
+
+        match File::open(profiles_file)
+            .with_context(|| "Failed to open plugin_profiles.json")
+            .and_then(|f| {
+                serde_json::from_reader(f)
+                    .with_context(|| "Failed to deserialize plugin_profiles.json")
+            }) {
+            Ok(p) => p,
+            Err(e) => {
+                warn!("{:#?}", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let mut profiles_file =
+            get_data_dir().ok_or_else(|| anyhow!("Failed to get app save dir"))?;
+        profiles_file.push("plugin_profiles.json");
+
+        let file = File::create(profiles_file)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.0.keys().cloned().collect()
+    }
+}
+
+/// The installed plugins that `plugins.txt` in the config dir doesn't disable.
+/// The game enables plugins that aren't listed there.
+fn enabled_plugins(config_dir: &Path) -> Vec<PluginMetadata> {
+    let disabled: Vec<String> = fs::read_to_string(config_dir.join("plugins.txt"))
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| line.starts_with(char::is_whitespace))
+        .map(plugin_metadata::tokenize)
+        .filter(|tokens| tokens.len() >= 2 && tokens[1] == "0")
+        .map(|tokens| tokens[0].clone())
+        .collect();
+    plugin_metadata::load_installed(&config_dir.join("plugins"))
+        .into_iter()
+        .filter(|plugin| !disabled.contains(&plugin.folder_name))
+        .collect()
+}
+
+/// Enables exactly the plugins in the given profile by rewriting `plugins.txt` in the config dir.
+/// If `use_pool` is set, the profile's plugins are linked in from the plugin pool first,
+/// and previously linked plugins that aren't part of the profile are removed.
+pub fn apply(profile_name: &str, config_dir: &Path, use_pool: bool) -> Result<()> {
+    let profiles = PluginProfiles::load();
+    let enabled = profiles
+        .0
+        .get(profile_name)
+        .ok_or_else(|| anyhow!("Plugin profile '{}' doesn't exist", profile_name))?;
+    let plugins_dir = config_dir.join("plugins");
+
+    if use_pool {
+        for plugin in plugin_metadata::load_installed(&plugins_dir) {
+            let plugin_dir = plugins_dir.join(&plugin.folder_name);
+            if !enabled.contains(&plugin.folder_name) && plugin_pool::is_linked(&plugin_dir) {
+                plugin_pool::unlink(&plugin_dir)?;
+            }
+        }
+        for name in enabled {
+            if let Err(e) = plugin_pool::link(name, &plugins_dir) {
+                warn!("Failed to link plugin {}: {:#}", name, e);
+            }
+        }
+    }
+
+    let mut contents = String::from("state\n");
+    for plugin in plugin_metadata::load_installed(&plugins_dir) {
+        let state = u8::from(enabled.contains(&plugin.folder_name));
+        writeln!(contents, "\t\"{}\" {}", plugin.folder_name, state)?;
+    }
+    fs::create_dir_all(config_dir)?;
+    fs::write(config_dir.join("plugins.txt"), contents)?;
+    info!("Applied plugin profile '{}'", profile_name);
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub enum ProfilesMessage {
+    NameChanged(String),
+    SaveCurrent,
+    Delete(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProfilesFrame {
+    pub profiles: PluginProfiles,
+    name: String,
+}
+
+impl ProfilesFrame {
+    pub fn new() -> Self {
+        Self {
+            profiles: PluginProfiles::load(),
+            name: String::new(),
+        }
+    }
+
+    pub fn update(&mut self, message: ProfilesMessage) -> Command<Message> {
+        match message {
+            ProfilesMessage::NameChanged(name) => self.name = name,
+            ProfilesMessage::SaveCurrent => {
+                let config_dir = get_game_data_dir();
+                let enabled = config_dir
+                    .as_deref()
+                    .map(enabled_plugins)
+                    .unwrap_or_default();
+                if let Some(plugins_dir) = config_dir.map(|dir| dir.join("plugins")) {
+                    for plugin in &enabled {
+                        if let Err(e) = plugin_pool::add(&plugins_dir.join(&plugin.folder_name)) {
+                            warn!("Failed to pool plugin {}: {:#}", plugin.name, e);
+                        }
+                    }
+                }
+                let name = self.name.trim().to_string();
+                info!(
+                    "Saving {} enabled plugins as profile '{}'",
+                    enabled.len(),
+                    name
+                );
+                self.profiles
+                    .0
+                    .insert(name, enabled.into_iter().map(|p| p.folder_name).collect());
+                self.name.clear();
+                if let Err(e) = self.profiles.save() {
+                    error!("Failed to save plugin profiles: {:#}", e);
+                }
+            }
+            ProfilesMessage::Delete(name) => {
+                self.profiles.0.remove(&name);
+                if let Err(e) = self.profiles.save() {
+                    error!("Failed to save plugin profiles: {:#}", e);
+                }
+            }
+        }
+        Command::none()
+    }
+
+    pub fn view(&self) -> Element<ProfilesMessage> {
+        let mut save_button =
+            Button::new(Text::new("Save enabled plugins as profile")).style(text_button());
+        if !self.name.trim().is_empty() {
+            save_button = save_button.on_press(ProfilesMessage::SaveCurrent);
+        }
+
+        let list =
+            self.profiles
+                .0
+                .iter()
+                .fold(Column::new().spacing(5), |column, (name, plugins)| {
+                    column.push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .push(Text::new(name))
+                            .push(
+                                Text::new(format!("{} plugins", plugins.len()))
+                                    .size(14)
+                                    .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6))),
+                            )
+                            .push(Space::new(Length::Fill, Length::Shrink))
+                            .push(style::tooltip(
+                                Button::new(style::labeled(style::delete_icon(), "Delete"))
+                                    .style(theme::Button::Destructive)
+                                    .on_press(ProfilesMessage::Delete(name.clone())),
+                                "Delete this profile",
+                            )),
+                    )
+                });
+
+        Column::new()
+            .spacing(10)
+            .padding(iced::Padding {
+                top: 10.0,
+                right: 30.0,
+                bottom: 0.0,
+                left: 30.0,
+            })
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .push(
+                        TextInput::new("Profile name", &self.name)
+                            .on_input(ProfilesMessage::NameChanged)
+                            .padding(10),
+                    )
+                    .push(save_button),
+            )
+            .push(list)
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::TempDir;
+
+    #[test]
+    fn only_enabled_plugins_are_saved() {
+        let dir = TempDir::new("profiles-enabled");
+        for name in ["On", "Off", "Unlisted"] {
+            fs::create_dir_all(dir.path().join("plugins").join(name)).unwrap();
+        }
+        fs::write(
+            dir.path().join("plugins.txt"),
+            "state\n\t\"On\" 1\n\t\"Off\" 0\n\t\"Removed\" 1\n",
+        )
+        .unwrap();
+        let mut enabled: Vec<String> = enabled_plugins(dir.path())
+            .into_iter()
+            .map(|p| p.folder_name)
+            .collect();
+        enabled.sort();
+        assert_eq!(enabled, ["On", "Unlisted"]);
+    }
+}
@@ -1,5 +1,12 @@
+use crate::notifications::{notify, Level, Notification};
+use crate::plugin_index::{self, CachedIndex, IndexEntry};
+use crate::plugin_metadata::PluginMetadata;
 use crate::style::icon_button;
-use crate::{get_data_dir, style, Message};
+use crate::trash::{self, Deleted};
+use crate::{
+    activity, cache, get_data_dir, get_default_game_data_dir, get_game_data_dir, network,
+    plugin_metadata, plugin_pool, quarantine, style, Message,
+};
 use anyhow::Context;
 use anyhow::Result;
 use espim::Plugin as EspimPlugin;
@@ -7,9 +14,9 @@ use iced::widget::{button, image, Column, Container, Image, Row, Scrollable, Spa
 use iced::{alignment, theme, Alignment, Color, Command, Element, Length};
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 lazy_static! {
     static ref CACHE_FILENAME_REGEX: Regex = Regex::new(r"[^\w.-]").unwrap();
@@ -22,15 +29,78 @@ pub enum PluginsFrameState {
 }
 
 impl PluginsFrameState {
+    /// Shows the plugins from the cached index until they're loaded.
     pub fn new() -> (Self, Command<Message>) {
+        let cached = CachedIndex::load().entries();
+        let state = if cached.is_empty() {
+            Self::Loading
+        } else {
+            Self::Ready {
+                plugins: offline_plugins(cached),
+            }
+        };
         (
-            Self::Loading,
+            state,
             Command::perform(load_plugins(), Message::PluginFrameLoaded),
         )
     }
 
+    /// Whether the plugins are only known from the cached index.
+    pub fn is_offline(&self) -> bool {
+        match self {
+            Self::Loading => false,
+            Self::Ready { plugins } => plugins
+                .iter()
+                .any(|p| matches!(p.state, PluginState::Offline { .. })),
+        }
+    }
+
+    /// Replaces the plugins whose index entries changed, keeping the others as they are.
+    pub fn merge(&mut self, changed: &[String], reloaded: Vec<Plugin>) {
+        if let Self::Ready { plugins } = self {
+            plugins.retain(|p| !changed.contains(&p.name));
+            plugins.extend(reloaded);
+            plugins.sort_unstable_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+        }
+        self.check_dependencies();
+    }
+
+    /// The names of the plugins in the list.
+    pub fn names(&self) -> Vec<String> {
+        match self {
+            Self::Loading => vec![],
+            Self::Ready { plugins } => plugins.iter().map(|p| p.name.clone()).collect(),
+        }
+    }
+
     pub fn from(plugins: Vec<Plugin>) -> Self {
-        Self::Ready { plugins }
+        let mut state = Self::Ready { plugins };
+        state.check_dependencies();
+        state
+    }
+
+    /// Re-evaluates dependencies and conflicts of all installed plugins,
+    /// logging a warning for every plugin that newly has problems.
+    pub fn check_dependencies(&mut self) {
+        if let Self::Ready { plugins } = self {
+            let installed = plugins_dir()
+                .map(|dir| plugin_metadata::load_installed(&dir))
+                .unwrap_or_default();
+            for plugin in plugins.iter_mut() {
+                let warnings = match &plugin.state {
+                    PluginState::Idle {
+                        installed: Some(_), ..
+                    } => find_metadata(&installed, &plugin.name)
+                        .map(|meta| plugin_metadata::check(meta, &installed))
+                        .unwrap_or_default(),
+                    _ => vec![],
+                };
+                for warning in warnings.iter().filter(|w| !plugin.warnings.contains(w)) {
+                    warn!("Plug-In {}: {}", plugin.name, warning);
+                }
+                plugin.warnings = warnings;
+            }
+        }
     }
 
     pub fn view(&self) -> Container<Message> {
@@ -99,7 +169,17 @@ pub enum PluginMessage {
 #[derive(Debug, Clone)]
 pub enum PluginState {
     Working,
-    Idle { espim_plugin: Box<EspimPlugin> },
+    Idle {
+        espim_plugin: Box<EspimPlugin>,
+        /// The version in [`plugins_dir`], "unknown" if the plugin doesn't say
+        installed: Option<String>,
+    },
+    /// Known only from the cached index, since the index couldn't be loaded
+    Offline {
+        entry: Box<IndexEntry>,
+        /// The installed version, "unknown" if the plugin doesn't say
+        installed: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -107,13 +187,14 @@ pub struct Plugin {
     pub state: PluginState,
     pub name: String,
     icon: Option<image::Handle>,
+    warnings: Vec<String>,
 }
 
 impl Plugin {
     pub fn update(&mut self, message: PluginMessage) -> Command<Message> {
         match message {
             PluginMessage::Install => {
-                if let PluginState::Idle { espim_plugin } = &mut self.state {
+                if let PluginState::Idle { espim_plugin, .. } = &mut self.state {
                     let name = self.name.clone();
                     let plugin = espim_plugin.clone();
                     self.state = PluginState::Working;
@@ -123,30 +204,32 @@ impl Plugin {
                 }
             }
             PluginMessage::Remove => {
-                if let PluginState::Idle { espim_plugin } = &mut self.state {
-                    espim_plugin.remove().unwrap_or_else(|e| {
-                        error!("Failed to remove Plug-In {}: {}", self.name, e);
-                    });
-                }
-            }
-            PluginMessage::OpenHREF => {
-                if let PluginState::Idle { espim_plugin } = &mut self.state {
-                    if espim_plugin.is_available() {
-                        let url = espim_plugin
-                            .homepage()
-                            .unwrap_or("No homepage available".to_string());
-                        if url.trim().starts_with("http://") || url.trim().starts_with("https://") {
-                            if open::that(url.trim()).is_err() {
-                                error!("URL could not be opened: '{}'", url);
-                            }
-                        } else {
-                            error!("URL validation failed: '{}' is not http(s):// .", url);
-                        }
+                if let PluginState::Idle { espim_plugin, .. } = &mut self.state {
+                    // Held instead of removed through ESPIM where possible, so it can be undone
+                    if let Some(folder) = find_folder(&self.name) {
+                        return Command::perform(
+                            trash::perform_hold(Deleted::Plugin(self.name.clone()), vec![folder]),
+                            |held| held.map_or(Message::Dummy(()), Message::Deleted),
+                        );
+                    }
+                    // ESPIM would remove it from the default plugins folder instead
+                    if installs_in_place() {
+                        espim_plugin.remove().unwrap_or_else(|e| {
+                            error!("Failed to remove Plug-In {}: {}", self.name, e);
+                        });
                     }
                 }
             }
+            PluginMessage::OpenHREF => match &self.state {
+                PluginState::Idle { espim_plugin, .. } if espim_plugin.is_available() => {
+                    open_homepage(espim_plugin.homepage());
+                }
+                PluginState::Offline { entry, .. } => open_homepage(entry.homepage.clone()),
+                _ => {}
+            },
             PluginMessage::WorkFinished(plugin) => {
                 self.state = PluginState::Idle {
+                    installed: installed_version(&plugin),
                     espim_plugin: plugin,
                 };
             }
@@ -175,14 +258,16 @@ impl Plugin {
         let mut controls = Row::new().spacing(10);
 
         match &self.state {
-            PluginState::Idle { espim_plugin } => {
+            PluginState::Idle {
+                espim_plugin,
+                installed,
+            } => {
                 let versions = espim_plugin.versions();
                 titlebox = titlebox
                     .push(
-                        Text::new(if espim_plugin.is_installed() {
-                            format!("Installed: {}", versions.0.unwrap_or("unknown"))
-                        } else {
-                            String::from("Not installed")
+                        Text::new(match installed {
+                            Some(version) => format!("Installed: {version}"),
+                            None => String::from("Not installed"),
                         })
                         .size(14)
                         .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6))),
@@ -205,28 +290,83 @@ impl Plugin {
                     .size(14)
                     .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6))),
                 );
+                for warning in &self.warnings {
+                    infos = infos.push(
+                        Text::new(format!("Warning: {warning}"))
+                            .size(14)
+                            .style(theme::Text::Color(Color::from_rgb(0.8, 0.4, 0.0))),
+                    );
+                }
 
+                let install_label = if installed.is_some() {
+                    "Update"
+                } else {
+                    "Install"
+                };
                 let mut install_button =
-                    button::Button::new(style::update_icon()).style(icon_button()); // TODO: Use other icon here?
+                    button::Button::new(style::labeled(style::update_icon(), install_label))
+                        .style(icon_button()); // TODO: Use other icon here?
                 if espim_plugin.is_available() {
                     install_button = install_button.on_press(PluginMessage::Install);
                 }
 
                 let mut remove_button =
-                    button::Button::new(style::delete_icon()).style(theme::Button::Destructive);
-                if espim_plugin.is_installed() {
+                    button::Button::new(style::labeled(style::delete_icon(), "Remove"))
+                        .style(theme::Button::Destructive);
+                if installed.is_some() {
                     remove_button = remove_button.on_press(PluginMessage::Remove);
                 }
 
-                let mut href_button = button::Button::new(style::href_icon()).style(icon_button()); // TODO: Use other icon here?
+                let mut href_button =
+                    button::Button::new(style::labeled(style::href_icon(), "Homepage"))
+                        .style(icon_button()); // TODO: Use other icon here?
                 if espim_plugin.is_available() {
                     href_button = href_button.on_press(PluginMessage::OpenHREF);
                 }
 
                 controls = controls
-                    .push(href_button)
-                    .push(install_button)
-                    .push(remove_button);
+                    .push(style::tooltip(href_button, "Open the plugin's homepage"))
+                    .push(style::tooltip(install_button, install_label))
+                    .push(style::tooltip(remove_button, "Remove the plugin"));
+            }
+            PluginState::Offline { entry, installed } => {
+                titlebox = titlebox
+                    .push(
+                        Text::new(match installed {
+                            Some(version) => format!("Installed: {version}"),
+                            None => String::from("Not installed"),
+                        })
+                        .size(14)
+                        .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6))),
+                    )
+                    .push(
+                        Text::new(format!(
+                            "Available: {} (offline copy of the index)",
+                            entry.version.as_deref().unwrap_or("unknown")
+                        ))
+                        .size(14)
+                        .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6))),
+                    );
+                infos = infos.push(Space::with_height(5)).push(
+                    Text::new(
+                        entry
+                            .short_description
+                            .clone()
+                            .unwrap_or("Not available".to_string()),
+                    )
+                    .size(14)
+                    .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6))),
+                );
+                let href_button =
+                    button::Button::new(style::labeled(style::href_icon(), "Homepage"))
+                        .style(icon_button())
+                        .on_press_maybe(
+                            entry.homepage.is_some().then_some(PluginMessage::OpenHREF),
+                        );
+                controls = controls.push(style::tooltip(
+                    href_button,
+                    "Open the plugin's homepage. Installing needs the plugin index, which couldn't be loaded",
+                ));
             }
             PluginState::Working => {
                 infos = infos.push(
@@ -246,25 +386,93 @@ impl Plugin {
     }
 }
 
+fn open_homepage(url: Option<String>) {
+    let url = url.unwrap_or("No homepage available".to_string());
+    if url.trim().starts_with("http://") || url.trim().starts_with("https://") {
+        if open::that(url.trim()).is_err() {
+            error!("URL could not be opened: '{}'", url);
+        }
+    } else {
+        error!("URL validation failed: '{}' is not http(s):// .", url);
+    }
+}
+
+fn from_espim(p: EspimPlugin) -> Plugin {
+    let name = String::from(p.name());
+    let icon = load_icon_cached(&p)
+        .map(image::Handle::from_memory)
+        .map_err(|e| debug!("failed to fetch icon: {}", e))
+        .ok();
+    Plugin {
+        state: PluginState::Idle {
+            installed: installed_version(&p),
+            espim_plugin: Box::new(p),
+        },
+        name,
+        icon,
+        warnings: vec![],
+    }
+}
+
+/// The plugins as the cached index lists them, with the icons that are cached for them.
+fn offline_plugins(entries: Vec<IndexEntry>) -> Vec<Plugin> {
+    let installed = plugins_dir()
+        .map(|dir| plugin_metadata::load_installed(&dir))
+        .unwrap_or_default();
+    let mut plugins: Vec<Plugin> = entries
+        .into_iter()
+        .map(|entry| {
+            let installed = find_metadata(&installed, &entry.name)
+                .map(|meta| meta.version.clone().unwrap_or("unknown".into()));
+            let icon = entry
+                .version
+                .as_ref()
+                .and_then(|version| {
+                    icon_cache_file(&entry.name, installed.as_ref().unwrap_or(version)).ok()
+                })
+                .filter(|f| f.is_file())
+                .map(image::Handle::from_path);
+            Plugin {
+                name: entry.name.clone(),
+                state: PluginState::Offline {
+                    entry: Box::new(entry),
+                    installed,
+                },
+                icon,
+                warnings: vec![],
+            }
+        })
+        .collect();
+    plugins.sort_unstable_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    plugins
+}
+
+/// Loads the plugins with the given names again, e.g. after their index entries changed.
+pub async fn load_changed(changed: Vec<String>) -> (Vec<String>, Vec<Plugin>) {
+    let plugins = match espim::retrieve_plugins() {
+        Ok(retrieved) => retrieved
+            .into_iter()
+            .filter(|p| changed.iter().any(|c| c == p.name()))
+            .map(from_espim)
+            .collect(),
+        Err(e) => {
+            warn!("Failed to reload changed plugins: {:#}", e);
+            return (vec![], vec![]);
+        }
+    };
+    (changed, plugins)
+}
+
 pub async fn load_plugins() -> Vec<Plugin> {
+    // Keeps the cached index current, which the list falls back to when this fails next time
+    if network::is_online() {
+        if let Err(e) = plugin_index::refresh() {
+            warn!("Failed to refresh the plugin index: {:#}", e);
+        }
+    }
     let mut plugins = vec![];
     match espim::retrieve_plugins() {
-        Ok(retrieved) => {
-            for p in retrieved {
-                let name = String::from(p.name());
-                let icon = load_icon_cached(&p)
-                    .map(image::Handle::from_memory)
-                    .map_err(|e| debug!("failed to fetch icon: {}", e))
-                    .ok();
-                plugins.push(Plugin {
-                    state: PluginState::Idle {
-                        espim_plugin: Box::new(p),
-                    },
-                    name,
-                    icon,
-                });
-            }
-        }
+        Ok(retrieved) => plugins.extend(retrieved.into_iter().map(from_espim)),
         Err(e) => {
             error!(
                 "Failed to initialize ESPIM, Plug-Ins will be unavailable: {:#}",
@@ -276,14 +484,86 @@ pub async fn load_plugins() -> Vec<Plugin> {
     plugins
 }
 
+fn find_metadata<'a>(installed: &'a [PluginMetadata], name: &str) -> Option<&'a PluginMetadata> {
+    installed.iter().find(|p| p.name.eq_ignore_ascii_case(name))
+}
+
+/// Where an installed plugin lives, going by the name in its metadata.
+pub fn find_folder(name: &str) -> Option<PathBuf> {
+    find_in(&plugins_dir()?, name)
+}
+
+/// The plugins folder of the games the launcher starts, which is the active profile's or the
+/// portable one if there is one.
+pub fn plugins_dir() -> Option<PathBuf> {
+    Some(get_game_data_dir()?.join("plugins"))
+}
+
+pub fn same_dir(a: &Path, b: &Path) -> bool {
+    a == b || fs::canonicalize(a).is_ok_and(|a| fs::canonicalize(b).is_ok_and(|b| a == b))
+}
+
+/// Whether ESPIM installs straight into [`plugins_dir`].
+fn installs_in_place() -> bool {
+    match (espim_dir(), plugins_dir()) {
+        (Some(espim_dir), Some(plugins_dir)) => same_dir(&espim_dir, &plugins_dir),
+        _ => true,
+    }
+}
+
+/// The version installed in [`plugins_dir`], "unknown" if the plugin doesn't say.
+pub fn installed_version(plugin: &EspimPlugin) -> Option<String> {
+    if installs_in_place() {
+        return plugin
+            .is_installed()
+            .then(|| plugin.versions().0.unwrap_or("unknown").to_string());
+    }
+    let folder = find_folder(plugin.name())?;
+    let meta = plugin_metadata::load(&folder)?;
+    Some(meta.version.unwrap_or("unknown".into()))
+}
+
+/// Unlinks a plugin that came from the pool, and moves one that didn't into the trash.
+pub fn take_out(name: &str, folder: &Path) -> Result<()> {
+    if plugin_pool::is_linked(folder) {
+        plugin_pool::unlink(folder)
+    } else {
+        trash::hold(Deleted::Plugin(name.into()), vec![folder.to_path_buf()]).map(|_| ())
+    }
+}
+
+/// Links the pooled plugin into `plugins_dir` in place of the version there, returning its folder.
+pub fn replace_with_pooled(name: &str, pooled: &Path, plugins_dir: &Path) -> Result<PathBuf> {
+    if let Some(existing) = find_in(plugins_dir, name) {
+        take_out(name, &existing)?;
+    }
+    plugin_pool::link_pooled(pooled, plugins_dir)
+}
+
+/// The folder of a plugin in `plugins_dir`, going by the name in its metadata.
+pub fn find_in(plugins_dir: &Path, name: &str) -> Option<PathBuf> {
+    let installed = plugin_metadata::load_installed(&plugins_dir.to_path_buf());
+    find_metadata(&installed, name).map(|meta| plugins_dir.join(&meta.folder_name))
+}
+
+/// The plugins folder ESPIM installs into, which is the game's default one. It can't be pointed
+/// anywhere else, e.g. at the folder of a profile or a portable install.
+pub fn espim_dir() -> Option<PathBuf> {
+    Some(get_default_game_data_dir()?.join("plugins"))
+}
+
 fn get_cache_file(p: &EspimPlugin) -> Result<PathBuf> {
+    let version = p.versions().0.unwrap_or_else(|| p.versions().1.unwrap());
+    icon_cache_file(p.name(), version)
+}
+
+fn icon_cache_file(name: &str, version: &str) -> Result<PathBuf> {
     let cache_dir = get_data_dir().unwrap().join("icons");
     if !(cache_dir.exists()) {
         std::fs::create_dir(&cache_dir).with_context(|| "Failed to create icon cache")?;
     }
 
-    let version = p.versions().0.unwrap_or_else(|| p.versions().1.unwrap());
-    let desired = format!("{}-{}", p.name(), version);
+    let desired = format!("{name}-{version}");
     let filename = CACHE_FILENAME_REGEX.replace_all(&desired, "_");
     Ok(cache_dir.join(&*filename))
 }
@@ -297,6 +577,7 @@ fn load_icon_cached(p: &EspimPlugin) -> Result<Vec<u8>> {
 
     let cache_file = get_cache_file(p)?;
     if cache_file.exists() && cache_file.is_file() && !p.is_installed() {
+        cache::touch(&cache_file);
         let mut bytes = vec![];
         File::open(cache_file)?.read_to_end(&mut bytes)?;
         Ok(bytes)
@@ -309,6 +590,94 @@ fn load_icon_cached(p: &EspimPlugin) -> Result<Vec<u8>> {
     }
 }
 
+/// Moves ESPIM's copy of a plugin out of the way while one is installed for another plugins
+/// folder, so the default plugins folder is left as it was.
+fn set_aside(folder: &Path) -> Result<PathBuf> {
+    let name = folder
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid plugin dir {}", folder.to_string_lossy()))?;
+    let aside = get_data_dir()
+        .ok_or_else(|| anyhow!("Failed to get app save dir"))?
+        .join("plugins_aside")
+        .join(name);
+    if aside.exists() {
+        fs::remove_dir_all(&aside)?;
+    }
+    fs::create_dir_all(&aside)?;
+    trash::move_dir(folder, &aside)?;
+    Ok(aside)
+}
+
+/// Checks the dependencies and conflicts of a downloaded plugin against the plugins in
+/// `plugins_dir`, before it's linked in there, and tells the user about any problems.
+fn warn_about_dependencies(folder: &Path, plugins_dir: &Path) {
+    let Some(meta) = plugin_metadata::load(folder) else {
+        return;
+    };
+    let others: Vec<PluginMetadata> = plugin_metadata::load_installed(&plugins_dir.to_path_buf())
+        .into_iter()
+        .filter(|p| !p.name.eq_ignore_ascii_case(&meta.name))
+        .collect();
+    let problems = plugin_metadata::check(&meta, &others);
+    if problems.is_empty() {
+        return;
+    }
+    for problem in &problems {
+        warn!("Plug-In {}: {}", meta.name, problem);
+    }
+    notify(
+        Notification::new(
+            Level::Warning,
+            format!("{}: {}", meta.name, problems.join(", ")),
+        )
+        .sticky(),
+    );
+}
+
+/// Installs or updates a plugin through ESPIM and makes it available in `plugins_dir`, returning
+/// its folder there. The download is kept once in the plugin pool and linked from there. ESPIM
+/// only installs into [`espim_dir`], so for other plugins folders it's put back as it was.
+pub fn install(plugin: &mut EspimPlugin, plugins_dir: &Path) -> Result<PathBuf> {
+    let name = plugin.name().to_string();
+    let espim_dir = espim_dir().ok_or_else(|| anyhow!("Failed to get ESPIM's plugins dir"))?;
+    let in_place = same_dir(&espim_dir, plugins_dir);
+    let existing = find_in(&espim_dir, &name);
+    let aside = match existing.as_ref().filter(|_| !in_place) {
+        Some(folder) => Some((set_aside(folder)?, folder.clone())),
+        None => None,
+    };
+    let existing = existing.filter(|_| in_place);
+    let result = quarantine::stage(&name, &espim_dir, existing.clone())
+        .and_then(|staged| {
+            // ESPIM would write through the link into the pool, which others link to as well
+            if let Some(folder) = existing.filter(|f| plugin_pool::is_linked(f)) {
+                plugin_pool::unlink(&folder)?;
+            }
+            staged.finish(plugin.download())
+        })
+        .and_then(|()| {
+            find_in(&espim_dir, &name)
+                .ok_or_else(|| anyhow!("Failed to find {} after installing it", name))
+        })
+        .and_then(|folder| {
+            warn_about_dependencies(&folder, plugins_dir);
+            let pooled = plugin_pool::add(&folder)?;
+            fs::remove_dir_all(&folder)?;
+            replace_with_pooled(&name, &pooled, plugins_dir)
+        });
+    if let Some((aside, folder)) = aside {
+        if let Err(e) = trash::move_dir(&aside, &folder) {
+            error!(
+                "Failed to put {} back from {}: {:#}",
+                folder.to_string_lossy(),
+                aside.to_string_lossy(),
+                e
+            );
+        }
+    }
+    result
+}
+
 pub async fn perform_install(mut plugin: EspimPlugin) -> EspimPlugin {
     if plugin.is_installed() {
         match get_cache_file(&plugin) {
@@ -323,8 +692,33 @@ pub async fn perform_install(mut plugin: EspimPlugin) -> EspimPlugin {
         };
     }
 
-    if let Err(e) = plugin.download() {
-        error!("Install failed: {:#}", e);
+    let name = plugin.name().to_string();
+    let previous = installed_version(&plugin).map(|v| Some(v).filter(|v| v != "unknown"));
+    let result = plugins_dir()
+        .ok_or_else(|| anyhow!("Failed to get the game's data dir"))
+        .and_then(|dir| install(&mut plugin, &dir));
+    match result {
+        Ok(_) => {
+            let version = plugin.versions().1.map(String::from);
+            activity::record(match previous {
+                Some(from) => activity::Event::PluginUpdated {
+                    plugin: name,
+                    from,
+                    to: version,
+                },
+                None => activity::Event::PluginInstalled {
+                    plugin: name,
+                    version,
+                },
+            });
+        }
+        Err(e) => {
+            error!("Install failed: {:#}", e);
+            notify(Notification::new(
+                Level::Error,
+                format!("Installing {name} failed, see the log for details"),
+            ));
+        }
     }
     plugin
 }
@@ -0,0 +1,90 @@
+//! Forks and total conversions, so they can be installed without knowing which repository
+//! publishes their releases. Only the game's own releases ship with the launcher, since a fork
+//! is only listed once it's known to publish builds the launcher can install. Others are added
+//! to presets.json in the data dir, by hand or with "Save as preset" in the install form, and
+//! that file can be shared with other players.
+
+use crate::get_data_dir;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+/// The curated list that ships with the launcher
+const BUILT_IN: &str = include_str!("../assets/presets.json");
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    /// The GitHub repository publishing the releases, as `owner/repo`.
+    /// Unless `asset_pattern` is set, their assets have to be named like the game's for the right one to be picked.
+    pub repo: String,
+    #[serde(default)]
+    pub description: String,
+    /// For forks whose assets aren't named like the game's, see `InstanceSource::asset_pattern`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset_pattern: Option<String>,
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+fn custom_path() -> Option<PathBuf> {
+    Some(get_data_dir()?.join("presets.json"))
+}
+
+/// Whether `repo` looks like `owner/repo`.
+pub fn is_valid_repo(repo: &str) -> bool {
+    let mut parts = repo.split('/');
+    let valid = |part: Option<&str>| {
+        part.is_some_and(|p| {
+            !p.is_empty()
+                && p.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+        })
+    };
+    valid(parts.next()) && valid(parts.next()) && parts.next().is_none()
+}
+
+/// Adds a preset to presets.json in the data dir, replacing one of the same name.
+pub fn save(preset: Preset) -> Result<()> {
+    let path = custom_path().ok_or_else(|| anyhow!("Failed to get data dir"))?;
+    let mut custom: Vec<Preset> = if path.exists() {
+        serde_json::from_reader(File::open(&path)?)?
+    } else {
+        vec![]
+    };
+    custom.retain(|p| p.name != preset.name);
+    info!("Saving the preset {} for {}", preset.name, preset.repo);
+    custom.push(preset);
+    fs::write(&path, serde_json::to_vec_pretty(&custom)?)?;
+    Ok(())
+}
+
+/// The built-in presets, followed by the ones from presets.json in the data dir.
+/// Those replace built-in presets of the same name.
+pub fn load() -> Vec<Preset> {
+    let mut presets: Vec<Preset> = serde_json::from_str(BUILT_IN).unwrap_or_else(|e| {
+        error!("Failed to deserialize the built-in presets: {}", e);
+        vec![]
+    });
+    let Some(path) = custom_path().filter(|p| p.exists()) else {
+        return presets;
+    };
+    match File::open(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|f| serde_json::from_reader::<_, Vec<Preset>>(f).map_err(anyhow::Error::from))
+    {
+        Ok(custom) => {
+            for preset in custom {
+                presets.retain(|p| p.name != preset.name);
+                presets.push(preset);
+            }
+        }
+        Err(e) => warn!("Failed to load {}: {:#}", path.to_string_lossy(), e),
+    }
+    presets
+}
@@ -0,0 +1,209 @@
+//! Named launcher profiles, each with its own settings, instances, game data and GitHub token,
+//! so people sharing a computer keep their things apart. A profile is picked with
+//! `--profile <name>`, or in a small window at startup once there are any besides the default.
+
+use crate::get_base_data_dir;
+use iced::widget::{Button, Column, Container, Row, Scrollable, Text, TextInput};
+use iced::{executor, window, Alignment, Application, Command, Element, Length, Theme};
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::OnceLock;
+
+pub const FLAG: &str = "--profile";
+/// The profile that uses the data dir itself, as it was before there were profiles
+pub const DEFAULT: &str = "Default";
+
+/// Whether `name` can be used as a folder name everywhere.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.trim() == name
+        && !name.eq_ignore_ascii_case(DEFAULT)
+        && !name.starts_with('.')
+        && !name.contains(['/', '\\', ':', '*', '?', '"', '<', '>', '|'])
+}
+
+/// The profile given on the command line or in `ESLAUNCHER2_PROFILE`, which may be [`DEFAULT`].
+fn requested() -> Option<String> {
+    static PROFILE: OnceLock<Option<String>> = OnceLock::new();
+    PROFILE
+        .get_or_init(|| {
+            let mut args = std::env::args();
+            while let Some(arg) = args.next() {
+                if arg == FLAG {
+                    return args.next();
+                }
+                if let Some(name) = arg.strip_prefix("--profile=") {
+                    return Some(name.into());
+                }
+            }
+            std::env::var("ESLAUNCHER2_PROFILE")
+                .ok()
+                .filter(|name| !name.is_empty())
+        })
+        .clone()
+}
+
+/// Fails if the requested profile can't be used, instead of quietly starting with the default
+/// one. Checked at startup, so [`current`] can rely on the name being valid.
+pub fn check_requested() -> Result<(), String> {
+    match requested() {
+        Some(name) if !name.eq_ignore_ascii_case(DEFAULT) && !is_valid_name(&name) => {
+            Err(format!("'{name}' can't be used as a profile name"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The profile in use, None for the default one.
+pub fn current() -> Option<String> {
+    requested().filter(|name| is_valid_name(name))
+}
+
+/// Where profiles other than the default one keep their data.
+pub fn dir(name: &str) -> Option<PathBuf> {
+    Some(get_base_data_dir()?.join("profiles").join(name))
+}
+
+/// The profiles besides the default one, sorted by name.
+pub fn list() -> Vec<String> {
+    let Some(dir) = get_base_data_dir().map(|d| d.join("profiles")) else {
+        return vec![];
+    };
+    let mut profiles: Vec<String> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|name| is_valid_name(name))
+        .collect();
+    profiles.sort_by_key(|name| name.to_lowercase());
+    profiles
+}
+
+/// Whether to ask which profile to use before starting.
+pub fn should_pick() -> bool {
+    requested().is_none() && !list().is_empty()
+}
+
+/// Starts the launcher again with the given profile, keeping the other arguments.
+pub fn relaunch(profile: &str) -> std::io::Result<()> {
+    let mut args = vec![];
+    let mut original = std::env::args().skip(1);
+    while let Some(arg) = original.next() {
+        if arg == FLAG {
+            original.next();
+        } else if !arg.starts_with("--profile=") {
+            args.push(arg);
+        }
+    }
+    process::Command::new(std::env::current_exe()?)
+        .args(args)
+        .arg(FLAG)
+        .arg(profile)
+        .spawn()?;
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub enum PickerMessage {
+    Pick(String),
+    NameChanged(String),
+    Create,
+}
+
+/// Asks which profile to start with, then starts the launcher with it.
+pub struct ProfilePicker {
+    profiles: Vec<String>,
+    new_name: String,
+    error: Option<String>,
+}
+
+impl Application for ProfilePicker {
+    type Executor = executor::Default;
+    type Message = PickerMessage;
+    type Theme = Theme;
+    type Flags = ();
+
+    fn new(_flags: ()) -> (Self, Command<PickerMessage>) {
+        (
+            Self {
+                profiles: list(),
+                new_name: String::new(),
+                error: None,
+            },
+            Command::none(),
+        )
+    }
+
+    fn title(&self) -> String {
+        "ESLauncher2".into()
+    }
+
+    fn update(&mut self, message: PickerMessage) -> Command<PickerMessage> {
+        match message {
+            PickerMessage::Pick(profile) => match relaunch(&profile) {
+                Ok(()) => return window::close(window::Id::MAIN),
+                Err(e) => self.error = Some(format!("Failed to start the launcher: {e}")),
+            },
+            PickerMessage::NameChanged(name) => {
+                self.new_name = name;
+                self.error = None;
+            }
+            PickerMessage::Create => {
+                let name = self.new_name.trim().to_string();
+                if !is_valid_name(&name) {
+                    self.error = Some(format!("{name} can't be used as a profile name"));
+                    return Command::none();
+                }
+                let result = dir(&name)
+                    .ok_or_else(|| anyhow!("Failed to get app save dir"))
+                    .and_then(|dir| Ok(fs::create_dir_all(dir)?));
+                match result {
+                    Ok(()) => return self.update(PickerMessage::Pick(name)),
+                    Err(e) => self.error = Some(format!("Failed to create {name}: {e}")),
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view(&self) -> Element<PickerMessage> {
+        let profiles = std::iter::once(DEFAULT.to_string())
+            .chain(self.profiles.iter().cloned())
+            .fold(Column::new().spacing(5), |column, profile| {
+                column.push(
+                    Button::new(Text::new(profile.clone()))
+                        .width(Length::Fill)
+                        .on_press(PickerMessage::Pick(profile)),
+                )
+            });
+        let name = self.new_name.trim();
+        let can_create = is_valid_name(name) && !self.profiles.iter().any(|p| p == name);
+        Container::new(
+            Column::new()
+                .spacing(10)
+                .push(Text::new("Who's playing?").size(24))
+                .push(Scrollable::new(profiles).height(Length::Fill))
+                .push(
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Alignment::Center)
+                        .push(
+                            TextInput::new("New profile", &self.new_name)
+                                .on_input(PickerMessage::NameChanged)
+                                .on_submit(PickerMessage::Create)
+                                .padding(5),
+                        )
+                        .push(
+                            Button::new(Text::new("Create"))
+                                .on_press_maybe(can_create.then_some(PickerMessage::Create)),
+                        ),
+                )
+                .push_maybe(self.error.as_ref().map(Text::new)),
+        )
+        .padding(20)
+        .into()
+    }
+}
@@ -0,0 +1,158 @@
+//! Guards plugin installs against leaving a half-extracted plugin behind. The previous version is
+//! kept aside while ESPIM downloads and extracts the new one, and if that fails or the result
+//! doesn't look like a plugin, it's moved into the quarantine folder and the previous version
+//! put back.
+
+use crate::trash::move_dir;
+use crate::{get_data_dir, plugin_metadata};
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use time::{format_description, OffsetDateTime};
+
+/// A plugin has at least one of these, everything else is optional
+const CONTENT_DIRS: [&str; 4] = ["data", "images", "sounds", "shaders"];
+
+fn quarantine_dir() -> Option<PathBuf> {
+    Some(get_data_dir()?.join("quarantine"))
+}
+
+fn folders(plugins_dir: &Path) -> BTreeSet<PathBuf> {
+    fs::read_dir(plugins_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect()
+}
+
+/// Fails if `plugin_dir` is missing content the game needs, or has data files it can't read.
+fn validate(plugin_dir: &Path) -> Result<()> {
+    if !CONTENT_DIRS.iter().any(|d| plugin_dir.join(d).is_dir()) {
+        return Err(anyhow!(
+            "{} has none of {}",
+            plugin_dir.to_string_lossy(),
+            CONTENT_DIRS.join(", ")
+        ));
+    }
+    let data_dir = plugin_dir.join("data");
+    for file in fs_extra::dir::get_dir_content(&data_dir)
+        .map(|c| c.files)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|f| f.ends_with(".txt"))
+    {
+        // The game only reads UTF-8, which truncated files often aren't anymore
+        fs::read_to_string(&file).with_context(|| format!("Failed to read {file}"))?;
+    }
+    Ok(())
+}
+
+/// A plugin install in progress, see [`stage`].
+#[derive(Debug)]
+pub struct Staged {
+    name: String,
+    plugins_dir: PathBuf,
+    /// The plugin folders before the install
+    before: BTreeSet<PathBuf>,
+    /// Where the previous version was installed, and the copy of it that's kept aside
+    previous: Option<(PathBuf, PathBuf)>,
+}
+
+/// Keeps a copy of the installed version of the plugin in `folder`, if any, before installing
+/// another one into `plugins_dir`.
+pub fn stage(name: &str, plugins_dir: &Path, folder: Option<PathBuf>) -> Result<Staged> {
+    let mut staged = Staged {
+        name: name.to_string(),
+        plugins_dir: plugins_dir.to_path_buf(),
+        before: folders(plugins_dir),
+        previous: None,
+    };
+    if let Some(folder) = folder.filter(|f| f.exists()) {
+        let kept = quarantine_dir()
+            .ok_or_else(|| anyhow!("Failed to get the quarantine dir"))?
+            .join(format!("{name}.previous"));
+        if kept.exists() {
+            fs::remove_dir_all(&kept)?;
+        }
+        fs::create_dir_all(&kept)?;
+        let options = fs_extra::dir::CopyOptions::new().content_only(true);
+        fs_extra::dir::copy(&folder, &kept, &options)
+            .with_context(|| format!("Failed to keep a copy of {name}"))?;
+        staged.previous = Some((folder, kept));
+    }
+    Ok(staged)
+}
+
+impl Staged {
+    /// The folders the install wrote to: new ones of this plugin, and the one the previous version
+    /// was in. New folders of other plugins belong to installs running at the same time.
+    fn touched(&self) -> Vec<PathBuf> {
+        let mut touched: Vec<PathBuf> = folders(&self.plugins_dir)
+            .difference(&self.before)
+            .filter(|dir| {
+                plugin_metadata::load(dir).is_some_and(|meta| {
+                    meta.name.eq_ignore_ascii_case(&self.name)
+                        || meta.folder_name.eq_ignore_ascii_case(&self.name)
+                })
+            })
+            .cloned()
+            .collect();
+        if let Some((folder, _)) = &self.previous {
+            if folder.exists() && !touched.contains(folder) {
+                touched.push(folder.clone());
+            }
+        }
+        touched
+    }
+
+    /// Checks the outcome of the install, quarantining what it left behind if it failed.
+    pub fn finish(self, result: Result<()>) -> Result<()> {
+        let touched = self.touched();
+        let result = result.and_then(|()| {
+            if touched.is_empty() {
+                return Err(anyhow!("Nothing was installed"));
+            }
+            touched.iter().try_for_each(|dir| validate(dir))
+        });
+        match result {
+            Ok(()) => {
+                if let Some((_, kept)) = &self.previous {
+                    fs::remove_dir_all(kept)?;
+                }
+                Ok(())
+            }
+            Err(e) => {
+                self.quarantine(&touched)?;
+                Err(e)
+            }
+        }
+    }
+
+    fn quarantine(&self, touched: &[PathBuf]) -> Result<()> {
+        let timestamp = OffsetDateTime::now_utc().format(&format_description::parse(
+            "[year]-[month]-[day] [hour]-[minute]-[second]",
+        )?)?;
+        let dir = quarantine_dir()
+            .ok_or_else(|| anyhow!("Failed to get the quarantine dir"))?
+            .join(format!("{} {timestamp}", self.name));
+        fs::create_dir_all(&dir)?;
+        for folder in touched {
+            if let Some(folder_name) = folder.file_name() {
+                move_dir(folder, &dir.join(folder_name))?;
+            }
+        }
+        warn!(
+            "Moved the failed install of {} to {}",
+            self.name,
+            dir.to_string_lossy()
+        );
+        if let Some((folder, kept)) = &self.previous {
+            move_dir(kept, folder)?;
+            info!("Put back the previous version of {}", self.name);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+//! Keeps the launcher usable when its data dir can't be written to, e.g. on locked-down machines
+//! or live systems. The data is copied into a new private temporary folder and kept there for the
+//! session instead, and if not even that can be written to, saving is skipped instead of failing
+//! each time.
+
+use crate::instance_store::{DATABASE_FILE, PER_INSTANCE_DIR};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, io, process};
+
+static STATE: OnceLock<State> = OnceLock::new();
+/// Created and removed again to find out whether a folder can be written to
+const PROBE_FILE: &str = ".write-test";
+/// Subfolders of the data dir that are copied along, since the session needs them to behave the
+/// same. Instances, versions and the like are too big and are only referred to.
+const COPIED_DIRS: [&str; 3] = ["scripts", "icons", "music"];
+
+#[derive(Debug)]
+struct State {
+    configured: PathBuf,
+    /// Where the data is kept instead, if the configured dir isn't writable
+    fallback: Option<PathBuf>,
+    /// False if nothing could be written to at all
+    persistent: bool,
+}
+
+pub fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(PROBE_FILE);
+    let writable = fs::create_dir_all(dir).is_ok() && File::create(&probe).is_ok();
+    let _ = fs::remove_file(probe);
+    writable
+}
+
+/// Copies the files at the top of `from` into `to`, along with the list of instances and the
+/// [`COPIED_DIRS`], so the session starts out with the settings and instances as they were.
+fn copy_data(from: &Path, to: &Path) {
+    let files = fs::read_dir(from)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+        .map(|e| PathBuf::from(e.file_name()))
+        .chain(["instances.json", DATABASE_FILE].map(|file| Path::new("instances").join(file)))
+        .chain(
+            fs::read_dir(from.join("instances").join(PER_INSTANCE_DIR))
+                .into_iter()
+                .flatten()
+                .filter_map(Result::ok)
+                .map(|e| {
+                    Path::new("instances")
+                        .join(PER_INSTANCE_DIR)
+                        .join(e.file_name())
+                }),
+        );
+    for file in files {
+        let source = from.join(&file);
+        if !source.exists() {
+            continue;
+        }
+        let target = to.join(&file);
+        if let Some(parent) = target.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Err(e) = fs::copy(&source, &target) {
+            // The logger isn't set up yet, since its file goes into the dir decided on here
+            eprintln!("Failed to copy {}: {}", source.to_string_lossy(), e);
+        }
+    }
+    let options = fs_extra::dir::CopyOptions::new();
+    for dir in COPIED_DIRS
+        .map(|d| from.join(d))
+        .iter()
+        .filter(|d| d.is_dir())
+    {
+        if let Err(e) = fs_extra::dir::copy(dir, to, &options) {
+            eprintln!("Failed to copy {}: {}", dir.to_string_lossy(), e);
+        }
+    }
+}
+
+/// Creates a new folder only this user can access, like mkdtemp. A folder that exists already is
+/// never reused, since another user could have planted scripts or stale data in it.
+fn create_private_temp() -> Option<PathBuf> {
+    let base = env::temp_dir();
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    for attempt in 0..16 {
+        let dir = base.join(format!(
+            "ESLauncher2-{}-{:x}-{}",
+            process::id(),
+            stamp,
+            attempt
+        ));
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        match builder.create(&dir) {
+            Ok(()) if is_own(&dir) => return Some(dir),
+            Ok(()) => return None,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(_) => return None,
+        }
+    }
+    None
+}
+
+/// Whether `dir` is a real folder owned by this user and closed to everyone else.
+#[cfg(unix)]
+fn is_own(dir: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    // Files this process creates belong to it, so the probe tells who this user is
+    let probe = dir.join(PROBE_FILE);
+    let owner = File::create(&probe)
+        .and_then(|_| fs::metadata(&probe))
+        .map(|m| m.uid());
+    let _ = fs::remove_file(probe);
+    match (fs::symlink_metadata(dir), owner) {
+        (Ok(meta), Ok(uid)) => meta.is_dir() && meta.uid() == uid && meta.mode() & 0o077 == 0,
+        _ => false,
+    }
+}
+
+/// Folders in the per-user temp dir are private already.
+#[cfg(not(unix))]
+fn is_own(dir: &Path) -> bool {
+    fs::symlink_metadata(dir).is_ok_and(|m| m.is_dir()) && is_writable(dir)
+}
+
+fn decide(configured: PathBuf) -> State {
+    if is_writable(&configured) {
+        return State {
+            configured,
+            fallback: None,
+            persistent: true,
+        };
+    }
+    if let Some(temp) = create_private_temp() {
+        copy_data(&configured, &temp);
+        return State {
+            configured,
+            fallback: Some(temp),
+            persistent: true,
+        };
+    }
+    State {
+        configured,
+        fallback: None,
+        persistent: false,
+    }
+}
+
+/// The dir to keep the data in, which is `configured` unless that can't be written to. Whether it
+/// can is only checked the first time.
+pub fn resolve(configured: PathBuf) -> PathBuf {
+    let state = STATE.get_or_init(|| decide(configured.clone()));
+    state.fallback.clone().unwrap_or(configured)
+}
+
+/// Whether anything the launcher saves is kept, if only for this session.
+pub fn is_persistent() -> bool {
+    STATE.get().is_none_or(|state| state.persistent)
+}
+
+/// What to tell about the data dir, if it isn't writable.
+pub fn banner() -> Option<String> {
+    let state = STATE.get()?;
+    let configured = state.configured.to_string_lossy();
+    match &state.fallback {
+        Some(fallback) => Some(format!(
+            "{configured} can't be written to, so changes are kept in {} for this session only",
+            fallback.to_string_lossy()
+        )),
+        None if !state.persistent => Some(format!(
+            "{configured} can't be written to, so changes to settings and instances won't be saved"
+        )),
+        None => None,
+    }
+}
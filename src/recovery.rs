@@ -0,0 +1,84 @@
+//! Picks up after work the launcher was killed in the middle of. Installs download into a folder
+//! in the instance that's only removed once they succeed, so resuming one that was interrupted
+//! while extracting doesn't download the build again, and partial files and work folders are
+//! cleaned up at the next start.
+
+use crate::archival::THAWING_SUFFIX;
+use crate::high_dpi;
+use crate::instance::get_instances_dir;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where installs download to inside the instance dir
+pub const DOWNLOAD_DIR: &str = ".download";
+/// Appended to the names of files while they're being downloaded
+pub const PARTIAL_SUFFIX: &str = ".part";
+
+pub fn download_dir(destination: &Path) -> PathBuf {
+    destination.join(DOWNLOAD_DIR)
+}
+
+/// A finished download of `name` left behind by an interrupted install, unless its size
+/// differs from the expected one.
+pub fn finished_download(destination: &Path, name: &str, size: Option<u64>) -> Option<PathBuf> {
+    let path = download_dir(destination).join(name);
+    let len = fs::metadata(&path).ok().filter(|m| m.is_file())?.len();
+    if size.is_some_and(|size| size != len) {
+        warn!(
+            "Not reusing {}, it has {} bytes instead of {}",
+            path.to_string_lossy(),
+            len,
+            size.unwrap_or_default()
+        );
+        return None;
+    }
+    Some(path)
+}
+
+/// Whether an interrupted install into `destination` finished downloading anything.
+pub fn has_finished_downloads(destination: &Path) -> bool {
+    fs::read_dir(download_dir(destination))
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .any(|e| !e.file_name().to_string_lossy().ends_with(PARTIAL_SUFFIX))
+}
+
+fn remove(path: &Path) {
+    let result = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    match result {
+        Ok(()) => info!("Removed leftover {}", path.to_string_lossy()),
+        Err(e) => warn!("Failed to remove {}: {}", path.to_string_lossy(), e),
+    }
+}
+
+/// Removes partial downloads in the given instance dirs, and the work folders of thawing
+/// archived instances and of downloading the high-DPI pack.
+pub async fn clean_leftovers(instance_dirs: Vec<PathBuf>) {
+    for dir in instance_dirs {
+        fs::read_dir(download_dir(&dir))
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .filter(|e| e.file_name().to_string_lossy().ends_with(PARTIAL_SUFFIX))
+            .for_each(|e| remove(&e.path()));
+    }
+    if let Some(instances_dir) = get_instances_dir() {
+        fs::read_dir(instances_dir)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .filter(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                name.starts_with('.') && name.ends_with(THAWING_SUFFIX)
+            })
+            .for_each(|e| remove(&e.path()));
+    }
+    if let Some(work_dir) = high_dpi::work_dir().filter(|d| d.exists()) {
+        remove(&work_dir);
+    }
+}
@@ -0,0 +1,49 @@
+//! The tags of all releases, cached on disk so the version picker doesn't have to wait for GitHub.
+
+use crate::{get_data_dir, github, network};
+use anyhow::Result;
+use std::fs::File;
+use std::path::PathBuf;
+
+fn path() -> Option<PathBuf> {
+    Some(get_data_dir()?.join("releases.json"))
+}
+
+/// The tags as of the last fetch, newest first.
+pub fn load_cached() -> Vec<String> {
+    path()
+        .filter(|p| p.exists())
+        .and_then(|p| File::open(p).ok())
+        .and_then(|f| match serde_json::from_reader(f) {
+            Ok(tags) => Some(tags),
+            Err(e) => {
+                warn!("Failed to deserialize releases.json: {}", e);
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+fn save(tags: &[String]) -> Result<()> {
+    let path = path().ok_or_else(|| anyhow!("Failed to get app save dir"))?;
+    serde_json::to_writer_pretty(File::create(path)?, tags)?;
+    Ok(())
+}
+
+pub async fn perform_fetch() -> Option<Vec<String>> {
+    if !network::is_online() {
+        return None;
+    }
+    match github::get_release_tags() {
+        Ok(tags) => {
+            if let Err(e) = save(&tags) {
+                error!("Failed to save releases.json: {:#}", e);
+            }
+            Some(tags)
+        }
+        Err(e) => {
+            error!("Failed to fetch releases: {:#}", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Samples the memory and CPU use of running games, so stutters can be matched with memory pressure.
+
+use lazy_static::lazy_static;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+/// Guards against cycles in the process tree, which shouldn't exist but cost nothing to rule out
+const MAX_PROCESS_DEPTH: usize = 32;
+
+lazy_static! {
+    /// Kept between samples, since CPU usage is measured as the difference to the previous one
+    static ref SYSTEM: Mutex<System> = Mutex::new(System::new());
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Usage {
+    /// Resident memory in bytes
+    pub memory: u64,
+    /// In percent of one core, so it can exceed 100 for games using several
+    pub cpu: f32,
+}
+
+fn descends_from(system: &System, pid: Pid, ancestor: Pid) -> bool {
+    let mut current = Some(pid);
+    for _ in 0..MAX_PROCESS_DEPTH {
+        match current {
+            Some(pid) if pid == ancestor => return true,
+            Some(pid) => current = system.process(pid).and_then(|p| p.parent()),
+            None => return false,
+        }
+    }
+    false
+}
+
+/// The usage of each game, including its child processes, since e.g. the AppImage runtime
+/// starts the actual game as one.
+pub fn sample(games: Vec<(String, u32)>) -> Vec<(String, Usage)> {
+    let mut system = SYSTEM.lock().unwrap_or_else(PoisonError::into_inner);
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing().with_memory().with_cpu(),
+    );
+    games
+        .into_iter()
+        .map(|(name, pid)| {
+            let root = Pid::from_u32(pid);
+            let usage = system
+                .processes()
+                .values()
+                .filter(|p| descends_from(&system, p.pid(), root))
+                .fold(Usage::default(), |usage, p| Usage {
+                    memory: usage.memory + p.memory(),
+                    cpu: usage.cpu + p.cpu_usage(),
+                });
+            (name, usage)
+        })
+        .collect()
+}
+
+pub async fn perform_sample(games: Vec<(String, u32)>) -> Vec<(String, Usage)> {
+    sample(games)
+}
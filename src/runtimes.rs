@@ -0,0 +1,75 @@
+//! Windows builds need the Microsoft Visual C++ runtime, which not every system has installed.
+//! Without it the game exits before writing a single line, so this recognizes that case and
+//! offers to install the runtime.
+
+use crate::instance::Arch;
+use crate::notifications::{notify, Level, Notification};
+use crate::{bandwidth, http};
+use anyhow::Result;
+use std::path::PathBuf;
+
+/// The NTSTATUS a process exits with when the loader can't find one of its DLLs
+const STATUS_DLL_NOT_FOUND: u32 = 0xC000_0135;
+/// The runtime's DLLs, as named in the loader's errors
+const RUNTIME_DLLS: [&str; 4] = ["vcruntime140", "msvcp140", "vcomp140", "concrt140"];
+
+/// Whether a Windows game exited like this because the runtime is missing.
+pub fn is_missing(exit_code: Option<i32>, stderr: &str) -> bool {
+    // The exit code is an NTSTATUS, which Rust hands out as signed
+    let dll_not_found = exit_code.is_some_and(|code| code as u32 == STATUS_DLL_NOT_FOUND);
+    let stderr = stderr.to_lowercase();
+    dll_not_found || RUNTIME_DLLS.iter().any(|dll| stderr.contains(dll))
+}
+
+fn redist_url(arch: Arch) -> &'static str {
+    match arch.resolve() {
+        Arch::X86 => "https://aka.ms/vs/17/release/vc_redist.x86.exe",
+        Arch::Arm64 => "https://aka.ms/vs/17/release/vc_redist.arm64.exe",
+        _ => "https://aka.ms/vs/17/release/vc_redist.x64.exe",
+    }
+}
+
+async fn download_redist(arch: Arch) -> Result<PathBuf> {
+    let url = redist_url(arch);
+    info!("Downloading the Visual C++ runtime from {}", url);
+    let bytes = http::client()
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    bandwidth::record(bytes.len() as u64);
+    let path = std::env::temp_dir().join("vc_redist.exe");
+    tokio::fs::write(&path, bytes).await?;
+    Ok(path)
+}
+
+/// Downloads the runtime's installer and starts it, which asks for permission and guides
+/// through the rest. Falls back to downloading it in the browser if that fails.
+pub async fn perform_install(arch: Arch) {
+    notify(Notification::new(
+        Level::Info,
+        "Downloading the Visual C++ runtime…",
+    ));
+    // Opened rather than spawned, since the installer needs to be elevated
+    let result = download_redist(arch)
+        .await
+        .and_then(|path| Ok(open::that(path)?));
+    match result {
+        Ok(()) => notify(Notification::new(
+            Level::Info,
+            "Follow the installer, then play again",
+        )),
+        Err(e) => {
+            error!("Failed to install the Visual C++ runtime: {:#}", e);
+            if let Err(e) = open::that(redist_url(arch)) {
+                error!("URL could not be opened: {}", e);
+            }
+            notify(Notification::new(
+                Level::Warning,
+                "Couldn't start the runtime's installer, run it from your downloads instead",
+            ));
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Playing without plugins, to find out whether a crash is caused by one. The plugins folder is
+//! moved aside for the run and put back once the game exits, or when the launcher starts the next
+//! time if it was closed before that.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Next to the plugins folder, where the game doesn't look for plugins
+const ASIDE_NAME: &str = "plugins (moved aside by ESLauncher2)";
+
+/// A plugins folder that was moved aside, to be put back with [`PluginsAside::put_back`].
+#[derive(Debug)]
+pub struct PluginsAside {
+    plugins: PathBuf,
+    aside: PathBuf,
+}
+
+/// Moves the plugins folder in `config_dir` aside, None if there is none.
+pub fn move_aside(config_dir: &Path) -> Result<Option<PluginsAside>> {
+    let plugins = config_dir.join("plugins");
+    let aside = config_dir.join(ASIDE_NAME);
+    if !plugins.exists() {
+        return Ok(None);
+    }
+    if aside.exists() {
+        return Err(anyhow!(
+            "{} already exists, put its plugins back first",
+            aside.to_string_lossy()
+        ));
+    }
+    fs::rename(&plugins, &aside).with_context(|| {
+        format!(
+            "Failed to move {} aside, is the game running?",
+            plugins.to_string_lossy()
+        )
+    })?;
+    info!("Moved {} aside for this run", plugins.to_string_lossy());
+    Ok(Some(PluginsAside { plugins, aside }))
+}
+
+fn put_back(plugins: &Path, aside: &Path) -> Result<()> {
+    // The game creates an empty one when it starts
+    if plugins.exists() {
+        fs::remove_dir(plugins).with_context(|| {
+            format!(
+                "Plugins were added to {} in the meantime, so the others stay in {}",
+                plugins.to_string_lossy(),
+                aside.to_string_lossy()
+            )
+        })?;
+    }
+    fs::rename(aside, plugins)
+        .with_context(|| format!("Failed to put back {}", plugins.to_string_lossy()))
+}
+
+impl PluginsAside {
+    pub fn put_back(self) -> Result<()> {
+        put_back(&self.plugins, &self.aside)?;
+        info!("Put back {}", self.plugins.to_string_lossy());
+        Ok(())
+    }
+}
+
+/// Puts back plugins that are still aside because the launcher was closed while playing without
+/// them.
+pub async fn recover(config_dirs: Vec<PathBuf>) {
+    for dir in config_dirs.into_iter().collect::<BTreeSet<_>>() {
+        let aside = dir.join(ASIDE_NAME);
+        if !aside.is_dir() {
+            continue;
+        }
+        match put_back(&dir.join("plugins"), &aside) {
+            Ok(()) => info!(
+                "Put back the plugins in {} that were moved aside to play without them",
+                dir.to_string_lossy()
+            ),
+            Err(e) => warn!("{:#}", e),
+        }
+    }
+}
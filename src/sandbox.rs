@@ -0,0 +1,93 @@
+//! Support for running the launcher itself inside Flatpak or Snap. Inside Flatpak, the game and
+//! file managers can't be started directly, since the game needs the host's libraries and the
+//! sandbox has no file manager, so those go through `flatpak-spawn --host`.
+
+use lazy_static::lazy_static;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sandbox {
+    Flatpak,
+    Snap,
+}
+
+impl fmt::Display for Sandbox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Flatpak => "Flatpak",
+            Self::Snap => "Snap",
+        })
+    }
+}
+
+lazy_static! {
+    static ref CURRENT: Option<Sandbox> = detect();
+}
+
+fn detect() -> Option<Sandbox> {
+    let sandbox =
+        if std::env::var_os("FLATPAK_ID").is_some() || Path::new("/.flatpak-info").exists() {
+            Some(Sandbox::Flatpak)
+        } else if std::env::var_os("SNAP").is_some() {
+            Some(Sandbox::Snap)
+        } else {
+            None
+        };
+    if let Some(sandbox) = sandbox {
+        info!("Running inside {}", sandbox);
+    }
+    sandbox
+}
+
+/// The sandbox the launcher runs in, if any.
+pub fn current() -> Option<Sandbox> {
+    *CURRENT
+}
+
+/// Makes `cmd` run on the host instead of inside the sandbox, where that's necessary.
+/// Its program, arguments, environment and working directory are carried over,
+/// so this has to be called before setting up its stdio, which can't be.
+pub fn host_command(cmd: Command) -> Command {
+    if current() != Some(Sandbox::Flatpak) {
+        return cmd;
+    }
+    let mut host = Command::new("flatpak-spawn");
+    host.arg("--host").arg("--watch-bus");
+    // flatpak-spawn starts the process from the host's environment, not the launcher's
+    for (key, value) in cmd.get_envs() {
+        if let Some(value) = value {
+            let mut env = key.to_os_string();
+            env.push("=");
+            env.push(value);
+            let mut arg = std::ffi::OsString::from("--env=");
+            arg.push(env);
+            host.arg(arg);
+        }
+    }
+    if let Some(dir) = cmd.get_current_dir() {
+        let mut arg = std::ffi::OsString::from("--directory=");
+        arg.push(dir);
+        host.arg(arg);
+    }
+    host.arg(cmd.get_program()).args(cmd.get_args());
+    host
+}
+
+/// Opens a file or folder with the default application, e.g. a folder in the file manager.
+pub fn open(path: &Path) -> io::Result<()> {
+    if current() != Some(Sandbox::Flatpak) {
+        return open::that(path);
+    }
+    // Files in the launcher's data dir have the same path on the host, and the host has the apps
+    let mut cmd = Command::new("xdg-open");
+    cmd.arg(path);
+    let status = host_command(cmd).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("xdg-open exited with {status}")))
+    }
+}
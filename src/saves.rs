@@ -0,0 +1,301 @@
+use crate::get_data_dir;
+use crate::plugin_metadata::tokenize;
+use anyhow::Result;
+use fs_extra::dir::{copy, CopyOptions};
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use time::{format_description, OffsetDateTime};
+
+/// A pilot file in the game's `saves` folder.
+#[derive(Debug, Clone)]
+pub struct SaveFile {
+    pub path: PathBuf,
+    pub pilot: String,
+    pub date: Option<String>,
+    pub credits: Option<i64>,
+    pub modified: Option<OffsetDateTime>,
+    /// The game's `~~previous` autosaves of this pilot
+    pub previous: Vec<PathBuf>,
+}
+
+impl SaveFile {
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Reads the pilot name, in-game date and credits from a save file.
+/// Values that can't be parsed are left empty.
+pub fn parse(path: &Path) -> SaveFile {
+    let mut save = SaveFile {
+        path: path.to_path_buf(),
+        pilot: path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        date: None,
+        credits: None,
+        modified: fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .map(OffsetDateTime::from),
+        previous: vec![],
+    };
+
+    let Ok(text) = fs::read_to_string(path) else {
+        return save;
+    };
+    let mut in_account = false;
+    for line in text.lines() {
+        let tokens = tokenize(line);
+        if tokens.is_empty() {
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) {
+            in_account = tokens[0] == "account";
+            match tokens[0].as_str() {
+                "pilot" if tokens.len() >= 3 => {
+                    save.pilot = format!("{} {}", tokens[1], tokens[2]);
+                }
+                "date" if tokens.len() >= 4 => {
+                    save.date = Some(format!("{}.{}.{}", tokens[1], tokens[2], tokens[3]));
+                }
+                _ => {}
+            }
+        } else if in_account && tokens[0] == "credits" {
+            save.credits = tokens.get(1).and_then(|c| c.parse().ok());
+        }
+    }
+    save
+}
+
+/// The file name of the pilot an autosave like `Jane Doe~~previous-1.txt` belongs to.
+fn autosave_of(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy();
+    let (pilot, _) = name.split_once("~~previous")?;
+    Some(format!("{}.txt", pilot.trim_end_matches(".txt")))
+}
+
+/// Lists all pilot files in the given config dir, most recently modified first.
+/// Autosaves are listed with their pilot, unless the pilot file itself is gone.
+pub fn list(config_dir: &Path) -> Result<Vec<SaveFile>> {
+    let saves_dir = config_dir.join("saves");
+    if !saves_dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(saves_dir)?
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == "txt"))
+        .collect();
+    files.sort();
+    let (autosaves, pilots): (Vec<_>, Vec<_>) = files
+        .into_iter()
+        .partition(|p| autosave_of(p).is_some_and(|pilot| p.with_file_name(pilot).is_file()));
+    let mut saves: Vec<SaveFile> = pilots.iter().map(|p| parse(p)).collect();
+    for autosave in autosaves {
+        let pilot = autosave_of(&autosave).map(|pilot| autosave.with_file_name(pilot));
+        if let Some(save) = saves.iter_mut().find(|s| Some(&s.path) == pilot.as_ref()) {
+            save.previous.push(autosave);
+        }
+    }
+    saves.sort_by_key(|s| Reverse(s.modified));
+    Ok(saves)
+}
+
+pub fn get_backups_dir(instance_name: &str) -> Option<PathBuf> {
+    let mut dir = get_data_dir()?;
+    dir.push("save_backups");
+    dir.push(instance_name);
+    Some(dir)
+}
+
+pub(crate) fn timestamp() -> Result<String> {
+    Ok(OffsetDateTime::now_utc().format(&format_description::parse(
+        "[year]-[month]-[day] [hour]-[minute]-[second]",
+    )?)?)
+}
+
+/// Copies a pilot file into the launcher's backup folder for the instance.
+pub fn backup(save: &Path, instance_name: &str) -> Result<PathBuf> {
+    let backups_dir =
+        get_backups_dir(instance_name).ok_or_else(|| anyhow!("Failed to get backups dir"))?;
+    fs::create_dir_all(&backups_dir)?;
+    let stem = save
+        .file_stem()
+        .ok_or_else(|| anyhow!("Invalid save file {}", save.to_string_lossy()))?;
+    let target = backups_dir.join(format!("{} {}.txt", stem.to_string_lossy(), timestamp()?));
+    fs::copy(save, &target)?;
+    info!(
+        "Backed up {} to {}",
+        save.to_string_lossy(),
+        target.to_string_lossy()
+    );
+    Ok(target)
+}
+
+/// Copies a pilot file next to itself, picking a file name that isn't taken yet.
+pub fn duplicate(save: &Path) -> Result<PathBuf> {
+    let stem = save
+        .file_stem()
+        .ok_or_else(|| anyhow!("Invalid save file {}", save.to_string_lossy()))?
+        .to_string_lossy();
+    let mut target = save.with_file_name(format!("{stem} (copy).txt"));
+    let mut n = 2;
+    while target.exists() {
+        target = save.with_file_name(format!("{stem} (copy {n}).txt"));
+        n += 1;
+    }
+    fs::copy(save, &target)?;
+    info!(
+        "Duplicated {} to {}",
+        save.to_string_lossy(),
+        target.to_string_lossy()
+    );
+    Ok(target)
+}
+
+/// Copies a pilot file into another config dir's `saves` folder.
+/// An existing pilot with the same file name is backed up before being overwritten.
+pub fn copy_to(save: &Path, target_config_dir: &Path, target_instance: &str) -> Result<PathBuf> {
+    let file_name = save
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid save file {}", save.to_string_lossy()))?;
+    let target_dir = target_config_dir.join("saves");
+    let target = target_dir.join(file_name);
+    if save.parent() == Some(target_dir.as_path()) {
+        return Err(anyhow!("Both instances share the same saves folder"));
+    }
+    fs::create_dir_all(&target_dir)?;
+    if target.exists() {
+        backup(&target, target_instance)?;
+    }
+    fs::copy(save, &target)?;
+    info!(
+        "Copied {} to {}",
+        save.to_string_lossy(),
+        target.to_string_lossy()
+    );
+    Ok(target)
+}
+
+/// Copies the whole `saves` folder of a config dir into a timestamped snapshot,
+/// then removes the oldest snapshots so that at most `keep` remain.
+pub fn snapshot(config_dir: &Path, instance_name: &str, keep: usize) -> Result<Option<PathBuf>> {
+    let saves_dir = config_dir.join("saves");
+    if keep == 0 || !saves_dir.exists() {
+        return Ok(None);
+    }
+    let backups_dir =
+        get_backups_dir(instance_name).ok_or_else(|| anyhow!("Failed to get backups dir"))?;
+    let target = backups_dir.join(format!("snapshot {}", timestamp()?));
+    fs::create_dir_all(&target)?;
+    let options = CopyOptions {
+        content_only: true,
+        ..CopyOptions::new()
+    };
+    copy(&saves_dir, &target, &options).map_err(|e| anyhow!("Failed to copy saves: {}", e))?;
+    info!("Created save snapshot at {}", target.to_string_lossy());
+
+    let mut snapshots: Vec<PathBuf> = fs::read_dir(&backups_dir)?
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_dir()
+                && p.file_name()
+                    .is_some_and(|n| n.to_string_lossy().starts_with("snapshot "))
+        })
+        .collect();
+    // The timestamp format sorts chronologically
+    snapshots.sort();
+    while snapshots.len() > keep {
+        let oldest = snapshots.remove(0);
+        debug!("Removing old save snapshot {}", oldest.to_string_lossy());
+        fs::remove_dir_all(oldest)?;
+    }
+    Ok(Some(target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::TempDir;
+
+    #[test]
+    fn parse_reads_pilot_date_and_credits() {
+        let dir = TempDir::new("saves-parse");
+        let path = dir.path().join("Jane Doe.txt");
+        fs::write(
+            &path,
+            "pilot Jane Doe\n\
+             date 16 11 3013\n\
+             system Sol\n\
+             account\n\
+             \tcredits 131000\n\
+             \tscore 400\n\
+             ship Shuttle\n\
+             \tcredits 5\n",
+        )
+        .unwrap();
+        let save = parse(&path);
+        assert_eq!(save.pilot, "Jane Doe");
+        assert_eq!(save.date.as_deref(), Some("16.11.3013"));
+        assert_eq!(save.credits, Some(131_000));
+        assert!(save.modified.is_some());
+    }
+
+    #[test]
+    fn parse_keeps_what_it_can_of_broken_saves() {
+        let dir = TempDir::new("saves-broken");
+        let path = dir.path().join("Broken.txt");
+        fs::write(&path, "pilot Lonely\naccount\n\tcredits lots\n").unwrap();
+        let save = parse(&path);
+        assert_eq!(save.pilot, "Broken");
+        assert_eq!(save.date, None);
+        assert_eq!(save.credits, None);
+
+        let missing = parse(&dir.path().join("Missing.txt"));
+        assert_eq!(missing.pilot, "Missing");
+        assert!(missing.modified.is_none());
+    }
+
+    #[test]
+    fn autosaves_are_listed_with_their_pilot() {
+        let dir = TempDir::new("saves-autosaves");
+        let saves_dir = dir.path().join("saves");
+        fs::create_dir_all(&saves_dir).unwrap();
+        for name in [
+            "Jane Doe.txt",
+            "Jane Doe~~previous-1.txt",
+            "Jane Doe~~previous-2.txt",
+            "Jane Doe~Snapshot.txt",
+            "Gone~~previous-1.txt",
+        ] {
+            fs::write(saves_dir.join(name), "pilot Jane Doe\n").unwrap();
+        }
+        let mut saves = list(dir.path()).unwrap();
+        saves.sort_by_key(SaveFile::file_name);
+        let names: Vec<String> = saves.iter().map(SaveFile::file_name).collect();
+        assert_eq!(
+            names,
+            [
+                "Gone~~previous-1.txt",
+                "Jane Doe.txt",
+                "Jane Doe~Snapshot.txt"
+            ]
+        );
+        assert!(saves[0].previous.is_empty());
+        assert_eq!(
+            saves[1].previous,
+            [
+                saves_dir.join("Jane Doe~~previous-1.txt"),
+                saves_dir.join("Jane Doe~~previous-2.txt")
+            ]
+        );
+        assert!(saves[2].previous.is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+use crate::instance::{open_folder, Instance};
+use crate::saves::{self, SaveFile};
+use crate::style::{icon_button, text_button};
+use crate::trash::{self, Deleted};
+use crate::{style, Message};
+use iced::widget::{Button, Column, Container, PickList, Row, Scrollable, Space, Text};
+use iced::{alignment, theme, Alignment, Color, Command, Element, Length};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use time::format_description;
+
+#[derive(Debug, Clone)]
+pub enum SavesMessage {
+    InstanceSelected(String),
+    Refresh,
+    Backup(PathBuf),
+    Duplicate(PathBuf),
+    Delete(PathBuf),
+    Reveal(PathBuf),
+    CopyTo(PathBuf, String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SavesFrame {
+    selected: Option<String>,
+    saves: Vec<SaveFile>,
+}
+
+impl SavesFrame {
+    pub fn update(
+        &mut self,
+        message: SavesMessage,
+        instances: &BTreeMap<String, Instance>,
+    ) -> Command<Message> {
+        let instance = |name: &Option<String>| name.as_ref().and_then(|n| instances.get(n));
+        match message {
+            SavesMessage::InstanceSelected(name) => self.selected = Some(name),
+            SavesMessage::Refresh => {}
+            SavesMessage::Backup(path) => {
+                if let Some(instance) = instance(&self.selected) {
+                    if let Err(e) = saves::backup(&path, &instance.name) {
+                        error!("Failed to back up pilot: {:#}", e);
+                    }
+                }
+            }
+            SavesMessage::Duplicate(path) => {
+                if let Err(e) = saves::duplicate(&path) {
+                    error!("Failed to duplicate pilot: {:#}", e);
+                }
+            }
+            SavesMessage::Delete(path) => {
+                if let Some(save) = self.saves.iter().find(|s| s.path == path) {
+                    let mut paths = vec![path];
+                    paths.extend(save.previous.iter().cloned());
+                    return Command::perform(
+                        trash::perform_hold(Deleted::Pilot(save.pilot.clone()), paths),
+                        |held| held.map_or(Message::Dummy(()), Message::Deleted),
+                    );
+                }
+            }
+            SavesMessage::CopyTo(path, target) => {
+                match instances.get(&target).and_then(|i| i.config_dir()) {
+                    Some(config_dir) => {
+                        if let Err(e) = saves::copy_to(&path, &config_dir, &target) {
+                            error!("Failed to copy pilot to {}: {:#}", target, e);
+                        }
+                    }
+                    None => error!("Failed to find config dir of instance {}", target),
+                }
+            }
+            SavesMessage::Reveal(path) => {
+                if let Some(parent) = path.parent() {
+                    return Command::perform(open_folder(parent.to_path_buf()), Message::Dummy);
+                }
+            }
+        }
+        self.saves = match instance(&self.selected).and_then(Instance::config_dir) {
+            Some(config_dir) => saves::list(&config_dir).unwrap_or_else(|e| {
+                error!("Failed to list saves: {:#}", e);
+                vec![]
+            }),
+            None => vec![],
+        };
+        Command::none()
+    }
+
+    pub fn view(&self, instance_names: Vec<String>) -> Element<SavesMessage> {
+        let header = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Text::new("Instance:"))
+            .push(PickList::new(
+                instance_names.clone(),
+                self.selected.clone(),
+                SavesMessage::InstanceSelected,
+            ))
+            .push(Space::new(Length::Fill, Length::Shrink))
+            .push(
+                Button::new(Text::new("Refresh"))
+                    .style(text_button())
+                    .on_press(SavesMessage::Refresh),
+            );
+
+        let list: Element<_> = if self.saves.is_empty() {
+            Text::new(if self.selected.is_some() {
+                "No pilots found"
+            } else {
+                "Choose an instance to list its pilots"
+            })
+            .style(theme::Text::Color(Color::from_rgb8(150, 150, 150)))
+            .horizontal_alignment(alignment::Horizontal::Center)
+            .width(Length::Fill)
+            .into()
+        } else {
+            let targets: Vec<String> = instance_names
+                .iter()
+                .filter(|n| Some(*n) != self.selected.as_ref())
+                .cloned()
+                .collect();
+            self.saves
+                .iter()
+                .fold(Column::new().spacing(5), |column, save| {
+                    column.push(save_view(save, targets.clone()))
+                })
+                .into()
+        };
+
+        Container::new(
+            Column::new()
+                .spacing(20)
+                .padding(20)
+                .push(header)
+                .push(Scrollable::new(list)),
+        )
+        .width(Length::Fill)
+        .padding(iced::Padding {
+            top: 0.0,
+            right: 30.0,
+            bottom: 0.0,
+            left: 30.0,
+        })
+        .into()
+    }
+}
+
+fn save_view(save: &SaveFile, targets: Vec<String>) -> Element<SavesMessage> {
+    let path = save.path.clone();
+    let mut details = vec![save.file_name()];
+    match save.previous.len() {
+        0 => {}
+        1 => details.push("1 autosave".into()),
+        n => details.push(format!("{n} autosaves")),
+    }
+    if let Some(date) = &save.date {
+        details.push(format!("In-game date: {date}"));
+    }
+    if let Some(credits) = save.credits {
+        details.push(format!("{credits} credits"));
+    }
+    if let Some(modified) = save.modified.and_then(|m| {
+        format_description::parse("[year]-[month]-[day] [hour]:[minute]")
+            .ok()
+            .and_then(|f| m.format(&f).ok())
+    }) {
+        details.push(format!("Saved {modified}"));
+    }
+
+    Row::new()
+        .spacing(10)
+        .padding(5)
+        .align_items(Alignment::Center)
+        .push(
+            Column::new().push(Text::new(&save.pilot).size(20)).push(
+                Text::new(details.join("  ·  "))
+                    .size(12)
+                    .style(theme::Text::Color(Color::from_rgb(0.6, 0.6, 0.6))),
+            ),
+        )
+        .push(Space::new(Length::Fill, Length::Shrink))
+        .push(
+            Button::new(Text::new("Back up"))
+                .style(text_button())
+                .on_press(SavesMessage::Backup(save.path.clone())),
+        )
+        .push(
+            Button::new(Text::new("Duplicate"))
+                .style(text_button())
+                .on_press(SavesMessage::Duplicate(save.path.clone())),
+        )
+        .push(
+            PickList::new(targets, None::<String>, move |target| {
+                SavesMessage::CopyTo(path.clone(), target)
+            })
+            .placeholder("Copy pilot to…"),
+        )
+        .push(style::tooltip(
+            Button::new(style::labeled(style::folder_icon(), "Folder"))
+                .style(icon_button())
+                .on_press(SavesMessage::Reveal(save.path.clone())),
+            "Show the pilot in its folder",
+        ))
+        .push(style::tooltip(
+            Button::new(style::labeled(style::delete_icon(), "Delete"))
+                .style(theme::Button::Destructive)
+                .on_press(SavesMessage::Delete(save.path.clone())),
+            "Delete this pilot and its autosaves",
+        ))
+        .into()
+}
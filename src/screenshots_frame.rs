@@ -0,0 +1,181 @@
+use crate::instance::Instance;
+use crate::style::{icon_button, text_button};
+use crate::{sandbox, style, Message};
+use iced::widget::{
+    image, Button, Column, Container, Image, PickList, Row, Scrollable, Space, Text,
+};
+use iced::{alignment, theme, Alignment, Color, Command, Element, Length};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const THUMBNAIL_WIDTH: f32 = 200.;
+const THUMBNAILS_PER_ROW: usize = 4;
+
+#[derive(Debug, Clone)]
+pub enum ScreenshotsMessage {
+    InstanceSelected(String),
+    Refresh,
+    Open(PathBuf),
+    Delete(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+struct Screenshot {
+    path: PathBuf,
+    handle: image::Handle,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScreenshotsFrame {
+    selected: Option<String>,
+    screenshots: Vec<Screenshot>,
+}
+
+/// Lists the screenshots the game saved into a config dir, newest first.
+fn list_screenshots(config_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(config_dir.join("screenshots")) else {
+        return vec![];
+    };
+    let mut screenshots: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("png") || e.eq_ignore_ascii_case("jpg"))
+        })
+        .collect();
+    screenshots.sort();
+    screenshots.reverse();
+    screenshots
+}
+
+impl ScreenshotsFrame {
+    pub fn update(
+        &mut self,
+        message: ScreenshotsMessage,
+        instances: &BTreeMap<String, Instance>,
+    ) -> Command<Message> {
+        match message {
+            ScreenshotsMessage::InstanceSelected(name) => self.selected = Some(name),
+            ScreenshotsMessage::Refresh => {}
+            ScreenshotsMessage::Open(path) => {
+                if let Err(e) = sandbox::open(&path) {
+                    error!("Failed to open {}: {}", path.to_string_lossy(), e);
+                }
+                return Command::none();
+            }
+            ScreenshotsMessage::Delete(path) => match fs::remove_file(&path) {
+                Ok(()) => info!("Deleted {}", path.to_string_lossy()),
+                Err(e) => error!("Failed to delete {}: {}", path.to_string_lossy(), e),
+            },
+        }
+        self.screenshots = self
+            .selected
+            .as_ref()
+            .and_then(|n| instances.get(n))
+            .and_then(Instance::config_dir)
+            .map(|dir| list_screenshots(&dir))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|path| Screenshot {
+                handle: image::Handle::from_path(&path),
+                path,
+            })
+            .collect();
+        Command::none()
+    }
+
+    pub fn view(&self, instance_names: Vec<String>) -> Element<ScreenshotsMessage> {
+        let header = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Text::new("Instance:"))
+            .push(PickList::new(
+                instance_names,
+                self.selected.clone(),
+                ScreenshotsMessage::InstanceSelected,
+            ))
+            .push(Space::new(Length::Fill, Length::Shrink))
+            .push(
+                Button::new(Text::new("Refresh"))
+                    .style(text_button())
+                    .on_press(ScreenshotsMessage::Refresh),
+            );
+
+        let gallery: Element<_> = if self.screenshots.is_empty() {
+            Text::new(if self.selected.is_some() {
+                "No screenshots found"
+            } else {
+                "Choose an instance to show its screenshots"
+            })
+            .style(theme::Text::Color(Color::from_rgb8(150, 150, 150)))
+            .horizontal_alignment(alignment::Horizontal::Center)
+            .width(Length::Fill)
+            .into()
+        } else {
+            self.screenshots
+                .chunks(THUMBNAILS_PER_ROW)
+                .fold(Column::new().spacing(10), |column, chunk| {
+                    column.push(
+                        chunk
+                            .iter()
+                            .fold(Row::new().spacing(10), |row, s| row.push(thumbnail(s))),
+                    )
+                })
+                .into()
+        };
+
+        Container::new(
+            Column::new()
+                .spacing(20)
+                .padding(20)
+                .push(header)
+                .push(Scrollable::new(gallery)),
+        )
+        .width(Length::Fill)
+        .padding(iced::Padding {
+            top: 0.0,
+            right: 30.0,
+            bottom: 0.0,
+            left: 30.0,
+        })
+        .into()
+    }
+}
+
+fn thumbnail(screenshot: &Screenshot) -> Element<ScreenshotsMessage> {
+    Column::new()
+        .spacing(5)
+        .width(Length::Fixed(THUMBNAIL_WIDTH))
+        .push(
+            Button::new(
+                Image::new(screenshot.handle.clone()).width(Length::Fixed(THUMBNAIL_WIDTH)),
+            )
+            .padding(0)
+            .style(icon_button())
+            .on_press(ScreenshotsMessage::Open(screenshot.path.clone())),
+        )
+        .push(
+            Row::new()
+                .align_items(Alignment::Center)
+                .push(
+                    Text::new(
+                        screenshot
+                            .path
+                            .file_name()
+                            .map(|n| n.to_string_lossy().to_string())
+                            .unwrap_or_default(),
+                    )
+                    .size(10)
+                    .width(Length::Fill),
+                )
+                .push(style::tooltip(
+                    Button::new(style::labeled(style::delete_icon(), "Delete"))
+                        .style(theme::Button::Destructive)
+                        .on_press(ScreenshotsMessage::Delete(screenshot.path.clone())),
+                    "Delete this screenshot",
+                )),
+        )
+        .into()
+}
@@ -0,0 +1,118 @@
+//! User scripts that run on launcher events.
+//!
+//! Every `.rhai` file in the scripts dir may define any of these functions:
+//! - `on_install_complete(name, version)`
+//! - `on_update_complete(name, version)`
+//! - `on_update_available(name)`
+//! - `on_game_exit(name, exit_code)`
+//!
+//! Scripts can call `log(text)`, `notify(text)` and `exec(program, [args])`,
+//! which returns the exit code of the program.
+
+use crate::get_data_dir;
+use crate::notifications::{notify, Level, Notification};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Scope};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::thread;
+
+pub fn get_scripts_dir() -> Option<PathBuf> {
+    Some(get_data_dir()?.join("scripts"))
+}
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.register_fn("log", |text: &str| info!("[script] {}", text));
+    engine.register_fn("notify", |text: &str| {
+        notify(Notification::new(Level::Info, text));
+    });
+    engine.register_fn(
+        "exec",
+        |program: &str, args: Array| -> Result<i64, Box<EvalAltResult>> {
+            let args: Vec<String> = args.into_iter().map(|a| a.to_string()).collect();
+            let status = Command::new(program)
+                .args(&args)
+                .status()
+                .map_err(|e| format!("Failed to run {program}: {e}"))?;
+            Ok(status.code().unwrap_or(-1).into())
+        },
+    );
+    engine
+}
+
+fn scripts() -> Vec<PathBuf> {
+    let Some(Ok(entries)) = get_scripts_dir().map(fs::read_dir) else {
+        return vec![];
+    };
+    let mut scripts: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "rhai"))
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+/// A hook argument. Unlike [`Dynamic`], this can be sent to the script thread.
+pub enum HookArg {
+    Text(String),
+    Number(i64),
+}
+
+impl From<String> for HookArg {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<i64> for HookArg {
+    fn from(number: i64) -> Self {
+        Self::Number(number)
+    }
+}
+
+impl From<HookArg> for Dynamic {
+    fn from(arg: HookArg) -> Self {
+        match arg {
+            HookArg::Text(text) => text.into(),
+            HookArg::Number(number) => number.into(),
+        }
+    }
+}
+
+fn run_hook(hook: &str, args: Vec<Dynamic>) {
+    let scripts = scripts();
+    if scripts.is_empty() {
+        return;
+    }
+    let engine = engine();
+    for script in scripts {
+        let name = script.to_string_lossy().to_string();
+        let ast = match engine.compile_file(script) {
+            Ok(ast) => ast,
+            Err(e) => {
+                error!("Failed to compile script {}: {}", name, e);
+                continue;
+            }
+        };
+        match engine.call_fn::<Dynamic>(&mut Scope::new(), &ast, hook, args.clone()) {
+            Ok(_) => debug!("Ran {} in {}", hook, name),
+            Err(e) => {
+                // Scripts don't have to implement every hook
+                let not_implemented = matches!(
+                    &*e,
+                    EvalAltResult::ErrorFunctionNotFound(signature, _) if signature.starts_with(hook)
+                );
+                if !not_implemented {
+                    error!("Script {} failed in {}: {}", name, hook, e);
+                }
+            }
+        }
+    }
+}
+
+/// Runs `hook` in all scripts on a background thread.
+pub fn fire(hook: &'static str, args: Vec<HookArg>) {
+    thread::spawn(move || run_hook(hook, args.into_iter().map(Into::into).collect()));
+}
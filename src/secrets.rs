@@ -0,0 +1,67 @@
+//! Credentials are kept in the OS keyring (Secret Service, Keychain or Credential Manager)
+//! rather than in the settings files, which are plaintext.
+
+use crate::profiles;
+use crate::settings::Settings;
+use anyhow::Result;
+use keyring::Entry;
+
+const SERVICE: &str = "ESLauncher2";
+const GITHUB_TOKEN: &str = "github-token";
+
+/// Each profile has a token of its own
+fn github_token_entry() -> keyring::Result<Entry> {
+    match profiles::current() {
+        Some(profile) => Entry::new(SERVICE, &format!("{GITHUB_TOKEN}:{profile}")),
+        None => Entry::new(SERVICE, GITHUB_TOKEN),
+    }
+}
+
+/// None if there's no token, or the keyring can't be reached.
+pub fn github_token() -> Option<String> {
+    match github_token_entry().and_then(|entry| entry.get_password()) {
+        Ok(token) => Some(token),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            warn!("Failed to read the GitHub token from the keyring: {}", e);
+            None
+        }
+    }
+}
+
+pub fn set_github_token(token: &str) -> Result<()> {
+    github_token_entry()?.set_password(token)?;
+    info!("Stored the GitHub token in the keyring");
+    Ok(())
+}
+
+pub fn clear_github_token() -> Result<()> {
+    match github_token_entry()?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => {
+            info!("Removed the GitHub token from the keyring");
+            Ok(())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Moves a token entered before it was kept in the keyring out of the settings.
+/// It stays there if the keyring can't be reached, so it keeps working meanwhile.
+pub fn migrate(settings: &mut Settings) {
+    let token = settings.local.http.github_token.trim().to_string();
+    if token.is_empty() {
+        return;
+    }
+    match set_github_token(&token) {
+        Ok(()) => {
+            settings.local.http.github_token.clear();
+            if let Err(e) = settings.save() {
+                error!("Failed to save settings: {:#}", e);
+            }
+        }
+        Err(e) => warn!(
+            "Failed to move the GitHub token into the keyring, keeping it in the settings: {:#}",
+            e
+        ),
+    }
+}
@@ -0,0 +1,47 @@
+//! UI state that is brought back at the next start, so closing the launcher by accident doesn't
+//! lose e.g. an install that was being set up.
+
+use crate::install_frame::InstallDraft;
+use crate::{get_data_dir, Tab};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub tab: Option<Tab>,
+    /// The job the log was filtered to
+    pub log_filter: Option<String>,
+    /// The instance whose details were shown
+    pub expanded_instance: Option<String>,
+    /// What was entered into the install form
+    pub install: Option<InstallDraft>,
+}
+
+impl Session {
+    fn path() -> Option<PathBuf> {
+        Some(get_data_dir()?.join("session.json"))
+    }
+
+    pub fn load() -> Self {
+        Self::path()
+            .filter(|p| p.exists())
+            .and_then(|p| File::open(p).ok())
+            .and_then(|f| match serde_json::from_reader(f) {
+                Ok(session) => Some(session),
+                Err(e) => {
+                    warn!("Failed to deserialize session.json: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path().ok_or_else(|| anyhow!("Failed to get app save dir"))?;
+        serde_json::to_writer_pretty(File::create(path)?, self)?;
+        Ok(())
+    }
+}
@@ -1,45 +1,202 @@
-use crate::get_data_dir;
-use crate::music::MusicState;
+use crate::backups::BackupConfig;
+use crate::http::HttpConfig;
+use crate::instance_store::StoreType;
+use crate::instances_frame::{DoubleClickAction, InstanceSort};
+use crate::logger::LogLevel;
+use crate::music::{MusicState, MusicWhilePlaying};
+use crate::style::ThemeChoice;
+use crate::sync::SyncConfig;
+use crate::{get_data_dir, instance, read_only};
 use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::fs::File;
+use std::path::PathBuf;
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     pub music_state: MusicState,
+    /// Whether music is shuffled from an installed instance's soundtrack instead
+    pub game_soundtrack: bool,
+    /// What the music does while a game is running, unless an instance says otherwise
+    pub music_while_playing: MusicWhilePlaying,
+    /// No music at startup and no animations, also enabled for a session by `--quiet`
+    pub quiet_mode: bool,
+    /// Only lets players start instances, see `kiosk`. `--no-kiosk` turns it off for a session.
+    pub kiosk_mode: bool,
+    pub log_level: LogLevel,
+    pub theme: ThemeChoice,
+    /// Whether icon buttons show a text label next to their icon
+    pub button_labels: bool,
+    pub instance_sort: InstanceSort,
+    pub double_click_action: DoubleClickAction,
+    /// How many automatic save snapshots to keep per instance, 0 disables them
+    pub save_snapshots: usize,
+    /// How many previous builds to keep per instance, 0 disables them
+    pub keep_versions: usize,
+    /// Whether games can be launched while another instance is running
+    pub allow_concurrent_games: bool,
+    /// Whether games keep running when the launcher is closed, at the cost of the live console
+    pub detach_games: bool,
+    /// How many game sessions to keep logfiles of per instance, 0 keeps all
+    pub keep_log_sessions: usize,
+    /// Days after which game logfiles get removed, 0 keeps them forever
+    pub keep_log_days: u64,
+    /// MiB each game logfile may grow to before the middle of the output is left out, 0 means
+    /// unlimited
+    pub game_log_limit: u64,
+    /// Hours of play after which a game session is pointed out, and again every as many hours,
+    /// 0 disables the reminder
+    pub play_reminder: u64,
+    /// Minutes a job may go without progress before it's pointed out as stalled, 0 disables this
+    pub stall_timeout: u64,
+    /// Minutes between background update checks, 0 disables them
+    pub update_check_interval: u64,
+    /// Days after which unplayed PR instances with a closed PR are suggested for deletion, 0 disables this
+    pub housekeeping_days: u64,
+    /// MiB the caches may take up before the least recently used files get removed, 0 means unlimited
+    pub cache_limit: u64,
+    /// Stored in sync.json, since settings.json itself gets synced
+    #[serde(skip)]
+    pub sync: SyncConfig,
+    /// Stored in local.json, since these only make sense on this machine
+    #[serde(skip)]
+    pub local: LocalSettings,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocalSettings {
+    /// Where new instances get installed, defaults to a folder in the data dir
+    pub instances_dir: Option<PathBuf>,
+    /// How the list of instances is stored in the instances dir
+    pub instance_store: StoreType,
+    /// Size and position of the window when it was last closed
+    pub window: Option<WindowGeometry>,
+    /// Share of the window height taken by the tabs, the log gets the rest
+    pub log_split: f32,
+    /// Scheduled backups of instances into a folder on this machine
+    pub backups: BackupConfig,
+    /// Whether the user agreed to share usage statistics, asked per machine
+    pub telemetry: bool,
+    /// Proxy and credentials, which depend on the network this machine is in
+    pub http: HttpConfig,
+    /// How far the instance list was scrolled down when the launcher was closed, in pixels
+    pub instances_scroll: f32,
+    /// The output device music plays through, the system default if None
+    pub audio_device: Option<String>,
+    /// GiB per month after which downloads are warned about, 0 disables the warning
+    pub download_cap: u64,
+}
+
+impl Default for LocalSettings {
+    fn default() -> Self {
+        Self {
+            instances_dir: None,
+            instance_store: StoreType::default(),
+            window: None,
+            log_split: 0.75,
+            backups: BackupConfig::default(),
+            telemetry: false,
+            http: HttpConfig::default(),
+            instances_scroll: 0.0,
+            audio_device: None,
+            download_cap: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub width: f32,
+    pub height: f32,
+    /// Unknown until the window has been moved at least once
+    pub x: Option<i32>,
+    pub y: Option<i32>,
+}
+
+impl LocalSettings {
+    pub fn load() -> Self {
+        load_file("local.json")
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            music_state: MusicState::default(),
+            game_soundtrack: false,
+            music_while_playing: MusicWhilePlaying::Pause,
+            quiet_mode: false,
+            kiosk_mode: false,
+            log_level: LogLevel::default(),
+            theme: ThemeChoice::default(),
+            button_labels: false,
+            instance_sort: InstanceSort::default(),
+            double_click_action: DoubleClickAction::default(),
+            save_snapshots: 5,
+            keep_versions: 0,
+            allow_concurrent_games: false,
+            detach_games: false,
+            keep_log_sessions: 20,
+            keep_log_days: 0,
+            game_log_limit: 100,
+            play_reminder: 0,
+            stall_timeout: 5,
+            update_check_interval: 60,
+            housekeeping_days: 30,
+            cache_limit: 200,
+            sync: SyncConfig::default(),
+            local: LocalSettings::default(),
+        }
+    }
 }
 
 impl Settings {
     pub fn save(&self) -> Result<()> {
-        let mut settings_file =
-            get_data_dir().ok_or_else(|| anyhow!("Failed to get app save dir"))?;
-        settings_file.push("settings.json");
+        if !read_only::is_persistent() {
+            return Ok(());
+        }
+        let data_dir = get_data_dir().ok_or_else(|| anyhow!("Failed to get app save dir"))?;
 
-        let file = File::create(settings_file)?;
+        let file = File::create(data_dir.join("settings.json"))?;
         serde_json::to_writer_pretty(file, self)?;
+        let file = File::create(data_dir.join("sync.json"))?;
+        serde_json::to_writer_pretty(file, &self.sync)?;
+        let file = File::create(data_dir.join("local.json"))?;
+        serde_json::to_writer_pretty(file, &self.local)?;
+        instance::forget_instances_dir();
         Ok(())
     }
 
     pub fn load() -> Self {
-        let mut settings_file = get_data_dir()
-            .ok_or_else(|| anyhow!("Failed to get app save dir"))
-            .unwrap();
-        settings_file.push("settings.json");
+        let mut settings: Self = load_file("settings.json");
+        settings.sync = load_file("sync.json");
+        settings.local = LocalSettings::load();
+        settings
+    }
+}
 
-        if !settings_file.exists() {
-            return Self::default();
-        }
+fn load_file<T: DeserializeOwned + Default>(name: &str) -> T {
+    let mut path = get_data_dir()
+        .ok_or_else(|| anyhow!("Failed to get app save dir"))
+        .unwrap();
+    path.push(name);
+
+    if !path.exists() {
+        return T::default();
+    }
 
-        match File::open(settings_file)
-            .with_context(|| "Failed to open settings.json")
-            .and_then(|f| {
-                serde_json::from_reader(f).with_context(|| "Failed to deserialize settings.json")
-            }) {
-            Ok(s) => s,
-            Err(e) => {
-                warn!("{:#?}", e);
-                Self::default()
-            }
+    match File::open(path)
+        .with_context(|| format!("Failed to open {name}"))
+        .and_then(|f| {
+            serde_json::from_reader(f).with_context(|| format!("Failed to deserialize {name}"))
+        }) {
+        Ok(s) => s,
+        Err(e) => {
+            warn!("{:#?}", e);
+            T::default()
         }
     }
 }
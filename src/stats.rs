@@ -0,0 +1,430 @@
+use crate::activity::{self, Entry};
+use crate::bandwidth::Ledger;
+use crate::benchmark::{self, Sample};
+use crate::settings::LocalSettings;
+use crate::style::text_button;
+use crate::{get_data_dir, read_only, Message};
+use anyhow::Result;
+use iced::widget::{Button, Column, Container, Row, Scrollable, Space, Text};
+use iced::{theme, Alignment, Color, Command, Element, Length};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::sync::Mutex;
+use std::time::Duration;
+use time::{format_description, OffsetDateTime};
+
+/// Serializes read-modify-write cycles of stats.json, since games can be running in parallel
+static LOCK: Mutex<()> = Mutex::new(());
+/// How many runs are remembered per instance
+const RUN_HISTORY_LIMIT: usize = 20;
+/// Entries of the activity log listed, the rest is only in activity.json
+const ACTIVITY_SHOWN: usize = 50;
+
+/// One session of playing an instance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Run {
+    /// Unix timestamp
+    pub started: i64,
+    /// In seconds
+    pub duration: u64,
+    pub success: bool,
+    /// None if the game was killed by a signal
+    pub exit_code: Option<i32>,
+}
+
+impl Run {
+    pub fn status(&self) -> String {
+        match self.exit_code {
+            _ if self.success => "exited normally".into(),
+            // Windows exit codes are NTSTATUS values, which are easier to look up in hex
+            Some(code) if code < 0 => format!("crashed with {:#X}", code as u32),
+            Some(code) => format!("exited with code {code}"),
+            None => "killed by a signal".into(),
+        }
+    }
+
+    pub fn started(&self) -> String {
+        OffsetDateTime::from_unix_timestamp(self.started)
+            .ok()
+            .and_then(|t| {
+                t.format(
+                    &format_description::parse("[year]-[month]-[day] [hour]:[minute] UTC").ok()?,
+                )
+                .ok()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstanceStats {
+    /// In seconds
+    pub playtime: u64,
+    pub launches: u32,
+    /// Unix timestamp
+    pub last_played: Option<i64>,
+    /// The most recent runs, oldest first
+    pub runs: Vec<Run>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub instances: BTreeMap<String, InstanceStats>,
+    pub installs: u32,
+    pub updates: u32,
+}
+
+impl Stats {
+    fn path() -> Option<PathBuf> {
+        Some(get_data_dir()?.join("stats.json"))
+    }
+
+    pub fn load() -> Self {
+        Self::path()
+            .filter(|p| p.exists())
+            .and_then(|p| File::open(p).ok())
+            .and_then(|f| match serde_json::from_reader(f) {
+                Ok(stats) => Some(stats),
+                Err(e) => {
+                    warn!("Failed to deserialize stats.json: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = Self::path().ok_or_else(|| anyhow!("Failed to get app save dir"))?;
+        serde_json::to_writer_pretty(File::create(path)?, self)?;
+        Ok(())
+    }
+
+    pub fn total_playtime(&self) -> u64 {
+        self.instances.values().map(|i| i.playtime).sum()
+    }
+
+    pub fn total_launches(&self) -> u32 {
+        self.instances.values().map(|i| i.launches).sum()
+    }
+}
+
+fn modify(f: impl FnOnce(&mut Stats)) {
+    if !read_only::is_persistent() {
+        return;
+    }
+    let _guard = LOCK.lock();
+    let mut stats = Stats::load();
+    f(&mut stats);
+    if let Err(e) = stats.save() {
+        error!("Failed to save stats.json: {:#}", e);
+    }
+}
+
+/// Returns the run as it was recorded.
+pub fn record_launch(instance_name: &str, playtime: Duration, status: ExitStatus) -> Run {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let run = Run {
+        started: now - playtime.as_secs() as i64,
+        duration: playtime.as_secs(),
+        success: status.success(),
+        exit_code: status.code(),
+    };
+    modify(|stats| {
+        let entry = stats.instances.entry(instance_name.into()).or_default();
+        entry.playtime += playtime.as_secs();
+        entry.launches += 1;
+        entry.last_played = Some(now);
+        entry.runs.push(run.clone());
+        let excess = entry.runs.len().saturating_sub(RUN_HISTORY_LIMIT);
+        entry.runs.drain(..excess);
+    });
+    run
+}
+
+/// The most recent runs of an instance, newest first.
+pub fn runs(instance_name: &str) -> Vec<Run> {
+    let mut runs = Stats::load()
+        .instances
+        .remove(instance_name)
+        .map(|s| s.runs)
+        .unwrap_or_default();
+    runs.reverse();
+    runs
+}
+
+/// Moves the stats of a renamed instance over to its new name.
+pub fn rename(old_name: &str, new_name: &str) {
+    modify(|stats| {
+        if let Some(entry) = stats.instances.remove(old_name) {
+            stats.instances.insert(new_name.into(), entry);
+        }
+    });
+}
+
+pub fn record_install() {
+    modify(|stats| stats.installs += 1);
+}
+
+pub fn record_update() {
+    modify(|stats| stats.updates += 1);
+}
+
+pub fn format_duration(seconds: u64) -> String {
+    let (hours, minutes) = (seconds / 3600, seconds / 60 % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+/// How long ago a unix timestamp was, in days, like "3 days ago".
+pub fn format_age(timestamp: i64) -> String {
+    let days = (OffsetDateTime::now_utc().unix_timestamp() - timestamp) / (24 * 60 * 60);
+    match days {
+        ..=0 => "today".into(),
+        1 => "yesterday".into(),
+        days => format!("{days} days ago"),
+    }
+}
+
+pub(crate) fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KiB", "MiB", "GiB"] {
+        if size < 1024. {
+            return format!("{size:.1} {unit}");
+        }
+        size /= 1024.;
+    }
+    format!("{size:.1} TiB")
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StatsSnapshot {
+    stats: Stats,
+    bandwidth: Ledger,
+    /// In GiB, 0 if there's none
+    download_cap: u64,
+    /// Disk usage per instance, in bytes
+    disk_usage: BTreeMap<String, u64>,
+    benchmarks: BTreeMap<String, Vec<Sample>>,
+    /// Newest first
+    activity: Vec<Entry>,
+}
+
+async fn collect(instances: Vec<(String, PathBuf)>) -> StatsSnapshot {
+    let disk_usage = instances
+        .into_iter()
+        .map(|(name, path)| {
+            let size = fs_extra::dir::get_size(&path).unwrap_or_else(|e| {
+                warn!("Failed to get size of {}: {}", path.to_string_lossy(), e);
+                0
+            });
+            (name, size)
+        })
+        .collect();
+    StatsSnapshot {
+        stats: Stats::load(),
+        bandwidth: Ledger::load(),
+        download_cap: LocalSettings::load().download_cap,
+        disk_usage,
+        benchmarks: benchmark::load(),
+        activity: activity::load().into_iter().rev().collect(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum StatsMessage {
+    Refresh(Vec<(String, PathBuf)>),
+    Loaded(StatsSnapshot),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StatsFrame {
+    snapshot: Option<StatsSnapshot>,
+}
+
+impl StatsFrame {
+    pub fn update(&mut self, message: StatsMessage) -> Command<Message> {
+        match message {
+            StatsMessage::Refresh(instances) => {
+                return Command::perform(collect(instances), |snapshot| {
+                    Message::StatsMessage(StatsMessage::Loaded(snapshot))
+                });
+            }
+            StatsMessage::Loaded(snapshot) => self.snapshot = Some(snapshot),
+        }
+        Command::none()
+    }
+
+    pub fn view(&self, instances: Vec<(String, PathBuf)>) -> Element<StatsMessage> {
+        let header = Row::new()
+            .align_items(Alignment::Center)
+            .push(Text::new("Statistics").size(26))
+            .push(Space::new(Length::Fill, Length::Shrink))
+            .push(
+                Button::new(Text::new("Refresh"))
+                    .style(text_button())
+                    .on_press(StatsMessage::Refresh(instances)),
+            );
+
+        let content: Element<_> = match &self.snapshot {
+            None => Text::new("Loading statistics…")
+                .style(theme::Text::Color(Color::from_rgb8(150, 150, 150)))
+                .into(),
+            Some(snapshot) => {
+                let stats = &snapshot.stats;
+                let summary = Column::new()
+                    .spacing(5)
+                    .push(stat_row(
+                        "Total playtime",
+                        format_duration(stats.total_playtime()),
+                    ))
+                    .push(stat_row("Launches", stats.total_launches().to_string()))
+                    .push(stat_row("Installs performed", stats.installs.to_string()))
+                    .push(stat_row("Updates performed", stats.updates.to_string()))
+                    .push(stat_row(
+                        "Disk used by instances",
+                        format_size(snapshot.disk_usage.values().sum()),
+                    ));
+                let per_instance = snapshot.disk_usage.iter().fold(
+                    Column::new()
+                        .spacing(5)
+                        .push(Text::new("Per instance").size(20)),
+                    |column, (name, size)| {
+                        let instance = stats.instances.get(name).cloned().unwrap_or_default();
+                        column.push(stat_row(
+                            name,
+                            format!(
+                                "{} played  ·  {} launches  ·  {}",
+                                format_duration(instance.playtime),
+                                instance.launches,
+                                format_size(*size)
+                            ),
+                        ))
+                    },
+                );
+                let bandwidth = &snapshot.bandwidth;
+                let this_month = match snapshot.download_cap {
+                    0 => format_size(bandwidth.this_month()),
+                    cap => format!("{} of {cap} GiB", format_size(bandwidth.this_month())),
+                };
+                let downloads = bandwidth.previous_months().into_iter().take(12).fold(
+                    Column::new()
+                        .spacing(5)
+                        .push(Text::new("Downloads").size(20))
+                        .push(stat_row("Today", format_size(bandwidth.today())))
+                        .push(stat_row("This month", this_month)),
+                    |column, (month, bytes)| column.push(stat_row(&month, format_size(bytes))),
+                );
+                let load_times = (!snapshot.benchmarks.is_empty()).then(|| {
+                    snapshot.benchmarks.iter().fold(
+                        Column::new()
+                            .spacing(10)
+                            .push(Text::new("Load times").size(20)),
+                        |column, (name, samples)| {
+                            let load = benchmark::by_version(samples, |s| Some(s.load_ms));
+                            let window = benchmark::by_version(samples, |s| s.window_ms);
+                            column.push(load_time_chart(name.clone(), load)).push_maybe(
+                                (!window.is_empty()).then(|| {
+                                    load_time_chart(
+                                        format!("{name}, until the window shows"),
+                                        window,
+                                    )
+                                }),
+                            )
+                        },
+                    )
+                });
+                let recent_activity = snapshot.activity.iter().take(ACTIVITY_SHOWN).fold(
+                    Column::new()
+                        .spacing(5)
+                        .push(Text::new("Recent activity").size(20)),
+                    |column, entry| column.push(stat_row(&entry.event.to_string(), entry.time())),
+                );
+                Column::new()
+                    .spacing(20)
+                    .push(summary)
+                    .push(recent_activity)
+                    .push(downloads)
+                    .push(per_instance)
+                    .push_maybe(load_times)
+                    .into()
+            }
+        };
+
+        Container::new(Scrollable::new(
+            Column::new()
+                .spacing(20)
+                .padding(20)
+                .push(header)
+                .push(content),
+        ))
+        .width(Length::Fill)
+        .padding(iced::Padding {
+            top: 0.0,
+            right: 30.0,
+            bottom: 0.0,
+            left: 30.0,
+        })
+        .into()
+    }
+}
+
+/// A bar per version, as long as its median time relative to the slowest one.
+fn load_time_chart<'a>(title: String, versions: Vec<(String, u64)>) -> Element<'a, StatsMessage> {
+    let slowest = versions
+        .iter()
+        .map(|(_, ms)| *ms)
+        .max()
+        .unwrap_or_default()
+        .max(1);
+    versions
+        .into_iter()
+        .fold(
+            Column::new().spacing(3).push(Text::new(title)),
+            |column, (version, ms)| {
+                // Portions have to be non-zero, so the shortest bar is still visible
+                let portion = (ms * 100 / slowest).clamp(1, 100) as u16;
+                column.push(
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Alignment::Center)
+                        .push(Text::new(version).size(14).width(Length::Fixed(150.)))
+                        .push(
+                            Row::new()
+                                .width(Length::Fill)
+                                .push(
+                                    Container::new(Space::with_height(Length::Fixed(12.)))
+                                        .width(Length::FillPortion(portion))
+                                        .style(theme::Container::Box),
+                                )
+                                .push_maybe((portion < 100).then(|| {
+                                    Space::with_width(Length::FillPortion(100 - portion))
+                                })),
+                        )
+                        .push(
+                            Text::new(benchmark::format_ms(ms))
+                                .size(14)
+                                .width(Length::Fixed(60.))
+                                .style(theme::Text::Color(Color::from_rgb(0.4, 0.4, 0.4))),
+                        ),
+                )
+            },
+        )
+        .into()
+}
+
+fn stat_row<'a>(label: &str, value: String) -> Element<'a, StatsMessage> {
+    Row::new()
+        .spacing(10)
+        .push(Text::new(label.to_string()))
+        .push(Space::new(Length::Fill, Length::Shrink))
+        .push(Text::new(value).style(theme::Text::Color(Color::from_rgb(0.4, 0.4, 0.4))))
+        .into()
+}
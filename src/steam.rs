@@ -0,0 +1,286 @@
+//! Adds instances to the local Steam client as non-Steam games, so its overlay and
+//! controller configurations work with them. Steam keeps those in a binary VDF file per user.
+
+use crate::desktop_entry;
+use crate::instance::Instance;
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MAP: u8 = 0x00;
+const STRING: u8 = 0x01;
+const INT: u8 = 0x02;
+const MAP_END: u8 = 0x08;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    Map(Vec<(String, Value)>),
+    String(String),
+    Int(u32),
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn byte(&mut self) -> Result<u8> {
+        let byte = *self
+            .bytes
+            .get(self.position)
+            .ok_or_else(|| anyhow!("Unexpected end of file"))?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let rest = &self.bytes[self.position..];
+        let end = rest
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(|| anyhow!("Unterminated string"))?;
+        self.position += end + 1;
+        Ok(String::from_utf8_lossy(&rest[..end]).into())
+    }
+
+    fn int(&mut self) -> Result<u32> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + 4)
+            .ok_or_else(|| anyhow!("Unexpected end of file"))?;
+        self.position += 4;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn map(&mut self) -> Result<Vec<(String, Value)>> {
+        let mut entries = vec![];
+        loop {
+            let value = match self.byte()? {
+                MAP_END => return Ok(entries),
+                MAP => {
+                    let key = self.string()?;
+                    (key, Value::Map(self.map()?))
+                }
+                STRING => (self.string()?, Value::String(self.string()?)),
+                INT => (self.string()?, Value::Int(self.int()?)),
+                other => return Err(anyhow!("Unsupported VDF type {:#04x}", other)),
+            };
+            entries.push(value);
+        }
+    }
+}
+
+fn write_map(out: &mut Vec<u8>, entries: &[(String, Value)]) {
+    for (key, value) in entries {
+        let tag = match value {
+            Value::Map(_) => MAP,
+            Value::String(_) => STRING,
+            Value::Int(_) => INT,
+        };
+        out.push(tag);
+        out.extend(key.as_bytes());
+        out.push(0);
+        match value {
+            Value::Map(entries) => write_map(out, entries),
+            Value::String(s) => {
+                out.extend(s.as_bytes());
+                out.push(0);
+            }
+            Value::Int(i) => out.extend(i.to_le_bytes()),
+        }
+    }
+    out.push(MAP_END);
+}
+
+/// Where the Steam client keeps its per-user data
+fn userdata_dir() -> Option<PathBuf> {
+    let candidates: Vec<PathBuf> = if cfg!(windows) {
+        ["ProgramFiles(x86)", "ProgramFiles"]
+            .iter()
+            .filter_map(std::env::var_os)
+            .map(|dir| PathBuf::from(dir).join("Steam"))
+            .collect()
+    } else {
+        let home = PathBuf::from(std::env::var_os("HOME")?);
+        if cfg!(target_os = "macos") {
+            vec![home.join("Library/Application Support/Steam")]
+        } else {
+            vec![
+                home.join(".steam/steam"),
+                home.join(".local/share/Steam"),
+                home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"),
+            ]
+        }
+    };
+    candidates
+        .into_iter()
+        .map(|dir| dir.join("userdata"))
+        .find(|dir| dir.is_dir())
+}
+
+fn quote(path: &Path) -> String {
+    format!("\"{}\"", path.to_string_lossy())
+}
+
+/// Steam identifies non-Steam games by a checksum of their executable and name.
+fn app_id(exe: &str, name: &str) -> u32 {
+    crc32fast::hash(format!("{exe}{name}").as_bytes()) | 0x8000_0000
+}
+
+fn shortcut(instance: &Instance) -> Vec<(String, Value)> {
+    let name = format!("Endless Sky ({})", instance.name.replace('\n', " "));
+    let exe = quote(&instance.executable);
+    let start_dir = instance.executable.parent().map(quote).unwrap_or_default();
+    let launch_options: Vec<String> = desktop_entry::command_line(instance)
+        .into_iter()
+        .skip(1)
+        .map(|arg| {
+            if arg.contains(' ') {
+                format!("\"{arg}\"")
+            } else {
+                arg
+            }
+        })
+        .collect();
+    let icon = desktop_entry::icon_path(&instance.name)
+        .filter(|p| p.exists())
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let string = |key: &str, value: String| (key.to_string(), Value::String(value));
+    let int = |key: &str, value: u32| (key.to_string(), Value::Int(value));
+    vec![
+        int("appid", app_id(&exe, &name)),
+        string("AppName", name),
+        string("Exe", exe),
+        string("StartDir", start_dir),
+        string("icon", icon),
+        string("ShortcutPath", String::new()),
+        string("LaunchOptions", launch_options.join(" ")),
+        int("IsHidden", 0),
+        int("AllowDesktopConfig", 1),
+        int("AllowOverlay", 1),
+        int("OpenVR", 0),
+        int("Devkit", 0),
+        string("DevkitGameID", String::new()),
+        int("DevkitOverrideAppID", 0),
+        int("LastPlayTime", 0),
+        string("FlatpakAppID", String::new()),
+        ("tags".into(), Value::Map(vec![])),
+    ]
+}
+
+/// Adds the instance to the shortcuts of one Steam user, unless it's already there.
+fn add_to(file: &Path, instance: &Instance) -> Result<bool> {
+    let mut root = if file.exists() {
+        let bytes = fs::read(file)?;
+        Parser {
+            bytes: &bytes,
+            position: 0,
+        }
+        .map()?
+    } else {
+        vec![]
+    };
+    if !root.iter().any(|(key, _)| key == "shortcuts") {
+        root.push(("shortcuts".into(), Value::Map(vec![])));
+    }
+    let Some((_, Value::Map(shortcuts))) = root.iter_mut().find(|(key, _)| key == "shortcuts")
+    else {
+        return Err(anyhow!("Malformed shortcuts.vdf"));
+    };
+    let exe = Value::String(quote(&instance.executable));
+    let exists = shortcuts.iter().any(|(_, shortcut)| match shortcut {
+        Value::Map(fields) => fields
+            .iter()
+            .any(|(key, value)| key.eq_ignore_ascii_case("exe") && *value == exe),
+        _ => false,
+    });
+    if exists {
+        return Ok(false);
+    }
+    shortcuts.push((shortcuts.len().to_string(), Value::Map(shortcut(instance))));
+
+    let mut out = vec![];
+    write_map(&mut out, &root);
+    if file.exists() {
+        fs::copy(file, file.with_extension("vdf.bak"))?;
+    }
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(file, out)?;
+    info!("Added {} to {}", instance.name, file.to_string_lossy());
+    Ok(true)
+}
+
+/// Adds the instance for every Steam user on this computer, returning for how many it was new.
+/// Steam only picks the shortcut up after a restart, and overwrites the file if it's running meanwhile.
+pub fn add_shortcut(instance: &Instance) -> Result<usize> {
+    let userdata = userdata_dir().ok_or_else(|| anyhow!("Couldn't find a Steam installation"))?;
+    let mut added = 0;
+    for user in fs::read_dir(userdata)?.filter_map(Result::ok) {
+        let is_user = user
+            .file_name()
+            .to_string_lossy()
+            .chars()
+            .all(|c| c.is_ascii_digit());
+        if !is_user || !user.path().is_dir() {
+            continue;
+        }
+        if add_to(&user.path().join("config/shortcuts.vdf"), instance)? {
+            added += 1;
+        }
+    }
+    Ok(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(bytes: &[u8]) -> Result<Vec<(String, Value)>> {
+        Parser { bytes, position: 0 }.map()
+    }
+
+    #[test]
+    fn written_maps_parse_back() {
+        let entries = vec![(
+            "shortcuts".to_string(),
+            Value::Map(vec![(
+                "0".to_string(),
+                Value::Map(vec![
+                    ("AppName".into(), Value::String("Endless Sky".into())),
+                    ("appid".into(), Value::Int(0xdead_beef)),
+                    ("tags".into(), Value::Map(vec![])),
+                ]),
+            )]),
+        )];
+        let mut bytes = vec![];
+        write_map(&mut bytes, &entries);
+        assert_eq!(parse(&bytes).unwrap(), entries);
+    }
+
+    #[test]
+    fn parse_reads_the_binary_format() {
+        let bytes = b"\x00list\x00\x01name\x00value\x00\x02id\x00\x01\x02\x00\x00\x08\x08";
+        assert_eq!(
+            parse(bytes).unwrap(),
+            vec![(
+                "list".into(),
+                Value::Map(vec![
+                    ("name".into(), Value::String("value".into())),
+                    ("id".into(), Value::Int(0x0201)),
+                ])
+            )]
+        );
+    }
+
+    #[test]
+    fn parse_rejects_broken_files() {
+        assert!(parse(b"\x01name\x00value").is_err());
+        assert!(parse(b"\x02id\x00\x01\x02").is_err());
+        assert!(parse(b"\x07odd\x00\x08").is_err());
+        assert!(parse(b"\x00unterminated").is_err());
+    }
+}
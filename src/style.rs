@@ -1,7 +1,74 @@
+use crate::notifications::Level;
 use iced::border::Radius;
-use iced::widget::{button, container, Text};
-use iced::{alignment, Background, Border, Color, Font, Length, Theme, Vector};
+use iced::theme::Palette;
+use iced::widget::{button, container, Row, Text, Tooltip};
+use iced::{alignment, Alignment, Background, Border, Color, Element, Font, Length, Theme, Vector};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+const HIGH_CONTRAST: &str = "High contrast";
+
+static BUTTON_LABELS: AtomicBool = AtomicBool::new(false);
+/// Whether quiet mode is on, which keeps the UI from animating
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+/// How often spinners advance to their next frame
+pub const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+
+lazy_static! {
+    static ref SPINNER_START: Instant = Instant::now();
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThemeChoice {
+    #[default]
+    Standard,
+    /// Stronger contrast, and states are shown with icons and labels rather than color alone
+    HighContrast,
+}
+
+impl ThemeChoice {
+    pub const ALL: [Self; 2] = [Self::Standard, Self::HighContrast];
+
+    pub fn theme(self) -> Theme {
+        match self {
+            Self::Standard => Theme::custom("LightModified".into(), {
+                let mut palette = Palette::LIGHT;
+                palette.primary = Color::from_rgb(0.2, 0.2, 0.2);
+                palette
+            }),
+            Self::HighContrast => Theme::custom(
+                HIGH_CONTRAST.into(),
+                Palette {
+                    background: Color::WHITE,
+                    text: Color::BLACK,
+                    primary: Color::BLACK,
+                    // Blue and orange stay distinguishable with the common kinds of colorblindness
+                    success: Color::from_rgb8(0, 90, 181),
+                    danger: Color::from_rgb8(220, 50, 32),
+                },
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ThemeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Standard => write!(f, "Standard"),
+            Self::HighContrast => write!(f, "{HIGH_CONTRAST}"),
+        }
+    }
+}
+
+fn is_high_contrast(theme: &Theme) -> bool {
+    theme.to_string() == HIGH_CONTRAST
+}
 
 fn icon(unicode: char) -> Text<'static> {
     Text::new(unicode.to_string())
@@ -39,6 +106,73 @@ pub fn folder_icon() -> Text<'static> {
     icon('\u{E930}')
 }
 
+pub fn menu_icon() -> Text<'static> {
+    icon('\u{E9BD}')
+}
+
+pub fn checkmark_icon() -> Text<'static> {
+    icon('\u{EA10}')
+}
+
+pub fn warning_icon() -> Text<'static> {
+    icon('\u{EA07}')
+}
+
+/// Sets whether icon buttons spell out what they do next to their icon.
+pub fn set_button_labels(enabled: bool) {
+    BUTTON_LABELS.store(enabled, Ordering::Relaxed);
+}
+
+/// Sets whether the UI stays still, e.g. spinners show a fixed frame.
+pub fn set_quiet(enabled: bool) {
+    QUIET.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// The content of an icon button, followed by `label` if button labels are enabled.
+pub fn labeled<'a, M: 'a>(icon: Text<'a>, label: &'a str) -> Element<'a, M> {
+    if BUTTON_LABELS.load(Ordering::Relaxed) {
+        Row::new()
+            .spacing(5)
+            .align_items(Alignment::Center)
+            .push(icon)
+            .push(Text::new(label).size(14))
+            .into()
+    } else {
+        icon.into()
+    }
+}
+
+/// Shows `text` when hovering over `content`, so icons can be told apart before clicking them.
+pub fn tooltip<'a, M: 'a>(content: impl Into<Element<'a, M>>, text: &'a str) -> Element<'a, M> {
+    Tooltip::new(
+        content,
+        Text::new(text).size(12),
+        iced::widget::tooltip::Position::Bottom,
+    )
+    .gap(4)
+    .padding(5)
+    .style(iced::theme::Container::Box)
+    .into()
+}
+
+/// Indicates ongoing work. Its frame is derived from the time, so it only animates
+/// as long as the view is redrawn every [`SPINNER_INTERVAL`]. In quiet mode it stands still.
+pub fn spinner() -> Text<'static> {
+    let text = if is_quiet() {
+        '…'
+    } else {
+        let frame = SPINNER_START.elapsed().as_millis() / SPINNER_INTERVAL.as_millis();
+        SPINNER_FRAMES[frame as usize % SPINNER_FRAMES.len()]
+    };
+    Text::new(text.to_string())
+        .font(Font::with_name("DejaVu Sans Mono"))
+        .size(20)
+}
+
 pub fn icon_button() -> iced::theme::Button {
     iced::theme::Button::Custom(Box::new(ButtonStyle::Icon))
 }
@@ -50,8 +184,17 @@ pub fn tab_bar() -> iced_aw::style::tab_bar::TabBarStyles {
     iced_aw::style::tab_bar::TabBarStyles::Custom(Rc::new(CustomTabBar))
 }
 
-pub fn log_container(log: &str) -> iced::theme::Container {
-    iced::theme::Container::Custom(Box::new(LogContainer::from(log)))
+pub fn log_container(level: log::Level) -> iced::theme::Container {
+    iced::theme::Container::Custom(Box::new(LogContainer::from(level)))
+}
+
+/// Outlines what keyboard navigation is on.
+pub fn focus_outline() -> iced::theme::Container {
+    iced::theme::Container::Custom(Box::new(FocusOutline))
+}
+
+pub fn notification_container(level: Level) -> iced::theme::Container {
+    iced::theme::Container::Custom(Box::new(NotificationContainer(level)))
 }
 
 /// graphic design is my passion
@@ -63,17 +206,26 @@ pub enum ButtonStyle {
 impl button::StyleSheet for ButtonStyle {
     type Style = Theme;
 
-    fn active(&self, _: &Self::Style) -> button::Appearance {
+    fn active(&self, style: &Self::Style) -> button::Appearance {
+        let high_contrast = is_high_contrast(style);
         match self {
             Self::Icon => button::Appearance {
-                text_color: Color::from_rgb(0.5, 0.5, 0.5),
+                text_color: if high_contrast {
+                    Color::BLACK
+                } else {
+                    Color::from_rgb(0.5, 0.5, 0.5)
+                },
                 ..Default::default()
             },
             Self::Text => button::Appearance {
                 background: Some(Background::Color(Color::WHITE)),
                 border: Border {
-                    color: Color::from_rgb(0.8, 0.8, 0.8),
-                    width: 0.3,
+                    color: if high_contrast {
+                        Color::BLACK
+                    } else {
+                        Color::from_rgb(0.8, 0.8, 0.8)
+                    },
+                    width: if high_contrast { 1.5 } else { 0.3 },
                     radius: Radius::from(2.0),
                 },
                 shadow_offset: Vector::new(0.3, 0.3),
@@ -87,14 +239,18 @@ impl button::StyleSheet for ButtonStyle {
 
         match self {
             Self::Icon => button::Appearance {
-                text_color: Color::from_rgb(0.3, 0.3, 0.3),
+                text_color: if is_high_contrast(style) {
+                    Color::from_rgb8(0, 90, 181)
+                } else {
+                    Color::from_rgb(0.3, 0.3, 0.3)
+                },
                 shadow_offset: active.shadow_offset + Vector::new(0.0, 1.0),
                 ..active
             },
             Self::Text => button::Appearance {
                 border: Border {
                     color: Color::from_rgb(0.4, 0.4, 0.4),
-                    ..Default::default()
+                    ..active.border
                 },
                 shadow_offset: active.shadow_offset + Vector::new(0.1, 0.3),
                 ..active
@@ -115,18 +271,24 @@ impl button::StyleSheet for ButtonStyle {
 }
 
 pub struct LogContainer {
+    text_color: Color,
     background: Option<Color>,
 }
 
-impl From<&str> for LogContainer {
-    fn from(log: &str) -> Self {
+impl From<log::Level> for LogContainer {
+    fn from(level: log::Level) -> Self {
         Self {
-            background: if log.starts_with("WARN") {
-                Some(Color::new(1., 1., 0.5, 0.5))
-            } else if log.starts_with("ERROR") {
-                Some(Color::new(1., 0.5, 0.5, 0.5))
-            } else {
-                None
+            // Pure yellow is unreadable on white, so warnings get a darker amber
+            text_color: match level {
+                log::Level::Error => Color::from_rgb8(190, 30, 30),
+                log::Level::Warn => Color::from_rgb8(150, 105, 0),
+                log::Level::Info => Color::from_rgb(0.4, 0.4, 0.4),
+                log::Level::Debug | log::Level::Trace => Color::from_rgb(0.7, 0.7, 0.7),
+            },
+            background: match level {
+                log::Level::Warn => Some(Color::new(1., 1., 0.5, 0.5)),
+                log::Level::Error => Some(Color::new(1., 0.5, 0.5, 0.5)),
+                _ => None,
             },
         }
     }
@@ -135,10 +297,69 @@ impl From<&str> for LogContainer {
 impl container::StyleSheet for LogContainer {
     type Style = Theme;
 
-    fn appearance(&self, _: &Self::Style) -> container::Appearance {
+    fn appearance(&self, style: &Self::Style) -> container::Appearance {
+        let high_contrast = is_high_contrast(style);
+        container::Appearance {
+            text_color: Some(if high_contrast {
+                Color::BLACK
+            } else {
+                self.text_color
+            }),
+            background: self
+                .background
+                .map(|c| Color {
+                    a: if high_contrast { 1. } else { c.a },
+                    ..c
+                })
+                .map(Background::Color),
+            ..Default::default()
+        }
+    }
+}
+
+pub struct NotificationContainer(Level);
+
+impl container::StyleSheet for NotificationContainer {
+    type Style = Theme;
+
+    fn appearance(&self, style: &Self::Style) -> container::Appearance {
+        let high_contrast = is_high_contrast(style);
+        let accent = match self.0 {
+            Level::Info => Color::from_rgb(0.5, 0.7, 1.),
+            Level::Success => Color::from_rgb(0.5, 0.9, 0.6),
+            Level::Warning => Color::new(1., 1., 0.5, 1.),
+            Level::Error => Color::new(1., 0.5, 0.5, 1.),
+        };
+        container::Appearance {
+            background: Some(Background::Color(Color { a: 0.3, ..accent })),
+            border: Border {
+                color: if high_contrast { Color::BLACK } else { accent },
+                width: if high_contrast { 2. } else { 1. },
+                radius: Radius::from(4.0),
+            },
+            ..Default::default()
+        }
+    }
+}
+
+pub struct FocusOutline;
+
+impl container::StyleSheet for FocusOutline {
+    type Style = Theme;
+
+    fn appearance(&self, style: &Self::Style) -> container::Appearance {
+        let high_contrast = is_high_contrast(style);
         container::Appearance {
-            text_color: Some(Color::from_rgb(0.6, 0.6, 0.6)),
-            background: self.background.map(Background::Color),
+            border: Border {
+                // Blue stands out from the gray borders, and from the accents of either theme
+                color: if high_contrast {
+                    Color::from_rgb8(0, 90, 181)
+                } else {
+                    Color::from_rgb8(30, 120, 215)
+                },
+                width: if high_contrast { 3. } else { 2. },
+                radius: Radius::from(4.0),
+            },
             ..Default::default()
         }
     }
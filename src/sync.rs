@@ -0,0 +1,531 @@
+use crate::notifications::{notify, Level, Notification};
+use crate::settings::Settings;
+use crate::settings_frame::SettingsMessage;
+use crate::{get_data_dir, get_game_data_dir, http, send_message, Message};
+use anyhow::{Context, Result};
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use time::OffsetDateTime;
+
+const MANIFEST_NAME: &str = "eslauncher2-sync.json";
+/// Launcher files that are kept in sync, relative to the data dir
+const LAUNCHER_FILES: [&str; 2] = ["settings.json", "plugin_profiles.json"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncBackendType {
+    #[default]
+    Disabled,
+    Folder,
+    WebDav,
+}
+
+impl SyncBackendType {
+    pub const ALL: [Self; 3] = [Self::Disabled, Self::Folder, Self::WebDav];
+}
+
+impl fmt::Display for SyncBackendType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Disabled => write!(f, "Disabled"),
+            Self::Folder => write!(f, "Folder"),
+            Self::WebDav => write!(f, "WebDAV"),
+        }
+    }
+}
+
+/// Where to sync to. This is machine-specific, so it's stored in its own file and never synced itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncConfig {
+    pub backend: SyncBackendType,
+    /// A folder path or a WebDAV URL, which may contain credentials
+    pub location: String,
+}
+
+/// Maps relative file paths to content hashes
+type Manifest = BTreeMap<String, u64>;
+
+trait SyncBackend {
+    fn manifest(&self) -> Result<Manifest>;
+    fn write_manifest(&self, manifest: &Manifest) -> Result<()>;
+    fn download(&self, path: &str) -> Result<Vec<u8>>;
+    fn upload(&self, path: &str, data: &[u8]) -> Result<()>;
+    fn delete(&self, path: &str) -> Result<()>;
+}
+
+/// A plain folder, usually one that is synced by another program like Syncthing or Dropbox.
+struct FolderBackend {
+    root: PathBuf,
+}
+
+impl SyncBackend for FolderBackend {
+    fn manifest(&self) -> Result<Manifest> {
+        // An unmounted drive or a sync program that hasn't started yet would look like a folder
+        // that was emptied, so its files would be deleted locally too
+        if !self.root.is_dir() {
+            return Err(anyhow!(
+                "The sync folder {} doesn't exist, is its drive connected?",
+                self.root.to_string_lossy()
+            ));
+        }
+        // The folder may have been changed by another program, so always hash the actual files
+        let mut manifest = Manifest::new();
+        for dir in ["launcher", "saves"] {
+            let Ok(entries) = fs::read_dir(self.root.join(dir)) else {
+                continue;
+            };
+            for entry in entries.filter_map(Result::ok) {
+                let path = format!("{}/{}", dir, entry.file_name().to_string_lossy());
+                if entry.path().is_file() && is_synced(&path) {
+                    manifest.insert(path, hash(&fs::read(entry.path())?));
+                }
+            }
+        }
+        Ok(manifest)
+    }
+
+    fn write_manifest(&self, _manifest: &Manifest) -> Result<()> {
+        Ok(())
+    }
+
+    fn download(&self, path: &str) -> Result<Vec<u8>> {
+        Ok(fs::read(self.root.join(path))?)
+    }
+
+    fn upload(&self, path: &str, data: &[u8]) -> Result<()> {
+        let target = self.root.join(path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(fs::write(target, data)?)
+    }
+
+    fn delete(&self, path: &str) -> Result<()> {
+        Ok(fs::remove_file(self.root.join(path))?)
+    }
+}
+
+/// A WebDAV collection. Since listing collections requires parsing PROPFIND responses,
+/// the contents are tracked in a manifest file stored alongside them instead.
+struct WebDavBackend {
+    url: String,
+}
+
+impl WebDavBackend {
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.url.trim_end_matches('/'), path)
+    }
+}
+
+impl SyncBackend for WebDavBackend {
+    fn manifest(&self) -> Result<Manifest> {
+        match http::agent().get(&self.url(MANIFEST_NAME)).call() {
+            Ok(res) => Ok(res.into_json()?),
+            Err(ureq::Error::Status(404, _)) => Ok(Manifest::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write_manifest(&self, manifest: &Manifest) -> Result<()> {
+        self.upload(MANIFEST_NAME, &serde_json::to_vec_pretty(manifest)?)
+    }
+
+    fn download(&self, path: &str) -> Result<Vec<u8>> {
+        let mut bytes = vec![];
+        http::agent()
+            .get(&self.url(path))
+            .call()?
+            .into_reader()
+            .read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn upload(&self, path: &str, data: &[u8]) -> Result<()> {
+        if let Some((dir, _)) = path.rsplit_once('/') {
+            // Fails if the collection already exists, which is fine
+            let _ = http::agent().request("MKCOL", &self.url(dir)).call();
+        }
+        http::agent().put(&self.url(path)).send_bytes(data)?;
+        Ok(())
+    }
+
+    fn delete(&self, path: &str) -> Result<()> {
+        http::agent().delete(&self.url(path)).call()?;
+        Ok(())
+    }
+}
+
+/// FNV-1a, which unlike the std hashers is guaranteed to be stable across builds
+fn hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Whether `path` is one of the files kept in sync, in either direction. Anything else found
+/// remotely is left alone.
+fn is_synced(path: &str) -> bool {
+    match path.split_once('/') {
+        Some(("launcher", file)) => LAUNCHER_FILES.contains(&file),
+        // Skip the game's own rolling backups
+        Some(("saves", file)) => {
+            !file.contains('/') && file.ends_with(".txt") && !file.contains("~~")
+        }
+        _ => false,
+    }
+}
+
+fn local_path(path: &str) -> Option<PathBuf> {
+    let (dir, file) = path.split_once('/')?;
+    match dir {
+        "launcher" if LAUNCHER_FILES.contains(&file) => Some(get_data_dir()?.join(file)),
+        "saves" => Some(get_game_data_dir()?.join("saves").join(file)),
+        _ => None,
+    }
+}
+
+fn local_manifest() -> Result<Manifest> {
+    let mut manifest = Manifest::new();
+    if let Some(data_dir) = get_data_dir() {
+        for file in LAUNCHER_FILES {
+            if let Ok(data) = fs::read(data_dir.join(file)) {
+                manifest.insert(format!("launcher/{file}"), hash(&data));
+            }
+        }
+    }
+    if let Some(saves_dir) = get_game_data_dir().map(|d| d.join("saves")) {
+        if saves_dir.exists() {
+            for entry in fs::read_dir(saves_dir)?.filter_map(Result::ok) {
+                let path = format!("saves/{}", entry.file_name().to_string_lossy());
+                if entry.path().is_file() && is_synced(&path) {
+                    manifest.insert(path, hash(&fs::read(entry.path())?));
+                }
+            }
+        }
+    }
+    Ok(manifest)
+}
+
+fn state_file() -> Option<PathBuf> {
+    Some(get_data_dir()?.join("sync_state.json"))
+}
+
+/// The manifest as of the last successful sync, used to tell which side changed a file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncState {
+    /// What was synced with, see [`target_id`]
+    target: u64,
+    files: Manifest,
+}
+
+/// Tells sync targets apart without storing the location, which may contain credentials.
+fn target_id(config: &SyncConfig) -> u64 {
+    hash(format!("{:?} {}", config.backend, config.location.trim()).as_bytes())
+}
+
+/// The files as of the last sync with the configured target. Nothing is known about other
+/// targets, so syncing with one for the first time only adds files on either side.
+fn load_state(config: &SyncConfig) -> Manifest {
+    state_file()
+        .and_then(|p| File::open(p).ok())
+        .and_then(|f| serde_json::from_reader::<_, SyncState>(f).ok())
+        .filter(|state| state.target == target_id(config))
+        .map(|state| state.files)
+        .unwrap_or_default()
+}
+
+/// Whether the remote has none of the synced files anymore, while local ones were synced
+/// before. That's more likely a broken remote than deliberately deleted files.
+fn remote_was_emptied(local: &Manifest, remote: &Manifest, base: &Manifest) -> bool {
+    !remote.keys().any(|path| is_synced(path)) && base.keys().any(|path| local.contains_key(path))
+}
+
+fn write_local(path: &str, data: &[u8]) -> Result<()> {
+    let target = local_path(path).ok_or_else(|| anyhow!("Unexpected sync path {}", path))?;
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(fs::write(target, data)?)
+}
+
+/// Stores the remote version of a conflicting pilot next to the local one.
+fn keep_conflict_copy(path: &str, data: &[u8]) -> Result<()> {
+    let target = local_path(path).ok_or_else(|| anyhow!("Unexpected sync path {}", path))?;
+    let stem = target
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let conflict = target.with_file_name(format!(
+        "{} (sync conflict {}).txt",
+        stem,
+        OffsetDateTime::now_utc().unix_timestamp()
+    ));
+    fs::write(&conflict, data)?;
+    warn!(
+        "{} was changed both locally and remotely, the remote version was saved as {}",
+        path,
+        conflict.to_string_lossy()
+    );
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    Nothing,
+    Upload,
+    DeleteRemote,
+    Download,
+    DeleteLocal,
+    /// For saves changed on both sides, keeps the remote one next to the local one
+    KeepConflictCopy,
+    /// Other files changed on both sides, where the local one wins
+    KeepLocal,
+}
+
+/// A three-way merge of a file, going by its hash locally, remotely and after the last sync.
+fn merge(path: &str, local: Option<&u64>, remote: Option<&u64>, base: Option<&u64>) -> Action {
+    if local == remote {
+        Action::Nothing
+    } else if remote == base {
+        // Only changed locally
+        match local {
+            Some(_) => Action::Upload,
+            None => Action::DeleteRemote,
+        }
+    } else if local == base {
+        // Only changed remotely
+        match remote {
+            Some(_) => Action::Download,
+            None => Action::DeleteLocal,
+        }
+    } else if path.starts_with("saves/") && remote.is_some() {
+        Action::KeepConflictCopy
+    } else {
+        Action::KeepLocal
+    }
+}
+
+/// `manual` syncs were started by the user, who is told to start one when the remote was emptied.
+pub fn sync(config: &SyncConfig, manual: bool) -> Result<()> {
+    let backend: Box<dyn SyncBackend> = match config.backend {
+        SyncBackendType::Disabled => return Ok(()),
+        SyncBackendType::Folder => Box::new(FolderBackend {
+            root: Path::new(config.location.trim()).to_path_buf(),
+        }),
+        SyncBackendType::WebDav => Box::new(WebDavBackend {
+            url: config.location.trim().to_string(),
+        }),
+    };
+    info!("Syncing saves and settings");
+
+    let mut base = load_state(config);
+    let local = local_manifest()?;
+    let mut remote = backend.manifest().context("Failed to read remote files")?;
+    if remote_was_emptied(&local, &remote, &base) {
+        if !manual {
+            warn!("The remote has none of the synced files anymore, not syncing");
+            notify(
+                Notification::new(
+                    Level::Warning,
+                    "The sync location has none of your pilots and settings anymore, so nothing was synced to keep them from being deleted here too. If it was emptied on purpose, use Sync now in the settings to upload them again.",
+                )
+                .sticky(),
+            );
+            return Ok(());
+        }
+        info!("The remote has none of the synced files anymore, uploading them again");
+        base.clear();
+    }
+    let mut settings_changed = false;
+
+    let paths: BTreeSet<String> = local.keys().chain(remote.keys()).cloned().collect();
+    for path in paths {
+        if !is_synced(&path) {
+            debug!("Leaving the unknown remote file {} alone", path);
+            continue;
+        }
+        let l = local.get(&path);
+        match merge(&path, l, remote.get(&path), base.get(&path)) {
+            Action::Nothing => {}
+            Action::Upload => {
+                debug!("Uploading {}", path);
+                backend.upload(&path, &fs::read(local_path(&path).unwrap())?)?;
+                remote.insert(path.clone(), *l.unwrap());
+            }
+            Action::DeleteRemote => {
+                debug!("Deleting remote {}", path);
+                backend.delete(&path)?;
+                remote.remove(&path);
+            }
+            Action::Download => {
+                debug!("Downloading {}", path);
+                write_local(&path, &backend.download(&path)?)?;
+                settings_changed |= path == "launcher/settings.json";
+            }
+            Action::DeleteLocal => {
+                debug!("Deleting local {}", path);
+                fs::remove_file(local_path(&path).unwrap())?;
+            }
+            Action::KeepConflictCopy => {
+                keep_conflict_copy(&path, &backend.download(&path)?)?;
+            }
+            Action::KeepLocal => {
+                warn!(
+                    "{} was changed both locally and remotely, keeping the local version",
+                    path
+                );
+                if let Some(l) = l {
+                    backend.upload(&path, &fs::read(local_path(&path).unwrap())?)?;
+                    remote.insert(path.clone(), *l);
+                }
+            }
+        }
+    }
+
+    backend.write_manifest(&remote)?;
+    if let Some(state_file) = state_file() {
+        let state = SyncState {
+            target: target_id(config),
+            files: remote,
+        };
+        serde_json::to_writer_pretty(File::create(state_file)?, &state)?;
+    }
+    if settings_changed {
+        send_message(Message::SettingsMessage(SettingsMessage::Reload));
+    }
+    info!("Sync finished");
+    Ok(())
+}
+
+pub async fn perform_sync(manual: bool) {
+    if let Err(e) = sync(&Settings::load().sync, manual) {
+        error!("Sync failed: {:#}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::TempDir;
+
+    const SAVE: &str = "saves/Some Pilot.txt";
+    const SETTINGS: &str = "launcher/settings.json";
+
+    #[test]
+    fn unchanged_files_are_left_alone() {
+        assert_eq!(merge(SAVE, Some(&1), Some(&1), Some(&1)), Action::Nothing);
+        // Changed the same way on both sides
+        assert_eq!(merge(SAVE, Some(&2), Some(&2), Some(&1)), Action::Nothing);
+        assert_eq!(merge(SAVE, None, None, Some(&1)), Action::Nothing);
+    }
+
+    #[test]
+    fn local_changes_go_out() {
+        assert_eq!(merge(SAVE, Some(&2), Some(&1), Some(&1)), Action::Upload);
+        assert_eq!(merge(SAVE, Some(&1), None, None), Action::Upload);
+        assert_eq!(merge(SAVE, None, Some(&1), Some(&1)), Action::DeleteRemote);
+    }
+
+    #[test]
+    fn remote_changes_come_in() {
+        assert_eq!(merge(SAVE, Some(&1), Some(&2), Some(&1)), Action::Download);
+        assert_eq!(merge(SAVE, None, Some(&1), None), Action::Download);
+        assert_eq!(merge(SAVE, Some(&1), None, Some(&1)), Action::DeleteLocal);
+    }
+
+    #[test]
+    fn conflicts_keep_both_saves_but_only_the_local_settings() {
+        assert_eq!(
+            merge(SAVE, Some(&2), Some(&3), Some(&1)),
+            Action::KeepConflictCopy
+        );
+        assert_eq!(
+            merge(SETTINGS, Some(&2), Some(&3), Some(&1)),
+            Action::KeepLocal
+        );
+        // Nothing to keep a copy of if it was deleted remotely
+        assert_eq!(merge(SAVE, Some(&2), None, Some(&1)), Action::KeepLocal);
+    }
+
+    #[test]
+    fn only_known_files_are_synced() {
+        assert!(is_synced(SAVE));
+        assert!(is_synced(SETTINGS));
+        assert!(is_synced("launcher/plugin_profiles.json"));
+        assert!(!is_synced("launcher/local.json"));
+        assert!(!is_synced("saves/Some Pilot.txt~~previous-1.txt"));
+        assert!(!is_synced("saves/nested/Pilot.txt"));
+        assert!(!is_synced("saves/notes.md"));
+        assert!(!is_synced("plugins/plugin.txt"));
+    }
+
+    #[test]
+    fn an_emptied_remote_is_not_synced() {
+        let manifest =
+            |paths: &[&str]| -> Manifest { paths.iter().map(|p| (p.to_string(), 1)).collect() };
+        let synced = manifest(&[SAVE, SETTINGS]);
+        assert!(remote_was_emptied(&synced, &Manifest::new(), &synced));
+        // Unknown files don't count
+        assert!(remote_was_emptied(
+            &synced,
+            &manifest(&["saves/notes.md"]),
+            &synced
+        ));
+        // The first sync with a new target, or nothing left here that could be deleted
+        assert!(!remote_was_emptied(
+            &synced,
+            &Manifest::new(),
+            &Manifest::new()
+        ));
+        assert!(!remote_was_emptied(
+            &Manifest::new(),
+            &Manifest::new(),
+            &synced
+        ));
+        assert!(!remote_was_emptied(&synced, &manifest(&[SAVE]), &synced));
+    }
+
+    #[test]
+    fn a_missing_sync_folder_fails() {
+        let dir = TempDir::new("sync-folder");
+        let missing = FolderBackend {
+            root: dir.path().join("unmounted"),
+        };
+        assert!(missing.manifest().is_err());
+        let empty = FolderBackend {
+            root: dir.path().to_path_buf(),
+        };
+        assert_eq!(empty.manifest().unwrap(), Manifest::new());
+    }
+
+    #[test]
+    fn targets_are_told_apart() {
+        let config = |backend, location: &str| SyncConfig {
+            backend,
+            location: location.into(),
+        };
+        let folder = target_id(&config(SyncBackendType::Folder, "/mnt/sync"));
+        assert_eq!(
+            folder,
+            target_id(&config(SyncBackendType::Folder, " /mnt/sync "))
+        );
+        assert_ne!(
+            folder,
+            target_id(&config(SyncBackendType::Folder, "/mnt/other"))
+        );
+        assert_ne!(
+            folder,
+            target_id(&config(SyncBackendType::WebDav, "/mnt/sync"))
+        );
+    }
+
+    #[test]
+    fn hash_is_fnv_1a() {
+        assert_eq!(hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -0,0 +1,150 @@
+//! Strictly opt-in usage statistics, to learn which instance types are actually used.
+//! Nothing is counted before the user agrees, and only totals are ever submitted.
+
+use crate::instance::InstanceType;
+use crate::settings::LocalSettings;
+use crate::{get_data_dir, http, network};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use time::OffsetDateTime;
+
+/// Where the totals get submitted. Only official builds set this, other builds just count locally.
+const ENDPOINT: Option<&str> = option_env!("ESLAUNCHER2_TELEMETRY_URL");
+/// In seconds
+const SUBMIT_INTERVAL: i64 = 7 * 24 * 60 * 60;
+
+/// Serializes read-modify-write cycles of telemetry.json
+static LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Telemetry {
+    /// By instance type
+    installs: BTreeMap<String, u32>,
+    launches: BTreeMap<String, u32>,
+    /// Unix timestamp of the first count since the last submission
+    since: Option<i64>,
+}
+
+/// Everything that gets submitted, with nothing that identifies the user or their instances
+#[derive(Serialize)]
+struct Payload<'a> {
+    launcher_version: &'a str,
+    os: &'a str,
+    arch: &'a str,
+    installs: &'a BTreeMap<String, u32>,
+    launches: &'a BTreeMap<String, u32>,
+}
+
+impl Telemetry {
+    fn path() -> Option<PathBuf> {
+        Some(get_data_dir()?.join("telemetry.json"))
+    }
+
+    fn load() -> Self {
+        Self::path()
+            .filter(|p| p.exists())
+            .and_then(|p| File::open(p).ok())
+            .and_then(|f| match serde_json::from_reader(f) {
+                Ok(telemetry) => Some(telemetry),
+                Err(e) => {
+                    warn!("Failed to deserialize telemetry.json: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = Self::path().ok_or_else(|| anyhow!("Failed to get app save dir"))?;
+        serde_json::to_writer_pretty(File::create(path)?, self)?;
+        Ok(())
+    }
+
+    fn payload(&self) -> Payload {
+        Payload {
+            launcher_version: version!(),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            installs: &self.installs,
+            launches: &self.launches,
+        }
+    }
+}
+
+fn is_enabled() -> bool {
+    LocalSettings::load().telemetry
+}
+
+fn modify(f: impl FnOnce(&mut Telemetry)) {
+    if !is_enabled() {
+        return;
+    }
+    let _guard = LOCK.lock();
+    let mut telemetry = Telemetry::load();
+    telemetry
+        .since
+        .get_or_insert_with(|| OffsetDateTime::now_utc().unix_timestamp());
+    f(&mut telemetry);
+    if let Err(e) = telemetry.save() {
+        error!("Failed to save telemetry.json: {:#}", e);
+    }
+}
+
+pub fn record_install(instance_type: InstanceType) {
+    modify(|t| *t.installs.entry(format!("{instance_type:?}")).or_default() += 1);
+}
+
+pub fn record_launch(instance_type: InstanceType) {
+    modify(|t| *t.launches.entry(format!("{instance_type:?}")).or_default() += 1);
+}
+
+/// Exactly what the next submission would contain, so users can check it.
+pub fn preview() -> String {
+    let telemetry = Telemetry::load();
+    serde_json::to_string_pretty(&telemetry.payload()).unwrap_or_default()
+}
+
+/// Forgets the counts, e.g. when the user opts out.
+pub fn clear() {
+    let _guard = LOCK.lock();
+    if let Some(path) = Telemetry::path().filter(|p| p.exists()) {
+        if let Err(e) = fs::remove_file(&path) {
+            error!("Failed to remove {}: {}", path.to_string_lossy(), e);
+        }
+    }
+}
+
+/// Submits the totals once a week, if the user agreed and this build has somewhere to send them.
+/// Counting starts over after each submission.
+pub async fn perform_submit() {
+    let Some(endpoint) = ENDPOINT else {
+        return;
+    };
+    if !is_enabled() || !network::is_online() {
+        return;
+    }
+    let _guard = LOCK.lock();
+    let telemetry = Telemetry::load();
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let due = telemetry
+        .since
+        .is_some_and(|since| now - since >= SUBMIT_INTERVAL);
+    if !due {
+        return;
+    }
+    match http::agent().post(endpoint).send_json(telemetry.payload()) {
+        Ok(_) => {
+            debug!("Submitted usage statistics");
+            if let Err(e) = Telemetry::default().save() {
+                error!("Failed to save telemetry.json: {:#}", e);
+            }
+        }
+        // Tried again next time, the counts are kept until then
+        Err(e) => debug!("Failed to submit usage statistics: {}", e),
+    }
+}
@@ -0,0 +1,97 @@
+use crate::get_data_dir;
+use crate::install_frame::InstanceSource;
+use crate::instance::Instance;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+
+/// The configuration of an instance, minus the installed build itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Template {
+    pub source: InstanceSource,
+    #[serde(default)]
+    pub plugin_profile: Option<String>,
+    #[serde(default)]
+    pub launch_args: String,
+    #[serde(default)]
+    pub isolated_config: bool,
+    /// See `groups`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+}
+
+impl Template {
+    pub fn from_instance(instance: &Instance) -> Self {
+        Self {
+            source: instance.source.clone(),
+            plugin_profile: instance.plugin_profile.clone(),
+            launch_args: instance.launch_args.clone(),
+            isolated_config: instance.isolated_config,
+            group: instance.group.clone(),
+        }
+    }
+
+    /// Applies everything except the source, which only matters when installing.
+    pub fn apply(&self, instance: &mut Instance) {
+        instance.plugin_profile = self.plugin_profile.clone();
+        instance.launch_args = self.launch_args.clone();
+        instance.isolated_config = self.isolated_config;
+        instance.group = self.group.clone();
+    }
+}
+
+/// Named templates to create instances from.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Templates(pub BTreeMap<String, Template>);
+
+impl Templates {
+    pub fn load() -> Self {
+        let Some(mut templates_file) = get_data_dir() else {
+            return Self::default();
+        };
+        templates_file.push("templates.json");
+        if !templates_file.exists() {
+            return Self::default();
+        }
+
+        match File::open(templates_file)
+            .with_context(|| "Failed to open templates.json")
+            .and_then(|f| {
+                serde_json::from_reader(f).with_context(|| "Failed to deserialize templates.json")
+            }) {
+            Ok(t) => t,
+            Err(e) => {
+                warn!("{:#?}", e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let mut templates_file =
+            get_data_dir().ok_or_else(|| anyhow!("Failed to get app save dir"))?;
+        templates_file.push("templates.json");
+
+        let file = File::create(templates_file)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+/// Saves the configuration of `instance` as a template named after it.
+pub fn save_instance(instance: &Instance) -> Result<()> {
+    let mut templates = Templates::load();
+    templates
+        .0
+        .insert(instance.name.clone(), Template::from_instance(instance));
+    templates.save()?;
+    info!("Saved {} as a template", instance.name);
+    Ok(())
+}
+
+pub fn delete(name: &str) -> Result<()> {
+    let mut templates = Templates::load();
+    templates.0.remove(name);
+    templates.save()
+}
@@ -0,0 +1,272 @@
+//! A holding area for deleted instances and plugins. They are moved there first and only purged
+//! once the chance to undo the deletion has passed, or on the next start if the launcher was
+//! closed before that.
+
+use crate::instance::Instance;
+use crate::notifications::{notify, Level, Notification};
+use crate::{activity, benchmark, get_data_dir};
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A bit longer than the notification offering the undo stays up
+pub const UNDO_WINDOW: Duration = Duration::from_secs(10);
+/// Holds folders next to where they were, if they're on another drive than the data dir
+const ELSEWHERE_DIR: &str = ".eslauncher2-trash";
+/// Lists the folders held elsewhere, so they're purged too if the launcher is closed meanwhile
+const ELSEWHERE_LIST: &str = "elsewhere";
+
+#[derive(Debug, Clone)]
+pub enum Deleted {
+    Instance(Box<Instance>),
+    /// By the plugin's name, its folder may be named differently
+    Plugin(String),
+    /// By the pilot's name, held along with its autosaves
+    Pilot(String),
+}
+
+impl Deleted {
+    /// Like "the instance X", for the activity log.
+    fn describe(&self) -> String {
+        match self {
+            Self::Instance(instance) => format!("the instance {}", instance.name),
+            Self::Plugin(name) => format!("the plugin {name}"),
+            Self::Pilot(name) => format!("the pilot {name}"),
+        }
+    }
+}
+
+impl fmt::Display for Deleted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Instance(instance) => f.write_str(&instance.name),
+            Self::Plugin(name) | Self::Pilot(name) => f.write_str(name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Held {
+    pub deleted: Deleted,
+    /// Where each folder came from, and where it's held now
+    moved: Vec<(PathBuf, PathBuf)>,
+    dir: PathBuf,
+    /// Holding folders on other drives, see [`ELSEWHERE_DIR`]
+    elsewhere: Vec<PathBuf>,
+}
+
+fn trash_dir() -> Option<PathBuf> {
+    Some(get_data_dir()?.join("trash"))
+}
+
+#[cfg(unix)]
+fn same_volume(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(a).is_ok_and(|a| fs::metadata(b).is_ok_and(|b| a.dev() == b.dev()))
+}
+
+/// Compares the drive letters or UNC shares.
+#[cfg(not(unix))]
+fn same_volume(a: &Path, b: &Path) -> bool {
+    let prefix = |path: &Path| {
+        fs::canonicalize(path).ok().and_then(|p| {
+            p.components()
+                .next()
+                .map(|c| c.as_os_str().to_ascii_lowercase())
+        })
+    };
+    prefix(a).is_some_and(|a| prefix(b) == Some(a))
+}
+
+/// Where to hold `path`, so it's renamed rather than copied: `dir` if it's on the same drive,
+/// otherwise a hidden folder next to `path`.
+fn holding_dir(path: &Path, dir: &Path) -> Result<PathBuf> {
+    if same_volume(path, dir) {
+        return Ok(dir.to_path_buf());
+    }
+    let parent = path
+        .parent()
+        .ok_or_else(|| anyhow!("{} has no parent", path.to_string_lossy()))?;
+    let name = dir.file_name().unwrap_or_default();
+    Ok(parent.join(ELSEWHERE_DIR).join(name))
+}
+
+/// Removes a holding folder on another drive, and the trash folder it was in once it's empty.
+fn remove_elsewhere(dir: &Path) {
+    if let Err(e) = fs::remove_dir_all(dir) {
+        error!("Failed to purge {}: {}", dir.to_string_lossy(), e);
+    }
+    if let Some(parent) = dir.parent() {
+        fs::remove_dir(parent).ok();
+    }
+}
+
+/// Renames where possible and copies otherwise, e.g. when the folder is on another drive.
+/// Single files, like pilots, are moved too.
+pub(crate) fn move_dir(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if from.is_file() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+        return Ok(());
+    }
+    let options = fs_extra::dir::CopyOptions::new().content_only(true);
+    fs_extra::dir::move_dir(from, to, &options)?;
+    Ok(())
+}
+
+/// Moves the given folders or files into the holding area. Ones that don't exist are skipped,
+/// and if one can't be moved, the ones moved so far are put back.
+pub fn hold(deleted: Deleted, paths: Vec<PathBuf>) -> Result<Held> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let dir = trash_dir()
+        .ok_or_else(|| anyhow!("Failed to get trash dir"))?
+        .join(nanos.to_string());
+    fs::create_dir_all(&dir)?;
+    let mut held = Held {
+        deleted,
+        moved: vec![],
+        dir,
+        elsewhere: vec![],
+    };
+    for (i, path) in paths.into_iter().filter(|p| p.exists()).enumerate() {
+        let result = holding_dir(&path, &held.dir).and_then(|holding| {
+            if holding != held.dir && !held.elsewhere.contains(&holding) {
+                fs::create_dir_all(&holding)?;
+                held.elsewhere.push(holding.clone());
+                let list: Vec<String> = held
+                    .elsewhere
+                    .iter()
+                    .map(|d| d.to_string_lossy().to_string())
+                    .collect();
+                fs::write(held.dir.join(ELSEWHERE_LIST), list.join("\n"))?;
+            }
+            let target = holding.join(i.to_string());
+            move_dir(&path, &target).map(|()| target)
+        });
+        let target = match result {
+            Ok(target) => target,
+            Err(e) => {
+                if let Err(e) = restore(&held) {
+                    error!("Failed to put back {}: {:#}", held.deleted, e);
+                }
+                return Err(e.context(format!("Failed to move {}", path.to_string_lossy())));
+            }
+        };
+        held.moved.push((path, target));
+    }
+    info!(
+        "Holding {} until it can't be restored anymore",
+        held.deleted
+    );
+    Ok(held)
+}
+
+pub async fn perform_hold(deleted: Deleted, paths: Vec<PathBuf>) -> Option<Box<Held>> {
+    let name = deleted.to_string();
+    match hold(deleted, paths) {
+        Ok(held) => {
+            activity::record(activity::Event::Deleted {
+                what: held.deleted.describe(),
+            });
+            Some(Box::new(held))
+        }
+        Err(e) => {
+            error!("Failed to delete {}: {:#}", name, e);
+            notify(Notification::new(
+                Level::Error,
+                format!("Deleting {name} failed, see the log for details"),
+            ));
+            None
+        }
+    }
+}
+
+/// Moves everything back where it came from, unless something else took its place meanwhile.
+pub fn restore(held: &Held) -> Result<()> {
+    for (original, target) in &held.moved {
+        if original.exists() {
+            return Err(anyhow!(
+                "{} exists again, not overwriting it",
+                original.to_string_lossy()
+            ));
+        }
+        move_dir(target, original)?;
+    }
+    for dir in &held.elsewhere {
+        remove_elsewhere(dir);
+    }
+    fs::remove_file(held.dir.join(ELSEWHERE_LIST)).ok();
+    if let Err(e) = fs::remove_dir(&held.dir) {
+        warn!("Failed to remove {}: {}", held.dir.to_string_lossy(), e);
+    }
+    info!("Restored {}", held.deleted);
+    Ok(())
+}
+
+pub async fn perform_restore(held: Box<Held>) -> Option<Deleted> {
+    match restore(&held) {
+        Ok(()) => {
+            activity::record(activity::Event::Undeleted {
+                what: held.deleted.describe(),
+            });
+            Some(held.deleted)
+        }
+        Err(e) => {
+            error!("Failed to restore {}: {:#}", held.deleted, e);
+            notify(Notification::new(
+                Level::Error,
+                format!("Restoring {} failed: {e}", held.deleted),
+            ));
+            None
+        }
+    }
+}
+
+/// Deletes the held folders for good once the undo window has passed. Restored ones are already gone.
+pub async fn purge_later(held: Box<Held>) {
+    tokio::time::sleep(UNDO_WINDOW).await;
+    if !held.dir.exists() {
+        return;
+    }
+    for dir in &held.elsewhere {
+        remove_elsewhere(dir);
+    }
+    match fs::remove_dir_all(&held.dir) {
+        Ok(()) => info!("Purged {}", held.deleted),
+        Err(e) => error!("Failed to purge {}: {}", held.dir.to_string_lossy(), e),
+    }
+    // Kept until now, so undoing the deletion keeps them too
+    if let Deleted::Instance(instance) = &held.deleted {
+        benchmark::forget(&instance.name);
+    }
+}
+
+/// Purges whatever was still held when the launcher was closed.
+pub async fn purge_leftovers() {
+    let Some(dir) = trash_dir().filter(|d| d.exists()) else {
+        return;
+    };
+    let lists = fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|e| fs::read_to_string(e.path().join(ELSEWHERE_LIST)).ok());
+    for list in lists {
+        list.lines()
+            .map(Path::new)
+            .filter(|d| d.exists())
+            .for_each(remove_elsewhere);
+    }
+    match fs::remove_dir_all(&dir) {
+        Ok(()) => debug!("Purged leftovers in {}", dir.to_string_lossy()),
+        Err(e) => warn!("Failed to purge {}: {}", dir.to_string_lossy(), e),
+    }
+}
@@ -1,11 +1,41 @@
-use crate::github::Artifact;
+use crate::bandwidth::{self, Meter};
+use crate::errors::ChecksumMismatch;
+use crate::github::{Artifact, PRState};
 use crate::install_frame::InstanceSourceType;
-use crate::instance::{Instance, InstanceType, Progress};
-use crate::{archive, github, install, jenkins, send_progress_message};
-use anyhow::Result;
+use crate::instance::{Instance, InstanceType, Provenance};
+use crate::job::{self, Progress};
+use crate::{archive, github, install, jenkins, modifications};
+use anyhow::{Context, Result};
 use futures::{StreamExt, TryStreamExt};
+use regex::Regex;
 use std::path::PathBuf;
+use std::sync::OnceLock;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 use tokio::fs::OpenOptions;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// How many instances are updated at once. Updates block the thread they run on for most of
+/// the time, so one of the runtime's worker threads is left for everything else.
+fn update_workers() -> usize {
+    std::thread::available_parallelism()
+        .map_or(1, |n| n.get().saturating_sub(1))
+        .max(1)
+}
+
+/// Waits until fewer than [`update_workers`] updates are running. Updates of different
+/// instances touch disjoint folders, so they only have to wait for a free worker.
+pub async fn acquire_slot(name: &str) -> SemaphorePermit<'static> {
+    static SLOTS: OnceLock<Semaphore> = OnceLock::new();
+    let slots = SLOTS.get_or_init(|| Semaphore::new(update_workers()));
+    if let Ok(permit) = slots.try_acquire() {
+        return permit;
+    }
+    job::stage(name, "Waiting for other updates");
+    job::expect_wait(name);
+    // The semaphore is never closed
+    slots.acquire().await.unwrap()
+}
 
 pub async fn update_instance(instance: Instance) -> Result<Instance> {
     if instance.instance_type == InstanceType::Unknown {
@@ -15,42 +45,124 @@ pub async fn update_instance(instance: Instance) -> Result<Instance> {
     let new_instance = if InstanceSourceType::Continuous == instance.source.r#type {
         match update_continuous_instance(&instance).await {
             Ok(i) => i,
+            Err(e) if job::is_cancelled(&e) => return Err(e),
             Err(e) => {
                 error!("Failed to perform incremental update: {}", e);
                 info!("falling back to reinstall");
-                install::install(
-                    instance.path.clone(),
-                    instance.name,
-                    instance.instance_type,
-                    instance.source,
-                )?
+                reinstall(&instance).await?
             }
         }
     } else {
-        let version = if InstanceSourceType::PR == instance.source.r#type {
-            github::get_pr(instance.source.identifier.parse()?)?
-                .head
-                .sha
-        } else {
-            // InstanceSourceType::Release
-            github::get_latest_release("endless-sky/endless-sky")?
-        };
+        let version = latest_version(&instance)?;
         if version.eq(&instance.version) {
             return Err(anyhow!("Latest version is already installed"));
         }
         info!(
             "Incremental update isn't supported for this InstanceSourceType, triggering reinstall"
         );
+        reinstall(&instance).await?
+    };
+
+    info!("Done!");
+    Ok(new_instance)
+}
+
+/// Installs the newest build over the instance. Nobody is around to choose between assets that
+/// fit equally well, so the one named like the installed build is picked, or else the first by
+/// name. Its pattern is kept, so later updates pick the same one.
+async fn reinstall(instance: &Instance) -> Result<Instance> {
+    let install = |source| {
         install::install(
             instance.path.clone(),
-            instance.name,
+            instance.name.clone(),
             instance.instance_type,
-            instance.source,
-        )?
+            source,
+        )
+    };
+    let error = match install(instance.source.clone()).await {
+        Err(e) if e.is::<install::AmbiguousAsset>() => e,
+        result => return result,
     };
+    let mut candidates = error
+        .downcast::<install::AmbiguousAsset>()
+        .map(|ambiguous| ambiguous.candidates)
+        .unwrap_or_default();
+    candidates.sort();
+    let like_installed = instance
+        .provenance
+        .asset_name
+        .as_deref()
+        .map(install::pattern_for_asset)
+        .filter(|pattern| {
+            Regex::new(pattern).is_ok_and(|regex| candidates.iter().any(|c| regex.is_match(c)))
+        });
+    let pattern = like_installed
+        .or_else(|| {
+            candidates
+                .first()
+                .map(|name| install::pattern_for_asset(name))
+        })
+        .ok_or_else(|| anyhow!("None of the assets fit"))?;
+    warn!(
+        "Several assets fit {}, using those matching '{}' from now on",
+        instance.name, pattern
+    );
+    let mut source = instance.source.clone();
+    source.asset_pattern = Some(pattern);
+    install(source).await
+}
 
-    info!("Done!");
-    Ok(new_instance)
+/// The newest version available from the instance's source.
+pub fn latest_version(instance: &Instance) -> Result<String> {
+    match instance.source.r#type {
+        InstanceSourceType::Continuous => jenkins::get_latest_sha(),
+        InstanceSourceType::PR => Ok(github::get_pr(instance.source.identifier.parse()?)?
+            .head
+            .sha),
+        InstanceSourceType::Release => github::get_latest_release(instance.source.repo()),
+    }
+}
+
+/// What the update check found out about an instance.
+#[derive(Debug, Clone)]
+pub struct UpdateStatus {
+    pub name: String,
+    pub update_available: bool,
+    /// Whether the PR of a PR instance is still open
+    pub pr_state: Option<PRState>,
+}
+
+fn check(instance: &Instance) -> Result<UpdateStatus> {
+    // The PR has both the newest commit and the state, so it only gets requested once
+    if instance.source.r#type == InstanceSourceType::PR {
+        let pr = github::get_pr(instance.source.identifier.parse()?)?;
+        return Ok(UpdateStatus {
+            name: instance.name.clone(),
+            update_available: pr.head.sha != instance.version,
+            pr_state: Some(pr.pr_state()),
+        });
+    }
+    Ok(UpdateStatus {
+        name: instance.name.clone(),
+        update_available: latest_version(instance)? != instance.version,
+        pr_state: None,
+    })
+}
+
+/// Checks every instance for newer versions, and PR instances for whether their PR was closed.
+/// Instances that couldn't be checked are left out.
+pub async fn check_for_updates(instances: Vec<Instance>) -> Vec<UpdateStatus> {
+    instances
+        .iter()
+        .filter(|instance| instance.instance_type != InstanceType::Unknown)
+        .filter_map(|instance| match check(instance) {
+            Ok(status) => Some(status),
+            Err(e) => {
+                warn!("Failed to check {} for updates: {:#}", instance.name, e);
+                None
+            }
+        })
+        .collect()
 }
 
 fn find_archive_path(instance_path: PathBuf, instance_type: InstanceType) -> Result<PathBuf> {
@@ -82,8 +194,13 @@ async fn update_continuous_instance(instance: &Instance) -> Result<Instance> {
         return Ok(instance.clone());
     }
 
-    let artifacts = jenkins::get_latest_artifacts()?;
-    let artifact = install::choose_artifact(artifacts, instance.instance_type)?;
+    let build = jenkins::get_latest_build()?;
+    let artifact = install::choose_artifact(
+        build.artifacts,
+        instance.instance_type,
+        instance.source.arch,
+        instance.source.asset_pattern.as_deref(),
+    )?;
 
     let url = format!(
         "https://ci.mcofficer.me/job/EndlessSky-continuous-bitar/lastBuild/artifact/{}",
@@ -92,9 +209,15 @@ async fn update_continuous_instance(instance: &Instance) -> Result<Instance> {
 
     bitar_update_archive(&instance.name, &archive_path, url).await?;
 
+    job::check_cancelled(&instance.name)?;
     if !InstanceType::AppImage.archive_matches(&archive_path.to_string_lossy()) {
-        send_progress_message(&instance.name, "Extracting archive".into());
-        archive::unpack(&archive_path, &instance.path, !cfg!(target_os = "macos"))?;
+        job::stage(&instance.name, "Extracting archive");
+        archive::unpack(
+            &archive_path,
+            &instance.path,
+            !cfg!(target_os = "macos"),
+            Some(&instance.name),
+        )?;
     }
 
     let mut new_instance = instance.clone();
@@ -106,7 +229,23 @@ async fn update_continuous_instance(instance: &Instance) -> Result<Instance> {
             new_instance.executable = post_cmake_exec;
         }
     }
+    new_instance.provenance = Provenance {
+        release_tag: None,
+        asset_id: None,
+        asset_name: Some(artifact.name().into()),
+        commit: Some(version.clone()),
+        build_date: build
+            .timestamp
+            .and_then(|millis| OffsetDateTime::from_unix_timestamp(millis / 1000).ok())
+            .and_then(|date| date.format(&Rfc3339).ok()),
+    };
     new_instance.version = version;
+    if let Err(e) = modifications::record(&new_instance) {
+        warn!(
+            "Failed to record the data files of {}: {:#}",
+            new_instance.name, e
+        );
+    }
     Ok(new_instance)
 }
 
@@ -121,6 +260,8 @@ async fn bitar_update_archive(
         target_path.to_string_lossy()
     );
 
+    // Only the changed chunks get downloaded, so their size isn't known up front
+    bandwidth::check_cap(None);
     // Open archive which source we want to clone
     let reader = bitar::archive_reader::HttpReader::from_url(url.parse()?);
     let mut source_archive = bitar::Archive::try_init(reader).await?;
@@ -134,17 +275,17 @@ async fn bitar_update_archive(
         .open(&target_path)
         .await?;
 
-    send_progress_message(instance_name, "Scanning local chunks".into());
+    job::stage(instance_name, "Scanning local chunks");
     // Scan the target file for chunks and build a chunk index
     let mut output_index = bitar::ChunkIndex::new_empty(source_archive.chunk_hash_length());
     {
         let chunker = source_archive.chunker_config().new_chunker(&mut target);
         let mut chunk_stream = chunker.map_ok(|(offset, chunk)| (offset, chunk.verify()));
         while let Some(r) = chunk_stream.next().await {
-            send_progress_message(
+            job::check_cancelled(instance_name)?;
+            job::progress(
                 instance_name,
-                Progress::from("Scanning local chunks")
-                    .done(output_index.len() as u32)
+                Progress::new(output_index.len() as u32)
                     .total(source_archive.total_chunks() as u32)
                     .total_approx(true),
             );
@@ -158,24 +299,29 @@ async fn bitar_update_archive(
     let mut output = bitar::CloneOutput::new(target, source_archive.build_source_index());
 
     // Reorder chunks in the output
-    send_progress_message(instance_name, "Reordering chunks".into());
+    job::stage(instance_name, "Reordering chunks");
     let reused_bytes = output.reorder_in_place(output_index).await?;
     info!("Used {}b from existing file", reused_bytes);
 
     // Fetch the rest of the chunks from the source archive
     let mut chunk_stream = source_archive.chunk_stream(output.chunks());
     let mut read_from_remote = 0;
-    while let Some(result) = chunk_stream.next().await {
-        send_progress_message(
+    let mut meter = Meter::default();
+    job::stage(instance_name, "Fetching remote chunks");
+    // Chunks can't be fetched again individually, so retrying falls back to a full download
+    while let Some(result) = job::watch(instance_name, chunk_stream.next())
+        .await?
+        .ok_or_else(|| anyhow!("Retrying with a full download"))?
+    {
+        job::progress(
             instance_name,
-            Progress::from("Fetching remote chunks")
-                .done(read_from_remote as u32)
-                .units("b"),
+            Progress::new(read_from_remote as u32).units("b"),
         );
         let compressed = result?;
         read_from_remote += compressed.len();
+        meter.add(compressed.len());
         let unverified = compressed.decompress()?;
-        let verified = unverified.verify()?;
+        let verified = unverified.verify().context(ChecksumMismatch)?;
         output.feed(&verified).await?;
     }
 
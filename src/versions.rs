@@ -0,0 +1,349 @@
+//! Previous builds of instances, kept around so they can be switched to without downloading anything.
+
+use crate::instance::{Instance, Provenance};
+use crate::{get_data_dir, instance_lock};
+use anyhow::Result;
+use fs_extra::dir::CopyOptions;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Entries of an instance dir that belong to the user rather than to a build
+const PRESERVED: [&str; 3] = ["logs", "config", instance_lock::FILE_NAME];
+/// Where the build gets copied to while updating, if it isn't kept anyway.
+/// Sanitized versions never start with a dot, so this can't clash with one.
+const RESTORE_POINT: &str = ".restore-point";
+/// Where the files the user chose to preserve wait while updating
+const PRESERVED_FILES: &str = ".preserved";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeptVersion {
+    pub version: String,
+    /// Relative to the version's dir, since it differs between some builds
+    pub executable: PathBuf,
+    #[serde(default)]
+    pub provenance: Provenance,
+}
+
+pub fn get_versions_dir(instance_name: &str) -> Option<PathBuf> {
+    let mut dir = get_data_dir()?;
+    dir.push("versions");
+    dir.push(instance_name);
+    Some(dir)
+}
+
+fn version_dir(instance_name: &str, version: &str) -> Result<PathBuf> {
+    let dir_name: String = version
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    Ok(get_versions_dir(instance_name)
+        .ok_or_else(|| anyhow!("Failed to get versions dir"))?
+        .join(dir_name))
+}
+
+/// Whether an entry of an instance dir belongs to the user, so installing a build leaves it alone.
+pub fn belongs_to_user(file_name: &OsStr) -> bool {
+    PRESERVED.iter().any(|p| file_name == *p)
+}
+
+/// The parts of `dir` that make up the build.
+fn build_entries(dir: &Path) -> Result<Vec<PathBuf>> {
+    Ok(fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .filter(|e| !belongs_to_user(&e.file_name()))
+        .map(|e| e.path())
+        .collect())
+}
+
+/// Moves the build in `from` into `to`. Renaming is instant, copying is only needed across filesystems.
+fn move_build(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in build_entries(from)? {
+        let target = to.join(entry.file_name().unwrap_or_default());
+        if fs::rename(&entry, &target).is_err() {
+            fs_extra::move_items(&[&entry], to, &CopyOptions::new())?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes kept versions beyond the `keep` newest ones.
+pub fn prune(instance: &mut Instance, keep: usize) -> Result<()> {
+    while instance.kept_versions.len() > keep {
+        if let Some(oldest) = instance.kept_versions.pop() {
+            let dir = version_dir(&instance.name, &oldest.version)?;
+            debug!("Removing kept version {}", dir.to_string_lossy());
+            if dir.exists() {
+                fs::remove_dir_all(dir)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Copies the current build of `instance` into its versions dir, so at most `keep` versions remain.
+pub fn store(instance: &mut Instance, keep: usize) -> Result<()> {
+    if keep == 0 {
+        return prune(instance, 0);
+    }
+    let dir = version_dir(&instance.name, &instance.version)?;
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir)?;
+    fs_extra::copy_items(&build_entries(&instance.path)?, &dir, &CopyOptions::new())?;
+    info!(
+        "Kept version {} of {} in {}",
+        instance.version,
+        instance.name,
+        dir.to_string_lossy()
+    );
+
+    let executable = instance
+        .executable
+        .strip_prefix(&instance.path)
+        .unwrap_or(&instance.executable)
+        .to_path_buf();
+    let version = instance.version.clone();
+    forget(instance, &version);
+    instance.kept_versions.insert(
+        0,
+        KeptVersion {
+            version,
+            executable,
+            provenance: instance.provenance.clone(),
+        },
+    );
+    prune(instance, keep)
+}
+
+/// Drops a kept version from the list and removes it from disk.
+pub fn forget(instance: &mut Instance, version: &str) {
+    instance.kept_versions.retain(|v| v.version != version);
+    if let Ok(dir) = version_dir(&instance.name, version) {
+        if dir.exists() {
+            if let Err(e) = fs::remove_dir_all(&dir) {
+                error!("Failed to remove {}: {}", dir.to_string_lossy(), e);
+            }
+        }
+    }
+}
+
+/// Swaps the current build of `instance` with the kept `version`.
+pub fn switch(mut instance: Instance, version: &str) -> Result<Instance> {
+    if version == instance.version {
+        return Err(anyhow!(
+            "{} is already at version {}",
+            instance.name,
+            version
+        ));
+    }
+    let index = instance
+        .kept_versions
+        .iter()
+        .position(|v| v.version == version)
+        .ok_or_else(|| anyhow!("Version {} of {} isn't kept", version, instance.name))?;
+    let _lock = instance_lock::acquire(&instance.path, "switching versions")?;
+    let stored_dir = version_dir(&instance.name, version)?;
+    if !stored_dir.exists() {
+        instance.kept_versions.remove(index);
+        return Err(anyhow!("{} doesn't exist", stored_dir.to_string_lossy()));
+    }
+    info!(
+        "Switching {} from {} to {}",
+        instance.name, instance.version, version
+    );
+
+    // Park the current build, so switching back is just as quick
+    let current_dir = version_dir(&instance.name, &instance.version)?;
+    if current_dir.exists() {
+        fs::remove_dir_all(&current_dir)?;
+    }
+    move_build(&instance.path, &current_dir)?;
+    move_build(&stored_dir, &instance.path)?;
+    fs::remove_dir_all(&stored_dir)?;
+
+    let kept = instance.kept_versions.remove(index);
+    let current = KeptVersion {
+        version: instance.version.clone(),
+        executable: instance
+            .executable
+            .strip_prefix(&instance.path)
+            .unwrap_or(&instance.executable)
+            .to_path_buf(),
+        provenance: instance.provenance.clone(),
+    };
+    instance
+        .kept_versions
+        .retain(|v| v.version != current.version);
+    instance.kept_versions.insert(0, current);
+    instance.executable = instance.path.join(kept.executable);
+    instance.version = kept.version;
+    instance.provenance = kept.provenance;
+    Ok(instance)
+}
+
+/// A copy of an instance's build to go back to, if an update gets cancelled halfway through.
+pub struct RestorePoint {
+    dir: PathBuf,
+    /// Made only for the update, rather than being a kept version
+    temporary: bool,
+}
+
+/// Reuses the kept copy of the current version if there is one, since copying a build takes a while.
+pub fn restore_point(instance: &Instance) -> Result<RestorePoint> {
+    let kept_dir = version_dir(&instance.name, &instance.version)?;
+    if kept_dir.exists()
+        && instance
+            .kept_versions
+            .iter()
+            .any(|v| v.version == instance.version)
+    {
+        return Ok(RestorePoint {
+            dir: kept_dir,
+            temporary: false,
+        });
+    }
+    let dir = get_versions_dir(&instance.name)
+        .ok_or_else(|| anyhow!("Failed to get versions dir"))?
+        .join(RESTORE_POINT);
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir)?;
+    fs_extra::copy_items(&build_entries(&instance.path)?, &dir, &CopyOptions::new())?;
+    Ok(RestorePoint {
+        dir,
+        temporary: true,
+    })
+}
+
+impl RestorePoint {
+    /// Replaces whatever build is in `instance_dir` now with the one from before.
+    pub fn restore(self, instance_dir: &Path) -> Result<()> {
+        fs::create_dir_all(instance_dir)?;
+        for entry in build_entries(instance_dir)? {
+            if entry.is_dir() {
+                fs::remove_dir_all(&entry)?;
+            } else {
+                fs::remove_file(&entry)?;
+            }
+        }
+        if self.temporary {
+            move_build(&self.dir, instance_dir)?;
+            fs::remove_dir_all(&self.dir)?;
+        } else {
+            fs_extra::copy_items(
+                &build_entries(&self.dir)?,
+                instance_dir,
+                &CopyOptions::new(),
+            )?;
+        }
+        info!("Restored {}", instance_dir.to_string_lossy());
+        Ok(())
+    }
+
+    pub fn discard(self) {
+        if self.temporary && self.dir.exists() {
+            if let Err(e) = fs::remove_dir_all(&self.dir) {
+                error!("Failed to remove {}: {}", self.dir.to_string_lossy(), e);
+            }
+        }
+    }
+}
+
+/// Files and folders inside an instance that the user chose to keep through updates,
+/// copied aside while the build gets replaced.
+#[must_use]
+pub struct PreservedFiles {
+    dir: PathBuf,
+    /// Relative to the instance dir
+    entries: Vec<PathBuf>,
+}
+
+/// Copies a file, or a folder with everything in it, to `to`, replacing what's there.
+fn copy_entry(from: &Path, to: &Path) -> Result<()> {
+    if to.is_dir() {
+        fs::remove_dir_all(to)?;
+    } else if to.exists() {
+        fs::remove_file(to)?;
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        fs_extra::dir::copy(from, to, &CopyOptions::new().content_only(true))?;
+    } else {
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+impl PreservedFiles {
+    pub fn stash(instance: &Instance) -> Result<Self> {
+        let dir = get_versions_dir(&instance.name)
+            .ok_or_else(|| anyhow!("Failed to get versions dir"))?
+            .join(PRESERVED_FILES);
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        let mut entries = vec![];
+        for relative in &instance.preserved {
+            let source = instance.path.join(relative);
+            if !source.exists() {
+                warn!(
+                    "{} doesn't exist anymore, so it can't be preserved",
+                    source.to_string_lossy()
+                );
+                continue;
+            }
+            copy_entry(&source, &dir.join(relative))?;
+            entries.push(relative.clone());
+        }
+        Ok(Self { dir, entries })
+    }
+
+    /// Copies the preserved files over whatever the update put in their place. If some can't be,
+    /// the stash is kept so they can be copied by hand.
+    pub fn put_back(self, instance_dir: &Path) -> Result<()> {
+        let mut failed = vec![];
+        for relative in &self.entries {
+            if let Err(e) = copy_entry(&self.dir.join(relative), &instance_dir.join(relative)) {
+                warn!("Failed to put back {}: {:#}", relative.to_string_lossy(), e);
+                failed.push(relative.to_string_lossy().to_string());
+            }
+        }
+        if !failed.is_empty() {
+            return Err(anyhow!(
+                "Failed to put back {}, they're still in {}",
+                failed.join(", "),
+                self.dir.to_string_lossy()
+            ));
+        }
+        if !self.entries.is_empty() {
+            info!(
+                "Put back {} preserved files and folders",
+                self.entries.len()
+            );
+        }
+        self.discard();
+        Ok(())
+    }
+
+    pub fn discard(self) {
+        if self.dir.exists() {
+            if let Err(e) = fs::remove_dir_all(&self.dir) {
+                error!("Failed to remove {}: {}", self.dir.to_string_lossy(), e);
+            }
+        }
+    }
+}